@@ -61,6 +61,7 @@ async fn submit_topology_forecasts(world: &mut MyWorld, energy: f64) {
 	let bid_forecast = ForecastSchema {
 		area_uuid: world.buyer_id.clone(),
 		area_hash: world.buyer_hash.clone().unwrap(),
+		market_id: market.market_id.clone(),
 		community_uuid: "community1".to_string(),
 		time_slot: world.target_delivery_time,
 		creation_time: 1,
@@ -71,6 +72,7 @@ async fn submit_topology_forecasts(world: &mut MyWorld, energy: f64) {
 	let offer_forecast = ForecastSchema {
 		area_uuid: world.seller_id.clone(),
 		area_hash: world.seller_hash.clone().unwrap(),
+		market_id: market.market_id.clone(),
 		community_uuid: "community1".to_string(),
 		time_slot: world.target_delivery_time,
 		creation_time: 1,
@@ -40,10 +40,12 @@ async fn submit_measurements(world: &mut MyWorld, _user1: String, _user2: String
 		.unwrap_or_else(|_| "http://127.0.0.1:8080".to_string());
 	let adapter = AreaMarketInfoAdapter::new(Some(orderbook_url));
 
+	let market_id = world.topology_schema.clone().unwrap().market_id;
 	let measurements = vec![
 		MeasurementSchema {
 			area_uuid: world.buyer_id.clone(),
 			area_hash: world.buyer_hash.clone().unwrap(),
+			market_id: market_id.clone(),
 			community_uuid: "community1".to_string(),
 			energy_kwh: 12.0,
 			time_slot: world.target_delivery_time,
@@ -52,6 +54,7 @@ async fn submit_measurements(world: &mut MyWorld, _user1: String, _user2: String
 		MeasurementSchema {
 			area_uuid: world.seller_id.clone(),
 			area_hash: world.seller_hash.clone().unwrap(),
+			market_id: market_id.clone(),
 			community_uuid: "community1".to_string(),
 			energy_kwh: -8.0,
 			time_slot: world.target_delivery_time,
@@ -1,17 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use gsy_community_client::external_api::{
 	ExternalCommunityTopology, ExternalForecast, ExternalMeasurement,
 };
-use gsy_community_client::node_connector::orders::publish_orders;
+use gsy_community_client::node_connector::orders::{
+	is_registered_user, publish_orders, publish_settlement_orders,
+};
 use gsy_community_client::offchain_storage_connector::adapter::AreaMarketInfoAdapter;
 use gsy_community_client::time_utils::{get_current_timestamp_in_secs, get_last_and_next_timeslot};
+use gsy_offchain_primitives::db_api_schema::market::MarketTopologySchema;
 use gsy_offchain_primitives::db_api_schema::profiles::{ForecastSchema, MeasurementSchema};
 use gsy_offchain_primitives::constants::GlobalConstants;
+use gsy_offchain_primitives::signer::load_signer;
 use reqwest::Client;
 use std::time::Duration;
-use subxt_signer::sr25519::dev;
+use subxt_signer::sr25519::Keypair;
 use tokio::time::sleep;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 #[derive(Clone)]
 struct AppState {
@@ -21,6 +25,7 @@ struct AppState {
 	forecast_url: String,
 	measurements_url: String,
 	topology_url: String,
+	signer: Keypair,
 }
 
 impl AppState {
@@ -32,6 +37,10 @@ impl AppState {
 			forecast_url: "http://localhost:8000/forecasts".to_string(),
 			measurements_url: "http://localhost:8000/measurements".to_string(),
 			topology_url: "http://localhost:8000/ontology".to_string(),
+			signer: load_signer().unwrap_or_else(|e| {
+				error!("Failed to load the signer: {:?}", e);
+				std::process::exit(1);
+			}),
 		}
 	}
 
@@ -52,7 +61,33 @@ impl AppState {
 		response.json::<ExternalCommunityTopology>().await
 	}
 
+	/// Wait until the signer is registered as a user, which is required to publish orders.
+	async fn wait_for_registration(&self) {
+		loop {
+			match is_registered_user(self.gsy_node_url.clone(), &self.signer).await {
+				Ok(true) => {
+					info!("Signer account is registered. Starting to forward.");
+					return;
+				},
+				Ok(false) => {
+					warn!("Signer account not yet registered. Retrying in 10 seconds...");
+				},
+				Err(e) => {
+					error!(
+						"Error checking registration status: {:?}. Retrying in 10 seconds...",
+						e
+					);
+				},
+			}
+			sleep(Duration::from_secs(10)).await;
+		}
+	}
+
 	async fn poll_and_forward(&self) {
+		self.wait_for_registration().await;
+		// The measured areas, by slot and area hash, whose deviations have been traded in the
+		// settlement market.
+		let mut settled_areas: HashSet<(u64, String)> = HashSet::new();
 		loop {
 			let seconds_since_epoch = get_current_timestamp_in_secs();
 
@@ -85,7 +120,10 @@ impl AppState {
 						.into_iter()
 						.map(|forecast| {
 							self.api_adapter.convert_forecast_to_internal_schema(
-								&forecast, area_uuid_to_hash[&forecast.area_uuid].clone())
+								&forecast,
+								area_uuid_to_hash[&forecast.area_uuid].clone(),
+								internal_topology.market_id.clone(),
+							)
 						})
 						.filter(|forecast| {
 							self.api_adapter.validate_forecast(forecast, seconds_since_epoch)
@@ -101,7 +139,7 @@ impl AppState {
 							self.gsy_node_url.clone(),
 							valid_forecasts.clone(),
 							internal_topology.clone(),
-							&dev::alice(),
+							&self.signer,
 						)
 						.await
 						.unwrap();
@@ -115,22 +153,59 @@ impl AppState {
 			// Fetch and forward measurements
 			match self.fetch_measurements().await {
 				Ok(measurements) => {
-					let valid_measurements: Vec<MeasurementSchema> = measurements
-						.into_iter()
-						.map(|measurement| {
-							self.api_adapter.convert_measurement_to_internal_schema(
-								&measurement, area_uuid_to_hash[&measurement.area_uuid].clone())
-						})
-						.filter(|measurement| {
-							self.api_adapter.validate_measurement(measurement, seconds_since_epoch)
-						})
-						.collect();
+					// The area hashes are generated for each market topology, so the measurements
+					// are matched with the topology of the market of their own slot.
+					let mut market_topologies: HashMap<u64, Option<MarketTopologySchema>> =
+						HashMap::new();
+					let mut valid_measurements: Vec<MeasurementSchema> = Vec::new();
+					for measurement in measurements {
+						if !market_topologies.contains_key(&measurement.time_slot) {
+							let market_topology = self
+								.api_adapter
+								.get_market_topology(
+									&internal_topology.community_uuid,
+									measurement.time_slot,
+								)
+								.await;
+							market_topologies.insert(measurement.time_slot, market_topology);
+						}
+						let Some(market) = &market_topologies[&measurement.time_slot] else {
+							info!(
+								"No market for the measurement of slot {}",
+								measurement.time_slot
+							);
+							continue;
+						};
+						let Some(area) = market
+							.community_areas
+							.iter()
+							.find(|area| area.area_uuid == measurement.area_uuid)
+						else {
+							info!("Area {} not found in its market", measurement.area_uuid);
+							continue;
+						};
+						let measurement = self.api_adapter.convert_measurement_to_internal_schema(
+							&measurement,
+							area.area_hash.clone(),
+							market.market_id.clone(),
+						);
+						if self.api_adapter.validate_measurement(&measurement, seconds_since_epoch)
+						{
+							valid_measurements.push(measurement);
+						}
+					}
 					if !valid_measurements.is_empty() {
 						if let Err(e) =
-							self.api_adapter.forward_measurement(valid_measurements).await
+							self.api_adapter.forward_measurement(valid_measurements.clone()).await
 						{
 							info!("Failed to forward measurements: {}", e);
 						}
+						self.publish_settlement_orders(
+							&valid_measurements,
+							&market_topologies,
+							&mut settled_areas,
+						)
+						.await;
 					} else {
 						info!("No valid measurements to forward.");
 					}
@@ -142,6 +217,52 @@ impl AppState {
 			sleep(Duration::from_secs(GlobalConstants.TIME_SLOT_SEC)).await;
 		}
 	}
+
+	/// Trade the deviations of the measured areas in the settlement market of their slot, once per
+	/// area. The orders of a slot that fail, e.g. because its settlement market is not open yet, are
+	/// retried at the next poll.
+	async fn publish_settlement_orders(
+		&self,
+		measurements: &[MeasurementSchema],
+		market_topologies: &HashMap<u64, Option<MarketTopologySchema>>,
+		settled_areas: &mut HashSet<(u64, String)>,
+	) {
+		for (time_slot, market) in market_topologies {
+			let Some(market) = market else { continue };
+			let measurements: Vec<MeasurementSchema> = measurements
+				.iter()
+				.filter(|measurement| {
+					measurement.time_slot == *time_slot
+						&& !settled_areas.contains(&(*time_slot, measurement.area_hash.clone()))
+				})
+				.cloned()
+				.collect();
+			if measurements.is_empty() {
+				continue;
+			}
+			let trades = match self.api_adapter.get_trades(*time_slot).await {
+				Ok(trades) => trades,
+				Err(e) => {
+					error!("Failed to fetch the trades of slot {}: {}", time_slot, e);
+					continue;
+				},
+			};
+			match publish_settlement_orders(
+				self.gsy_node_url.clone(),
+				&measurements,
+				&trades,
+				market,
+				&self.signer,
+			)
+			.await
+			{
+				Ok(()) => settled_areas.extend(
+					measurements.into_iter().map(|measurement| (*time_slot, measurement.area_hash)),
+				),
+				Err(e) => info!("Failed to publish the settlement orders of {}: {}", time_slot, e),
+			}
+		}
+	}
 }
 
 #[tokio::main]
@@ -1,11 +1,15 @@
+use crate::node_connector::orders::gsy_node::orderbook_registry::events::AllOrdersInserted;
 use crate::node_connector::orders::gsy_node::runtime_types::gsy_primitives::orders::{
 	InputBid, InputOffer, InputOrder, OrderComponent,
 };
 use crate::time_utils::get_current_timestamp_in_secs;
 use anyhow::{Error, Result};
 use gsy_offchain_primitives::db_api_schema::market::{AreaTopologySchema, MarketTopologySchema};
-use gsy_offchain_primitives::db_api_schema::profiles::ForecastSchema;
+use gsy_offchain_primitives::db_api_schema::profiles::{ForecastSchema, MeasurementSchema};
+use gsy_offchain_primitives::db_api_schema::trades::TradeSchema;
 use gsy_offchain_primitives::utils::{string_to_h256, NODE_FLOAT_SCALING_FACTOR};
+use gsy_offchain_primitives::MarketType;
+use std::collections::HashMap;
 use subxt::{utils::AccountId32, OnlineClient, SubstrateConfig};
 use subxt_signer::sr25519::Keypair;
 use tracing::info;
@@ -16,31 +20,69 @@ const OFFER_RATE: f64 = 0.07;
 #[subxt::subxt(runtime_metadata_path = "../offchain-primitives/metadata.scale")]
 pub mod gsy_node {}
 
+/// Whether `signer` is a registered user, which is required to insert orders.
+pub async fn is_registered_user(url: String, signer: &Keypair) -> Result<bool, Error> {
+	let api = OnlineClient::<SubstrateConfig>::from_insecure_url(url).await?;
+	let storage_address = gsy_node::storage()
+		.gsy_collateral()
+		.registered_user(AccountId32::from(signer.public_key()));
+	Ok(api.storage().at_latest().await?.fetch(&storage_address).await?.is_some())
+}
+
 pub async fn publish_orders(
 	url: String,
 	forecasts: Vec<ForecastSchema>,
 	market: MarketTopologySchema,
 	signer: &Keypair,
+) -> Result<(), Error> {
+	let input_orders = create_input_orders(forecasts, market, signer);
+	insert_orders(url, input_orders, signer).await
+}
+
+/// Publish the settlement-market orders trading the deviations of the measured areas.
+pub async fn publish_settlement_orders(
+	url: String,
+	measurements: &[MeasurementSchema],
+	trades: &[TradeSchema],
+	market: &MarketTopologySchema,
+	signer: &Keypair,
+) -> Result<(), Error> {
+	let input_orders = create_settlement_orders(measurements, trades, market, signer);
+	if input_orders.is_empty() {
+		info!("No deviation to trade in the settlement market of {}", market.time_slot);
+		return Ok(());
+	}
+	insert_orders(url, input_orders, signer).await
+}
+
+/// Insert the orders in batches of at most `MaxOrdersPerBatch` orders, the limit of the runtime.
+async fn insert_orders(
+	url: String,
+	input_orders: Vec<InputOrder<AccountId32>>,
+	signer: &Keypair,
 ) -> Result<(), Error> {
 	let api = OnlineClient::<SubstrateConfig>::from_insecure_url(url).await?;
+	let max_orders_per_batch = api
+		.constants()
+		.at(&gsy_node::constants().orderbook_registry().max_orders_per_batch())?;
 
-	let input_orders = create_input_orders(forecasts, market, signer);
-	let register_order_tx = gsy_node::tx().orderbook_worker().insert_orders(input_orders);
-
-	let order_submit_and_watch = api
-		.tx()
-		.sign_and_submit_then_watch_default(&register_order_tx, signer)
-		.await?
-		.wait_for_finalized_success()
-		.await?;
-
-	let transfer_event = order_submit_and_watch
-		.find_first::<gsy_node::orderbook_registry::events::AllOrdersInserted>()?;
-
-	if let Some(event) = transfer_event {
-		info!("Orders publishing success: {event:?}");
-	} else {
-		info!("Failed to find AllOrdersInserted Event");
+	for batch in input_orders.chunks(max_orders_per_batch as usize) {
+		let register_order_tx = gsy_node::tx().orderbook_worker().insert_orders(batch.to_vec());
+
+		let order_submit_and_watch = api
+			.tx()
+			.sign_and_submit_then_watch_default(&register_order_tx, signer)
+			.await?
+			.wait_for_finalized_success()
+			.await?;
+
+		let inserted_event = order_submit_and_watch.find_first::<AllOrdersInserted>()?;
+
+		if let Some(event) = inserted_event {
+			info!("Orders publishing success: {event:?}");
+		} else {
+			info!("Failed to find AllOrdersInserted Event");
+		}
 	}
 
 	Ok(())
@@ -64,6 +106,7 @@ fn _create_bid_object(
 				market_id: string_to_h256(market.market_id.clone()),
 				creation_time: now,
 				time_slot: market.time_slot as u64,
+				valid_until: None,
 			},
 		},
 	}
@@ -87,6 +130,7 @@ fn _create_offer_object(
 				market_id: string_to_h256(market.market_id.clone()),
 				creation_time: now,
 				time_slot: market.time_slot as u64,
+				valid_until: None,
 			},
 		},
 	}
@@ -102,7 +146,8 @@ pub fn create_input_orders(
 	let mut input_orders: Vec<InputOrder<AccountId32>> = Vec::new();
 
 	for forecast in forecasts {
-		let area_info = market.community_areas.iter().find(|area| area.area_hash == forecast.area_hash);
+		let area_info =
+			market.community_areas.iter().find(|area| area.area_hash == forecast.area_hash);
 		if area_info.is_none() {
 			continue;
 		}
@@ -127,3 +172,67 @@ pub fn create_input_orders(
 	}
 	input_orders
 }
+
+/// The settlement-market orders of the measured areas of `market`, for their deviation from the
+/// energy they traded in the slot: a Bid for the energy an area consumed beyond its trades, and an
+/// Offer for the energy it consumed less or produced more than its trades. The trades of all the
+/// markets of the slot are netted, including the settlement-market trades already made, so that a
+/// deviation is not traded twice.
+pub fn create_settlement_orders(
+	measurements: &[MeasurementSchema],
+	trades: &[TradeSchema],
+	market: &MarketTopologySchema,
+	signer: &Keypair,
+) -> Vec<InputOrder<AccountId32>> {
+	let now: u64 = get_current_timestamp_in_secs();
+	let time_slot = market.time_slot as u64;
+	let market_id = MarketType::Settlement.market_id(time_slot);
+
+	let mut deviations: HashMap<&str, f64> = HashMap::new();
+	for measurement in measurements.iter().filter(|measurement| measurement.time_slot == time_slot)
+	{
+		*deviations.entry(measurement.area_hash.as_str()).or_default() += measurement.energy_kwh;
+	}
+	for trade in trades.iter().filter(|trade| trade.time_slot == time_slot) {
+		let energy = trade.parameters.selected_energy;
+		if let Some(deviation) = deviations.get_mut(trade.bid.bid_component.area_uuid.as_str()) {
+			*deviation -= energy;
+		}
+		if let Some(deviation) = deviations.get_mut(trade.offer.offer_component.area_uuid.as_str())
+		{
+			*deviation += energy;
+		}
+	}
+
+	let mut input_orders: Vec<InputOrder<AccountId32>> = Vec::new();
+	for area in market.community_areas.iter() {
+		let Some(deviation) = deviations.get(area.area_hash.as_str()) else {
+			continue;
+		};
+		let energy = (deviation.abs() * NODE_FLOAT_SCALING_FACTOR) as u64;
+		if energy == 0 {
+			continue;
+		}
+		let order_component = |rate: f64| OrderComponent {
+			area_uuid: string_to_h256(area.area_hash.clone()),
+			energy,
+			energy_rate: (deviation.abs() * rate * NODE_FLOAT_SCALING_FACTOR) as u64,
+			market_id,
+			creation_time: now,
+			time_slot,
+			valid_until: None,
+		};
+		if *deviation > 0. {
+			input_orders.push(InputOrder::Bid(InputBid {
+				buyer: AccountId32::from(signer.public_key()),
+				bid_component: order_component(BID_RATE),
+			}));
+		} else {
+			input_orders.push(InputOrder::Offer(InputOffer {
+				seller: AccountId32::from(signer.public_key()),
+				offer_component: order_component(OFFER_RATE),
+			}));
+		}
+	}
+	input_orders
+}
@@ -1,19 +1,18 @@
 use crate::external_api::{ExternalCommunityTopology, ExternalForecast, ExternalMeasurement};
 use crate::time_utils::get_current_timestamp_in_secs;
-use blake2_rfc::blake2b::blake2b;
+use gsy_offchain_primitives::constants::GlobalConstants;
 use gsy_offchain_primitives::db_api_schema::market::{AreaTopologySchema, MarketTopologySchema};
 use gsy_offchain_primitives::db_api_schema::profiles::{ForecastSchema, MeasurementSchema};
+use gsy_offchain_primitives::db_api_schema::trades::TradeSchema;
 use gsy_offchain_primitives::utils::h256_to_string;
 use gsy_offchain_primitives::MarketType;
 use reqwest::Client;
 use subxt::utils::H256;
 use tracing::info;
 
-fn generate_market_id(market_type: MarketType, delivery_timestamp: u64) -> H256 {
-	let mut buffer = Vec::new();
-	buffer.extend_from_slice(market_type.as_str().as_bytes());
-	buffer.extend_from_slice(&delivery_timestamp.to_be_bytes());
-	H256(blake2b(32, &[], &buffer).as_bytes().try_into().expect("hash is 32 bytes"))
+/// Whether `time_slot` is the start of a slot.
+fn is_aligned_to_time_slot(time_slot: u64) -> bool {
+	time_slot % GlobalConstants.TIME_SLOT_SEC == 0
 }
 
 #[derive(Clone)]
@@ -21,6 +20,7 @@ pub struct AreaMarketInfoAdapter {
 	client: Client,
 	internal_forecast_url: String,
 	internal_measurements_url: String,
+	internal_trades_url: String,
 	pub internal_topology_url: String,
 	pub internal_community_market_url: String,
 }
@@ -32,6 +32,7 @@ impl AreaMarketInfoAdapter {
 			client: Client::new(),
 			internal_forecast_url: hostname.clone() + "/forecasts",
 			internal_measurements_url: hostname.clone() + "/measurements",
+			internal_trades_url: hostname.clone() + "/trades",
 			internal_topology_url: hostname.clone() + "/market",
 			internal_community_market_url: hostname.clone() + "/community-market",
 		}
@@ -59,9 +60,22 @@ impl AreaMarketInfoAdapter {
 		Ok(())
 	}
 
+	/// The trades delivered in `time_slot`, in all the markets of the slot.
+	pub async fn get_trades(&self, time_slot: u64) -> Result<Vec<TradeSchema>, reqwest::Error> {
+		let trades_url = format!(
+			"{}?start_time={}&end_time={}",
+			self.internal_trades_url,
+			time_slot,
+			time_slot + GlobalConstants.TIME_SLOT_SEC - 1
+		);
+		self.client.get(&trades_url).send().await?.error_for_status()?.json().await
+	}
+
 	// Validation logic (basic validation, can be extended)
 	pub fn validate_forecast(&self, forecast: &ForecastSchema, seconds_since_epoch: u64) -> bool {
-		forecast.energy_kwh > 0.0 && forecast.time_slot > seconds_since_epoch
+		forecast.energy_kwh > 0.0
+			&& forecast.time_slot > seconds_since_epoch
+			&& is_aligned_to_time_slot(forecast.time_slot)
 	}
 
 	pub fn validate_measurement(
@@ -69,17 +83,21 @@ impl AreaMarketInfoAdapter {
 		measurement: &MeasurementSchema,
 		seconds_since_epoch: u64,
 	) -> bool {
-		measurement.energy_kwh > 0.0 && measurement.time_slot <= seconds_since_epoch
+		measurement.energy_kwh > 0.0
+			&& measurement.time_slot <= seconds_since_epoch
+			&& is_aligned_to_time_slot(measurement.time_slot)
 	}
 
 	pub fn convert_forecast_to_internal_schema(
 		&self,
 		forecast: &ExternalForecast,
-		area_hash: String
+		area_hash: String,
+		market_id: String,
 	) -> ForecastSchema {
 		ForecastSchema {
 			area_uuid: forecast.area_uuid.clone(),
 			area_hash: area_hash.clone(),
+			market_id,
 			community_uuid: forecast.community_uuid.clone(),
 			time_slot: forecast.time_slot,
 			creation_time: forecast.creation_time,
@@ -92,10 +110,12 @@ impl AreaMarketInfoAdapter {
 		&self,
 		measurement: &ExternalMeasurement,
 		area_hash: String,
+		market_id: String,
 	) -> MeasurementSchema {
 		MeasurementSchema {
 			area_uuid: measurement.area_uuid.clone(),
 			area_hash: area_hash.clone(),
+			market_id,
 			community_uuid: measurement.community_uuid.clone(),
 			time_slot: measurement.time_slot,
 			creation_time: measurement.creation_time,
@@ -114,24 +134,34 @@ impl AreaMarketInfoAdapter {
 		}
 	}
 
-	pub async fn get_or_create_market_topology(
+	/// The topology of the market of the community in `time_slot`, if it has been created.
+	pub async fn get_market_topology(
 		&self,
-		topology: ExternalCommunityTopology,
+		community_uuid: &str,
 		time_slot: u64,
 	) -> Option<MarketTopologySchema> {
 		let community_market_url = self.internal_community_market_url.clone()
 			+ "?community_uuid="
-			+ topology.community_uuid.as_str()
+			+ community_uuid
 			+ "&time_slot="
 			+ time_slot.to_string().as_str();
-		let market_topology = self.get_existing_market_topology(community_market_url).await;
+		self.get_existing_market_topology(community_market_url).await
+	}
+
+	pub async fn get_or_create_market_topology(
+		&self,
+		topology: ExternalCommunityTopology,
+		time_slot: u64,
+	) -> Option<MarketTopologySchema> {
+		let market_topology =
+			self.get_market_topology(topology.community_uuid.as_str(), time_slot).await;
 		match market_topology {
 			Some(topology) => Some(topology),
 			None => {
 				let new_market = MarketTopologySchema {
 					community_name: topology.community_name.clone(),
 					community_uuid: topology.community_uuid.clone(),
-					market_id: h256_to_string(generate_market_id(MarketType::Spot, time_slot)),
+					market_id: h256_to_string(MarketType::Spot.market_id(time_slot)),
 					time_slot: time_slot as u32,
 					creation_time: get_current_timestamp_in_secs() as u32,
 					community_areas: topology
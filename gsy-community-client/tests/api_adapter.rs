@@ -91,8 +91,11 @@ mod tests {
             area_uuid: "area_uuid".to_string(),
             confidence: 0.4
         };
-        let converted_forecast = adapter.convert_forecast_to_internal_schema(&forecast);
+        let converted_forecast = adapter.convert_forecast_to_internal_schema(
+            &forecast, "area_hash".to_string(), "market_id".to_string());
         assert_eq!(converted_forecast.area_uuid, "area_uuid");
+        assert_eq!(converted_forecast.area_hash, "area_hash");
+        assert_eq!(converted_forecast.market_id, "market_id");
         assert_eq!(converted_forecast.community_uuid, "comm_uuid");
         assert_eq!(converted_forecast.energy_kwh, 11.);
         assert_eq!(converted_forecast.confidence, 0.4);
@@ -110,8 +113,11 @@ mod tests {
             energy_kwh: 11.,
             area_uuid: "area_uuid".to_string(),
         };
-        let converted_measurement = adapter.convert_measurement_to_internal_schema(&measurement);
+        let converted_measurement = adapter.convert_measurement_to_internal_schema(
+            &measurement, "area_hash".to_string(), "market_id".to_string());
         assert_eq!(converted_measurement.area_uuid, "area_uuid");
+        assert_eq!(converted_measurement.area_hash, "area_hash");
+        assert_eq!(converted_measurement.market_id, "market_id");
         assert_eq!(converted_measurement.community_uuid, "comm_uuid");
         assert_eq!(converted_measurement.energy_kwh, 11.);
         assert_eq!(converted_measurement.time_slot, 123123);
@@ -1,10 +1,14 @@
-use gsy_community_client::node_connector::orders::create_input_orders;
+use gsy_community_client::node_connector::orders::{create_input_orders, create_settlement_orders};
 use gsy_community_client::node_connector::orders::gsy_node::runtime_types::gsy_primitives::orders::InputOrder;
 use gsy_community_client::time_utils::get_current_timestamp_in_secs;
 use gsy_offchain_primitives::db_api_schema::market::{AreaTopologySchema, MarketTopologySchema};
-use gsy_offchain_primitives::db_api_schema::profiles::ForecastSchema;
+use gsy_offchain_primitives::db_api_schema::profiles::{ForecastSchema, MeasurementSchema};
+use gsy_offchain_primitives::db_api_schema::trades::TradeSchema;
 use gsy_offchain_primitives::utils::h256_to_string;
+use gsy_offchain_primitives::test_utils::TradeFixture;
+use gsy_offchain_primitives::MarketType;
 use subxt::utils::H256;
+use subxt_signer::sr25519::dev;
 
 
 #[cfg(test)]
@@ -20,9 +24,13 @@ mod tests {
     #[test]
     fn test_orders_to_node_are_created_correctly() {
         setup_tracing();
+        let area1_hash = h256_to_string(H256::random());
+        let area2_hash = h256_to_string(H256::random());
         let forecasts: Vec<ForecastSchema> = vec![
             ForecastSchema {
                 area_uuid: "area1".to_string(),
+                area_hash: area1_hash.clone(),
+                market_id: "market".to_string(),
                 creation_time: 123123,
                 time_slot: 456456,
                 energy_kwh: 12.,
@@ -31,6 +39,8 @@ mod tests {
             },
             ForecastSchema {
                 area_uuid: "area2".to_string(),
+                area_hash: area2_hash.clone(),
+                market_id: "market".to_string(),
                 creation_time: 234234,
                 time_slot: 456456,
                 energy_kwh: -1.,
@@ -48,18 +58,18 @@ mod tests {
             community_areas: vec![
                 AreaTopologySchema {
                     area_uuid: "area1".to_string(),
-                    area_hash: h256_to_string(H256::random()),
+                    area_hash: area1_hash,
                     name: "Area 1".to_string(),
                 },
                 AreaTopologySchema {
                     area_uuid: "area2".to_string(),
-                    area_hash: h256_to_string(H256::random()),
+                    area_hash: area2_hash,
                     name: "Area 2".to_string(),
                 }
             ]
         };
 
-        let input_orders = create_input_orders(forecasts, market.clone());
+        let input_orders = create_input_orders(forecasts, market.clone(), &dev::alice());
         assert_eq!(input_orders.len(), 2);
         let current_time = get_current_timestamp_in_secs();
 
@@ -88,4 +98,60 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    fn trade(seller_hash: &str, buyer_hash: &str, energy: f64, market_type: MarketType) -> TradeSchema {
+        TradeFixture::new("trade", seller_hash, buyer_hash, energy)
+            .accounts("seller", "buyer")
+            .energy_rate(0.2)
+            .time_slot(456300)
+            .in_market(market_type)
+            .build()
+    }
+
+    #[test]
+    fn test_settlement_orders_trade_the_remaining_deviations() {
+        let area = |area_uuid: &str| AreaTopologySchema {
+            area_uuid: area_uuid.to_string(),
+            area_hash: h256_to_string(H256::random()),
+            name: area_uuid.to_string(),
+        };
+        let (house, pv, battery) = (area("house"), area("pv"), area("battery"));
+        let market = MarketTopologySchema {
+            creation_time: 345345,
+            time_slot: 456300,
+            market_id: h256_to_string(MarketType::Spot.market_id(456300)),
+            community_uuid: "community1".to_string(),
+            community_name: "My Community".to_string(),
+            community_areas: vec![house.clone(), pv.clone(), battery.clone()],
+        };
+        let measurement = |area: &AreaTopologySchema, energy_kwh: f64| MeasurementSchema {
+            area_uuid: area.area_uuid.clone(),
+            area_hash: area.area_hash.clone(),
+            market_id: market.market_id.clone(),
+            community_uuid: "community1".to_string(),
+            time_slot: 456300,
+            creation_time: 457200,
+            energy_kwh,
+        };
+        // The house bought 5 kWh but consumed 6 kWh, and already bought 0.5 kWh of its deviation in
+        // the settlement market. The PV produced what it sold, and the battery is not measured.
+        let measurements = vec![measurement(&house, 6.), measurement(&pv, -5.)];
+        let trades = vec![
+            trade(&pv.area_hash, &house.area_hash, 5., MarketType::Spot),
+            trade(&battery.area_hash, &house.area_hash, 0.5, MarketType::Settlement),
+        ];
+
+        let input_orders = create_settlement_orders(&measurements, &trades, &market, &dev::alice());
+
+        assert_eq!(input_orders.len(), 1);
+        match &input_orders[0] {
+            InputOrder::Bid(bid) => {
+                assert_eq!(h256_to_string(bid.bid_component.area_uuid), house.area_hash);
+                assert_eq!(bid.bid_component.market_id, MarketType::Settlement.market_id(456300));
+                assert_eq!(bid.bid_component.time_slot, 456300);
+                assert_eq!(bid.bid_component.energy, 5000);
+            },
+            InputOrder::Offer(_) => panic!("The house should bid for its deviation"),
+        }
+    }
+}
@@ -1,2 +1,5 @@
+pub mod node_index;
 pub mod offchain_storage;
-pub mod substrate_connector;
+pub mod penalty_export;
+pub mod statement_export;
+pub mod substrate_connector;
\ No newline at end of file
@@ -0,0 +1,38 @@
+use anyhow::Result;
+use tracing::info;
+use subxt::backend::rpc::{rpc_params, RpcClient};
+use gsy_offchain_primitives::db_api_schema::trades::TradeSchema;
+use gsy_offchain_primitives::node_to_api_schema::node_index::{
+    convert_indexed_trades_to_db_schema, IndexQuery, IndexedPage, IndexedTrade,
+};
+
+use crate::connectors::offchain_storage::timeslot_range;
+
+/// Fetch the trades of the timeslot indexed by the node in its offchain database.
+///
+/// The node must run with `--enable-offchain-indexing true`.
+pub async fn fetch_indexed_trades_for_timeslot(
+    node_url: &str,
+    timeslot: u64,
+    market_duration: u64,
+) -> Result<Vec<TradeSchema>> {
+    let (start_time, end_time) = timeslot_range(timeslot, market_duration);
+    info!("Fetching indexed trades from {} ({} - {})", node_url, start_time, end_time);
+
+    let rpc = RpcClient::from_insecure_url(node_url).await?;
+    let query = IndexQuery { from: Some(start_time), to: Some(end_time), ..Default::default() };
+    let mut trades: Vec<IndexedTrade> = Vec::new();
+    let mut cursor: Option<u64> = None;
+    loop {
+        let page: IndexedPage<IndexedTrade> = rpc
+            .request("gsy_indexedTrades", rpc_params![query.clone(), cursor, Option::<u32>::None])
+            .await?;
+        trades.extend(page.records);
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    Ok(convert_indexed_trades_to_db_schema(trades))
+}
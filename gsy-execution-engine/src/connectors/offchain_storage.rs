@@ -2,7 +2,9 @@ use anyhow::{Result, anyhow};
 use tracing::info;
 use reqwest::Client;
 use gsy_offchain_primitives::db_api_schema::{
-    profiles::MeasurementSchema, 
+    imbalance_prices::ImbalancePriceSchema,
+    profiles::{ForecastSchema, MeasurementSchema},
+    statements::{SettlementStatementSchema, StatementPeriod},
     trades::TradeSchema,
 };
 use gsy_offchain_primitives::constants::GlobalConstants;
@@ -11,6 +13,13 @@ fn round_down_timeslot(ts: u64) -> u64 {
     (ts / GlobalConstants.TIME_SLOT_SEC) * GlobalConstants.TIME_SLOT_SEC
 }
 
+/// The first and last timestamps of the market delivering at the given timeslot.
+pub fn timeslot_range(timeslot: u64, market_duration: u64) -> (u64, u64) {
+    let start_time = round_down_timeslot(timeslot);
+    let end_time = start_time + (market_duration.checked_sub(1).unwrap_or(GlobalConstants.TIME_SLOT_SEC));
+    (start_time, end_time)
+}
+
 pub async fn fetch_trades_and_measurements_for_timeslot(
     base_url: &str,
     timeslot: u64,
@@ -18,13 +27,10 @@ pub async fn fetch_trades_and_measurements_for_timeslot(
 ) -> Result<(Vec<TradeSchema>, Vec<MeasurementSchema>)> {
     let client = Client::new();
 
-    let start_time = round_down_timeslot(timeslot);
-    let end_time = start_time + (market_duration.checked_sub(1).unwrap_or(GlobalConstants.TIME_SLOT_SEC));
+    let (start_time, end_time) = timeslot_range(timeslot, market_duration);
 
     let trades_url = format!("{}/trades?start_time={}&end_time={}", base_url, start_time, end_time);
-    let measurements_url = format!("{}/measurements?start_time={}&end_time={}", base_url, start_time, end_time);
     info!("Fetching trades for {}", trades_url);
-    info!("Fetching measurements for {}", measurements_url);
 
     // 1) Fetch trades
     let trades_resp = client.get(&trades_url).send().await?;
@@ -38,6 +44,23 @@ pub async fn fetch_trades_and_measurements_for_timeslot(
     let trades: Vec<TradeSchema> = trades_resp.json().await?;
 
     // 2) Fetch measurements
+    let measurements = fetch_measurements_for_timeslot(base_url, timeslot, market_duration).await?;
+
+    Ok((trades, measurements))
+}
+
+pub async fn fetch_measurements_for_timeslot(
+    base_url: &str,
+    timeslot: u64,
+    market_duration: u64,
+) -> Result<Vec<MeasurementSchema>> {
+    let client = Client::new();
+
+    let (start_time, end_time) = timeslot_range(timeslot, market_duration);
+
+    let measurements_url = format!("{}/measurements?start_time={}&end_time={}", base_url, start_time, end_time);
+    info!("Fetching measurements for {}", measurements_url);
+
     let measurements_resp = client.get(&measurements_url).send().await?;
     if !measurements_resp.status().is_success() {
         return Err(anyhow!(
@@ -48,5 +71,92 @@ pub async fn fetch_trades_and_measurements_for_timeslot(
     }
     let measurements: Vec<MeasurementSchema> = measurements_resp.json().await?;
 
-    Ok((trades, measurements))
-}
\ No newline at end of file
+    Ok(measurements)
+}
+
+pub async fn fetch_forecasts_for_timeslot(
+    base_url: &str,
+    timeslot: u64,
+    market_duration: u64,
+) -> Result<Vec<ForecastSchema>> {
+    let client = Client::new();
+
+    let (start_time, end_time) = timeslot_range(timeslot, market_duration);
+
+    let forecasts_url = format!("{}/forecasts?start_time={}&end_time={}", base_url, start_time, end_time);
+    info!("Fetching forecasts for {}", forecasts_url);
+
+    let forecasts_resp = client.get(&forecasts_url).send().await?;
+    if !forecasts_resp.status().is_success() {
+        return Err(anyhow!(
+            "Failed to fetch forecasts for timeslot {}: HTTP {}",
+            timeslot,
+            forecasts_resp.status()
+        ));
+    }
+    let forecasts: Vec<ForecastSchema> = forecasts_resp.json().await?;
+
+    Ok(forecasts)
+}
+
+pub async fn fetch_imbalance_prices_for_timeslot(
+    base_url: &str,
+    timeslot: u64,
+    market_duration: u64,
+) -> Result<Vec<ImbalancePriceSchema>> {
+    let client = Client::new();
+
+    let (start_time, end_time) = timeslot_range(timeslot, market_duration);
+
+    let prices_url = format!("{}/imbalance-prices?start_time={}&end_time={}", base_url, start_time, end_time);
+    info!("Fetching imbalance prices for {}", prices_url);
+
+    let prices_resp = client.get(&prices_url).send().await?;
+    if !prices_resp.status().is_success() {
+        return Err(anyhow!(
+            "Failed to fetch imbalance prices for timeslot {}: HTTP {}",
+            timeslot,
+            prices_resp.status()
+        ));
+    }
+    let prices: Vec<ImbalancePriceSchema> = prices_resp.json().await?;
+
+    Ok(prices)
+}
+
+/// Store the statements, replacing the ones of the same account and period.
+pub async fn post_statements(base_url: &str, statements: &[SettlementStatementSchema]) -> Result<()> {
+    let statements_url = format!("{}/statements", base_url);
+    info!("Posting {} statements to {}", statements.len(), statements_url);
+
+    let statements_resp = Client::new().post(&statements_url).json(statements).send().await?;
+    if !statements_resp.status().is_success() {
+        return Err(anyhow!("Failed to post statements: HTTP {}", statements_resp.status()));
+    }
+    Ok(())
+}
+
+/// The statements of the given period starting between `start_time` and `end_time` (inclusive).
+pub async fn fetch_statements(
+    base_url: &str,
+    period: StatementPeriod,
+    start_time: u64,
+    end_time: u64,
+) -> Result<Vec<SettlementStatementSchema>> {
+    let statements_url = format!(
+        "{}/statements?period={}&start_time={}&end_time={}",
+        base_url,
+        period.as_str(),
+        start_time,
+        end_time,
+    );
+    info!("Fetching statements for {}", statements_url);
+
+    let statements_resp = Client::new().get(&statements_url).send().await?;
+    if !statements_resp.status().is_success() {
+        return Err(anyhow!("Failed to fetch statements: HTTP {}", statements_resp.status()));
+    }
+    let statements: Vec<SettlementStatementSchema> = statements_resp.json().await?;
+
+    Ok(statements)
+}
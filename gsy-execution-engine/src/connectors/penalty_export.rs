@@ -0,0 +1,84 @@
+use crate::primitives::penalty_calculator::Penalty;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+
+const CSV_HEADER: &str = "time_slot,penalized_account,area_uuid,market_id,trade_uuid,penalty_cost\n";
+
+/// The penalties of a dry run, written to a CSV or JSON file instead of being submitted.
+pub struct PenaltyExport {
+    path: PathBuf,
+    records: Vec<PenaltyRecord>,
+}
+
+#[derive(Serialize, Debug)]
+struct PenaltyRecord {
+    time_slot: u64,
+    #[serde(flatten)]
+    penalty: Penalty,
+}
+
+impl PenaltyExport {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, records: Vec::new() }
+    }
+
+    /// Add the penalties of `timeslot`, and rewrite the file with all the penalties of the run.
+    pub fn write(&mut self, timeslot: u64, penalties: Vec<Penalty>) -> Result<()> {
+        self.records.extend(
+            penalties.into_iter().map(|penalty| PenaltyRecord { time_slot: timeslot, penalty }),
+        );
+        let content = if self.path.extension().is_some_and(|extension| extension == "csv") {
+            self.to_csv()
+        } else {
+            serde_json::to_string_pretty(&self.records)?
+        };
+        std::fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write the penalties to {:?}", self.path))
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        for PenaltyRecord { time_slot, penalty } in &self.records {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                time_slot,
+                penalty.penalized_account,
+                penalty.area_uuid,
+                penalty.market_id,
+                penalty.trade_uuid,
+                penalty.penalty_cost,
+            ));
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn penalties_are_written_as_csv() {
+        let path = std::env::temp_dir().join(format!("penalties-{}.csv", std::process::id()));
+        let penalty = Penalty {
+            penalized_account: "account".to_string(),
+            area_uuid: "house".to_string(),
+            market_id: "market".to_string(),
+            trade_uuid: "t1".to_string(),
+            penalty_cost: 500,
+        };
+        let mut export = PenaltyExport::new(path.clone());
+        export.write(900, vec![penalty.clone()]).unwrap();
+        export.write(1_800, vec![penalty]).unwrap();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            csv,
+            "time_slot,penalized_account,area_uuid,market_id,trade_uuid,penalty_cost\n\
+             900,account,house,market,t1,500\n\
+             1800,account,house,market,t1,500\n"
+        );
+    }
+}
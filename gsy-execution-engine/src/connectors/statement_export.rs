@@ -0,0 +1,40 @@
+use anyhow::{Context, Result};
+use gsy_offchain_primitives::db_api_schema::statements::SettlementStatementSchema;
+use std::path::Path;
+
+const CSV_HEADER: &str = "account,period,period_start,period_end,energy_bought,energy_sold,\
+average_buy_rate,average_sell_rate,bought_value,sold_value,imbalance,penalties,net_amount\n";
+
+/// Write the statements to `path`, as CSV if its extension is `.csv` and as JSON otherwise.
+pub fn write_statements(path: &Path, statements: &[SettlementStatementSchema]) -> Result<()> {
+    let content = if path.extension().is_some_and(|extension| extension == "csv") {
+        to_csv(statements)
+    } else {
+        serde_json::to_string_pretty(statements)?
+    };
+    std::fs::write(path, content)
+        .with_context(|| format!("Failed to write the statements to {:?}", path))
+}
+
+fn to_csv(statements: &[SettlementStatementSchema]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    for statement in statements {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            statement.account,
+            statement.period.as_str(),
+            statement.period_start,
+            statement.period_end,
+            statement.energy_bought,
+            statement.energy_sold,
+            statement.average_buy_rate,
+            statement.average_sell_rate,
+            statement.bought_value,
+            statement.sold_value,
+            statement.imbalance,
+            statement.penalties,
+            statement.net_amount,
+        ));
+    }
+    csv
+}
@@ -3,50 +3,120 @@ use anyhow::{anyhow, Error, Result};
 use codec::{Decode, Encode};
 use std::str::FromStr;
 use subxt::{
+	error::DispatchError,
 	utils::{AccountId32, H256},
 	OnlineClient, SubstrateConfig,
 };
-use subxt_signer::sr25519::dev;
+use subxt_signer::sr25519::Keypair;
 use gsy_offchain_primitives::utils::string_to_h256;
-use tracing::info;
+use tracing::{info, warn};
 
 #[subxt::subxt(runtime_metadata_path = "../offchain-primitives/metadata.scale")]
 pub mod gsy_node {}
 
-pub async fn submit_penalties(node_url: &str, penalties: Vec<Penalty>) -> Result<(), Error> {
+/// Whether `signer` is registered as an exchange operator, which is required to submit penalties.
+pub async fn is_exchange_operator(node_url: &str, signer: &Keypair) -> Result<bool, Error> {
+	let api = OnlineClient::<SubstrateConfig>::from_insecure_url(node_url).await?;
+	let storage_address = gsy_node::storage()
+		.gsy_collateral()
+		.registered_exchange_operator(AccountId32::from(signer.public_key()));
+	Ok(api.storage().at_latest().await?.fetch(&storage_address).await?.is_some())
+}
+
+pub async fn submit_penalties(
+	node_url: &str,
+	signer: &Keypair,
+	penalties: Vec<Penalty>,
+) -> Result<(), Error> {
 	if penalties.is_empty() {
 		info!("No penalties to submit.");
 		return Ok(());
 	}
 
-	type NodeTradesPenalties =
-		gsy_node::runtime_types::gsy_primitives::trades::TradesPenalties<AccountId32, H256>;
-
+	// A penalty is never dropped: the timeslot fails, and is retried, until every penalized
+	// account can be parsed.
 	let node_penalties: Vec<NodeTradesPenalties> = penalties
 		.iter()
-		.filter_map(|p| {
-			let account = AccountId32::from_str(&p.penalized_account).ok()?;
+		.map(|p| {
+			let account = AccountId32::from_str(&p.penalized_account).map_err(|e| {
+				anyhow!(
+					"Invalid penalized account {} for trade {}: {:?}",
+					p.penalized_account,
+					p.trade_uuid,
+					e
+				)
+			})?;
 			let market_uuid = string_to_h256(p.market_id.clone());
 			let trade_uuid = string_to_h256(p.trade_uuid.clone());
 
-			Some(NodeTradesPenalties {
+			Ok(NodeTradesPenalties {
 				penalized_account: account,
 				market_uuid,
 				trade_uuid,
 				penalty_energy: p.penalty_cost,
 			})
 		})
-		.collect();
+		.collect::<Result<_, Error>>()?;
 
 	info!("Sending {} penalties to gsy-node.", node_penalties.len());
-	let penalty_extrinsic = gsy_node::tx().trades_settlement().submit_penalties(node_penalties);
+	let api = OnlineClient::<SubstrateConfig>::from_insecure_url(node_url).await?;
+	let max_penalties_per_batch = api
+		.constants()
+		.at(&gsy_node::constants().trades_settlement().max_penalties_per_batch())?;
+	for batch in node_penalties.chunks(max_penalties_per_batch as usize) {
+		submit_penalties_skipping_rejected(&api, signer, batch).await?;
+	}
+	Ok(())
+}
+
+/// Submit a batch of penalties, skipping the ones already submitted or expired.
+async fn submit_penalties_skipping_rejected(
+	api: &OnlineClient<SubstrateConfig>,
+	signer: &Keypair,
+	penalties: &[NodeTradesPenalties],
+) -> Result<(), Error> {
+	match submit_penalty_batch(api, signer, penalties.to_vec()).await {
+		Ok(()) => Ok(()),
+		// The whole batch is rejected if one of its penalties has already been submitted, or can
+		// no longer be submitted: the penalties are submitted one by one to skip only these.
+		Err(error) if penalty_rejection(&error).is_some() => {
+			info!("Penalty batch rejected ({:?}), submitting the penalties one by one.", error);
+			for penalty in penalties {
+				let Err(error) = submit_penalty_batch(api, signer, vec![penalty.clone()]).await
+				else {
+					continue;
+				};
+				match penalty_rejection(&error) {
+					Some(PenaltyRejection::AlreadySubmitted) => info!(
+						"Penalty of {:?} for trade {:?} already submitted, skipping.",
+						penalty.penalized_account, penalty.trade_uuid
+					),
+					Some(PenaltyRejection::Expired) => warn!(
+						"Penalty of {:?} for trade {:?} expired, skipping.",
+						penalty.penalized_account, penalty.trade_uuid
+					),
+					None => return Err(error.into()),
+				}
+			}
+			Ok(())
+		},
+		Err(error) => Err(error.into()),
+	}
+}
 
-	let signer = dev::alice();
+type NodeTradesPenalties =
+	gsy_node::runtime_types::gsy_primitives::trades::TradesPenalties<AccountId32, H256>;
 
-	let api = OnlineClient::<SubstrateConfig>::from_insecure_url(node_url).await?;
+/// Submit a batch of penalties in a single extrinsic, and wait for it to be finalized.
+async fn submit_penalty_batch(
+	api: &OnlineClient<SubstrateConfig>,
+	signer: &Keypair,
+	penalties: Vec<NodeTradesPenalties>,
+) -> Result<(), subxt::Error> {
+	let penalty_extrinsic = gsy_node::tx().trades_settlement().submit_penalties(penalties);
 	let tx_progress = api
 		.tx()
-		.sign_and_submit_then_watch_default(&penalty_extrinsic, &signer)
+		.sign_and_submit_then_watch_default(&penalty_extrinsic, signer)
 		.await?
 		.wait_for_finalized_success()
 		.await?;
@@ -58,6 +128,28 @@ pub async fn submit_penalties(node_url: &str, penalties: Vec<Penalty>) -> Result
 	} else {
 		info!("Penalty submission extrinsic finalized but event not found");
 	}
-
 	Ok(())
 }
+
+/// The rejections of a penalty by the runtime that leave nothing to submit.
+#[derive(Debug, PartialEq)]
+enum PenaltyRejection {
+	/// The account has already been penalized for the trade, possibly before the checkpoint of
+	/// the time slot was saved.
+	AlreadySubmitted,
+	/// The market of the penalty is older than the pruning horizon of the penalties.
+	Expired,
+}
+
+fn penalty_rejection(error: &subxt::Error) -> Option<PenaltyRejection> {
+	match error {
+		subxt::Error::Runtime(DispatchError::Module(module_error)) => {
+			match module_error.details().ok()?.variant.name.as_str() {
+				"PenaltyAlreadySubmitted" => Some(PenaltyRejection::AlreadySubmitted),
+				"PenaltyExpired" => Some(PenaltyRejection::Expired),
+				_ => None,
+			}
+		},
+		_ => None,
+	}
+}
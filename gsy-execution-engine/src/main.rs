@@ -4,11 +4,29 @@ mod connectors;
 mod utils;
 
 use clap::Parser;
-use tracing::{error, info};
-use utils::cli::{Cli, Commands};
+use tracing::{error, info, warn};
+use utils::cli::{Cli, Commands, ConnectionArgs, ExecutionArgs};
 use utils::telemetry::{get_subscriber, init_subscriber};
-use services::execution_orchestrator::run_execution_cycle;
-use gsy_offchain_primitives::{constants::GlobalConstants, utils::timestamp_to_datetime_string};
+use services::{
+    checkpoint::Checkpoint,
+    execution_orchestrator::{run_execution_cycle, ExecutionSettings},
+};
+use primitives::{
+    penalty_model::PenaltyConfig,
+    settlement_statement::{month_range, monthly_statements},
+};
+use connectors::{
+    offchain_storage::{fetch_statements, post_statements},
+    penalty_export::PenaltyExport,
+    statement_export::write_statements,
+    substrate_connector::is_exchange_operator,
+};
+use gsy_offchain_primitives::{
+    constants::GlobalConstants,
+    db_api_schema::statements::StatementPeriod,
+    signer::load_signer,
+    utils::timestamp_to_datetime_string,
+};
 
 #[tokio::main]
 async fn main() {
@@ -17,31 +35,200 @@ async fn main() {
 
     let cli = Cli::parse();
     match cli.command {
-        Commands::Web3 { 
-            offchain_host,
-            offchain_port,
-            node_host,
-            node_port,
+        Commands::Web3 {
+            connection,
             polling_interval,
-            market_duration,
-            penalty_rate
+            execution,
+            checkpoint_file,
         } => {
             info!("Starting engine...");
-            let offchain_url = format!("{}:{}", offchain_host, offchain_port);
-            let node_url = format!("{}:{}", node_host, node_port);
+            let (settings, mut export) = execution_settings(connection, execution);
+            wait_for_registration(&settings).await;
+            let mut checkpoint = if export.is_some() {
+                Checkpoint::in_memory()
+            } else {
+                match Checkpoint::load(&checkpoint_file) {
+                    Ok(checkpoint) => checkpoint,
+                    Err(e) => {
+                        error!("{:?}", e);
+                        std::process::exit(1);
+                    }
+                }
+            };
+            if let Some(timeslot) = checkpoint.last_processed_timeslot() {
+                info!("Resuming after timeslot {} ({})",
+                    timestamp_to_datetime_string(timeslot), timeslot);
+            }
 
             loop {
-                let timeslot = generate_previous_timeslot(market_duration);
-                info!("Execution cycle for timeslot {} ({})",
-                    timestamp_to_datetime_string(timeslot), timeslot);
-                if let Err(e) = run_execution_cycle(
-                    &offchain_url, &node_url, timeslot, penalty_rate, market_duration).await {
-                    error!("Cycle failed for {}: {:?}", timeslot, e);
+                // The areas deferred until their measurement arrives are re-evaluated first.
+                for (timeslot, areas) in checkpoint.deferred_timeslots() {
+                    info!("Re-evaluating {} areas of timeslot {} ({})",
+                        areas.len(), timestamp_to_datetime_string(timeslot), timeslot);
+                    match run_execution_cycle(&settings, timeslot, Some(areas.as_slice()), export.as_mut()).await {
+                        Ok(deferred_areas) => {
+                            if let Err(e) = checkpoint.save_deferred(timeslot, deferred_areas) {
+                                error!("{:?}", e);
+                            }
+                        }
+                        Err(e) => error!("Re-evaluation failed for {}: {:?}", timeslot, e),
+                    }
+                }
+
+                let latest_timeslot = generate_previous_timeslot(settings.market_duration);
+                // The timeslots are processed in order, and a failed timeslot is retried at the
+                // next poll before the following ones.
+                for timeslot in checkpoint.pending_timeslots(latest_timeslot, GlobalConstants.TIME_SLOT_SEC) {
+                    info!("Execution cycle for timeslot {} ({})",
+                        timestamp_to_datetime_string(timeslot), timeslot);
+                    let deferred_areas = match run_execution_cycle(&settings, timeslot, None, export.as_mut()).await {
+                        Ok(deferred_areas) => deferred_areas,
+                        Err(e) => {
+                            error!("Cycle failed for {}: {:?}", timeslot, e);
+                            break;
+                        }
+                    };
+                    if let Err(e) = checkpoint.save(timeslot, deferred_areas) {
+                        error!("{:?}", e);
+                        break;
+                    }
                 }
                 info!("Sleeping for {}s...", polling_interval);
                 tokio::time::sleep(std::time::Duration::from_secs(polling_interval)).await;
             }
         }
+        Commands::Backfill {
+            connection,
+            from,
+            to,
+            execution,
+        } => {
+            // The penalties of the historical timeslots are only audited: submitting them again
+            // would bypass the checkpoint of the live engine.
+            if !execution.dry_run {
+                warn!("Backfill always runs as a dry run, the penalties are not submitted");
+            }
+            let execution = ExecutionArgs { dry_run: true, ..execution };
+            let (settings, mut export) = execution_settings(connection, execution);
+            let first_timeslot = from / GlobalConstants.TIME_SLOT_SEC * GlobalConstants.TIME_SLOT_SEC;
+            info!("Backfilling timeslots {} to {}",
+                timestamp_to_datetime_string(first_timeslot), timestamp_to_datetime_string(to));
+
+            let mut failed_timeslots = Vec::new();
+            for timeslot in (first_timeslot..=to).step_by(GlobalConstants.TIME_SLOT_SEC as usize) {
+                info!("Execution cycle for timeslot {} ({})",
+                    timestamp_to_datetime_string(timeslot), timeslot);
+                match run_execution_cycle(&settings, timeslot, None, export.as_mut()).await {
+                    Ok(deferred_areas) if !deferred_areas.is_empty() => {
+                        info!("{} areas of {} are still awaiting their measurement",
+                            deferred_areas.len(), timeslot);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        error!("Cycle failed for {}: {:?}", timeslot, e);
+                        failed_timeslots.push(timeslot);
+                    }
+                }
+            }
+            if !failed_timeslots.is_empty() {
+                error!("Backfill failed for the timeslots {:?}", failed_timeslots);
+                std::process::exit(1);
+            }
+            info!("Backfill completed");
+        }
+        Commands::Statements {
+            connection,
+            month,
+            output,
+        } => {
+            let offchain_url = format!("{}:{}", connection.offchain_host, connection.offchain_port);
+            if let Err(e) = build_monthly_statements(&offchain_url, &month, &output).await {
+                error!("Statements failed for {}: {:?}", month, e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Aggregate the slot statements of `month`, store the monthly statements and write all of them
+/// to `output`.
+async fn build_monthly_statements(
+    offchain_url: &str,
+    month: &str,
+    output: &std::path::Path,
+) -> anyhow::Result<()> {
+    let (month_start, month_end) = month_range(month)?;
+    let mut statements = fetch_statements(offchain_url, StatementPeriod::Slot, month_start, month_end - 1).await?;
+    let monthly = monthly_statements(&statements, month_start, month_end, chrono::Utc::now().timestamp() as u64);
+    info!("Aggregated {} slot statements into {} monthly statements",
+        statements.len(), monthly.len());
+    post_statements(offchain_url, &monthly).await?;
+
+    statements.extend(monthly);
+    write_statements(output, &statements)?;
+    info!("Statements written to {:?}", output);
+    Ok(())
+}
+
+/// The settings of the execution cycles, and the export of the penalties in a dry run.
+fn execution_settings(
+    connection: ConnectionArgs,
+    execution: ExecutionArgs,
+) -> (ExecutionSettings, Option<PenaltyExport>) {
+    let penalty_config = match execution.penalty_config {
+        Some(path) => match PenaltyConfig::from_file(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("{:?}", e);
+                std::process::exit(1);
+            }
+        },
+        None => PenaltyConfig::default(),
+    };
+    let (export, signer) = if execution.dry_run {
+        info!("Dry run: the penalties are written to {:?} instead of being submitted", execution.output);
+        (Some(PenaltyExport::new(execution.output)), None)
+    } else {
+        match load_signer() {
+            Ok(signer) => (None, Some(signer)),
+            Err(e) => {
+                error!("Failed to load the signer: {:?}", e);
+                std::process::exit(1);
+            }
+        }
+    };
+    let settings = ExecutionSettings {
+        offchain_url: format!("{}:{}", connection.offchain_host, connection.offchain_port),
+        node_url: format!("{}:{}", connection.node_host, connection.node_port),
+        market_duration: execution.market_duration,
+        trades_from_node: execution.trades_from_node,
+        penalty_config,
+        penalty_rate: execution.penalty_rate,
+        missing_measurements: execution.missing_measurements,
+        non_reporting_penalty: execution.non_reporting_penalty,
+        measurement_deadline: execution.measurement_deadline,
+        flexibility_baseline: execution.flexibility_baseline,
+        baseline_days: execution.baseline_days,
+        signer,
+    };
+    (settings, export)
+}
+
+/// Wait until the signer of the penalties is registered as an exchange operator.
+async fn wait_for_registration(settings: &ExecutionSettings) {
+    let Some(signer) = settings.signer.as_ref() else {
+        return;
+    };
+    loop {
+        match is_exchange_operator(&settings.node_url, signer).await {
+            Ok(true) => {
+                info!("Signer account is registered as exchange operator.");
+                return;
+            }
+            Ok(false) => warn!("Signer account not yet registered as exchange operator. Retrying in 10 seconds..."),
+            Err(e) => error!("Error checking registration status: {:?}. Retrying in 10 seconds...", e),
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
     }
 }
 
@@ -51,6 +238,10 @@ fn generate_previous_timeslot(market_duration: u64) -> u64 {
     let now = Utc::now();
 
     let prev = now - Duration::minutes(GlobalConstants.EXECUTION_ENGINE_OFFSET_MIN);
+    // A slot is processed once its settlement market has closed, so that the deviations traded in
+    // it are netted before the penalties are computed.
+    let settled = now - Duration::minutes(GlobalConstants.SETTLEMENT_MARKET_CLOSE_OFFSET_MIN);
+    let prev = prev.min(settled);
 
     (prev.timestamp() as u64 / GlobalConstants.TIME_SLOT_SEC) * GlobalConstants.TIME_SLOT_SEC
 }
@@ -0,0 +1,400 @@
+use crate::primitives::{
+	penalty_calculator::{
+		is_topology_measurement, push_allocated_penalties, scale_cost, MissingMeasurement, Penalty,
+	},
+	penalty_model::PenaltyModels,
+};
+use gsy_offchain_primitives::{
+	db_api_schema::{
+		profiles::{ForecastSchema, MeasurementSchema},
+		trades::TradeSchema,
+	},
+	MarketType,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tracing::{info, warn};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The outcome of the verification of the flexibility activated in a slot.
+#[derive(Debug, Default)]
+pub struct FlexibilityReport {
+	/// The non-delivery penalties of the flexibility trades.
+	pub penalties: Vec<Penalty>,
+	/// The flexibility delivered by each area, keyed by area hash and slot, in kWh.
+	pub delivered: HashMap<(String, u64), f64>,
+	/// The activated areas which lacked a measurement.
+	pub missing_areas: Vec<String>,
+}
+
+/// Whether the trade was made in the flexibility market of its slot.
+pub fn is_flexibility_trade(trade: &TradeSchema) -> bool {
+	MarketType::of_market(&trade.market_id, trade.time_slot) == Some(MarketType::Flexibility)
+}
+
+/// The baseline of each area in `time_slot`, keyed by area uuid: its average measurement at the
+/// same time of the previous `days` days. The days without measurement of the area are left out
+/// of its average.
+pub fn previous_days_baselines(
+	history: &[MeasurementSchema],
+	time_slot: u64,
+	days: u64,
+) -> HashMap<String, f64> {
+	let previous_slots: Vec<u64> = (1..=days)
+		.filter_map(|day| time_slot.checked_sub(day * SECONDS_PER_DAY))
+		.collect();
+	let mut daily_energy: HashMap<(&str, u64), f64> = HashMap::new();
+	for measurement in history.iter().filter(|m| previous_slots.contains(&m.time_slot)) {
+		*daily_energy
+			.entry((measurement.area_uuid.as_str(), measurement.time_slot))
+			.or_default() += measurement.energy_kwh;
+	}
+
+	let mut totals: HashMap<String, (f64, f64)> = HashMap::new();
+	for ((area_uuid, _), energy) in daily_energy {
+		let (total, count) = totals.entry(area_uuid.to_string()).or_default();
+		*total += energy;
+		*count += 1.0;
+	}
+	totals
+		.into_iter()
+		.map(|(area_uuid, (total, count))| (area_uuid, total / count))
+		.collect()
+}
+
+/// The baseline of each area in `time_slot`, keyed by area uuid: the last forecast of the area
+/// submitted before `gate_closure`, the closing time of the flexibility market.
+pub fn forecast_baselines(
+	forecasts: &[ForecastSchema],
+	time_slot: u64,
+	gate_closure: u64,
+) -> HashMap<String, f64> {
+	let mut latest: HashMap<&str, &ForecastSchema> = HashMap::new();
+	for forecast in forecasts
+		.iter()
+		.filter(|f| f.time_slot == time_slot && f.creation_time <= gate_closure)
+	{
+		let entry = latest.entry(forecast.area_uuid.as_str()).or_insert(forecast);
+		if forecast.creation_time > entry.creation_time {
+			*entry = forecast;
+		}
+	}
+	latest
+		.into_iter()
+		.map(|(area_uuid, forecast)| (area_uuid.to_string(), forecast.energy_kwh))
+		.collect()
+}
+
+/// Verifies the delivery of the flexibility sold in the flexibility markets.
+///
+/// The area of the Offer of a flexibility trade commits to deviate from its baseline by the
+/// selected energy of the trade, so each activated area is expected to consume:
+///   expected_energy = baseline - activated_flexibility
+/// The measured energy is positive for consumption and negative for production, so a producer
+/// delivers its flexibility by producing more. The flexibility not delivered,
+///   measured_energy - expected_energy
+/// capped to the activated flexibility, is priced with the penalty model of the flexibility
+/// market type and allocated across the flexibility trades of the area. Delivering more than the
+/// activated flexibility is not penalized.
+///
+/// The areas without baseline are not verified. The areas without measurement are reported, and
+/// penalized according to `missing_measurement`, a zero delivery being the non-delivery of the
+/// whole activation.
+///
+/// # Arguments
+///
+/// * `trades` - The trades of the slot, of which only the flexibility trades are verified.
+/// * `measurements` - The measurements of the slot.
+/// * `baselines` - The baseline of each area, keyed by area uuid.
+/// * `penalty_models` - The penalty model of each market type.
+/// * `missing_measurement` - How the areas without measurement are penalized.
+pub fn verify_flexibility(
+	trades: &[TradeSchema],
+	measurements: &[MeasurementSchema],
+	baselines: &HashMap<String, f64>,
+	penalty_models: &PenaltyModels,
+	missing_measurement: MissingMeasurement,
+) -> FlexibilityReport {
+	let mut report = FlexibilityReport::default();
+	let mut missing_areas = BTreeSet::new();
+
+	let mut measurement_map: HashMap<(&str, u64), (&str, f64)> = HashMap::new();
+	for meas in measurements.iter().filter(|m| is_topology_measurement(m)) {
+		let (_, energy) = measurement_map
+			.entry((meas.area_hash.as_str(), meas.time_slot))
+			.or_insert((meas.area_uuid.as_str(), 0.0));
+		*energy += meas.energy_kwh;
+	}
+
+	// The flexibility trades of each activated area, account and slot.
+	let mut activations: BTreeMap<(&str, &str, u64), Vec<&TradeSchema>> = BTreeMap::new();
+	for trade in trades.iter().filter(|trade| is_flexibility_trade(trade)) {
+		activations
+			.entry((
+				trade.offer.offer_component.area_uuid.as_str(),
+				trade.seller.as_str(),
+				trade.time_slot,
+			))
+			.or_default()
+			.push(trade);
+	}
+
+	for ((area, account, time_slot), area_trades) in activations {
+		let activated: f64 = area_trades.iter().map(|trade| trade.parameters.selected_energy).sum();
+		let non_delivered = match measurement_map.get(&(area, time_slot)) {
+			Some((area_uuid, measured_energy)) => {
+				let Some(baseline) = baselines.get(*area_uuid) else {
+					warn!("No baseline for area {}, its flexibility is not verified", area_uuid);
+					continue;
+				};
+				let delivered = (baseline - measured_energy).clamp(0.0, activated);
+				report.delivered.insert((area.to_string(), time_slot), delivered);
+				activated - delivered
+			},
+			None => {
+				missing_areas.insert(area.to_string());
+				match missing_measurement {
+					MissingMeasurement::Defer => continue,
+					MissingMeasurement::ZeroDelivery => activated,
+					MissingMeasurement::NonReportingPenalty(cost) => {
+						push_flexibility_penalties(
+							&mut report.penalties,
+							account,
+							area,
+							&area_trades,
+							cost,
+						);
+						continue;
+					},
+				}
+			},
+		};
+		if non_delivered <= 0.0 {
+			continue;
+		}
+		info!(
+			"Area {} of {} did not deliver {} of {} kWh of flexibility in slot {}",
+			area, account, non_delivered, activated, time_slot
+		);
+		let cost = penalty_models.for_market(Some(MarketType::Flexibility)).penalty(
+			non_delivered,
+			activated,
+			time_slot,
+		);
+		push_flexibility_penalties(&mut report.penalties, account, area, &area_trades, cost);
+	}
+
+	report.missing_areas = missing_areas.into_iter().collect();
+	report
+}
+
+/// The measurements with the delivered flexibility added back, so that the imbalance of an area
+/// against its other trades does not include the flexibility it delivered.
+pub fn corrected_measurements(
+	measurements: &[MeasurementSchema],
+	delivered: &HashMap<(String, u64), f64>,
+) -> Vec<MeasurementSchema> {
+	// The delivered flexibility is added once, to the first measurement of the area in the slot.
+	let mut corrected_areas = BTreeSet::new();
+	measurements
+		.iter()
+		.map(|measurement| {
+			let mut measurement = measurement.clone();
+			let key = (measurement.area_hash.clone(), measurement.time_slot);
+			if is_topology_measurement(&measurement) {
+				if let Some(delivered) = delivered.get(&key) {
+					if corrected_areas.insert(key) {
+						measurement.energy_kwh += delivered;
+					}
+				}
+			}
+			measurement
+		})
+		.collect()
+}
+
+fn push_flexibility_penalties(
+	penalties: &mut Vec<Penalty>,
+	account: &str,
+	area: &str,
+	trades: &[&TradeSchema],
+	cost: f64,
+) {
+	let trades: Vec<(&TradeSchema, f64)> =
+		trades.iter().map(|trade| (*trade, trade.parameters.selected_energy)).collect();
+	push_allocated_penalties(penalties, account, area, &trades, scale_cost(cost));
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::primitives::penalty_calculator::{
+		compute_penalties, topology_market_id, PenaltyReport,
+	};
+	use gsy_offchain_primitives::test_utils::TradeFixture;
+
+	const TIME_SLOT: u64 = 1_700_000_100;
+
+	/// A trade of `energy` kWh from the area `seller` to the area `buyer` in the market of
+	/// `market_type`, each area being owned by the account of the same name.
+	fn trade(
+		trade_uuid: &str,
+		seller: &str,
+		buyer: &str,
+		energy: f64,
+		market_type: MarketType,
+	) -> TradeSchema {
+		TradeFixture::new(trade_uuid, seller, buyer, energy)
+			.time_slot(TIME_SLOT)
+			.in_market(market_type)
+			.build()
+	}
+
+	fn flexibility(trade_uuid: &str, area: &str, energy: f64) -> TradeSchema {
+		trade(trade_uuid, area, "dso", energy, MarketType::Flexibility)
+	}
+
+	fn measurement(area: &str, time_slot: u64, energy_kwh: f64) -> MeasurementSchema {
+		MeasurementSchema {
+			area_uuid: area.to_string(),
+			area_hash: area.to_string(),
+			market_id: topology_market_id(time_slot),
+			community_uuid: "community".to_string(),
+			time_slot,
+			creation_time: time_slot,
+			energy_kwh,
+		}
+	}
+
+	fn forecast(area: &str, creation_time: u64, energy_kwh: f64) -> ForecastSchema {
+		ForecastSchema {
+			area_uuid: area.to_string(),
+			area_hash: area.to_string(),
+			market_id: topology_market_id(TIME_SLOT),
+			community_uuid: "community".to_string(),
+			time_slot: TIME_SLOT,
+			creation_time,
+			energy_kwh,
+			confidence: 1.0,
+		}
+	}
+
+	fn baselines(baselines: &[(&str, f64)]) -> HashMap<String, f64> {
+		baselines.iter().map(|(area, energy)| (area.to_string(), *energy)).collect()
+	}
+
+	fn verify(
+		trades: &[TradeSchema],
+		measurements: &[MeasurementSchema],
+		baselines: &HashMap<String, f64>,
+		missing_measurement: MissingMeasurement,
+	) -> FlexibilityReport {
+		verify_flexibility(
+			trades,
+			measurements,
+			baselines,
+			&PenaltyModels::linear(0.1),
+			missing_measurement,
+		)
+	}
+
+	fn penalty_costs(penalties: &[Penalty]) -> Vec<(&str, u64)> {
+		penalties
+			.iter()
+			.map(|penalty| (penalty.trade_uuid.as_str(), penalty.penalty_cost))
+			.collect()
+	}
+
+	#[test]
+	fn previous_days_baseline_averages_the_same_slot_of_the_previous_days() {
+		let history = [
+			measurement("house", TIME_SLOT - SECONDS_PER_DAY, 1.0),
+			measurement("house", TIME_SLOT - SECONDS_PER_DAY, 3.0),
+			measurement("house", TIME_SLOT - 2 * SECONDS_PER_DAY, 6.0),
+			measurement("house", TIME_SLOT - SECONDS_PER_DAY + 900, 100.0),
+			measurement("house", TIME_SLOT - 4 * SECONDS_PER_DAY, 100.0),
+		];
+
+		// The third day has no measurement, and the fourth is out of the 3 days.
+		assert_eq!(previous_days_baselines(&history, TIME_SLOT, 3), baselines(&[("house", 5.0)]));
+	}
+
+	#[test]
+	fn forecast_baseline_is_the_last_forecast_before_gate_closure() {
+		let forecasts = [
+			forecast("house", TIME_SLOT - 3_600, 5.0),
+			forecast("house", TIME_SLOT - 600, 6.0),
+			forecast("house", TIME_SLOT + 60, 9.0),
+		];
+
+		assert_eq!(
+			forecast_baselines(&forecasts, TIME_SLOT, TIME_SLOT),
+			baselines(&[("house", 6.0)])
+		);
+	}
+
+	#[test]
+	fn undelivered_flexibility_is_penalized() {
+		// The house sold 2 + 1 kWh of flexibility with a baseline of 10 kWh, so it should have
+		// consumed 7 kWh, but consumed 8 kWh. The PV produced 1.5 kWh more than its baseline for
+		// 1 kWh of flexibility, which is not penalized.
+		let trades = [
+			flexibility("t1", "house", 2.0),
+			flexibility("t2", "house", 1.0),
+			flexibility("t3", "pv", 1.0),
+		];
+		let measurements =
+			[measurement("house", TIME_SLOT, 8.0), measurement("pv", TIME_SLOT, -4.5)];
+
+		let report = verify(
+			&trades,
+			&measurements,
+			&baselines(&[("house", 10.0), ("pv", -3.0)]),
+			MissingMeasurement::Defer,
+		);
+
+		// 1 kWh * 0.1 * 10,000 = 1,000, allocated as 2/3 and 1/3 of the activated flexibility.
+		assert_eq!(penalty_costs(&report.penalties), vec![("t1", 667), ("t2", 333)]);
+		assert_eq!(report.delivered[&("house".to_string(), TIME_SLOT)], 2.0);
+		assert_eq!(report.delivered[&("pv".to_string(), TIME_SLOT)], 1.0);
+	}
+
+	#[test]
+	fn delivered_flexibility_is_not_an_imbalance() {
+		// The house bought 10 kWh in the spot market and sold 2 kWh of flexibility, which it
+		// delivered by consuming 8 kWh.
+		let trades =
+			[trade("t1", "grid", "house", 10.0, MarketType::Spot), flexibility("t2", "house", 2.0)];
+		let measurements =
+			[measurement("house", TIME_SLOT, 8.0), measurement("grid", TIME_SLOT, -10.0)];
+
+		let flexibility = verify(
+			&trades,
+			&measurements,
+			&baselines(&[("house", 10.0)]),
+			MissingMeasurement::Defer,
+		);
+		let PenaltyReport { penalties, .. } = compute_penalties(
+			&trades,
+			&corrected_measurements(&measurements, &flexibility.delivered),
+			&PenaltyModels::linear(0.1),
+			MissingMeasurement::Defer,
+		);
+
+		assert!(flexibility.penalties.is_empty());
+		assert!(penalties.is_empty());
+	}
+
+	#[test]
+	fn activated_areas_without_measurement_are_deferred_or_not_delivered() {
+		let trades = [flexibility("t1", "house", 2.0)];
+		let baselines = baselines(&[("house", 10.0)]);
+
+		let deferred = verify(&trades, &[], &baselines, MissingMeasurement::Defer);
+		assert!(deferred.penalties.is_empty());
+		assert_eq!(deferred.missing_areas, vec!["house"]);
+
+		let zero_delivery = verify(&trades, &[], &baselines, MissingMeasurement::ZeroDelivery);
+		assert_eq!(penalty_costs(&zero_delivery.penalties), vec![("t1", 2_000)]);
+	}
+}
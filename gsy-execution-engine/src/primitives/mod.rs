@@ -1 +1,4 @@
-pub mod penalty_calculator;
\ No newline at end of file
+pub mod flexibility_verification;
+pub mod penalty_calculator;
+pub mod penalty_model;
+pub mod settlement_statement;
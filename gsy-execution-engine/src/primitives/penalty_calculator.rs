@@ -1,90 +1,570 @@
-use gsy_offchain_primitives::db_api_schema::{profiles::MeasurementSchema, trades::TradeSchema};
-use std::collections::HashMap;
+use crate::primitives::{
+	flexibility_verification::is_flexibility_trade, penalty_model::PenaltyModels,
+};
+use gsy_offchain_primitives::{
+	db_api_schema::{profiles::MeasurementSchema, trades::TradeSchema},
+	utils::h256_to_string,
+	MarketType,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use tracing::info;
 
-#[derive(Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct Penalty {
 	pub penalized_account: String,
+	/// The area of the account whose imbalance is penalized.
+	pub area_uuid: String,
 	pub market_id: String,
 	pub trade_uuid: String,
 	pub penalty_cost: u64,
 }
 
-/// Computes penalties for each trade based on the measured energy.
+/// The penalty costs are scaled by this factor and converted to integers.
+pub const PENALTY_SCALING_FACTOR: f64 = 10_000.0;
+
+/// How the trades of an area without measurement in a slot are penalized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MissingMeasurement {
+	/// Not penalized, until the measurement of the area arrives.
+	Defer,
+	/// Penalized as if the area had neither consumed nor produced energy.
+	ZeroDelivery,
+	/// Penalized with a flat non-reporting penalty, in the unit of the energy rates.
+	NonReportingPenalty(f64),
+}
+
+/// The imbalance of an evaluated area of an account in a slot, in kWh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AreaImbalance {
+	pub account: String,
+	pub area_uuid: String,
+	pub time_slot: u64,
+	pub imbalance: f64,
+}
+
+/// The penalties of a slot, the imbalances they penalize, and the areas which lacked a
+/// measurement.
+#[derive(Debug, Default)]
+pub struct PenaltyReport {
+	pub penalties: Vec<Penalty>,
+	pub imbalances: Vec<AreaImbalance>,
+	pub missing_areas: Vec<String>,
+}
+
+/// The energy traded by an area of an account in a slot.
+#[derive(Default)]
+struct AreaPosition<'a> {
+	/// Energy bought minus energy sold, in kWh.
+	net_traded_energy: f64,
+	/// The trades of the area, each with the energy it traded.
+	trades: Vec<(&'a TradeSchema, f64)>,
+}
+
+/// Computes penalties for the trades based on the measured energy.
+///
+/// The trades are aggregated per area, account and slot: the area of the Bid buys the selected
+/// energy of the trade and the area of the Offer sells it. The measurements are joined with the
+/// areas on the market and slot of their trades, and each area with a measurement then has one
+/// imbalance:
+///   imbalance = measured_energy - (bought_energy - sold_energy)
+/// The measured energy is positive for consumption and negative for production, so a positive
+/// imbalance means the area consumed more or produced less than it traded, and a negative one that
+/// it consumed less or produced more. The account of the area is penalized for the imbalance, and
+/// the penalty is allocated across the trades of the area in proportion to their selected energy.
 ///
-/// For each trade, the measurement is looked up (by using the area_uuid and market_id from the Bid).
-/// The delta is computed as:
-///   delta = measured_energy - traded_energy
-/// If delta > 0.0, it indicates under-trading for consumption and the buyer is penalized.
-/// If delta < 0.0, it indicates under-trading for production and the seller is penalized.
+/// The trades of each market type are priced with the penalty model of that market type, on their
+/// share of the imbalance. The settlement-market trades, made after delivery to trade the
+/// measured deviations, are netted with the other trades of their area, so the imbalance is what
+/// remains of the deviation after the settlement market.
+///
+/// The areas without measurement are reported, and penalized according to `missing_measurement`.
+///
+/// The flexibility trades are verified against the baselines of their areas instead, by
+/// `verify_flexibility`, and the measurements are expected to be corrected by the flexibility
+/// their areas delivered.
 ///
 /// # Arguments
 ///
 /// * `trades` - A slice of TradeSchema records.
 /// * `measurements` - A slice of MeasurementSchema records.
-/// * `penalty_rate` - The penalty rate as a f64 (e.g., 0.10 for 10%).
+/// * `penalty_models` - The penalty model of each market type.
+/// * `missing_measurement` - How the areas without measurement are penalized.
 ///
 /// # Returns
 ///
-/// A vector of Penalty structs.
+/// A PenaltyReport, with at most one penalty per trade of each penalized area.
 pub fn compute_penalties(
 	trades: &[TradeSchema],
 	measurements: &[MeasurementSchema],
-	penalty_rate: f64,
-) -> Vec<Penalty> {
+	penalty_models: &PenaltyModels,
+	missing_measurement: MissingMeasurement,
+) -> PenaltyReport {
 	let mut penalties = Vec::new();
+	let mut imbalances = Vec::new();
+	let mut missing_areas = BTreeSet::new();
 
-	// Create a lookup map for measurements by area_uuid
-	// TODO: temporarily use only the area_hash for identifying measurements. Should be improved
-	// by adding market_id in the measurements, and use this too for identification.
-	let mut measurement_map: HashMap<String, f64> = HashMap::new();
+	// The measured energy of each area in each market and slot. The area_hash of the measurements
+	// identifies the same areas as the area_uuid of the orders, but only within the topology of
+	// one market. The measurements without market_id, stored before it was added, are only
+	// identified by their area_hash and slot.
+	let mut measurement_map: HashMap<(&str, &str, u64), f64> = HashMap::new();
+	let mut unscoped_measurement_map: HashMap<(&str, u64), f64> = HashMap::new();
 	for meas in measurements {
-		measurement_map.insert(
-			meas.area_hash.clone(),
-			meas.energy_kwh, // energy is f64; positive means consumption, negative means production
+		if meas.market_id.is_empty() {
+			*unscoped_measurement_map
+				.entry((meas.area_hash.as_str(), meas.time_slot))
+				.or_default() += meas.energy_kwh;
+		} else {
+			*measurement_map
+				.entry((meas.market_id.as_str(), meas.area_hash.as_str(), meas.time_slot))
+				.or_default() += meas.energy_kwh;
+		}
+	}
+
+	// The traded energy of each area, account and slot. A BTreeMap keeps the penalties in a
+	// deterministic order.
+	let mut positions: BTreeMap<(&str, &str, u64), AreaPosition> = BTreeMap::new();
+	for trade in trades.iter().filter(|trade| !is_flexibility_trade(trade)) {
+		let traded_energy = trade.parameters.selected_energy;
+
+		let buyer_area = positions
+			.entry((
+				trade.bid.bid_component.area_uuid.as_str(),
+				trade.buyer.as_str(),
+				trade.time_slot,
+			))
+			.or_default();
+		buyer_area.net_traded_energy += traded_energy;
+		buyer_area.trades.push((trade, traded_energy));
+
+		let seller_area = positions
+			.entry((
+				trade.offer.offer_component.area_uuid.as_str(),
+				trade.seller.as_str(),
+				trade.time_slot,
+			))
+			.or_default();
+		seller_area.net_traded_energy -= traded_energy;
+		seller_area.trades.push((trade, traded_energy));
+	}
+
+	for ((area, account, time_slot), position) in positions {
+		// The area is measured in the market of one of its trades, or in the topology of the slot,
+		// or without market.
+		let topology_market = topology_market_id(time_slot);
+		let measured_energy = position
+			.trades
+			.iter()
+			.map(|(trade, _)| trade.market_id.as_str())
+			.chain([topology_market.as_str()])
+			.find_map(|market_id| measurement_map.get(&(market_id, area, time_slot)).copied())
+			.or_else(|| unscoped_measurement_map.get(&(area, time_slot)).copied());
+		let measured_energy = match measured_energy {
+			Some(measured_energy) => measured_energy,
+			None => {
+				missing_areas.insert(area.to_string());
+				match missing_measurement {
+					MissingMeasurement::Defer => continue,
+					MissingMeasurement::ZeroDelivery => 0.0,
+					MissingMeasurement::NonReportingPenalty(cost) => {
+						push_allocated_penalties(
+							&mut penalties,
+							account,
+							area,
+							&position.trades,
+							scale_cost(cost),
+						);
+						continue;
+					},
+				}
+			},
+		};
+		let imbalance = measured_energy - position.net_traded_energy;
+		if imbalance == 0.0 {
+			continue;
+		}
+		imbalances.push(AreaImbalance {
+			account: account.to_string(),
+			area_uuid: area.to_string(),
+			time_slot,
+			imbalance,
+		});
+		info!(
+			"Area {} of {} has an imbalance of {} kWh in slot {} over {} trades",
+			area,
+			account,
+			imbalance,
+			time_slot,
+			position.trades.len()
 		);
+
+		// The trades of the area, grouped by the type of their market.
+		let mut market_groups: Vec<(Option<MarketType>, Vec<(&TradeSchema, f64)>)> = Vec::new();
+		for &(trade, energy) in &position.trades {
+			let market_type = MarketType::of_market(&trade.market_id, trade.time_slot);
+			match market_groups.iter_mut().find(|(group_type, _)| *group_type == market_type) {
+				Some((_, group)) => group.push((trade, energy)),
+				None => market_groups.push((market_type, vec![(trade, energy)])),
+			}
+		}
+		let total_energy: f64 = position.trades.iter().map(|(_, energy)| energy).sum();
+		let group_count = market_groups.len() as f64;
+
+		for (market_type, group) in market_groups {
+			let group_energy: f64 = group.iter().map(|(_, energy)| energy).sum();
+			let group_imbalance = if total_energy > 0.0 {
+				imbalance * group_energy / total_energy
+			} else {
+				imbalance / group_count
+			};
+			let cost = penalty_models.for_market(market_type).penalty(
+				group_imbalance,
+				group_energy,
+				time_slot,
+			);
+
+			push_allocated_penalties(&mut penalties, account, area, &group, scale_cost(cost));
+		}
 	}
 
-	// Iterate over each trade and compute the penalty if a measurement exists.
-	for trade in trades {
-		// For consumers, we use the Bid's area and market.
+	PenaltyReport { penalties, imbalances, missing_areas: missing_areas.into_iter().collect() }
+}
 
-		if let Some(&measured_energy) = measurement_map.get(&trade.bid.bid_component.area_uuid.clone()) {
-			let traded_energy = trade.parameters.selected_energy;
+/// The markets of a slot share the topology of its spot market, in which the measurements of the
+/// slot are reported.
+pub(crate) fn topology_market_id(time_slot: u64) -> String {
+	h256_to_string(MarketType::Spot.market_id(time_slot))
+}
 
-			// Compute delta = measured_energy - traded_energy.
-			let delta = measured_energy - traded_energy;
+/// Whether the measurement is reported in the topology of its slot. The measurements without
+/// market_id, stored before it was added, are.
+pub(crate) fn is_topology_measurement(measurement: &MeasurementSchema) -> bool {
+	measurement.market_id.is_empty() ||
+		measurement.market_id == topology_market_id(measurement.time_slot)
+}
 
-			if delta > 0.0 {
-				// This is a consumption trade: measured energy exceeds traded energy.
-				// Penalize the buyer.
+/// Scale and convert to u64: apply the scaling factor of 10,000.
+pub(crate) fn scale_cost(cost: f64) -> u64 {
+	(cost * PENALTY_SCALING_FACTOR).round() as u64
+}
 
-				let raw_penalty = delta * penalty_rate;
+/// Allocates the penalty of an area across its trades, in proportion to their energy.
+pub(crate) fn push_allocated_penalties(
+	penalties: &mut Vec<Penalty>,
+	account: &str,
+	area: &str,
+	trades: &[(&TradeSchema, f64)],
+	penalty_cost: u64,
+) {
+	if penalty_cost == 0 {
+		return;
+	}
+	let energies: Vec<f64> = trades.iter().map(|(_, energy)| *energy).collect();
+	for ((trade, _), penalty_cost) in trades.iter().zip(allocate_pro_rata(penalty_cost, &energies))
+	{
+		if penalty_cost > 0 {
+			penalties.push(Penalty {
+				penalized_account: account.to_string(),
+				area_uuid: area.to_string(),
+				market_id: trade.market_id.clone(),
+				trade_uuid: trade.trade_uuid.clone(),
+				penalty_cost,
+			});
+		}
+	}
+}
 
-				// Scale and convert to u64: apply a scaling factor of 10,000.
-				let penalty_cost = (raw_penalty * 10_000.0).round() as u64;
+/// Splits `total` in proportion to the `weights`, so that the shares add up to `total`.
+///
+/// Each share is rounded down, and the units left are given to the shares with the largest
+/// remainders. If all the weights are zero, the total is split evenly.
+fn allocate_pro_rata(total: u64, weights: &[f64]) -> Vec<u64> {
+	if weights.is_empty() {
+		return Vec::new();
+	}
+	let total_weight: f64 = weights.iter().sum();
+	let exact_shares: Vec<f64> = if total_weight > 0.0 {
+		weights.iter().map(|weight| total as f64 * weight / total_weight).collect()
+	} else {
+		vec![total as f64 / weights.len() as f64; weights.len()]
+	};
 
-				penalties.push(Penalty {
-					penalized_account: trade.buyer.clone(),
-					market_id: trade.offer.offer_component.market_id.clone(),
-					trade_uuid: trade.trade_uuid.clone(),
-					penalty_cost,
-				});
-			} else if delta < 0.0 {
-				// This is a production trade: measured energy is less than traded energy.
-				// Penalize the seller.
-				let raw_penalty = (-delta) * penalty_rate;
-				let penalty_cost = (raw_penalty * 10_000.0).round() as u64;
+	let mut shares: Vec<u64> = exact_shares.iter().map(|share| share.floor() as u64).collect();
+	let allocated: u64 = shares.iter().sum();
+	let mut by_remainder: Vec<usize> = (0..shares.len()).collect();
+	by_remainder.sort_by(|&a, &b| {
+		let remainder_a = exact_shares[a] - exact_shares[a].floor();
+		let remainder_b = exact_shares[b] - exact_shares[b].floor();
+		remainder_b.total_cmp(&remainder_a).then(a.cmp(&b))
+	});
+	for index in by_remainder.into_iter().cycle().take(total.saturating_sub(allocated) as usize) {
+		shares[index] += 1;
+	}
+	shares
+}
 
-				penalties.push(Penalty {
-					penalized_account: trade.seller.clone(),
-					market_id: trade.market_id.clone(),
-					trade_uuid: trade.trade_uuid.clone(),
-					penalty_cost,
-				});
-			}
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::primitives::penalty_model::{PenaltyConfig, PenaltyModelConfig};
+	use gsy_offchain_primitives::test_utils::TradeFixture;
+
+	const TIME_SLOT: u64 = 1_700_000_000;
+	const MARKET_ID: &str = "market";
+
+	/// A trade of `energy` kWh from the area `seller` to the area `buyer`, each area being owned by
+	/// the account of the same name.
+	fn trade(trade_uuid: &str, seller: &str, buyer: &str, energy: f64) -> TradeSchema {
+		TradeFixture::new(trade_uuid, seller, buyer, energy)
+			.time_slot(TIME_SLOT)
+			.build()
+	}
+
+	fn in_market(mut trade: TradeSchema, market_type: MarketType) -> TradeSchema {
+		trade.market_id = h256_to_string(market_type.market_id(TIME_SLOT));
+		trade
+	}
+
+	fn measurement(area: &str, energy_kwh: f64) -> MeasurementSchema {
+		MeasurementSchema {
+			area_uuid: area.to_string(),
+			area_hash: area.to_string(),
+			market_id: MARKET_ID.to_string(),
+			community_uuid: "community".to_string(),
+			time_slot: TIME_SLOT,
+			creation_time: TIME_SLOT,
+			energy_kwh,
 		}
 	}
 
-	penalties
+	fn penalties_of<'a>(penalties: &'a [Penalty], account: &str) -> Vec<(&'a str, u64)> {
+		penalties
+			.iter()
+			.filter(|penalty| penalty.penalized_account == account)
+			.map(|penalty| (penalty.trade_uuid.as_str(), penalty.penalty_cost))
+			.collect()
+	}
+
+	fn penalize(trades: &[TradeSchema], measurements: &[MeasurementSchema]) -> Vec<Penalty> {
+		compute_penalties(
+			trades,
+			measurements,
+			&PenaltyModels::linear(0.1),
+			MissingMeasurement::Defer,
+		)
+		.penalties
+	}
+
+	#[test]
+	fn balanced_areas_are_not_penalized() {
+		let trades = [trade("t1", "pv", "house", 2.0), trade("t2", "pv", "house", 3.0)];
+		let measurements = [measurement("pv", -5.0), measurement("house", 5.0)];
+
+		assert!(penalize(&trades, &measurements).is_empty());
+	}
+
+	#[test]
+	fn imbalance_of_an_area_with_several_trades_is_computed_once() {
+		// The house bought 1 + 3 kWh in three trades, but consumed 6 kWh: the imbalance is 2 kWh,
+		// and not the 5 + 3 + 6 kWh of comparing each trade with the whole measurement.
+		let trades = [
+			trade("t1", "pv", "house", 1.0),
+			trade("t2", "wind", "house", 3.0),
+			trade("t3", "wind", "house", 0.0),
+		];
+		let measurements = [measurement("house", 6.0)];
+
+		let penalties = penalize(&trades, &measurements);
+
+		// 2 kWh * 0.1 * 10,000 = 2,000, allocated as 1/4 and 3/4 of the bought energy.
+		assert_eq!(penalties_of(&penalties, "house"), vec![("t1", 500), ("t2", 1_500)]);
+		assert!(penalties.iter().all(|penalty| penalty.market_id == MARKET_ID));
+	}
+
+	#[test]
+	fn net_traded_energy_accounts_for_bought_and_sold_energy() {
+		// The battery bought 4 kWh and sold 1 kWh, so it should have consumed 3 kWh. It consumed
+		// 2 kWh, an imbalance of 1 kWh allocated across its 5 kWh of trades.
+		let trades = [trade("t1", "pv", "battery", 4.0), trade("t2", "battery", "house", 1.0)];
+		let measurements =
+			[measurement("battery", 2.0), measurement("pv", -4.0), measurement("house", 1.0)];
+
+		let penalties = penalize(&trades, &measurements);
+
+		assert_eq!(penalties_of(&penalties, "battery"), vec![("t1", 800), ("t2", 200)]);
+		assert!(penalties_of(&penalties, "pv").is_empty());
+		assert!(penalties_of(&penalties, "house").is_empty());
+	}
+
+	#[test]
+	fn under_production_penalizes_the_seller_trades() {
+		// The PV sold 2 + 4 kWh to two houses, but produced only 3 kWh.
+		let trades = [trade("t1", "pv", "house_1", 2.0), trade("t2", "pv", "house_2", 4.0)];
+		let measurements =
+			[measurement("pv", -3.0), measurement("house_1", 2.0), measurement("house_2", 4.0)];
+
+		let penalties = penalize(&trades, &measurements);
+
+		assert_eq!(penalties.len(), 2);
+		assert_eq!(penalties_of(&penalties, "pv"), vec![("t1", 1_000), ("t2", 2_000)]);
+	}
+
+	#[test]
+	fn trades_are_priced_with_the_model_of_their_market_type() {
+		// The house bought 1 kWh in the spot market and 3 kWh in the settlement market, but consumed
+		// 6 kWh: 0.5 kWh of the imbalance is priced by the spot model, and 1.5 kWh by the
+		// settlement model.
+		let trades = [
+			in_market(trade("t1", "pv", "house", 1.0), MarketType::Spot),
+			in_market(trade("t2", "wind", "house", 3.0), MarketType::Settlement),
+		];
+		let config = PenaltyConfig(HashMap::from([
+			(MarketType::Spot, PenaltyModelConfig::Linear { rate: 0.2 }),
+			(
+				MarketType::Settlement,
+				PenaltyModelConfig::ToleranceBand { tolerance: 0.25, rate: 0.1 },
+			),
+		]));
+
+		let mut spot_measurement = measurement("house", 6.0);
+		spot_measurement.market_id = h256_to_string(MarketType::Spot.market_id(TIME_SLOT));
+
+		let penalties = compute_penalties(
+			&trades,
+			&[spot_measurement],
+			&config.models(0.1, &[]),
+			MissingMeasurement::Defer,
+		)
+		.penalties;
+
+		// Spot: 0.5 kWh * 0.2. Settlement: (1.5 kWh - 25% of 3 kWh) * 0.1.
+		assert_eq!(penalties_of(&penalties, "house"), vec![("t1", 1_000), ("t2", 750)]);
+	}
+
+	#[test]
+	fn settlement_trades_are_netted_against_the_deviation() {
+		// The house bought 5 kWh in the spot market but consumed 6 kWh, and bought the missing
+		// 1 kWh from the battery in the settlement market. The battery sold 1 kWh it had not
+		// traded before delivery, and is measured in the topology of the slot only.
+		let trades = [
+			in_market(trade("t1", "pv", "house", 5.0), MarketType::Spot),
+			in_market(trade("t2", "battery", "house", 0.5), MarketType::Settlement),
+			in_market(trade("t3", "battery", "house", 0.5), MarketType::Settlement),
+		];
+		let topology_measurement = |area: &str, energy_kwh: f64| MeasurementSchema {
+			market_id: topology_market_id(TIME_SLOT),
+			..measurement(area, energy_kwh)
+		};
+		let measurements = [
+			topology_measurement("house", 6.0),
+			topology_measurement("pv", -5.0),
+			topology_measurement("battery", -1.0),
+		];
+
+		let report = compute_penalties(
+			&trades,
+			&measurements,
+			&PenaltyModels::linear(0.1),
+			MissingMeasurement::Defer,
+		);
+
+		assert!(report.penalties.is_empty());
+		assert!(report.missing_areas.is_empty());
+	}
+
+	#[test]
+	fn areas_without_measurement_are_reported_and_deferred() {
+		let trades = [trade("t1", "pv", "house", 2.0), trade("t2", "pv", "house", 3.0)];
+
+		let report = compute_penalties(
+			&trades,
+			&[measurement("other", 5.0)],
+			&PenaltyModels::linear(0.1),
+			MissingMeasurement::Defer,
+		);
+
+		assert!(report.penalties.is_empty());
+		assert_eq!(report.missing_areas, vec!["house", "pv"]);
+	}
+
+	#[test]
+	fn measurements_are_joined_on_the_market_of_the_trades() {
+		// The area hash of the house in the topology of another market identifies another area.
+		let trades = [trade("t1", "pv", "house", 2.0)];
+		let mut other_market_measurement = measurement("house", 5.0);
+		other_market_measurement.market_id = "other_market".to_string();
+
+		let report = compute_penalties(
+			&trades,
+			&[other_market_measurement, measurement("pv", -2.0)],
+			&PenaltyModels::linear(0.1),
+			MissingMeasurement::Defer,
+		);
+
+		assert!(report.penalties.is_empty());
+		assert_eq!(report.missing_areas, vec!["house"]);
+	}
+
+	#[test]
+	fn measurements_without_market_are_joined_on_the_area_and_slot() {
+		// The house bought 2 kWh but consumed 3 kWh, measured before the market_id of the
+		// measurements was stored.
+		let trades = [trade("t1", "pv", "house", 2.0)];
+		let unscoped_measurement = |area: &str, energy_kwh: f64| MeasurementSchema {
+			market_id: String::new(),
+			..measurement(area, energy_kwh)
+		};
+
+		let report = compute_penalties(
+			&trades,
+			&[unscoped_measurement("house", 3.0), unscoped_measurement("pv", -2.0)],
+			&PenaltyModels::linear(0.1),
+			MissingMeasurement::Defer,
+		);
+
+		assert!(report.missing_areas.is_empty());
+		assert_eq!(penalties_of(&report.penalties, "house"), vec![("t1", 1_000)]);
+	}
+
+	#[test]
+	fn areas_without_measurement_can_be_penalized_as_zero_delivery() {
+		// The PV sold 5 kWh but did not report its production.
+		let trades = [trade("t1", "pv", "house", 2.0), trade("t2", "pv", "house", 3.0)];
+
+		let report = compute_penalties(
+			&trades,
+			&[measurement("house", 5.0)],
+			&PenaltyModels::linear(0.1),
+			MissingMeasurement::ZeroDelivery,
+		);
+
+		assert_eq!(penalties_of(&report.penalties, "pv"), vec![("t1", 2_000), ("t2", 3_000)]);
+		assert!(penalties_of(&report.penalties, "house").is_empty());
+		assert_eq!(report.missing_areas, vec!["pv"]);
+	}
+
+	#[test]
+	fn areas_without_measurement_can_be_given_a_flat_penalty() {
+		let trades = [trade("t1", "pv", "house", 1.0), trade("t2", "pv", "house", 3.0)];
+
+		let report = compute_penalties(
+			&trades,
+			&[measurement("pv", -4.0)],
+			&PenaltyModels::linear(0.1),
+			MissingMeasurement::NonReportingPenalty(0.2),
+		);
+
+		assert_eq!(penalties_of(&report.penalties, "house"), vec![("t1", 500), ("t2", 1_500)]);
+		assert!(penalties_of(&report.penalties, "pv").is_empty());
+		assert_eq!(report.missing_areas, vec!["house"]);
+	}
+
+	#[test]
+	fn allocated_shares_add_up_to_the_total() {
+		assert_eq!(allocate_pro_rata(10, &[1.0, 1.0, 1.0]), vec![4, 3, 3]);
+		assert_eq!(allocate_pro_rata(100, &[1.0, 2.0]), vec![33, 67]);
+		assert_eq!(allocate_pro_rata(5, &[0.0, 0.0]), vec![3, 2]);
+		assert!(allocate_pro_rata(5, &[]).is_empty());
+	}
 }
@@ -0,0 +1,236 @@
+use anyhow::{Context, Result};
+use gsy_offchain_primitives::{db_api_schema::imbalance_prices::ImbalancePriceSchema, MarketType};
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path};
+use tracing::warn;
+
+/// Prices the imbalance of an area in a slot.
+///
+/// The imbalance is positive when the area consumed more or produced less than it traded (short),
+/// and negative when it consumed less or produced more than it traded (long).
+pub trait PenaltyModel: Send + Sync {
+	/// The penalty for an imbalance of `imbalance` kWh over trades of `traded_energy` kWh, in the
+	/// unit of the energy rates.
+	fn penalty(&self, imbalance: f64, traded_energy: f64, time_slot: u64) -> f64;
+}
+
+/// The same price for short and long imbalances.
+pub struct LinearPenalty {
+	pub rate: f64,
+}
+
+impl PenaltyModel for LinearPenalty {
+	fn penalty(&self, imbalance: f64, _traded_energy: f64, _time_slot: u64) -> f64 {
+		imbalance.abs() * self.rate
+	}
+}
+
+/// Separate prices for short and long imbalances.
+pub struct AsymmetricPenalty {
+	pub short_price: f64,
+	pub long_price: f64,
+}
+
+impl PenaltyModel for AsymmetricPenalty {
+	fn penalty(&self, imbalance: f64, _traded_energy: f64, _time_slot: u64) -> f64 {
+		if imbalance > 0.0 {
+			imbalance * self.short_price
+		} else {
+			-imbalance * self.long_price
+		}
+	}
+}
+
+/// No penalty while the imbalance is within `tolerance` (e.g. 0.05 for ±5%) of the traded energy,
+/// and a linear penalty on the part of the imbalance outside of this band.
+pub struct ToleranceBandPenalty {
+	pub tolerance: f64,
+	pub rate: f64,
+}
+
+impl PenaltyModel for ToleranceBandPenalty {
+	fn penalty(&self, imbalance: f64, traded_energy: f64, _time_slot: u64) -> f64 {
+		let band = self.tolerance * traded_energy.abs();
+		(imbalance.abs() - band).max(0.0) * self.rate
+	}
+}
+
+/// The short and long prices of each slot, as published in the offchain storage. The imbalances of
+/// the slots without prices are not penalized.
+pub struct ImbalancePriceSeries {
+	prices: HashMap<u64, ImbalancePriceSchema>,
+}
+
+impl ImbalancePriceSeries {
+	pub fn new(prices: &[ImbalancePriceSchema]) -> Self {
+		Self { prices: prices.iter().map(|price| (price.time_slot, price.clone())).collect() }
+	}
+}
+
+impl PenaltyModel for ImbalancePriceSeries {
+	fn penalty(&self, imbalance: f64, _traded_energy: f64, time_slot: u64) -> f64 {
+		match self.prices.get(&time_slot) {
+			Some(price) if imbalance > 0.0 => imbalance * price.short_price,
+			Some(price) => -imbalance * price.long_price,
+			None => {
+				warn!("No imbalance price for slot {}, the imbalance is not penalized", time_slot);
+				0.0
+			},
+		}
+	}
+}
+
+/// The penalty model of a market type in the penalty configuration.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum PenaltyModelConfig {
+	Linear { rate: f64 },
+	Asymmetric { short_price: f64, long_price: f64 },
+	ToleranceBand { tolerance: f64, rate: f64 },
+	ImbalancePriceSeries,
+}
+
+/// The penalty models of the market types, read from a JSON file such as:
+///
+/// ```json
+/// {
+///   "Spot": { "model": "asymmetric", "short_price": 0.3, "long_price": 0.05 },
+///   "Settlement": { "model": "imbalance_price_series" }
+/// }
+/// ```
+///
+/// The market types without a model are penalized with a linear model at the `penalty_rate`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PenaltyConfig(pub HashMap<MarketType, PenaltyModelConfig>);
+
+impl PenaltyConfig {
+	pub fn from_file(path: &Path) -> Result<Self> {
+		let content = std::fs::read_to_string(path)
+			.with_context(|| format!("Failed to read the penalty configuration {:?}", path))?;
+		serde_json::from_str(&content)
+			.with_context(|| format!("Invalid penalty configuration {:?}", path))
+	}
+
+	/// Whether the imbalance prices have to be fetched from the offchain storage.
+	pub fn uses_imbalance_prices(&self) -> bool {
+		self.0.values().any(|model| *model == PenaltyModelConfig::ImbalancePriceSeries)
+	}
+
+	/// The models of an execution cycle, with the imbalance prices of its slot.
+	pub fn models(
+		&self,
+		penalty_rate: f64,
+		imbalance_prices: &[ImbalancePriceSchema],
+	) -> PenaltyModels {
+		let models = self
+			.0
+			.iter()
+			.map(|(market_type, config)| {
+				let model: Box<dyn PenaltyModel> = match config {
+					PenaltyModelConfig::Linear { rate } => Box::new(LinearPenalty { rate: *rate }),
+					PenaltyModelConfig::Asymmetric { short_price, long_price } => {
+						Box::new(AsymmetricPenalty {
+							short_price: *short_price,
+							long_price: *long_price,
+						})
+					},
+					PenaltyModelConfig::ToleranceBand { tolerance, rate } => {
+						Box::new(ToleranceBandPenalty { tolerance: *tolerance, rate: *rate })
+					},
+					PenaltyModelConfig::ImbalancePriceSeries => {
+						Box::new(ImbalancePriceSeries::new(imbalance_prices))
+					},
+				};
+				(*market_type, model)
+			})
+			.collect();
+		PenaltyModels { models, default: Box::new(LinearPenalty { rate: penalty_rate }) }
+	}
+}
+
+/// The penalty model of each market type.
+pub struct PenaltyModels {
+	models: HashMap<MarketType, Box<dyn PenaltyModel>>,
+	/// The model of the market types without a model, and of the trades of unknown markets.
+	default: Box<dyn PenaltyModel>,
+}
+
+impl PenaltyModels {
+	/// A linear model at `rate` for all the market types.
+	pub fn linear(rate: f64) -> Self {
+		Self { models: HashMap::new(), default: Box::new(LinearPenalty { rate }) }
+	}
+
+	pub fn for_market(&self, market_type: Option<MarketType>) -> &dyn PenaltyModel {
+		market_type
+			.and_then(|market_type| self.models.get(&market_type))
+			.unwrap_or(&self.default)
+			.as_ref()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TIME_SLOT: u64 = 1_700_000_100;
+
+	#[test]
+	fn linear_penalty_is_symmetric() {
+		let model = LinearPenalty { rate: 0.1 };
+		assert_eq!(model.penalty(2.0, 10.0, TIME_SLOT), model.penalty(-2.0, 10.0, TIME_SLOT));
+	}
+
+	#[test]
+	fn asymmetric_penalty_prices_short_and_long_imbalances_separately() {
+		let model = AsymmetricPenalty { short_price: 0.3, long_price: 0.05 };
+		assert_eq!(model.penalty(2.0, 10.0, TIME_SLOT), 0.6);
+		assert_eq!(model.penalty(-2.0, 10.0, TIME_SLOT), 0.1);
+	}
+
+	#[test]
+	fn tolerance_band_penalizes_only_the_imbalance_outside_of_the_band() {
+		let model = ToleranceBandPenalty { tolerance: 0.1, rate: 1.0 };
+		assert_eq!(model.penalty(1.0, 10.0, TIME_SLOT), 0.0);
+		assert_eq!(model.penalty(-0.5, 10.0, TIME_SLOT), 0.0);
+		assert_eq!(model.penalty(3.0, 10.0, TIME_SLOT), 2.0);
+		assert_eq!(model.penalty(-3.0, 10.0, TIME_SLOT), 2.0);
+	}
+
+	#[test]
+	fn imbalance_price_series_uses_the_prices_of_the_slot() {
+		let model = ImbalancePriceSeries::new(&[ImbalancePriceSchema {
+			time_slot: TIME_SLOT,
+			short_price: 0.3,
+			long_price: 0.05,
+			creation_time: TIME_SLOT,
+		}]);
+		assert_eq!(model.penalty(2.0, 10.0, TIME_SLOT), 0.6);
+		assert_eq!(model.penalty(-2.0, 10.0, TIME_SLOT), 0.1);
+		assert_eq!(model.penalty(2.0, 10.0, TIME_SLOT + 900), 0.0);
+	}
+
+	#[test]
+	fn penalty_models_are_selected_per_market_type() {
+		let config: PenaltyConfig = serde_json::from_str(
+			r#"{
+				"Spot": { "model": "asymmetric", "short_price": 0.3, "long_price": 0.05 },
+				"Settlement": { "model": "imbalance_price_series" }
+			}"#,
+		)
+		.unwrap();
+		assert!(config.uses_imbalance_prices());
+
+		let models = config.models(0.1, &[]);
+		assert_eq!(models.for_market(Some(MarketType::Spot)).penalty(-2.0, 10.0, TIME_SLOT), 0.1);
+		assert_eq!(
+			models.for_market(Some(MarketType::Settlement)).penalty(2.0, 10.0, TIME_SLOT),
+			0.0
+		);
+		assert_eq!(
+			models.for_market(Some(MarketType::Flexibility)).penalty(2.0, 10.0, TIME_SLOT),
+			0.2
+		);
+		assert_eq!(models.for_market(None).penalty(-2.0, 10.0, TIME_SLOT), 0.2);
+	}
+}
@@ -0,0 +1,228 @@
+use crate::primitives::penalty_calculator::{AreaImbalance, Penalty, PENALTY_SCALING_FACTOR};
+use anyhow::{anyhow, Result};
+use chrono::{Months, NaiveDate};
+use gsy_offchain_primitives::db_api_schema::{
+	statements::{SettlementStatementSchema, StatementPeriod},
+	trades::TradeSchema,
+};
+use std::collections::BTreeMap;
+
+/// Builds the statement of each account which traded or was penalized in the slot.
+///
+/// The traded values are the selected energy of the trades at their energy rate. The penalties
+/// and imbalances are the ones computed by the penalty calculator for the slot.
+pub fn slot_statements(
+	trades: &[TradeSchema],
+	penalties: &[Penalty],
+	imbalances: &[AreaImbalance],
+	time_slot: u64,
+	market_duration: u64,
+	creation_time: u64,
+) -> Vec<SettlementStatementSchema> {
+	let mut statements: BTreeMap<&str, SettlementStatementSchema> = BTreeMap::new();
+	let period_end = time_slot + market_duration;
+	let mut statement_of = |account| {
+		statements.entry(account).or_insert_with(|| {
+			empty_statement(account, StatementPeriod::Slot, time_slot, period_end, creation_time)
+		})
+	};
+
+	for trade in trades.iter().filter(|trade| trade.time_slot == time_slot) {
+		let energy = trade.parameters.selected_energy;
+		let value = energy * trade.parameters.energy_rate;
+
+		let buyer = statement_of(trade.buyer.as_str());
+		buyer.energy_bought += energy;
+		buyer.bought_value += value;
+
+		let seller = statement_of(trade.seller.as_str());
+		seller.energy_sold += energy;
+		seller.sold_value += value;
+	}
+	for imbalance in imbalances.iter().filter(|imbalance| imbalance.time_slot == time_slot) {
+		statement_of(imbalance.account.as_str()).imbalance += imbalance.imbalance.abs();
+	}
+	for penalty in penalties {
+		statement_of(penalty.penalized_account.as_str()).penalties +=
+			penalty.penalty_cost as f64 / PENALTY_SCALING_FACTOR;
+	}
+
+	statements.into_values().map(with_totals).collect()
+}
+
+/// Aggregates the slot statements of each account into one statement for the month starting at
+/// `month_start`.
+pub fn monthly_statements(
+	slot_statements: &[SettlementStatementSchema],
+	month_start: u64,
+	month_end: u64,
+	creation_time: u64,
+) -> Vec<SettlementStatementSchema> {
+	let mut statements: BTreeMap<&str, SettlementStatementSchema> = BTreeMap::new();
+	for slot_statement in slot_statements.iter().filter(|statement| {
+		statement.period == StatementPeriod::Slot
+			&& (month_start..month_end).contains(&statement.period_start)
+	}) {
+		let account = slot_statement.account.as_str();
+		let statement = statements.entry(account).or_insert_with(|| {
+			empty_statement(account, StatementPeriod::Month, month_start, month_end, creation_time)
+		});
+		statement.energy_bought += slot_statement.energy_bought;
+		statement.energy_sold += slot_statement.energy_sold;
+		statement.bought_value += slot_statement.bought_value;
+		statement.sold_value += slot_statement.sold_value;
+		statement.imbalance += slot_statement.imbalance;
+		statement.penalties += slot_statement.penalties;
+	}
+
+	statements.into_values().map(with_totals).collect()
+}
+
+/// The start (inclusive) and end (exclusive) timestamps of a month given as `YYYY-MM`, in UTC.
+pub fn month_range(month: &str) -> Result<(u64, u64)> {
+	let first_day = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+		.map_err(|_| anyhow!("Invalid month {}, expected YYYY-MM", month))?;
+	let next_first_day = first_day
+		.checked_add_months(Months::new(1))
+		.ok_or_else(|| anyhow!("Invalid month {}", month))?;
+	let timestamp = |day: NaiveDate| {
+		day.and_hms_opt(0, 0, 0)
+			.expect("midnight is a valid time; qed")
+			.and_utc()
+			.timestamp() as u64
+	};
+	Ok((timestamp(first_day), timestamp(next_first_day)))
+}
+
+fn empty_statement(
+	account: &str,
+	period: StatementPeriod,
+	period_start: u64,
+	period_end: u64,
+	creation_time: u64,
+) -> SettlementStatementSchema {
+	SettlementStatementSchema {
+		account: account.to_string(),
+		period,
+		period_start,
+		period_end,
+		energy_bought: 0.0,
+		energy_sold: 0.0,
+		average_buy_rate: 0.0,
+		average_sell_rate: 0.0,
+		bought_value: 0.0,
+		sold_value: 0.0,
+		imbalance: 0.0,
+		penalties: 0.0,
+		net_amount: 0.0,
+		creation_time,
+	}
+}
+
+/// Fills the average rates and the net amount from the energies, values and penalties.
+fn with_totals(mut statement: SettlementStatementSchema) -> SettlementStatementSchema {
+	let average = |value: f64, energy: f64| if energy > 0.0 { value / energy } else { 0.0 };
+	statement.average_buy_rate = average(statement.bought_value, statement.energy_bought);
+	statement.average_sell_rate = average(statement.sold_value, statement.energy_sold);
+	statement.net_amount = statement.sold_value - statement.bought_value - statement.penalties;
+	statement
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use gsy_offchain_primitives::test_utils::TradeFixture;
+
+	const TIME_SLOT: u64 = 1_700_000_100;
+
+	fn trade(trade_uuid: &str, seller: &str, buyer: &str, energy: f64, rate: f64) -> TradeSchema {
+		trade_in_slot(trade_uuid, seller, buyer, energy, rate, TIME_SLOT)
+	}
+
+	fn trade_in_slot(
+		trade_uuid: &str,
+		seller: &str,
+		buyer: &str,
+		energy: f64,
+		rate: f64,
+		time_slot: u64,
+	) -> TradeSchema {
+		TradeFixture::new(trade_uuid, seller, buyer, energy)
+			.energy_rate(rate)
+			.time_slot(time_slot)
+			.build()
+	}
+
+	fn statement_of<'a>(
+		statements: &'a [SettlementStatementSchema],
+		account: &str,
+	) -> &'a SettlementStatementSchema {
+		statements.iter().find(|statement| statement.account == account).unwrap()
+	}
+
+	#[test]
+	fn slot_statements_sum_the_trades_and_penalties_of_each_account() {
+		let trades = [trade("t1", "pv", "house", 2.0, 0.2), trade("t2", "wind", "house", 3.0, 0.3)];
+		let penalties = [Penalty {
+			penalized_account: "house".to_string(),
+			area_uuid: "house".to_string(),
+			market_id: "market".to_string(),
+			trade_uuid: "t2".to_string(),
+			penalty_cost: 1_000,
+		}];
+		let imbalances = [AreaImbalance {
+			account: "house".to_string(),
+			area_uuid: "house".to_string(),
+			time_slot: TIME_SLOT,
+			imbalance: -1.0,
+		}];
+
+		let statements =
+			slot_statements(&trades, &penalties, &imbalances, TIME_SLOT, 900, TIME_SLOT + 900);
+
+		assert_eq!(statements.len(), 3);
+		let house = statement_of(&statements, "house");
+		assert_eq!(house.period, StatementPeriod::Slot);
+		assert_eq!((house.period_start, house.period_end), (TIME_SLOT, TIME_SLOT + 900));
+		assert_eq!(house.energy_bought, 5.0);
+		assert!((house.bought_value - 1.3).abs() < 1e-9);
+		assert!((house.average_buy_rate - 0.26).abs() < 1e-9);
+		assert_eq!(house.imbalance, 1.0);
+		assert_eq!(house.penalties, 0.1);
+		assert!((house.net_amount + 1.4).abs() < 1e-9);
+
+		let pv = statement_of(&statements, "pv");
+		assert_eq!((pv.energy_sold, pv.average_sell_rate), (2.0, 0.2));
+		assert!((pv.net_amount - 0.4).abs() < 1e-9);
+	}
+
+	#[test]
+	fn monthly_statements_aggregate_the_slot_statements_of_the_month() {
+		let (month_start, month_end) = month_range("2023-11").unwrap();
+		assert_eq!((month_start, month_end), (1_698_796_800, 1_701_388_800));
+
+		let mut slot_statements = Vec::new();
+		// The last slot belongs to the next month.
+		for (time_slot, rate) in [(TIME_SLOT, 0.2), (TIME_SLOT + 900, 0.4), (month_end, 0.1)] {
+			slot_statements.extend(super::slot_statements(
+				&[trade_in_slot("t", "pv", "house", 1.0, rate, time_slot)],
+				&[],
+				&[],
+				time_slot,
+				900,
+				time_slot,
+			));
+		}
+
+		let statements = monthly_statements(&slot_statements, month_start, month_end, month_end);
+
+		assert_eq!(statements.len(), 2);
+		let house = statement_of(&statements, "house");
+		assert_eq!(house.period, StatementPeriod::Month);
+		assert_eq!((house.period_start, house.period_end), (month_start, month_end));
+		assert_eq!(house.energy_bought, 2.0);
+		assert!((house.average_buy_rate - 0.3).abs() < 1e-9);
+		assert!((house.net_amount + 0.6).abs() < 1e-9);
+		assert!(month_range("2023-13").is_err());
+	}
+}
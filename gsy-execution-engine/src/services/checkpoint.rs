@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// The last timeslot whose penalties have been submitted, persisted in a JSON file so that every
+/// timeslot is processed once, including the timeslots missed while the engine was down. The
+/// checkpoint also keeps the areas of the processed timeslots that are awaiting their measurement.
+#[derive(Debug)]
+pub struct Checkpoint {
+    /// Where the checkpoint is persisted, or `None` for a dry run.
+    path: Option<PathBuf>,
+    state: CheckpointState,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+struct CheckpointState {
+    last_processed_timeslot: Option<u64>,
+    #[serde(default)]
+    deferred_areas: BTreeMap<u64, Vec<String>>,
+}
+
+impl Checkpoint {
+    /// Load the checkpoint from `path`, starting without processed timeslots if the file does
+    /// not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        let state = if path.exists() {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read the checkpoint {:?}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid checkpoint {:?}", path))?
+        } else {
+            CheckpointState::default()
+        };
+        Ok(Self { path: Some(path.to_path_buf()), state })
+    }
+
+    /// A checkpoint kept in memory only, so that a dry run neither uses nor alters the timeslots
+    /// processed by the engine.
+    pub fn in_memory() -> Self {
+        Self { path: None, state: CheckpointState::default() }
+    }
+
+    pub fn last_processed_timeslot(&self) -> Option<u64> {
+        self.state.last_processed_timeslot
+    }
+
+    /// The timeslots to process, in order, up to `latest_timeslot`. Without a checkpoint, only
+    /// `latest_timeslot` is processed.
+    pub fn pending_timeslots(&self, latest_timeslot: u64, slot_length: u64) -> Vec<u64> {
+        match self.state.last_processed_timeslot {
+            Some(last) => (last + slot_length..=latest_timeslot)
+                .step_by(slot_length as usize)
+                .collect(),
+            None => vec![latest_timeslot],
+        }
+    }
+
+    /// The processed timeslots with areas awaiting their measurement, and these areas.
+    pub fn deferred_timeslots(&self) -> Vec<(u64, Vec<String>)> {
+        self.state
+            .deferred_areas
+            .iter()
+            .map(|(timeslot, areas)| (*timeslot, areas.clone()))
+            .collect()
+    }
+
+    /// Record `timeslot` as processed, with the areas still awaiting their measurement.
+    pub fn save(&mut self, timeslot: u64, deferred_areas: Vec<String>) -> Result<()> {
+        let mut state = self.state.clone();
+        state.last_processed_timeslot = Some(timeslot);
+        Self::defer(&mut state, timeslot, deferred_areas);
+        self.write(state)
+    }
+
+    /// Record the areas of an already processed `timeslot` still awaiting their measurement.
+    pub fn save_deferred(&mut self, timeslot: u64, deferred_areas: Vec<String>) -> Result<()> {
+        let mut state = self.state.clone();
+        Self::defer(&mut state, timeslot, deferred_areas);
+        self.write(state)
+    }
+
+    fn defer(state: &mut CheckpointState, timeslot: u64, deferred_areas: Vec<String>) {
+        if deferred_areas.is_empty() {
+            state.deferred_areas.remove(&timeslot);
+        } else {
+            state.deferred_areas.insert(timeslot, deferred_areas);
+        }
+    }
+
+    /// The file is replaced atomically, so that a crash never leaves a partially written
+    /// checkpoint.
+    fn write(&mut self, state: CheckpointState) -> Result<()> {
+        if let Some(path) = &self.path {
+            let temporary_path = path.with_extension("tmp");
+            std::fs::write(&temporary_path, serde_json::to_string(&state)?)
+                .with_context(|| format!("Failed to write the checkpoint {:?}", temporary_path))?;
+            std::fs::rename(&temporary_path, path)
+                .with_context(|| format!("Failed to write the checkpoint {:?}", path))?;
+        }
+        self.state = state;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLOT_LENGTH: u64 = 900;
+
+    #[test]
+    fn pending_timeslots_catch_up_from_the_checkpoint() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.json", std::process::id()));
+        let mut checkpoint = Checkpoint::load(&path).unwrap();
+        assert_eq!(checkpoint.pending_timeslots(9_000, SLOT_LENGTH), vec![9_000]);
+
+        checkpoint.save(6_300, Vec::new()).unwrap();
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(checkpoint.last_processed_timeslot(), Some(6_300));
+        assert_eq!(checkpoint.pending_timeslots(9_000, SLOT_LENGTH), vec![7_200, 8_100, 9_000]);
+        assert!(checkpoint.pending_timeslots(6_300, SLOT_LENGTH).is_empty());
+    }
+
+    #[test]
+    fn deferred_areas_are_kept_until_they_are_evaluated() {
+        let path = std::env::temp_dir().join(format!("deferred-{}.json", std::process::id()));
+        let mut checkpoint = Checkpoint::load(&path).unwrap();
+        checkpoint.save(5_400, vec!["house".to_string(), "pv".to_string()]).unwrap();
+        checkpoint.save(6_300, Vec::new()).unwrap();
+        checkpoint.save_deferred(5_400, vec!["pv".to_string()]).unwrap();
+
+        let mut checkpoint = Checkpoint::load(&path).unwrap();
+        assert_eq!(checkpoint.last_processed_timeslot(), Some(6_300));
+        assert_eq!(checkpoint.deferred_timeslots(), vec![(5_400, vec!["pv".to_string()])]);
+
+        checkpoint.save_deferred(5_400, Vec::new()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(checkpoint.deferred_timeslots().is_empty());
+    }
+}
@@ -1,30 +1,100 @@
-use anyhow::Result;
-use tracing::info;
-use gsy_offchain_primitives::utils::timestamp_to_datetime_string;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use subxt_signer::sr25519::Keypair;
+use tracing::{info, warn};
+use gsy_offchain_primitives::{constants::GlobalConstants, utils::timestamp_to_datetime_string};
 
 use crate::{
     primitives::{
-        penalty_calculator::{compute_penalties, Penalty},
+        flexibility_verification::{
+            corrected_measurements,
+            forecast_baselines,
+            is_flexibility_trade,
+            previous_days_baselines,
+            verify_flexibility,
+            FlexibilityReport,
+        },
+        penalty_calculator::{compute_penalties, MissingMeasurement, PenaltyReport},
+        penalty_model::PenaltyConfig,
+        settlement_statement::slot_statements,
     },
     connectors::{
-        offchain_storage::fetch_trades_and_measurements_for_timeslot,
+        node_index::fetch_indexed_trades_for_timeslot,
+        offchain_storage::{
+            fetch_forecasts_for_timeslot,
+            fetch_imbalance_prices_for_timeslot,
+            fetch_measurements_for_timeslot,
+            fetch_trades_and_measurements_for_timeslot,
+            post_statements,
+        },
+        penalty_export::PenaltyExport,
         substrate_connector::submit_penalties,
     },
+    utils::cli::{FlexibilityBaseline, MissingMeasurementPolicy},
 };
 
+/// The configuration of the execution cycles.
+pub struct ExecutionSettings {
+    pub offchain_url: String,
+    pub node_url: String,
+    pub market_duration: u64,
+    pub trades_from_node: bool,
+    pub penalty_config: PenaltyConfig,
+    pub penalty_rate: f64,
+    pub missing_measurements: MissingMeasurementPolicy,
+    pub non_reporting_penalty: f64,
+    /// Seconds after the end of a slot during which its missing measurements are awaited.
+    pub measurement_deadline: u64,
+    pub flexibility_baseline: FlexibilityBaseline,
+    pub baseline_days: u64,
+    /// The exchange operator key submitting the penalties, absent in a dry run.
+    pub signer: Option<Keypair>,
+}
+
+impl ExecutionSettings {
+    /// How the areas without measurement in `timeslot` are penalized at the time `now`.
+    fn missing_measurement(&self, timeslot: u64, now: u64) -> MissingMeasurement {
+        match self.missing_measurements {
+            MissingMeasurementPolicy::ZeroDelivery => MissingMeasurement::ZeroDelivery,
+            MissingMeasurementPolicy::NonReportingPenalty => {
+                MissingMeasurement::NonReportingPenalty(self.non_reporting_penalty)
+            }
+            MissingMeasurementPolicy::Defer
+                if now < timeslot + self.market_duration + self.measurement_deadline =>
+            {
+                MissingMeasurement::Defer
+            }
+            MissingMeasurementPolicy::Defer => MissingMeasurement::ZeroDelivery,
+        }
+    }
+}
+
 /// Higher-level function that does the repeated/polling logic
 /// 1) fetch trades/measurements
-/// 2) compute penalties
-/// 3) submit them
+/// 2) verify the activated flexibility and compute penalties
+/// 3) submit them, or write them to `export` in a dry run
+/// 4) store the settlement statements of the slot
+///
+/// With `pending_areas`, only the penalties of these areas are submitted, for the re-evaluation of
+/// the areas deferred by a previous cycle. Returns the areas deferred until their measurement
+/// arrives.
 pub async fn run_execution_cycle(
-    offchain_url: &str,
-    node_url: &str,
+    settings: &ExecutionSettings,
     timeslot: u64,
-    penalty_rate: f64,
-    market_duration: u64,
-) -> Result<()> {
+    pending_areas: Option<&[String]>,
+    export: Option<&mut PenaltyExport>,
+) -> Result<Vec<String>> {
+    let offchain_url = settings.offchain_url.as_str();
+    let market_duration = settings.market_duration;
+
     // 1) fetch trades/measurements
-    let (trades, measurements) = fetch_trades_and_measurements_for_timeslot(offchain_url, timeslot, market_duration).await?;
+    let (trades, measurements) = if settings.trades_from_node {
+        let trades = fetch_indexed_trades_for_timeslot(&settings.node_url, timeslot, market_duration).await?;
+        let measurements = fetch_measurements_for_timeslot(offchain_url, timeslot, market_duration).await?;
+        (trades, measurements)
+    } else {
+        fetch_trades_and_measurements_for_timeslot(offchain_url, timeslot, market_duration).await?
+    };
     info!(
         "Fetched {} trades, {} measurements for timeslot {}.",
         trades.len(),
@@ -32,11 +102,83 @@ pub async fn run_execution_cycle(
         timestamp_to_datetime_string(timeslot),
     );
 
-    // 2) compute penalties
-    let penalties: Vec<Penalty> = compute_penalties(&trades, &measurements, penalty_rate);
+    // 2) compute penalties, with the imbalance prices of the slot if a market type is priced by them
+    let imbalance_prices = if settings.penalty_config.uses_imbalance_prices() {
+        fetch_imbalance_prices_for_timeslot(offchain_url, timeslot, market_duration).await?
+    } else {
+        Vec::new()
+    };
+    let penalty_models = settings.penalty_config.models(settings.penalty_rate, &imbalance_prices);
+    let now = chrono::Utc::now().timestamp() as u64;
+    let missing_measurement = settings.missing_measurement(timeslot, now);
+    // The flexibility delivered by the activated areas is verified against their baselines, and
+    // added back to their measurements before their imbalances are computed.
+    let baselines = if trades.iter().any(is_flexibility_trade) {
+        fetch_flexibility_baselines(settings, timeslot).await?
+    } else {
+        HashMap::new()
+    };
+    let FlexibilityReport { penalties: flexibility_penalties, delivered, missing_areas: missing_activated_areas } =
+        verify_flexibility(&trades, &measurements, &baselines, &penalty_models, missing_measurement);
+    let measurements = corrected_measurements(&measurements, &delivered);
+    let PenaltyReport { mut penalties, imbalances, mut missing_areas } =
+        compute_penalties(&trades, &measurements, &penalty_models, missing_measurement);
+    penalties.extend(flexibility_penalties);
+    missing_areas.extend(missing_activated_areas);
+    missing_areas.sort();
+    missing_areas.dedup();
+    // The statements cover every area of the slot, so that a re-evaluation replaces them.
+    let statements = slot_statements(&trades, &penalties, &imbalances, timeslot, market_duration, now);
+    if let Some(pending_areas) = pending_areas {
+        penalties.retain(|penalty| pending_areas.contains(&penalty.area_uuid));
+        missing_areas.retain(|area| pending_areas.contains(area));
+    }
+    if !missing_areas.is_empty() {
+        warn!(
+            "{} areas without measurement for timeslot {}: {:?}",
+            missing_areas.len(),
+            timestamp_to_datetime_string(timeslot),
+            missing_areas,
+        );
+    }
     info!("Computed {} penalties", penalties.len());
 
     // 3) submit penalties
-    submit_penalties(node_url, penalties).await?;
-    Ok(())
+    match export {
+        Some(export) => export.write(timeslot, penalties)?,
+        None => {
+            let signer = settings.signer.as_ref().ok_or_else(|| anyhow!("No signer to submit the penalties"))?;
+            submit_penalties(&settings.node_url, signer, penalties).await?;
+            // 4) store the statements
+            post_statements(offchain_url, &statements).await?;
+        }
+    }
+
+    if missing_measurement == MissingMeasurement::Defer {
+        Ok(missing_areas)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// The baselines of the areas in `timeslot`, against which their activated flexibility is verified.
+async fn fetch_flexibility_baselines(settings: &ExecutionSettings, timeslot: u64) -> Result<HashMap<String, f64>> {
+    let offchain_url = settings.offchain_url.as_str();
+    match settings.flexibility_baseline {
+        FlexibilityBaseline::PreviousDays => {
+            let mut history = Vec::new();
+            for day in 1..=settings.baseline_days {
+                let Some(previous_timeslot) = timeslot.checked_sub(day * 86_400) else { break };
+                history.extend(
+                    fetch_measurements_for_timeslot(offchain_url, previous_timeslot, settings.market_duration).await?,
+                );
+            }
+            Ok(previous_days_baselines(&history, timeslot, settings.baseline_days))
+        }
+        FlexibilityBaseline::Forecast => {
+            let forecasts = fetch_forecasts_for_timeslot(offchain_url, timeslot, settings.market_duration).await?;
+            let gate_closure = timeslot as i64 + GlobalConstants.FLEX_MARKET_CLOSE_OFFSET_MIN * 60;
+            Ok(forecast_baselines(&forecasts, timeslot, gate_closure.max(0) as u64))
+        }
+    }
 }
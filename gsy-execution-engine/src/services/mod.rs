@@ -1 +1,2 @@
+pub mod checkpoint;
 pub mod execution_orchestrator;
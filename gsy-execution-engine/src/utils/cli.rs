@@ -1,4 +1,5 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
@@ -9,27 +10,135 @@ pub struct Cli {
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
+    /// Process every closed timeslot as it ends
     Web3 {
-        #[clap(default_value_t = String::from("http://127.0.0.1"))]
-        offchain_host: String,
+        #[clap(flatten)]
+        connection: ConnectionArgs,
 
-        #[clap(default_value_t = String::from("8080"))]
-        offchain_port: String,
+        #[clap(default_value_t = 30)]
+        polling_interval: u64,
 
-        #[clap(default_value_t = String::from("ws://127.0.0.1"))]
-        node_host: String,
+        #[clap(flatten)]
+        execution: ExecutionArgs,
 
-        #[clap(default_value_t = String::from("9944"))]
-        node_port: String,
+        /// JSON file recording the last processed timeslot, from which the missed timeslots are caught up
+        #[clap(long, default_value = "execution-engine-checkpoint.json")]
+        checkpoint_file: PathBuf,
+    },
 
-        #[clap(default_value_t = 30)]
-        polling_interval: u64,
+    /// Process the timeslots of a historical range as a dry run, e.g. to audit them after a
+    /// correction of their measurements. The penalties are written to the `--output` file, and
+    /// never submitted.
+    Backfill {
+        #[clap(flatten)]
+        connection: ConnectionArgs,
+
+        /// First timeslot of the range (timestamp in seconds)
+        #[clap(long)]
+        from: u64,
+
+        /// Last timeslot of the range (timestamp in seconds)
+        #[clap(long)]
+        to: u64,
+
+        #[clap(flatten)]
+        execution: ExecutionArgs,
+    },
+
+    /// Aggregate the slot statements of a month into monthly statements, store them and write
+    /// the slot and monthly statements to a file
+    Statements {
+        #[clap(flatten)]
+        connection: ConnectionArgs,
 
-        #[clap(default_value_t = 900)]
-        market_duration: u64,
+        /// Month of the statements, as YYYY-MM (UTC)
+        #[clap(long)]
+        month: String,
 
-        /// Penalty rate (e.g., 10 for 10%)
-        #[clap(default_value_t = 0.10)]
-        penalty_rate: f64,
+        /// File receiving the statements, as CSV if its extension is `.csv` and as JSON otherwise
+        #[clap(long, default_value = "statements.json")]
+        output: PathBuf,
     },
 }
+
+#[derive(Args, Debug)]
+pub struct ConnectionArgs {
+    #[clap(default_value_t = String::from("http://127.0.0.1"))]
+    pub offchain_host: String,
+
+    #[clap(default_value_t = String::from("8080"))]
+    pub offchain_port: String,
+
+    #[clap(default_value_t = String::from("ws://127.0.0.1"))]
+    pub node_host: String,
+
+    #[clap(default_value_t = String::from("9944"))]
+    pub node_port: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ExecutionArgs {
+    #[clap(default_value_t = 900)]
+    pub market_duration: u64,
+
+    /// Penalty rate (e.g., 10 for 10%) of the market types without a model in the penalty configuration
+    #[clap(default_value_t = 0.10)]
+    pub penalty_rate: f64,
+
+    /// JSON file mapping the market types to their penalty models
+    #[clap(long)]
+    pub penalty_config: Option<PathBuf>,
+
+    /// How the trades of the areas without measurement are penalized
+    #[clap(long, value_enum, default_value_t = MissingMeasurementPolicy::Defer)]
+    pub missing_measurements: MissingMeasurementPolicy,
+
+    /// Flat penalty of an area without measurement, with `--missing-measurements non-reporting-penalty`
+    #[clap(long, default_value_t = 1.0)]
+    pub non_reporting_penalty: f64,
+
+    /// Seconds after the end of a slot during which its missing measurements are awaited
+    #[clap(long, default_value_t = 3600)]
+    pub measurement_deadline: u64,
+
+    /// How the baseline of the areas activated in the flexibility markets is computed
+    #[clap(long, value_enum, default_value_t = FlexibilityBaseline::PreviousDays)]
+    pub flexibility_baseline: FlexibilityBaseline,
+
+    /// Number of previous days averaged by the `previous-days` flexibility baseline
+    #[clap(long, default_value_t = 5)]
+    pub baseline_days: u64,
+
+    /// Fetch the trades indexed by the node (`gsy_indexedTrades`) instead of the offchain storage
+    #[clap(long)]
+    pub trades_from_node: bool,
+
+    /// Compute the penalties without submitting them, and write them to the `--output` file
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// File receiving the penalties of a dry run, as CSV if its extension is `.csv` and as JSON otherwise
+    #[clap(long, default_value = "penalties.json")]
+    pub output: PathBuf,
+}
+
+/// Policy for the areas which traded in a slot but did not send its measurement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum MissingMeasurementPolicy {
+    /// Penalize the area as if it had neither consumed nor produced energy
+    ZeroDelivery,
+    /// Penalize the area with the flat `--non-reporting-penalty`
+    NonReportingPenalty,
+    /// Re-evaluate the area when its measurement arrives, until the `--measurement-deadline`, after
+    /// which it is penalized as zero delivery
+    Defer,
+}
+
+/// Baseline against which the delivery of the activated flexibility is verified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum FlexibilityBaseline {
+    /// The average measurement of the area in the same slot of the previous `--baseline-days` days
+    PreviousDays,
+    /// The last forecast of the area submitted before the gate closure of the flexibility market
+    Forecast,
+}
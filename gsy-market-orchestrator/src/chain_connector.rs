@@ -48,8 +48,15 @@ impl GsyMarketOrchestratorNodeClient {
 		Ok(status)
 	}
 
-	pub async fn update_market_status(&self, market_id: H256, is_open: bool) -> Result<()> {
-		let tx = gsy_node::tx().orderbook_registry().update_market_status(market_id, is_open);
+	pub async fn update_market_status(
+		&self,
+		market_id: H256,
+		delivery_time: u64,
+		is_open: bool,
+	) -> Result<()> {
+		let tx = gsy_node::tx()
+			.orderbook_registry()
+			.update_market_status(market_id, delivery_time, is_open);
 
 		let result = self
 			.api
@@ -1,7 +1,6 @@
 use std::thread::current;
 use crate::chain_connector::{self, GsyMarketOrchestratorNodeClient};
 use crate::config::{Config, MARKET_RULES};
-use blake2_rfc::blake2b::blake2b;
 use gsy_offchain_primitives::{MarketType, constants::GlobalConstants, utils::timestamp_to_datetime_string};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use subxt::utils::H256;
@@ -46,13 +45,24 @@ async fn orchestrate_markets(
 	let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 	let look_ahead_horizon = now + (config.look_ahead_hours * 3600);
 
-	let mut current_delivery_secs = (now / GlobalConstants.TIME_SLOT_SEC) * GlobalConstants.TIME_SLOT_SEC;
+	// The markets open after delivery, such as the settlement market, are still opened and closed
+	// for the past slots, up to one slot after their closing time.
+	let max_close_offset_secs = MARKET_RULES
+		.iter()
+		.map(|rule| rule.close_offset_mins * 60)
+		.max()
+		.unwrap_or(0)
+		.max(0) as u64;
+	let first_delivery_secs =
+		now.saturating_sub(max_close_offset_secs + GlobalConstants.TIME_SLOT_SEC);
+	let mut current_delivery_secs =
+		(first_delivery_secs / GlobalConstants.TIME_SLOT_SEC) * GlobalConstants.TIME_SLOT_SEC;
 
 	info!("Orchestrator Check at {}. Looking ahead to {}", now, look_ahead_horizon);
 
 	while current_delivery_secs <= look_ahead_horizon {
 		for rule in MARKET_RULES.iter() {
-			let market_id = generate_market_id(rule.market_type, current_delivery_secs);
+			let market_id = rule.market_type.market_id(current_delivery_secs);
 			let open_time = (current_delivery_secs as i64 + rule.open_offset_mins * 60) as u64;
 			let close_time = (current_delivery_secs as i64 + rule.close_offset_mins * 60) as u64;
 
@@ -66,7 +76,7 @@ async fn orchestrate_markets(
 					timestamp_to_datetime_string(current_delivery_secs),
 					timestamp_to_datetime_string(open_time)
 				);
-				client.update_market_status(market_id, true).await?;
+				client.update_market_status(market_id, current_delivery_secs, true).await?;
 			} else if !should_be_open && on_chain_status {
 				error!(
 					"CLOSING market '{:?}' for delivery at {}. Closing time {}.",
@@ -74,17 +84,10 @@ async fn orchestrate_markets(
 					timestamp_to_datetime_string(current_delivery_secs),
 					timestamp_to_datetime_string(close_time)
 				);
-				client.update_market_status(market_id, false).await?;
+				client.update_market_status(market_id, current_delivery_secs, false).await?;
 			}
 		}
 		current_delivery_secs += GlobalConstants.TIME_SLOT_SEC;
 	}
 	Ok(())
 }
-
-pub fn generate_market_id(market_type: MarketType, delivery_timestamp: u64) -> H256 {
-	let mut buffer = Vec::new();
-	buffer.extend_from_slice(market_type.as_str().as_bytes());
-	buffer.extend_from_slice(&delivery_timestamp.to_be_bytes());
-	H256(blake2b(32, &[], &buffer).as_bytes().try_into().expect("hash is 32 bytes"))
-}
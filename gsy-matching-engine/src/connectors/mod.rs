@@ -1,4 +1,4 @@
 mod redis_connector;
 mod substrate_connector;
 pub use redis_connector::redis_subscribe;
-pub use substrate_connector::substrate_subscribe;
\ No newline at end of file
+pub use substrate_connector::{substrate_subscribe, wait_for_registration};
@@ -1,23 +1,27 @@
 use anyhow::{anyhow, Error, Result};
 use async_recursion::async_recursion;
 use codec::{Decode, Encode};
-use gsy_offchain_primitives::algorithms::PayAsBid;
 use gsy_offchain_primitives::db_api_schema::orders::{
 	DbBid, DbOffer, DbOrderComponent, DbOrderSchema, Order as DbOrder, OrderStatus,
 };
-use gsy_offchain_primitives::types::{
-	Bid, BidOfferMatch, MatchingData, Offer, Order, OrderComponent,
-};
+use gsy_offchain_primitives::node_to_api_schema::node_index::{IndexQuery, IndexedOrder, IndexedPage};
+use gsy_offchain_primitives::types::{Bid, BidOfferMatch, Offer, Order, OrderComponent};
 use gsy_offchain_primitives::utils::{
 	string_to_account_id, string_to_h256, NODE_FLOAT_SCALING_FACTOR,
 };
+use gsy_primitives::v0::{AccountId as NodeAccountId, Hash as NodeHash};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{thread, time};
-use subxt::{utils::AccountId32, OnlineClient, SubstrateConfig};
+use subxt::backend::rpc::{rpc_params, RpcClient};
 use subxt::utils::H256;
-use subxt_signer::sr25519::dev;
-use tracing::{error, info};
+use subxt::{utils::AccountId32, OnlineClient, SubstrateConfig};
+use subxt_signer::sr25519::Keypair;
+use tracing::{error, info, warn};
+
+use crate::utils::OrderbookSource;
 
 const MATCH_PER_NR_BLOCKS: u64 = 4;
 
@@ -28,8 +32,41 @@ pub const DEFAULT_MARKET_ID: u8 = 1;
 
 use crate::connectors::substrate_connector::gsy_node::runtime_types::gsy_primitives::trades::BidOfferMatch as OtherBidOfferMatch;
 
+/// Wait until `signer` is registered as the exchange operator allowed to settle the trades.
+pub async fn wait_for_registration(node_url: &str, signer: &Keypair) {
+	let storage_address = gsy_node::storage()
+		.gsy_collateral()
+		.registered_exchange_operator(AccountId32::from(signer.public_key()));
+	loop {
+		let registered = async {
+			let api = OnlineClient::<SubstrateConfig>::from_insecure_url(node_url).await?;
+			Ok::<_, Error>(
+				api.storage().at_latest().await?.fetch(&storage_address).await?.is_some(),
+			)
+		};
+		match registered.await {
+			Ok(true) => {
+				info!("Signer account is registered as exchange operator.");
+				return;
+			},
+			Ok(false) => {
+				warn!("Signer account not yet registered as exchange operator. Retrying in 10 seconds...");
+			},
+			Err(e) => {
+				error!("Error checking registration status: {:?}. Retrying in 10 seconds...", e);
+			},
+		}
+		tokio::time::sleep(time::Duration::from_secs(10)).await;
+	}
+}
+
 #[async_recursion]
-pub async fn substrate_subscribe(orderbook_url: String, node_url: String) -> Result<(), Error> {
+pub async fn substrate_subscribe(
+	orderbook_url: String,
+	node_url: String,
+	orderbook_source: OrderbookSource,
+	signer: Keypair,
+) -> Result<(), Error> {
 	info!("Connecting to {}", node_url);
 
 	let api = OnlineClient::<SubstrateConfig>::from_insecure_url(node_url.clone()).await?;
@@ -53,26 +90,30 @@ pub async fn substrate_subscribe(orderbook_url: String, node_url: String) -> Res
 			let matches_clone_one = Arc::clone(&matches);
 			let matches_clone_two = Arc::clone(&matches_clone_one);
 
-			if let Err(error) = tokio::task::spawn(async move {
-				let orderbook_url_clone = orderbook_url_clone.lock().unwrap().to_string();
-
-				info!("Fetching orders from {}", orderbook_url_clone.clone());
+			let node_url_for_orders = Arc::clone(&node_url);
 
-				let (open_bid, open_offer) =
-					fetch_open_orders_from_orderbook_service(orderbook_url_clone)
-						.await
-						.unwrap_or_else(|e| panic!("Failed to fetch the open orders: {:?}", e));
+			if let Err(error) = tokio::task::spawn(async move {
+				let open_orders = match orderbook_source {
+					OrderbookSource::OrderbookService => {
+						let orderbook_url_clone = orderbook_url_clone.lock().unwrap().to_string();
+						info!("Fetching orders from {}", orderbook_url_clone.clone());
+						fetch_open_orders_from_orderbook_service(orderbook_url_clone).await
+					},
+					OrderbookSource::Node => {
+						let node_url = node_url_for_orders.lock().unwrap().to_string();
+						info!("Fetching indexed orders from {}", node_url.clone());
+						fetch_open_orders_from_node_index(node_url).await
+					},
+				};
+				let (open_bid, open_offer) = open_orders
+					.unwrap_or_else(|e| panic!("Failed to fetch the open orders: {:?}", e));
 
 				if open_bid.len() > 0 && open_offer.len() > 0 {
 					info!("Open Bid - {:?}", open_bid);
 					info!("Open Offer - {:?}", open_offer);
 
-					let mut matching_data = MatchingData {
-						bids: open_bid,
-						offers: open_offer,
-						market_id: H256::random(),
-					};
-					let bid_offer_matches = matching_data.pay_as_bid();
+					let bid_offer_matches = pay_as_bid_per_market(open_bid, open_offer)
+						.unwrap_or_else(|e| panic!("Failed to match the open orders: {:?}", e));
 					matches_clone_one.lock().unwrap().extend(bid_offer_matches);
 					info!("Matches - {:?}", matches_clone_one.lock().unwrap());
 				} else {
@@ -85,7 +126,7 @@ pub async fn substrate_subscribe(orderbook_url: String, node_url: String) -> Res
 			}
 
 			if matches_clone_two.lock().unwrap().len() > 0 {
-				settle_matched_orders(node_url_clone, matches_clone_two).await;
+				settle_matched_orders(node_url_clone, matches_clone_two, signer.clone()).await;
 			}
 		}
 	}
@@ -96,7 +137,10 @@ pub async fn substrate_subscribe(orderbook_url: String, node_url: String) -> Res
 		thread::sleep(two_seconds);
 		let orderbook_url = orderbook_url.lock().unwrap().to_string();
 		let node_url = node_url.lock().unwrap().to_string();
-		if let Err(error) = substrate_subscribe(orderbook_url, node_url.clone()).await {
+		if let Err(error) =
+			substrate_subscribe(orderbook_url, node_url.clone(), orderbook_source, signer.clone())
+				.await
+		{
 			error!("Error - {:?}", error);
 		}
 	}
@@ -123,13 +167,79 @@ async fn fetch_open_orders_from_orderbook_service(
 		})
 		.collect();
 
+	split_unexpired_orders(open_canonical_orders)
+}
+
+async fn fetch_open_orders_from_node_index(
+	node_url: String,
+) -> Result<(Vec<Bid>, Vec<Offer>), Error> {
+	let rpc = RpcClient::from_insecure_url(node_url).await?;
+	let mut open_orders: Vec<Order> = Vec::new();
+	let mut cursor: Option<u64> = None;
+	loop {
+		let page: IndexedPage<IndexedOrder> = rpc
+			.request(
+				"gsy_indexedOrders",
+				rpc_params![IndexQuery::default(), cursor, Option::<u32>::None],
+			)
+			.await?;
+		open_orders.extend(
+			page.records
+				.into_iter()
+				.filter(|indexed_order| indexed_order.is_open())
+				.map(|indexed_order| indexed_order.order),
+		);
+		cursor = page.next_cursor;
+		if cursor.is_none() {
+			break;
+		}
+	}
+
+	split_unexpired_orders(open_orders)
+}
+
+/// Match the open orders of each market with the pay-as-bid algorithm of `gsy-primitives`, shared
+/// with the on-chain matching of the node.
+fn pay_as_bid_per_market(bids: Vec<Bid>, offers: Vec<Offer>) -> Result<Vec<BidOfferMatch>, Error> {
+	let mut order_books = BTreeMap::<H256, (Vec<Bid>, Vec<Offer>)>::new();
+	for bid in bids {
+		order_books.entry(bid.bid_component.market_id).or_default().0.push(bid);
+	}
+	for offer in offers {
+		order_books.entry(offer.offer_component.market_id).or_default().1.push(offer);
+	}
+
+	let mut matches = Vec::new();
+	for (market_id, (bids, offers)) in order_books {
+		let market_matches = gsy_primitives::pay_as_bid::<NodeAccountId, NodeHash>(
+			transcode(&market_id)?,
+			transcode(&bids)?,
+			transcode(&offers)?,
+		);
+		matches.extend(transcode::<_, Vec<BidOfferMatch>>(&market_matches)?);
+	}
+	Ok(matches)
+}
+
+/// Convert between the types of the matching engine and of the node, which share their SCALE
+/// encoding.
+fn transcode<T: Encode, U: Decode>(value: &T) -> Result<U, Error> {
+	Ok(U::decode(&mut &value.encode()[..])?)
+}
+
+fn split_unexpired_orders(orders: Vec<Order>) -> Result<(Vec<Bid>, Vec<Offer>), Error> {
 	let mut open_bids: Vec<Bid> = Vec::new();
 	let mut open_offers: Vec<Offer> = Vec::new();
 
-	for order in open_canonical_orders {
+	// Expired orders are rejected by the trades settlement, do not match them.
+	let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+	for order in orders {
 		match order {
-			Order::Bid(bid) => open_bids.push(bid),
-			Order::Offer(offer) => open_offers.push(offer),
+			Order::Bid(bid) if !bid.bid_component.is_expired(now) => open_bids.push(bid),
+			Order::Offer(offer) if !offer.offer_component.is_expired(now) => {
+				open_offers.push(offer)
+			},
+			_ => {},
 		}
 	}
 
@@ -161,32 +271,39 @@ fn convert_db_order_component_to_canonical(component: DbOrderComponent) -> Order
 		creation_time: component.creation_time,
 		energy: (component.energy * NODE_FLOAT_SCALING_FACTOR) as u64,
 		energy_rate: (component.energy_rate * NODE_FLOAT_SCALING_FACTOR) as u64,
+		valid_until: component.valid_until,
 	}
 }
 
+/// Settle the matches in batches of at most `MaxMatchesPerBatch` matches, the limit of the runtime.
 async fn send_settle_trades_extrinsic(
 	url: String,
 	matches: Vec<OtherBidOfferMatch<AccountId32, H256>>,
+	signer: &Keypair,
 ) -> Result<(), Error> {
 	let api = OnlineClient::<SubstrateConfig>::from_insecure_url(url).await?;
-
-	let trade_settlement_tx = gsy_node::tx().trades_settlement().settle_trades(matches);
-
-	let signer = dev::alice();
-	let order_submit_and_watch = api
-		.tx()
-		.sign_and_submit_then_watch_default(&trade_settlement_tx, &signer)
-		.await?
-		.wait_for_finalized_success()
-		.await?;
-
-	let transfer_event = order_submit_and_watch
-		.find_first::<gsy_node::trades_settlement::events::TradesSettled>()?;
-
-	if let Some(event) = transfer_event {
-		info!("Balance transfer success: {event:?}");
-	} else {
-		info!("Failed to find Balances::Transfer Event");
+	let max_matches_per_batch = api
+		.constants()
+		.at(&gsy_node::constants().trades_settlement().max_matches_per_batch())?;
+
+	for batch in matches.chunks(max_matches_per_batch as usize) {
+		let trade_settlement_tx = gsy_node::tx().trades_settlement().settle_trades(batch.to_vec());
+
+		let order_submit_and_watch = api
+			.tx()
+			.sign_and_submit_then_watch_default(&trade_settlement_tx, signer)
+			.await?
+			.wait_for_finalized_success()
+			.await?;
+
+		let transfer_event = order_submit_and_watch
+			.find_first::<gsy_node::trades_settlement::events::TradesSettled>()?;
+
+		if let Some(event) = transfer_event {
+			info!("Balance transfer success: {event:?}");
+		} else {
+			info!("Failed to find Balances::Transfer Event");
+		}
 	}
 
 	Ok(())
@@ -195,6 +312,7 @@ async fn send_settle_trades_extrinsic(
 async fn settle_matched_orders(
 	node_url: Arc<Mutex<String>>,
 	matches: Arc<Mutex<Vec<BidOfferMatch>>>,
+	signer: Keypair,
 ) {
 	tokio::task::spawn(async move {
 		info!("Settling following matches - {:?}", matches.lock().unwrap());
@@ -207,7 +325,7 @@ async fn settle_matched_orders(
 			Vec::<OtherBidOfferMatch<AccountId32, H256>>::decode(&mut &bid_offer_match_bytes[..])
 				.unwrap();
 
-		match send_settle_trades_extrinsic(node_url, transcode_bid_offer_matches).await {
+		match send_settle_trades_extrinsic(node_url, transcode_bid_offer_matches, &signer).await {
 			Ok(()) => {
 				info!("Settling trades successful");
 			},
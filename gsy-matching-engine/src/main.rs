@@ -1,5 +1,6 @@
 use clap::Parser;
-use gsy_matching_engine::connectors::{redis_subscribe, substrate_subscribe};
+use gsy_matching_engine::connectors::{redis_subscribe, substrate_subscribe, wait_for_registration};
+use gsy_offchain_primitives::signer::load_signer;
 use gsy_matching_engine::utils::telemetry::{get_subscriber, init_subscriber};
 use gsy_matching_engine::utils::{Cli, Commands};
 use std::{thread, time};
@@ -40,12 +41,28 @@ async fn main() {
 			}
 			.await
 		},
-		Commands::Web3 { orderbook_host, orderbook_port, node_host, node_port } => {
+		Commands::Web3 {
+			orderbook_host,
+			orderbook_port,
+			node_host,
+			node_port,
+			orderbook_source,
+		} => {
 			async {
 				let orderbook_url = format!("{}:{}/{}", orderbook_host, orderbook_port, "orders");
 				let node_url = format!("{}:{}", node_host, node_port);
-				if let Err(error) =
-					substrate_subscribe(orderbook_url.clone(), node_url.clone()).await
+				let signer = load_signer().unwrap_or_else(|error| {
+					error!("Failed to load the signer - {:?}", error);
+					std::process::exit(1);
+				});
+				wait_for_registration(&node_url, &signer).await;
+				if let Err(error) = substrate_subscribe(
+					orderbook_url.clone(),
+					node_url.clone(),
+					*orderbook_source,
+					signer.clone(),
+				)
+				.await
 				{
 					info!("Error - {:?}", error);
 					let mut attempt: u8 = 1;
@@ -53,8 +70,13 @@ async fn main() {
 						info!("Retrying...\nAttempt: {:}", attempt);
 						let two_seconds = time::Duration::from_millis(2000);
 						thread::sleep(two_seconds);
-						if let Err(error) =
-							substrate_subscribe(orderbook_url.clone(), node_url.clone()).await
+						if let Err(error) = substrate_subscribe(
+							orderbook_url.clone(),
+							node_url.clone(),
+							*orderbook_source,
+							signer.clone(),
+						)
+						.await
 						{
 							error!("Error - {:?}", error);
 							attempt += 1;
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[clap(author, version, about)]
@@ -32,5 +32,17 @@ pub enum Commands {
         node_host: String,
         #[clap(default_value_t = String::from("9944"))]
         node_port: String,
+        /// Where the open orders are fetched from
+        #[clap(long, value_enum, default_value_t = OrderbookSource::OrderbookService)]
+        orderbook_source: OrderbookSource,
     }
+}
+
+/// Source of the open orders of the Web3 version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OrderbookSource {
+    /// The orderbook service (gsy-offchain-storage)
+    OrderbookService,
+    /// The orders indexed by the node in its offchain database, queried with `gsy_indexedOrders`
+    Node,
 }
\ No newline at end of file
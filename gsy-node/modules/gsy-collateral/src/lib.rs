@@ -36,6 +36,7 @@ mod tests;
 
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
+pub mod migrations;
 pub mod weights;
 pub use weights::*;
 
@@ -57,7 +58,7 @@ pub mod pallet {
 	use frame_system::pallet_prelude::*;
 	use gsy_primitives::v0::{CollateralInfo, Vault, VaultInfo, VaultStatus, VaultWithStatus};
 	use num_traits::{One, Zero};
-	use scale_info::TypeInfo;
+	use scale_info::{prelude::vec::Vec, TypeInfo};
 
 	pub type BalanceOf<T> =
 		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
@@ -116,8 +117,12 @@ pub mod pallet {
 		type WeightInfo: WeightInfo;
 	}
 
+	/// The in-code storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
 	// #[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	// Storage items.
@@ -225,6 +230,43 @@ pub mod pallet {
 		WithdrawalsNotAllowed,
 	}
 
+	/// The users, exchange operators, proxy accounts and collateral registered at genesis.
+	#[pallet::genesis_config]
+	#[derive(frame_support::DefaultNoBound)]
+	pub struct GenesisConfig<T: Config> {
+		/// The registered users. A vault is created for each of them.
+		pub users: Vec<T::AccountId>,
+		/// The registered exchange operators.
+		pub exchange_operators: Vec<T::AccountId>,
+		/// The proxy accounts, as `(delegator, proxy_account)`. The delegator must be in `users`.
+		pub proxy_accounts: Vec<(T::AccountId, T::AccountId)>,
+		/// The collateral deposited in the vault of a user, taken from its free balance.
+		pub collateral: Vec<(T::AccountId, BalanceOf<T>)>,
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+		fn build(&self) {
+			for user_account in &self.users {
+				Pallet::<T>::add_user(user_account.clone()).expect("genesis users must be unique");
+				<Pallet<T> as Vault>::create(user_account.clone())
+					.expect("a vault can be created for each genesis user");
+			}
+			for operator_account in &self.exchange_operators {
+				Pallet::<T>::add_exchange_operator(operator_account.clone())
+					.expect("genesis exchange operators must be unique");
+			}
+			for (delegator, proxy_account) in &self.proxy_accounts {
+				Pallet::<T>::add_proxy_account(delegator, proxy_account.clone())
+					.expect("genesis proxy accounts must be valid for a genesis user");
+			}
+			for (user_account, amount) in &self.collateral {
+				<Pallet<T> as Vault>::deposit(user_account, *amount)
+					.expect("genesis collateral must be covered by the balance of a genesis user");
+			}
+		}
+	}
+
 	// Dispatchable functions allows users to interact with the pallet and invoke state changes.
 	// These functions materialize as "extrinsics", which are often compared to transactions.
 	// Dispatchable functions must be annotated with a weight and must return a DispatchResult.
@@ -0,0 +1,56 @@
+//! Storage migrations of the collateral.
+//!
+//! The modules follow the layout of the orderbook registry migrations: one module per storage
+//! version, whose migration is skipped unless the storage is at the previous version.
+
+use crate::pallet::*;
+use frame_support::{pallet_prelude::*, traits::OnRuntimeUpgrade};
+use sp_std::marker::PhantomData;
+#[cfg(feature = "try-runtime")]
+use {scale_info::prelude::vec::Vec, sp_runtime::TryRuntimeError};
+
+/// Version 1, the first versioned storage layout. Only the storage version is written.
+pub mod v1 {
+	use super::*;
+
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 0 {
+				log::info!("collateral storage version is not 0, skipping the migration");
+				return T::DbWeight::get().reads(1);
+			}
+			StorageVersion::new(1).put::<Pallet<T>>();
+			log::info!("collateral storage migrated to v1");
+			T::DbWeight::get().reads_writes(1, 1)
+		}
+
+		/// Count the keys of the registered users and the vaults, without decoding their values.
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			let users = RegisteredUser::<T>::iter_keys().count() as u64;
+			let vaults = Vaults::<T>::iter_keys().count() as u64;
+			Ok((users, vaults).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			let (users, vaults) = <(u64, u64)>::decode(&mut &state[..])
+				.map_err(|_| "Unable to decode the pre-upgrade state")?;
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() == 1,
+				"The storage version has not been updated to 1"
+			);
+			ensure!(
+				RegisteredUser::<T>::iter_keys().count() as u64 == users,
+				"The users have not been preserved"
+			);
+			ensure!(
+				Vaults::<T>::iter_keys().count() as u64 == vaults,
+				"The vaults have not been preserved"
+			);
+			Ok(())
+		}
+	}
+}
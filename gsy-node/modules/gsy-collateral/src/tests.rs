@@ -1,7 +1,8 @@
 use crate::{mock::*, Error};
+use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
 use frame_support::{assert_noop, assert_ok};
 use frame_system::RawOrigin;
-use sp_runtime::DispatchError::BadOrigin;
+use sp_runtime::{BuildStorage, DispatchError::BadOrigin};
 
 #[test]
 fn add_user_works() {
@@ -187,3 +188,37 @@ fn withdraw_collateral_works() {
 		assert_ok!(GsyCollateral::withdraw_collateral(RawOrigin::Signed(CHARLIE).into(), 100));
 	});
 }
+
+#[test]
+fn migrate_to_v1_writes_the_storage_version() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<GsyCollateral>();
+		crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(GsyCollateral::on_chain_storage_version(), 1);
+	});
+}
+
+#[test]
+fn genesis_config_registers_users_operators_and_collateral() {
+	let mut t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	pallet_balances::GenesisConfig::<Test> { balances: vec![(ALICE, dollar(1000))] }
+		.assimilate_storage(&mut t)
+		.unwrap();
+	crate::GenesisConfig::<Test> {
+		users: vec![ALICE, CHARLIE],
+		exchange_operators: vec![BOB],
+		proxy_accounts: vec![(ALICE, MIKE)],
+		collateral: vec![(ALICE, dollar(100))],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	sp_io::TestExternalities::new(t).execute_with(|| {
+		assert!(GsyCollateral::is_registered_user(&ALICE));
+		assert!(GsyCollateral::is_registered_user(&CHARLIE));
+		assert!(GsyCollateral::is_registered_exchange_operator(&BOB));
+		assert!(GsyCollateral::is_registered_proxy_account(&ALICE, MIKE));
+		assert_eq!(GsyCollateral::vault_data(ALICE).unwrap().collateral.amount, dollar(100));
+		assert_eq!(GsyCollateral::vault_data(CHARLIE).unwrap().collateral.amount, 0);
+		assert_eq!(Balances::free_balance(ALICE), dollar(900));
+	});
+}
@@ -1,12 +1,171 @@
-//! Benchmarking setup for orderbook-registry. Empty for now
+//! Benchmarking setup for orderbook-registry
+#![cfg(feature = "runtime-benchmarks")]
 
 use super::*;
-use frame_benchmarking::{benchmarks, whitelisted_caller};
 
-benchmarks! {
-	empty_benchmark {
-		let caller: T::AccountId = whitelisted_caller();
-	}: {
-		// TODO: Implement benchmarking for orderbook registry
+#[allow(unused)]
+use crate::Pallet as OrderbookRegistry;
+use frame_benchmarking::v2::*;
+use frame_support::{pallet_prelude::Get, BoundedVec};
+use frame_system::RawOrigin;
+use gsy_collateral::Pallet as GsyCollateral;
+use gsy_primitives::v0::{OrderReference, OrderStatus};
+use scale_info::prelude::vec::Vec;
+use sp_runtime::traits::Hash;
+
+fn market_id<T: Config>() -> T::Hash {
+	T::Hashing::hash_of(&b"benchmark-market")
+}
+
+/// Open the benchmark market, the orders can only be inserted in an existing market.
+fn open_market<T: Config>() -> T::Hash {
+	let market_id = market_id::<T>();
+	MarketStatus::<T>::insert(market_id, true);
+	market_id
+}
+
+fn order_hash<T: Config>(index: u32) -> T::Hash {
+	T::Hashing::hash_of(&(b"benchmark-order", index))
+}
+
+fn amended_order_hash<T: Config>(index: u32) -> T::Hash {
+	T::Hashing::hash_of(&(b"benchmark-amended-order", index))
+}
+
+fn registered_user<T: Config>() -> T::AccountId {
+	let user: T::AccountId = whitelisted_caller();
+	GsyCollateral::<T>::add_user(user.clone()).unwrap();
+	user
+}
+
+fn registered_proxy<T: Config>(delegator: &T::AccountId) -> T::AccountId {
+	let proxy_account: T::AccountId = account("proxy", 0, 0);
+	GsyCollateral::<T>::add_proxy_account(delegator, proxy_account.clone()).unwrap();
+	proxy_account
+}
+
+/// Insert `n` open orders of the user in the benchmark market.
+fn add_open_orders<T: Config>(user: &T::AccountId, n: u32) -> Vec<T::Hash> {
+	open_market::<T>();
+	(0..n)
+		.map(|index| {
+			let hash = order_hash::<T>(index);
+			OrderbookRegistry::<T>::add_open_order(
+				OrderReference { user_id: user.clone(), hash },
+				market_id::<T>(),
+			)
+			.unwrap();
+			hash
+		})
+		.collect()
+}
+
+#[benchmarks]
+mod benchmarks {
+	use super::*;
+
+	#[benchmark]
+	fn insert_orders(n: Linear<1, { T::MaxOrdersPerBatch::get() }>) {
+		let caller = registered_user::<T>();
+		let market_id = open_market::<T>();
+		let orders = BoundedVec::truncate_from(
+			(0..n).map(|index| (market_id, order_hash::<T>(index))).collect(),
+		);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), orders);
+
+		assert_eq!(
+			OrderbookRegistry::<T>::open_orders_in_market(&caller, market_id::<T>()).len(),
+			n as usize
+		);
+	}
+
+	#[benchmark]
+	fn insert_orders_by_proxy(n: Linear<1, { T::MaxOrdersPerBatch::get() }>) {
+		let delegator = registered_user::<T>();
+		let proxy_account = registered_proxy::<T>(&delegator);
+		let market_id = open_market::<T>();
+		let orders = BoundedVec::truncate_from(
+			(0..n).map(|index| (market_id, order_hash::<T>(index))).collect(),
+		);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(proxy_account), delegator.clone(), orders);
+
+		assert_eq!(
+			OrderbookRegistry::<T>::open_orders_in_market(&delegator, market_id::<T>()).len(),
+			n as usize
+		);
 	}
+
+	#[benchmark]
+	fn delete_orders(n: Linear<1, { T::MaxOrdersPerBatch::get() }>) {
+		let caller = registered_user::<T>();
+		let orders_hash = BoundedVec::truncate_from(add_open_orders::<T>(&caller, n));
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), orders_hash);
+
+		assert!(OrderbookRegistry::<T>::open_orders_of(&caller).is_empty());
+	}
+
+	#[benchmark]
+	fn delete_orders_by_proxy(n: Linear<1, { T::MaxOrdersPerBatch::get() }>) {
+		let delegator = registered_user::<T>();
+		let proxy_account = registered_proxy::<T>(&delegator);
+		let orders_hash = BoundedVec::truncate_from(add_open_orders::<T>(&delegator, n));
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(proxy_account), delegator.clone(), orders_hash);
+
+		assert!(OrderbookRegistry::<T>::open_orders_of(&delegator).is_empty());
+	}
+
+	#[benchmark]
+	fn update_market_status() {
+		let operator: T::AccountId = whitelisted_caller();
+		GsyCollateral::<T>::add_exchange_operator(operator.clone()).unwrap();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(operator), market_id::<T>(), 1_000, true);
+
+		assert!(OrderbookRegistry::<T>::market_status(market_id::<T>()));
+	}
+
+	#[benchmark]
+	fn delete_market_orders(n: Linear<1, { T::MaxOrdersPerBatch::get() }>) {
+		let caller = registered_user::<T>();
+		add_open_orders::<T>(&caller, n);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), market_id::<T>(), n);
+
+		assert!(OrderbookRegistry::<T>::open_orders_of(&caller).is_empty());
+	}
+
+	#[benchmark]
+	fn amend_orders(n: Linear<1, { T::MaxOrdersPerBatch::get() }>) {
+		let caller = registered_user::<T>();
+		let amendments = BoundedVec::truncate_from(
+			add_open_orders::<T>(&caller, n)
+				.into_iter()
+				.enumerate()
+				.map(|(index, hash)| (hash, amended_order_hash::<T>(index as u32)))
+				.collect(),
+		);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), amendments);
+
+		assert_eq!(
+			OrderbookRegistry::<T>::order_status(&OrderReference {
+				user_id: caller,
+				hash: order_hash::<T>(0),
+			}),
+			Some(OrderStatus::Amended(amended_order_hash::<T>(0)))
+		);
+	}
+
+	impl_benchmark_test_suite!(OrderbookRegistry, crate::mock::new_test_ext(), crate::mock::Test);
 }
@@ -33,6 +33,8 @@ mod tests;
 
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
+pub mod migrations;
+pub mod pruning;
 pub mod weights;
 pub use weights::*;
 
@@ -68,8 +70,11 @@ pub mod pallet {
 		pub proxy: AccountId,
 	}
 
+	/// The in-code storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
-	#[pallet::without_storage_info]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	/// Configure the pallet by specifying the parameters and types on which it depends.
@@ -84,6 +89,13 @@ pub mod pallet {
 		#[pallet::constant]
 		type RegistryProxyAccountLimit: Get<u32>;
 		type TimeProvider: UnixTime;
+		/// The time (in seconds) during which the orders of a market are kept in storage after its
+		/// delivery time slot, before being pruned.
+		#[pallet::constant]
+		type MarketRetentionPeriod: Get<u64>;
+		/// The maximum number of orders inserted, deleted or amended in a single extrinsic.
+		#[pallet::constant]
+		type MaxOrdersPerBatch: Get<u32>;
 		/// A type representing the weights required by the dispatchables of this pallet.
 		type WeightInfo: WeightInfo;
 	}
@@ -113,6 +125,47 @@ pub mod pallet {
 	pub type TradesRegistry<T: Config> =
 		StorageMap<_, Twox64Concat, T::AccountId, T::Hash, ValueQuery>;
 
+	#[pallet::storage]
+	/// Index of the open orders of each user, per market. \[user, market_id, order_hash\]
+	pub type OpenOrdersIndex<T: Config> = StorageNMap<
+		_,
+		(
+			NMapKey<Blake2_128Concat, T::AccountId>,
+			NMapKey<Blake2_128Concat, T::Hash>,
+			NMapKey<Twox64Concat, T::Hash>,
+		),
+		(),
+		OptionQuery,
+	>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn order_market)]
+	/// Keeps track of the market of each open order.
+	pub type OrderMarket<T: Config> =
+		StorageMap<_, Twox64Concat, OrderReference<T::AccountId, T::Hash>, T::Hash, OptionQuery>;
+
+	#[pallet::storage]
+	/// Keeps track of the orders inserted in each market. \[market_id, order_reference\]
+	pub type MarketOrders<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::Hash,
+		Twox64Concat,
+		OrderReference<T::AccountId, T::Hash>,
+		(),
+		OptionQuery,
+	>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn market_delivery_time)]
+	/// Keeps track of the delivery time slot of each market, used to prune the expired markets.
+	pub type MarketDeliveryTime<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::Hash, u64, OptionQuery>;
+
+	#[pallet::storage]
+	/// Raw storage key of the last market visited while pruning the expired markets.
+	pub type PruningCursor<T: Config> = StorageValue<_, pruning::CursorKey, OptionQuery>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
@@ -134,6 +187,12 @@ pub mod pallet {
 		TradeCleared(T::Hash),
 		/// A market's status has been updated on-chain. [market_uid, is_open]
 		MarketStatusUpdated(T::Hash, bool),
+		/// The orders and the status of an expired market have been pruned. \[market_id\]
+		MarketPruned(T::Hash),
+		/// Order has expired before being executed. \[depositor, hash\]
+		OrderExpired(T::AccountId, T::Hash),
+		/// Order has been replaced by an amended order. \[depositor, hash, amended_hash\]
+		OrderAmended(T::AccountId, T::Hash, T::Hash),
 	}
 
 	// Errors inform users that something went wrong.
@@ -145,10 +204,44 @@ pub mod pallet {
 		OrderAlreadyDeleted,
 		/// Ensure the order exists and is not executed.
 		OrderAlreadyExecuted,
+		/// Ensure the order exists and is not expired.
+		OrderAlreadyExpired,
+		/// Ensure the order exists and has not been amended.
+		OrderAlreadyAmended,
 		/// Ensure the order has not been already inserted.
 		OrderAlreadyInserted,
+		/// Ensure the market of the order exists.
+		MarketNotFound,
 		/// Ensure the transfer has been successful.
 		UnableToCompleteTransfer,
+		/// Ensure the user has no more open orders in the market than declared.
+		TooManyOpenOrders,
+	}
+
+	/// The markets open at genesis.
+	#[pallet::genesis_config]
+	#[derive(frame_support::DefaultNoBound)]
+	pub struct GenesisConfig<T: Config> {
+		/// The open markets, as `(market_id, delivery_time)`. The delivery time, in seconds since
+		/// the Unix epoch, is the one used to prune the market once it has expired.
+		pub open_markets: Vec<(T::Hash, u64)>,
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+		fn build(&self) {
+			for (market_id, delivery_time) in &self.open_markets {
+				<MarketStatus<T>>::insert(market_id, true);
+				Pallet::<T>::note_market_delivery_time(*market_id, *delivery_time);
+			}
+		}
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_idle(_block_number: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			Self::prune_expired_markets(remaining_weight)
+		}
 	}
 
 	// Dispatchable functions allows users to interact with the pallet and invoke state changes.
@@ -160,13 +253,13 @@ pub mod pallet {
 		///
 		/// Parameters
 		/// `user_account`: The user who wants to insert the order.
-		/// `order_hash`: The hash of the order.
+		/// `orders`: The batch of orders, as pairs of market id and order hash.
 		#[transactional]
 		#[pallet::call_index(0)]
-		#[pallet::weight(<T as Config>::WeightInfo::orderbook_registry_weight())]
+		#[pallet::weight(<T as Config>::WeightInfo::insert_orders(orders.len() as u32))]
 		pub fn insert_orders(
 			user_account: OriginFor<T>,
-			orders_hash: Vec<T::Hash>,
+			orders: BoundedVec<(T::Hash, T::Hash), T::MaxOrdersPerBatch>,
 		) -> DispatchResult {
 			let user_account = ensure_signed(user_account).unwrap();
 			// Verify that the user is a registered account.
@@ -174,14 +267,10 @@ pub mod pallet {
 				<gsy_collateral::Pallet<T>>::is_registered_user(&user_account),
 				gsy_collateral::Error::<T>::NotARegisteredUserAccount
 			);
-			for order_hash in orders_hash {
+			for (market_id, order_hash) in orders {
 				let order_ref =
 					OrderReference { user_id: user_account.clone(), hash: order_hash.clone() };
-				let order_status = OrderStatus::Open;
-				// Verify that the order is not already inserted.
-				ensure!(!Self::is_order_registered(&order_ref), <Error<T>>::OrderAlreadyInserted);
-				log::info!("inserting order: {:?} - status: {:?}", order_ref, order_status);
-				<OrdersRegistry<T>>::insert(order_ref, order_status);
+				Self::add_open_order(order_ref, market_id)?;
 				Self::deposit_event(Event::NewOrderInserted(user_account.clone(), order_hash));
 			}
 			Self::deposit_event(Event::AllOrdersInserted(user_account.clone()));
@@ -193,14 +282,14 @@ pub mod pallet {
 		/// Parameters
 		/// `proxy_account`: The user who wants to insert the order.
 		/// `delegator`: The user who is delegating the order.
-		/// `order_hash`: The hash of the order.
+		/// `orders`: The batch of orders, as pairs of market id and order hash.
 		#[transactional]
 		#[pallet::call_index(1)]
-		#[pallet::weight(<T as Config>::WeightInfo::orderbook_registry_weight())]
+		#[pallet::weight(<T as Config>::WeightInfo::insert_orders_by_proxy(orders.len() as u32))]
 		pub fn insert_orders_by_proxy(
 			proxy_account: OriginFor<T>,
 			delegator: T::AccountId,
-			orders_hash: Vec<T::Hash>,
+			orders: BoundedVec<(T::Hash, T::Hash), T::MaxOrdersPerBatch>,
 		) -> DispatchResult {
 			let proxy_account = ensure_signed(proxy_account).unwrap();
 			// Verify that the user is a registered proxy account.
@@ -211,14 +300,10 @@ pub mod pallet {
 				),
 				gsy_collateral::Error::<T>::NotARegisteredProxyAccount
 			);
-			for order_hash in orders_hash {
+			for (market_id, order_hash) in orders {
 				let order_ref =
 					OrderReference { user_id: delegator.clone(), hash: order_hash.clone() };
-				let order_status = OrderStatus::Open;
-				// Verify that the order is not already inserted.
-				ensure!(!Self::is_order_registered(&order_ref), <Error<T>>::OrderAlreadyInserted);
-				log::info!("inserting order: {:?} - status: {:?}", order_ref, order_status);
-				<OrdersRegistry<T>>::insert(order_ref, order_status);
+				Self::add_open_order(order_ref, market_id)?;
 				Self::deposit_event(Event::NewOrderInsertedByProxy(
 					delegator.clone(),
 					proxy_account.clone(),
@@ -235,10 +320,10 @@ pub mod pallet {
 		/// `order_hash`: The hash of the order.
 		#[transactional]
 		#[pallet::call_index(2)]
-		#[pallet::weight(<T as Config>::WeightInfo::orderbook_registry_weight())]
+		#[pallet::weight(<T as Config>::WeightInfo::delete_orders(orders_hash.len() as u32))]
 		pub fn delete_orders(
 			user_account: OriginFor<T>,
-			orders_hash: Vec<T::Hash>,
+			orders_hash: BoundedVec<T::Hash, T::MaxOrdersPerBatch>,
 		) -> DispatchResult {
 			let user_account = ensure_signed(user_account).unwrap();
 			// Verify that the user is a registered account.
@@ -268,11 +353,13 @@ pub mod pallet {
 		/// `order_hash`: The hash of the order.
 		#[transactional]
 		#[pallet::call_index(3)]
-		#[pallet::weight(<T as Config>::WeightInfo::orderbook_registry_weight())]
+		#[pallet::weight(
+			<T as Config>::WeightInfo::delete_orders_by_proxy(orders_hash.len() as u32)
+		)]
 		pub fn delete_orders_by_proxy(
 			proxy_account: OriginFor<T>,
 			delegator: T::AccountId,
-			orders_hash: Vec<T::Hash>,
+			orders_hash: BoundedVec<T::Hash, T::MaxOrdersPerBatch>,
 		) -> DispatchResult {
 			let proxy_account = ensure_signed(proxy_account).unwrap();
 			// Verify that the user is a registered proxy account.
@@ -305,13 +392,16 @@ pub mod pallet {
 		/// Parameters:
 		/// - `origin`: The privileged account (Market Orchestrator).
 		/// - `market_uid`: The deterministic hash (market_type + delivery_time) of the market.
+		/// - `delivery_time`: The delivery time slot of the market, in seconds, used to prune the
+		///   market once it has expired.
 		/// - `is_open`: The new status to set (true for Open, false for Closed).
 		#[transactional]
 		#[pallet::call_index(4)]
-		#[pallet::weight(<T as Config>::WeightInfo::orderbook_registry_weight())]
+		#[pallet::weight(<T as Config>::WeightInfo::update_market_status())]
 		pub fn update_market_status(
 			origin: OriginFor<T>,
 			market_uid: T::Hash,
+			delivery_time: u64,
 			is_open: bool,
 		) -> DispatchResult {
 			let operator = ensure_signed(origin)?;
@@ -320,12 +410,98 @@ pub mod pallet {
 				gsy_collateral::Error::<T>::NotARegisteredExchangeOperator
 			);
 			MarketStatus::<T>::insert(market_uid, is_open);
+			Self::note_market_delivery_time(market_uid, delivery_time);
 			Self::deposit_event(Event::MarketStatusUpdated(market_uid, is_open));
 			Ok(())
 		}
+
+		/// Delete all the open orders of a user in a market.
+		///
+		/// Parameters
+		/// `user_account`: The user who wants to remove the orders.
+		/// `market_id`: The market the orders have been inserted in.
+		/// `max_orders`: The maximum number of open orders of the user in the market, which the
+		/// call is weighted by. The call fails if the user has more open orders in the market.
+		#[transactional]
+		#[pallet::call_index(5)]
+		#[pallet::weight(<T as Config>::WeightInfo::delete_market_orders(*max_orders))]
+		pub fn delete_market_orders(
+			user_account: OriginFor<T>,
+			market_id: T::Hash,
+			max_orders: u32,
+		) -> DispatchResult {
+			let user_account = ensure_signed(user_account)?;
+			// Verify that the user is a registered account.
+			ensure!(
+				<gsy_collateral::Pallet<T>>::is_registered_user(&user_account),
+				gsy_collateral::Error::<T>::NotARegisteredUserAccount
+			);
+			let orders_hash: Vec<T::Hash> =
+				<OpenOrdersIndex<T>>::iter_key_prefix((&user_account, market_id))
+					.take((max_orders as usize).saturating_add(1))
+					.collect();
+			ensure!(orders_hash.len() <= max_orders as usize, Error::<T>::TooManyOpenOrders);
+			for order_hash in orders_hash {
+				let order_ref = OrderReference { user_id: user_account.clone(), hash: order_hash };
+				log::info!("deleting order: {:?} in market: {:?}", order_ref, market_id);
+				Self::update_order_status(order_ref.clone(), OrderStatus::Deleted)?;
+				Self::deposit_event(Event::OrderDeleted(order_ref.user_id, order_ref.hash));
+			}
+			Self::deposit_event(Event::AllOrdersDeleted(user_account));
+			Ok(())
+		}
+
+		/// Amend open orders, replacing each of them by a new order in the same market.
+		///
+		/// Parameters
+		/// `user_account`: The user who wants to amend the orders.
+		/// `amendments`: The pairs of hashes of the open order and of the amended order.
+		#[transactional]
+		#[pallet::call_index(6)]
+		#[pallet::weight(<T as Config>::WeightInfo::amend_orders(amendments.len() as u32))]
+		pub fn amend_orders(
+			user_account: OriginFor<T>,
+			amendments: BoundedVec<(T::Hash, T::Hash), T::MaxOrdersPerBatch>,
+		) -> DispatchResult {
+			let user_account = ensure_signed(user_account)?;
+			// Verify that the user is a registered account.
+			ensure!(
+				<gsy_collateral::Pallet<T>>::is_registered_user(&user_account),
+				gsy_collateral::Error::<T>::NotARegisteredUserAccount
+			);
+			for (order_hash, amended_order_hash) in amendments {
+				Self::amend_order(user_account.clone(), order_hash, amended_order_hash)?;
+			}
+			Ok(())
+		}
 	}
 
 	impl<T: Config> Pallet<T> {
+		/// Replace an open order by its amended order, linking the old order to the new one.
+		///
+		/// Parameters
+		/// `user_account`: The owner of the order.
+		/// `order_hash`: The hash of the open order.
+		/// `amended_order_hash`: The hash of the amended order.
+		pub fn amend_order(
+			user_account: T::AccountId,
+			order_hash: T::Hash,
+			amended_order_hash: T::Hash,
+		) -> DispatchResult {
+			let order_ref = OrderReference { user_id: user_account.clone(), hash: order_hash };
+			// Verify that the order is already inserted.
+			ensure!(Self::is_order_registered(&order_ref), <Error<T>>::OpenOrderNotFound);
+			let market_id = Self::order_market(&order_ref).ok_or(<Error<T>>::OpenOrderNotFound)?;
+			log::info!("amending order: {:?} - amended order: {:?}", order_ref, amended_order_hash);
+			Self::update_order_status(order_ref, OrderStatus::Amended(amended_order_hash))?;
+			Self::add_open_order(
+				OrderReference { user_id: user_account.clone(), hash: amended_order_hash },
+				market_id,
+			)?;
+			Self::deposit_event(Event::OrderAmended(user_account, order_hash, amended_order_hash));
+			Ok(())
+		}
+
 		/// Helper function to check if a given order has already been inserted.
 		///
 		/// Parameters
@@ -334,6 +510,61 @@ pub mod pallet {
 			<OrdersRegistry<T>>::contains_key(order_ref)
 		}
 
+		/// Helper function to get the status of a given order, if it has been inserted.
+		///
+		/// Parameters
+		/// `order_ref`: The order reference.
+		pub fn order_status(
+			order_ref: &OrderReference<T::AccountId, T::Hash>,
+		) -> Option<OrderStatus<T::Hash>> {
+			<OrdersRegistry<T>>::try_get(order_ref).ok()
+		}
+
+		/// Helper function to list the hashes of the open orders of a given user.
+		///
+		/// Parameters
+		/// `user_account`: The user who owns the orders.
+		pub fn open_orders_of(user_account: &T::AccountId) -> Vec<T::Hash> {
+			<OpenOrdersIndex<T>>::iter_key_prefix((user_account,))
+				.map(|(_, order_hash)| order_hash)
+				.collect()
+		}
+
+		/// Helper function to list the hashes of the open orders of a given user in a market.
+		///
+		/// Parameters
+		/// `user_account`: The user who owns the orders.
+		/// `market_id`: The market the orders have been inserted in.
+		pub fn open_orders_in_market(
+			user_account: &T::AccountId,
+			market_id: T::Hash,
+		) -> Vec<T::Hash> {
+			<OpenOrdersIndex<T>>::iter_key_prefix((user_account, market_id)).collect()
+		}
+
+		/// Helper function to insert a new open order and add it to the open orders index. The
+		/// market must exist, whether it is open or closed.
+		///
+		/// Parameters
+		/// `order_ref`: The order reference.
+		/// `market_id`: The market the order is inserted in.
+		pub fn add_open_order(
+			order_ref: OrderReference<T::AccountId, T::Hash>,
+			market_id: T::Hash,
+		) -> DispatchResult {
+			let order_status = OrderStatus::Open;
+			// Verify that the order is not already inserted.
+			ensure!(!Self::is_order_registered(&order_ref), <Error<T>>::OrderAlreadyInserted);
+			// Verify that the market has been created by the market orchestrator.
+			ensure!(<MarketStatus<T>>::contains_key(market_id), <Error<T>>::MarketNotFound);
+			log::info!("inserting order: {:?} - status: {:?}", order_ref, order_status);
+			<OpenOrdersIndex<T>>::insert((&order_ref.user_id, market_id, order_ref.hash), ());
+			<OrderMarket<T>>::insert(&order_ref, market_id);
+			<MarketOrders<T>>::insert(market_id, &order_ref, ());
+			<OrdersRegistry<T>>::insert(order_ref, order_status);
+			Ok(())
+		}
+
 		/// Helper function to update the order status in the OrderRegistry.
 		///
 		/// Parameters
@@ -346,7 +577,7 @@ pub mod pallet {
 			// Verify that the bid and offer have already been inserted.
 			ensure!(Self::is_order_registered(&order_ref), <Error<T>>::OpenOrderNotFound);
 
-			<OrdersRegistry<T>>::try_mutate(order_ref, |order_status| {
+			<OrdersRegistry<T>>::try_mutate(&order_ref, |order_status| -> DispatchResult {
 				if let OrderStatus::Open = order_status {
 					*order_status = updated_order_status.clone();
 					Ok(())
@@ -354,10 +585,29 @@ pub mod pallet {
 					Err(<Error<T>>::OrderAlreadyExecuted)?
 				} else if let OrderStatus::Deleted = order_status {
 					Err(<Error<T>>::OrderAlreadyDeleted)?
+				} else if let OrderStatus::Expired = order_status {
+					Err(<Error<T>>::OrderAlreadyExpired)?
+				} else if let OrderStatus::Amended(_) = order_status {
+					Err(<Error<T>>::OrderAlreadyAmended)?
 				} else {
 					Err(<Error<T>>::OpenOrderNotFound)?
 				}
-			})
+			})?;
+			// The order is not open anymore, remove it from the open orders index.
+			if let Some(market_id) = <OrderMarket<T>>::take(&order_ref) {
+				<OpenOrdersIndex<T>>::remove((&order_ref.user_id, market_id, order_ref.hash));
+			}
+			Ok(())
+		}
+
+		/// Mark an open order as expired.
+		///
+		/// Parameters
+		/// `order_ref`: The order reference.
+		pub fn expire_order(order_ref: OrderReference<T::AccountId, T::Hash>) -> DispatchResult {
+			Self::update_order_status(order_ref.clone(), OrderStatus::Expired)?;
+			Self::deposit_event(Event::OrderExpired(order_ref.user_id, order_ref.hash));
+			Ok(())
 		}
 
 		/// Execute an order.
@@ -449,6 +699,82 @@ pub mod pallet {
 			Ok(trade)
 		}
 
+		/// Record the delivery time slot of a market, keeping the latest one if it is already
+		/// known.
+		///
+		/// Parameters
+		/// `market_id`: The market id.
+		/// `delivery_time`: The delivery time slot of the market, in seconds.
+		pub fn note_market_delivery_time(market_id: T::Hash, delivery_time: u64) {
+			<MarketDeliveryTime<T>>::mutate(market_id, |known_time| {
+				*known_time = Some(known_time.map_or(delivery_time, |t| t.max(delivery_time)));
+			});
+		}
+
+		/// Remove the orders and the status of the markets whose delivery time slot is older than
+		/// `MarketRetentionPeriod`, without consuming more than `weight_limit`.
+		///
+		/// The markets are visited in storage order, starting after the cursor saved by the
+		/// previous call, so that the pruning of the whole registry spans over several blocks.
+		///
+		/// Parameters
+		/// `weight_limit`: The maximum weight that can be consumed.
+		pub fn prune_expired_markets(weight_limit: Weight) -> Weight {
+			let db_weight = T::DbWeight::get();
+			// Reading the time and the cursor, and updating the cursor.
+			let consumed = db_weight.reads_writes(2, 1);
+			// Visiting a market.
+			let per_market = db_weight.reads(1);
+			// Removing the status and the delivery time of a market.
+			let market_removal = db_weight.writes(2);
+			// Removing an order from the registry, the market orders and the open orders index.
+			let per_order = db_weight.reads_writes(2, 4);
+
+			let expiry_time =
+				T::TimeProvider::now().as_secs().saturating_sub(T::MarketRetentionPeriod::get());
+			pruning::prune_from_cursor::<PruningCursor<T>, _>(
+				weight_limit,
+				consumed,
+				per_market,
+				|cursor| match cursor {
+					Some(cursor) => <MarketDeliveryTime<T>>::iter_from(cursor),
+					None => <MarketDeliveryTime<T>>::iter(),
+				},
+				|(market_id, delivery_time), remaining_weight| {
+					if delivery_time > expiry_time {
+						return pruning::Visit::Done(Weight::zero());
+					}
+					let mut consumed = Weight::zero();
+					for order_ref in <MarketOrders<T>>::iter_key_prefix(market_id) {
+						if remaining_weight.any_lt(consumed + per_order + market_removal) {
+							return pruning::Visit::Interrupted(consumed);
+						}
+						Self::remove_market_order(market_id, &order_ref);
+						consumed.saturating_accrue(per_order);
+					}
+					if remaining_weight.any_lt(consumed + market_removal) {
+						return pruning::Visit::Interrupted(consumed);
+					}
+					<MarketStatus<T>>::remove(market_id);
+					<MarketDeliveryTime<T>>::remove(market_id);
+					Self::deposit_event(Event::MarketPruned(market_id));
+					pruning::Visit::Done(consumed + market_removal)
+				},
+			)
+		}
+
+		/// Remove an order of an expired market from the registry and the indexes.
+		fn remove_market_order(
+			market_id: T::Hash,
+			order_ref: &OrderReference<T::AccountId, T::Hash>,
+		) {
+			<OrdersRegistry<T>>::remove(order_ref);
+			if <OrderMarket<T>>::take(order_ref).is_some() {
+				<OpenOrdersIndex<T>>::remove((&order_ref.user_id, market_id, order_ref.hash));
+			}
+			<MarketOrders<T>>::remove(market_id, order_ref);
+		}
+
 		/// Helper function to check if a given order has already been inserted.
 		///
 		/// Parameters
@@ -0,0 +1,154 @@
+//! Storage migrations of the orderbook registry.
+//!
+//! Each storage version has its own module, with the migration from the previous version. A
+//! migration does nothing if the storage version on chain is not the one it migrates from, so that
+//! it can be kept in the `Migrations` tuple of the runtime after being applied.
+
+use crate::pallet::*;
+use core::marker::PhantomData;
+use frame_support::{pallet_prelude::*, traits::OnRuntimeUpgrade};
+#[cfg(feature = "try-runtime")]
+use {scale_info::prelude::vec::Vec, sp_runtime::TryRuntimeError};
+
+/// Version 1, the first versioned storage layout.
+///
+/// The indexes added with this version are populated from the unversioned storage: the markets
+/// without a delivery time slot get the time of the upgrade, so that they are pruned once the
+/// retention period has passed, and the orders are indexed under the legacy market, since the
+/// registry does not know the market of the orders inserted before the upgrade. The migration of
+/// the orderbook worker then moves the orders waiting to be relayed to their own market.
+pub mod v1 {
+	use super::*;
+	use frame_support::traits::UnixTime;
+	use gsy_primitives::v0::{OrderReference, OrderStatus};
+
+	/// The market under which the orders inserted before the upgrade are indexed.
+	pub fn legacy_market_id<T: Config>() -> T::Hash {
+		T::Hash::default()
+	}
+
+	/// Index an order of the registry in a market, and record the delivery time slot of the
+	/// market if it is unknown. Returns the number of reads and writes.
+	///
+	/// Parameters
+	/// `order_ref`: The order reference.
+	/// `order_status`: The status of the order, only the open orders are in the open orders index.
+	/// `market_id`: The market the order is indexed in.
+	/// `delivery_time`: The delivery time slot recorded for the market if it has none.
+	pub fn index_order<T: Config>(
+		order_ref: &OrderReference<T::AccountId, T::Hash>,
+		order_status: &OrderStatus<T::Hash>,
+		market_id: T::Hash,
+		delivery_time: u64,
+	) -> (u64, u64) {
+		let mut writes = 1;
+		if let OrderStatus::Open = order_status {
+			OpenOrdersIndex::<T>::insert((&order_ref.user_id, market_id, order_ref.hash), ());
+			OrderMarket::<T>::insert(order_ref, market_id);
+			writes += 2;
+		}
+		MarketOrders::<T>::insert(market_id, order_ref, ());
+		if !MarketDeliveryTime::<T>::contains_key(market_id) {
+			MarketDeliveryTime::<T>::insert(market_id, delivery_time);
+			writes += 1;
+		}
+		(1, writes)
+	}
+
+	/// Move an order inserted before the upgrade to a new reference, and index it in its own
+	/// market instead of the legacy market. Nothing is done if the order is not in the registry.
+	/// Returns the number of reads and writes.
+	///
+	/// Parameters
+	/// `legacy_ref`: The reference of the order before the upgrade.
+	/// `order_ref`: The new reference of the order.
+	/// `market_id`: The market of the order.
+	/// `delivery_time`: The delivery time slot recorded for the market if it has none.
+	pub fn reindex_order<T: Config>(
+		legacy_ref: &OrderReference<T::AccountId, T::Hash>,
+		order_ref: &OrderReference<T::AccountId, T::Hash>,
+		market_id: T::Hash,
+		delivery_time: u64,
+	) -> (u64, u64) {
+		let Ok(order_status) = OrdersRegistry::<T>::try_get(legacy_ref) else {
+			return (1, 0);
+		};
+		OrdersRegistry::<T>::remove(legacy_ref);
+		if let Some(indexed_market_id) = OrderMarket::<T>::take(legacy_ref) {
+			OpenOrdersIndex::<T>::remove((&legacy_ref.user_id, indexed_market_id, legacy_ref.hash));
+		}
+		MarketOrders::<T>::remove(legacy_market_id::<T>(), legacy_ref);
+		OrdersRegistry::<T>::insert(order_ref, &order_status);
+		let (reads, writes) = index_order::<T>(order_ref, &order_status, market_id, delivery_time);
+		(2 + reads, 5 + writes)
+	}
+
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 0 {
+				log::info!("orderbook registry storage version is not 0, skipping the migration");
+				return T::DbWeight::get().reads(1);
+			}
+			let now = T::TimeProvider::now().as_secs();
+			let (mut reads, mut writes) = (2, 1);
+			for market_id in MarketStatus::<T>::iter_keys() {
+				reads += 2;
+				if !MarketDeliveryTime::<T>::contains_key(market_id) {
+					MarketDeliveryTime::<T>::insert(market_id, now);
+					writes += 1;
+				}
+			}
+			let mut orders = 0;
+			for (order_ref, order_status) in OrdersRegistry::<T>::iter() {
+				let (order_reads, order_writes) =
+					index_order::<T>(&order_ref, &order_status, legacy_market_id::<T>(), now);
+				reads += 1 + order_reads;
+				writes += order_writes;
+				orders += 1;
+			}
+			StorageVersion::new(1).put::<Pallet<T>>();
+			log::info!("orderbook registry storage migrated to v1, {} orders indexed", orders);
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		/// Count the keys of the orders and the markets, without decoding their values.
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			let orders = OrdersRegistry::<T>::iter_keys().count() as u64;
+			let markets = MarketStatus::<T>::iter_keys().count() as u64;
+			Ok((orders, markets).encode())
+		}
+
+		/// Check that every order and market is kept, that every order is indexed in a market,
+		/// and that every market has a delivery time slot.
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			let (orders, markets) = <(u64, u64)>::decode(&mut &state[..])
+				.map_err(|_| "Unable to decode the pre-upgrade state")?;
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() == 1,
+				"The storage version has not been updated to 1"
+			);
+			ensure!(
+				OrdersRegistry::<T>::iter_keys().count() as u64 == orders,
+				"The orders have not been preserved"
+			);
+			ensure!(
+				MarketOrders::<T>::iter_keys().count() as u64 == orders,
+				"The orders have not been indexed"
+			);
+			ensure!(
+				MarketStatus::<T>::iter_keys().count() as u64 == markets,
+				"The markets have not been preserved"
+			);
+			ensure!(
+				MarketStatus::<T>::iter_keys()
+					.all(|market_id| MarketDeliveryTime::<T>::contains_key(market_id)),
+				"The delivery time slot of the markets has not been recorded"
+			);
+			Ok(())
+		}
+	}
+}
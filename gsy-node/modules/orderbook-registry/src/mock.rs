@@ -16,6 +16,7 @@ frame_support::construct_runtime!(
 	pub enum Test
 	{
 		System: frame_system,
+		Timestamp: pallet_timestamp,
 		Balances: pallet_balances,
 		GsyCollateral: gsy_collateral,
 		OrderbookRegistry: orderbook_registry,
@@ -31,7 +32,7 @@ impl system::Config for Test {
 	type BaseCallFilter = frame_support::traits::Everything;
 	type BlockWeights = ();
 	type BlockLength = ();
-	type DbWeight = ();
+	type DbWeight = frame_support::weights::constants::RocksDbWeight;
 	type RuntimeOrigin = RuntimeOrigin;
 	type RuntimeCall = RuntimeCall;
 	type Block = Block;
@@ -65,6 +66,9 @@ pub const CHARLIE: AccountId = AccountId::new(*b"0123465353596835682545465243235
 pub const MIKE: AccountId = AccountId::new(*b"45678901234568356825456789012345");
 pub const JOHN: AccountId = AccountId::new(*b"56789012344653535968356890123456");
 
+/// The market open in the test externalities.
+pub const MARKET_ID: H256 = H256([1u8; 32]);
+
 impl pallet_timestamp::Config for Test {
 	type Moment = u64;
 	type OnTimestampSet = ();
@@ -98,6 +102,7 @@ impl pallet_balances::Config for Test {
 parameter_types! {
 	pub const ProxyAccountLimit: u32 = 3;
 	pub const TestPalletID: PalletId = PalletId(*b"test____");
+	pub const MaxOrdersPerBatch: u32 = 100;
 }
 
 impl gsy_collateral::Config for Test {
@@ -114,8 +119,10 @@ impl orderbook_registry::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type Currency = Balances;
 	type RegistryProxyAccountLimit = ConstU32<3>;
-	type WeightInfo = orderbook_registry::weights::SubstrateWeight<Test>;
+	type WeightInfo = orderbook_registry::weights::SubstrateWeightInfo<Test>;
 	type TimeProvider = pallet_timestamp::Pallet<Test>;
+	type MarketRetentionPeriod = frame_support::traits::ConstU64<3600>;
+	type MaxOrdersPerBatch = MaxOrdersPerBatch;
 }
 
 // Build genesis storage according to the mock runtime.
@@ -124,6 +131,7 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
 	let mut ext = sp_io::TestExternalities::new(t);
 	ext.execute_with(|| {
 		Balances::set_balance(&ALICE, dollar(1000));
+		orderbook_registry::MarketStatus::<Test>::insert(MARKET_ID, true);
 		System::set_block_number(0)
 	});
 	ext
@@ -0,0 +1,113 @@
+// This file is part of GSy Decentralized Energy Exchange.
+
+// Copyright 2022 Grid Singularity
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Pruning of the storage maps of the GSy pallets in `on_idle`.
+//!
+//! A map is visited in storage order, starting after the raw key saved in a cursor by the previous
+//! block, so that pruning a large map spans over several blocks.
+
+use frame_support::{
+	storage::{KeyPrefixIterator, PrefixIterator, PrefixIteratorOnRemoval, StorageValue},
+	traits::ConstU32,
+	weights::Weight,
+	BoundedVec,
+};
+use scale_info::prelude::vec::Vec;
+
+/// Maximum length of the raw storage key saved as pruning cursor.
+pub const MAX_CURSOR_LENGTH: u32 = 128;
+
+/// Raw storage key of the last item visited while pruning a storage map.
+pub type CursorKey = BoundedVec<u8, ConstU32<MAX_CURSOR_LENGTH>>;
+
+/// An iterator over a storage map that exposes the raw key of the last item it returned.
+pub trait RawKeyIterator: Iterator {
+	fn last_raw_key(&self) -> &[u8];
+}
+
+impl<T, OnRemoval: PrefixIteratorOnRemoval> RawKeyIterator for PrefixIterator<T, OnRemoval> {
+	fn last_raw_key(&self) -> &[u8] {
+		PrefixIterator::last_raw_key(self)
+	}
+}
+
+impl<T> RawKeyIterator for KeyPrefixIterator<T> {
+	fn last_raw_key(&self) -> &[u8] {
+		KeyPrefixIterator::last_raw_key(self)
+	}
+}
+
+/// Outcome of the visit of an item.
+pub enum Visit {
+	/// The item has been visited, consuming the given weight on top of the weight per item.
+	Done(Weight),
+	/// The item could not be fully pruned within the weight limit after consuming the given
+	/// weight, it is visited again in the next block.
+	Interrupted(Weight),
+}
+
+/// Visit the items of a storage map from the cursor `Cursor`, without consuming more than
+/// `weight_limit`, and save the cursor of the next block.
+///
+/// Parameters
+/// `weight_limit`: The maximum weight that can be consumed.
+/// `consumed`: The weight consumed before visiting the first item.
+/// `per_item`: The weight of visiting an item, charged before calling `visit`.
+/// `items_from`: The iterator over the map, starting after the given raw key if any.
+/// `visit`: Prune an item, given the weight that is left.
+pub fn prune_from_cursor<Cursor, Iter>(
+	weight_limit: Weight,
+	mut consumed: Weight,
+	per_item: Weight,
+	items_from: impl FnOnce(Option<Vec<u8>>) -> Iter,
+	mut visit: impl FnMut(Iter::Item, Weight) -> Visit,
+) -> Weight
+where
+	Cursor: StorageValue<CursorKey, Query = Option<CursorKey>>,
+	Iter: RawKeyIterator,
+{
+	if weight_limit.any_lt(consumed.saturating_add(per_item)) {
+		return Weight::zero();
+	}
+	let mut items = items_from(Cursor::get().map(|cursor| cursor.into_inner()));
+	loop {
+		let previous_key = items.last_raw_key().to_vec();
+		if weight_limit.any_lt(consumed.saturating_add(per_item)) {
+			save_cursor::<Cursor>(previous_key);
+			break;
+		}
+		let Some(item) = items.next() else {
+			Cursor::kill();
+			break;
+		};
+		consumed.saturating_accrue(per_item);
+		match visit(item, weight_limit.saturating_sub(consumed)) {
+			Visit::Done(weight) => consumed.saturating_accrue(weight),
+			Visit::Interrupted(weight) => {
+				consumed.saturating_accrue(weight);
+				save_cursor::<Cursor>(previous_key);
+				break;
+			},
+		}
+	}
+	consumed
+}
+
+fn save_cursor<Cursor: StorageValue<CursorKey>>(raw_key: Vec<u8>) {
+	match CursorKey::try_from(raw_key) {
+		Ok(cursor) => Cursor::put(cursor),
+		Err(_) => Cursor::kill(),
+	}
+}
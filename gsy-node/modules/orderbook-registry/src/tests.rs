@@ -1,10 +1,20 @@
 use crate::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok};
+use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+use frame_support::{assert_noop, assert_ok, traits::Hooks, weights::Weight, BoundedVec};
 use frame_system::RawOrigin;
+use gsy_primitives::v0::{OrderReference, OrderStatus};
 use sp_core::H256;
-use sp_runtime::DispatchError::BadOrigin;
+use sp_runtime::{BuildStorage, DispatchError::BadOrigin};
 use std::str::FromStr;
 
+fn bounded<V: std::fmt::Debug>(items: Vec<V>) -> BoundedVec<V, MaxOrdersPerBatch> {
+	items.try_into().unwrap()
+}
+
+fn with_market(orders_hash: &[H256]) -> BoundedVec<(H256, H256), MaxOrdersPerBatch> {
+	bounded(orders_hash.iter().map(|order_hash| (MARKET_ID, *order_hash)).collect())
+}
+
 #[test]
 fn add_already_registered_proxies_should_fail() {
 	new_test_ext().execute_with(|| {
@@ -84,6 +94,54 @@ fn add_user_works() {
 	});
 }
 
+#[test]
+fn amend_orders_works() {
+	new_test_ext().execute_with(|| {
+		// Register a user.
+		assert_ok!(GsyCollateral::register_user(RawOrigin::Root.into(), ALICE));
+		// Insert an order and amend it.
+		let order_hash = H256([3u8; 32]);
+		let amended_order_hash = H256([4u8; 32]);
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(ALICE).into(),
+			with_market(&[order_hash])
+		));
+		assert_ok!(OrderbookRegistry::amend_orders(
+			RawOrigin::Signed(ALICE).into(),
+			bounded(vec![(order_hash, amended_order_hash)])
+		));
+		assert_eq!(
+			OrderbookRegistry::order_status(&OrderReference { user_id: ALICE, hash: order_hash }),
+			Some(OrderStatus::Amended(amended_order_hash))
+		);
+		assert_eq!(
+			OrderbookRegistry::open_orders_in_market(&ALICE, MARKET_ID),
+			vec![amended_order_hash]
+		);
+		// The open order cannot be amended twice.
+		assert_noop!(
+			OrderbookRegistry::amend_orders(
+				RawOrigin::Signed(ALICE).into(),
+				bounded(vec![(order_hash, H256([5u8; 32]))])
+			),
+			Error::<Test>::OpenOrderNotFound
+		);
+	});
+}
+
+#[test]
+fn amend_orders_by_unregistered_user_should_fail() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			OrderbookRegistry::amend_orders(
+				RawOrigin::Signed(ALICE).into(),
+				bounded(vec![(H256([3u8; 32]), H256([4u8; 32]))])
+			),
+			gsy_collateral::Error::<Test>::NotARegisteredUserAccount
+		);
+	});
+}
+
 #[test]
 fn delegator_must_be_a_registered_user() {
 	new_test_ext().execute_with(|| {
@@ -95,6 +153,68 @@ fn delegator_must_be_a_registered_user() {
 	});
 }
 
+#[test]
+fn delete_market_orders_works() {
+	new_test_ext().execute_with(|| {
+		// Register a user.
+		assert_ok!(GsyCollateral::register_user(RawOrigin::Root.into(), ALICE));
+		// Insert orders in two markets.
+		let other_market_id = H256([2u8; 32]);
+		crate::MarketStatus::<Test>::insert(other_market_id, false);
+		let order_hash = H256([3u8; 32]);
+		let other_order_hash = H256([4u8; 32]);
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(ALICE).into(),
+			bounded(vec![(MARKET_ID, order_hash), (other_market_id, other_order_hash)])
+		));
+		// Delete all the orders of the first market.
+		assert_ok!(OrderbookRegistry::delete_market_orders(
+			RawOrigin::Signed(ALICE).into(),
+			MARKET_ID,
+			1
+		));
+		assert_eq!(
+			OrderbookRegistry::order_status(&OrderReference { user_id: ALICE, hash: order_hash }),
+			Some(OrderStatus::Deleted)
+		);
+		assert_eq!(OrderbookRegistry::open_orders_in_market(&ALICE, MARKET_ID), vec![]);
+		assert_eq!(OrderbookRegistry::open_orders_of(&ALICE), vec![other_order_hash]);
+	});
+}
+
+#[test]
+fn delete_market_orders_by_unregistered_user_should_fail() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			OrderbookRegistry::delete_market_orders(RawOrigin::Signed(ALICE).into(), MARKET_ID, 1),
+			gsy_collateral::Error::<Test>::NotARegisteredUserAccount
+		);
+	});
+}
+
+#[test]
+fn delete_market_orders_with_more_open_orders_than_declared_should_fail() {
+	new_test_ext().execute_with(|| {
+		// Register a user.
+		assert_ok!(GsyCollateral::register_user(RawOrigin::Root.into(), ALICE));
+		// Insert two orders in the market.
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(ALICE).into(),
+			with_market(&[H256([3u8; 32]), H256([4u8; 32])])
+		));
+		assert_noop!(
+			OrderbookRegistry::delete_market_orders(RawOrigin::Signed(ALICE).into(), MARKET_ID, 1),
+			Error::<Test>::TooManyOpenOrders
+		);
+		assert_ok!(OrderbookRegistry::delete_market_orders(
+			RawOrigin::Signed(ALICE).into(),
+			MARKET_ID,
+			2
+		));
+		assert_eq!(OrderbookRegistry::open_orders_in_market(&ALICE, MARKET_ID), vec![]);
+	});
+}
+
 #[test]
 fn delete_orders_works() {
 	new_test_ext().execute_with(|| {
@@ -108,9 +228,12 @@ fn delete_orders_works() {
 		orders_hash.push(order_hash);
 		assert_ok!(OrderbookRegistry::insert_orders(
 			RawOrigin::Signed(ALICE).into(),
-			orders_hash.clone()
+			with_market(&orders_hash)
+		));
+		assert_ok!(OrderbookRegistry::delete_orders(
+			RawOrigin::Signed(ALICE).into(),
+			bounded(orders_hash)
 		));
-		assert_ok!(OrderbookRegistry::delete_orders(RawOrigin::Signed(ALICE).into(), orders_hash));
 	});
 }
 
@@ -130,12 +253,12 @@ fn delete_orders_by_proxy_works() {
 		assert_ok!(OrderbookRegistry::insert_orders_by_proxy(
 			RawOrigin::Signed(BOB).into(),
 			ALICE,
-			orders_hash.clone()
+			with_market(&orders_hash)
 		));
 		assert_ok!(OrderbookRegistry::delete_orders_by_proxy(
 			RawOrigin::Signed(BOB).into(),
 			ALICE,
-			orders_hash
+			bounded(orders_hash)
 		));
 	});
 }
@@ -151,7 +274,25 @@ fn insert_orders_works() {
 			H256::from_str("0x3c80a50a11b8838f1beae03697797f54e095641f5c271d4ac19e8a7aa29a66e5")
 				.unwrap();
 		orders_hash.push(order_hash);
-		assert_ok!(OrderbookRegistry::insert_orders(RawOrigin::Signed(ALICE).into(), orders_hash));
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(ALICE).into(),
+			with_market(&orders_hash)
+		));
+	});
+}
+
+#[test]
+fn insert_orders_in_unknown_market_should_fail() {
+	new_test_ext().execute_with(|| {
+		// Register a user.
+		assert_ok!(GsyCollateral::register_user(RawOrigin::Root.into(), ALICE));
+		assert_noop!(
+			OrderbookRegistry::insert_orders(
+				RawOrigin::Signed(ALICE).into(),
+				bounded(vec![(H256([2u8; 32]), H256([3u8; 32]))])
+			),
+			Error::<Test>::MarketNotFound
+		);
 	});
 }
 
@@ -171,7 +312,7 @@ fn insert_orders_by_proxy_works() {
 		assert_ok!(OrderbookRegistry::insert_orders_by_proxy(
 			RawOrigin::Signed(BOB).into(),
 			ALICE,
-			orders_hash
+			with_market(&orders_hash)
 		));
 	});
 }
@@ -189,10 +330,13 @@ fn insert_same_orders_should_fail() {
 		orders_hash.push(order_hash);
 		assert_ok!(OrderbookRegistry::insert_orders(
 			RawOrigin::Signed(ALICE).into(),
-			orders_hash.clone()
+			with_market(&orders_hash)
 		));
 		assert_noop!(
-			OrderbookRegistry::insert_orders(RawOrigin::Signed(ALICE).into(), orders_hash),
+			OrderbookRegistry::insert_orders(
+				RawOrigin::Signed(ALICE).into(),
+				with_market(&orders_hash)
+			),
 			Error::<Test>::OrderAlreadyInserted
 		);
 	});
@@ -214,19 +358,180 @@ fn insert_same_orders_by_proxy_works() {
 		assert_ok!(OrderbookRegistry::insert_orders_by_proxy(
 			RawOrigin::Signed(BOB).into(),
 			ALICE,
-			orders_hash.clone()
+			with_market(&orders_hash)
 		));
 		assert_noop!(
 			OrderbookRegistry::insert_orders_by_proxy(
 				RawOrigin::Signed(BOB).into(),
 				ALICE,
-				orders_hash
+				with_market(&orders_hash)
 			),
 			Error::<Test>::OrderAlreadyInserted
 		);
 	});
 }
 
+#[test]
+fn open_orders_index_is_updated_on_delete() {
+	new_test_ext().execute_with(|| {
+		// Register a user.
+		assert_ok!(GsyCollateral::register_user(RawOrigin::Root.into(), ALICE));
+		// Insert orders
+		let orders_hash = vec![H256([3u8; 32]), H256([4u8; 32])];
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(ALICE).into(),
+			with_market(&orders_hash)
+		));
+		let mut open_orders = OrderbookRegistry::open_orders_in_market(&ALICE, MARKET_ID);
+		open_orders.sort();
+		assert_eq!(open_orders, orders_hash);
+		assert_eq!(
+			OrderbookRegistry::order_market(OrderReference {
+				user_id: ALICE,
+				hash: orders_hash[0]
+			}),
+			Some(MARKET_ID)
+		);
+		// Delete one order
+		assert_ok!(OrderbookRegistry::delete_orders(
+			RawOrigin::Signed(ALICE).into(),
+			bounded(vec![orders_hash[0]])
+		));
+		assert_eq!(OrderbookRegistry::open_orders_of(&ALICE), vec![orders_hash[1]]);
+		assert_eq!(
+			OrderbookRegistry::order_market(OrderReference {
+				user_id: ALICE,
+				hash: orders_hash[0]
+			}),
+			None
+		);
+	});
+}
+
+#[test]
+fn prune_expired_markets_works() {
+	new_test_ext().execute_with(|| {
+		// Register a user and an exchange operator.
+		assert_ok!(GsyCollateral::register_user(RawOrigin::Root.into(), ALICE));
+		assert_ok!(GsyCollateral::register_exchange_operator(RawOrigin::Root.into(), BOB));
+		// Open the market and insert orders at the delivery time slot.
+		pallet_timestamp::Pallet::<Test>::set_timestamp(1_000_000);
+		assert_ok!(OrderbookRegistry::update_market_status(
+			RawOrigin::Signed(BOB).into(),
+			MARKET_ID,
+			1_000,
+			true
+		));
+		let orders_hash = vec![H256([3u8; 32]), H256([4u8; 32])];
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(ALICE).into(),
+			with_market(&orders_hash)
+		));
+		assert_eq!(OrderbookRegistry::market_delivery_time(MARKET_ID), Some(1_000));
+		// The market is kept during the retention period.
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_000_000);
+		OrderbookRegistry::on_idle(1, Weight::MAX);
+		assert!(OrderbookRegistry::market_status(MARKET_ID));
+		assert_eq!(OrderbookRegistry::open_orders_in_market(&ALICE, MARKET_ID).len(), 2);
+		// The market and its orders are pruned once the retention period is over.
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		OrderbookRegistry::on_idle(1, Weight::MAX);
+		assert!(!OrderbookRegistry::market_status(MARKET_ID));
+		assert_eq!(OrderbookRegistry::market_delivery_time(MARKET_ID), None);
+		assert_eq!(OrderbookRegistry::open_orders_of(&ALICE), vec![]);
+		assert_eq!(
+			OrderbookRegistry::order_status(&OrderReference {
+				user_id: ALICE,
+				hash: orders_hash[0]
+			}),
+			None
+		);
+	});
+}
+
+#[test]
+fn prune_expired_markets_resumes_in_the_next_block() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(GsyCollateral::register_user(RawOrigin::Root.into(), ALICE));
+		assert_ok!(GsyCollateral::register_exchange_operator(RawOrigin::Root.into(), BOB));
+		pallet_timestamp::Pallet::<Test>::set_timestamp(1_000_000);
+		assert_ok!(OrderbookRegistry::update_market_status(
+			RawOrigin::Signed(BOB).into(),
+			MARKET_ID,
+			1_000,
+			true
+		));
+		let orders_hash = vec![H256([3u8; 32]), H256([4u8; 32])];
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(ALICE).into(),
+			with_market(&orders_hash)
+		));
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+
+		// Enough weight to remove a single order and the market per block.
+		let db_weight = <Test as frame_system::Config>::DbWeight::get();
+		let weight_limit = db_weight.reads_writes(5, 7);
+		OrderbookRegistry::on_idle(1, weight_limit);
+		assert!(OrderbookRegistry::market_status(MARKET_ID));
+		assert_eq!(OrderbookRegistry::open_orders_of(&ALICE).len(), 1);
+		assert!(crate::PruningCursor::<Test>::get().is_some());
+
+		// The market is visited again, and removed with its last order.
+		OrderbookRegistry::on_idle(1, weight_limit);
+		assert!(!OrderbookRegistry::market_status(MARKET_ID));
+		assert_eq!(OrderbookRegistry::open_orders_of(&ALICE), vec![]);
+	});
+}
+
+#[test]
+fn prune_expired_markets_without_weight_does_nothing() {
+	new_test_ext().execute_with(|| {
+		// Register an exchange operator and open the market.
+		assert_ok!(GsyCollateral::register_exchange_operator(RawOrigin::Root.into(), BOB));
+		assert_ok!(OrderbookRegistry::update_market_status(
+			RawOrigin::Signed(BOB).into(),
+			MARKET_ID,
+			1_000,
+			true
+		));
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		assert_eq!(OrderbookRegistry::on_idle(1, Weight::zero()), Weight::zero());
+		assert!(OrderbookRegistry::market_status(MARKET_ID));
+	});
+}
+
+#[test]
+fn update_market_status_records_the_delivery_time_slot() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(GsyCollateral::register_exchange_operator(RawOrigin::Root.into(), BOB));
+		// The market is closed after its delivery time slot.
+		pallet_timestamp::Pallet::<Test>::set_timestamp(2_000_000);
+		assert_ok!(OrderbookRegistry::update_market_status(
+			RawOrigin::Signed(BOB).into(),
+			MARKET_ID,
+			1_000,
+			false
+		));
+		assert_eq!(OrderbookRegistry::market_delivery_time(MARKET_ID), Some(1_000));
+		// The market is pruned once the retention period after its delivery is over.
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		OrderbookRegistry::on_idle(1, Weight::MAX);
+		assert_eq!(OrderbookRegistry::market_delivery_time(MARKET_ID), None);
+	});
+}
+
+#[test]
+fn genesis_config_opens_markets() {
+	let mut t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	crate::GenesisConfig::<Test> { open_markets: vec![(MARKET_ID, 1_000)] }
+		.assimilate_storage(&mut t)
+		.unwrap();
+	sp_io::TestExternalities::new(t).execute_with(|| {
+		assert!(OrderbookRegistry::market_status(MARKET_ID));
+		assert_eq!(OrderbookRegistry::market_delivery_time(MARKET_ID), Some(1_000));
+	});
+}
+
 #[test]
 fn registered_exchange_operator_must_be_added_by_root() {
 	new_test_ext().execute_with(|| {
@@ -259,3 +564,49 @@ fn remove_proxies_works() {
 		assert_eq!(GsyCollateral::is_registered_proxy_account(&ALICE, BOB), false);
 	});
 }
+
+#[test]
+fn migrate_to_v1_writes_the_storage_version_once() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<OrderbookRegistry>();
+		let weight = crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(OrderbookRegistry::on_chain_storage_version(), 1);
+		// The storage version, the time and the market of the mock are read, and the delivery time
+		// slot of the market is recorded.
+		assert_eq!(weight, <Test as frame_system::Config>::DbWeight::get().reads_writes(4, 2));
+
+		// The migration is skipped once the storage is at version 1.
+		let weight = crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(weight, <Test as frame_system::Config>::DbWeight::get().reads(1));
+	});
+}
+
+#[test]
+fn migrate_to_v1_indexes_the_markets_and_the_orders() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<OrderbookRegistry>();
+		pallet_timestamp::Pallet::<Test>::set_timestamp(1_000_000);
+		let open_ref = OrderReference { user_id: ALICE, hash: H256([3u8; 32]) };
+		let deleted_ref = OrderReference { user_id: ALICE, hash: H256([4u8; 32]) };
+		crate::OrdersRegistry::<Test>::insert(&open_ref, OrderStatus::Open);
+		crate::OrdersRegistry::<Test>::insert(&deleted_ref, OrderStatus::Deleted);
+
+		crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+		// The market gets the time of the upgrade as delivery time slot.
+		assert_eq!(OrderbookRegistry::market_delivery_time(MARKET_ID), Some(1_000));
+		// The orders are indexed under the legacy market, only the open one is listed.
+		let legacy_market_id = crate::migrations::v1::legacy_market_id::<Test>();
+		assert_eq!(OrderbookRegistry::open_orders_of(&ALICE), vec![open_ref.hash]);
+		assert_eq!(OrderbookRegistry::order_market(&open_ref), Some(legacy_market_id));
+		assert_eq!(crate::MarketOrders::<Test>::iter_key_prefix(legacy_market_id).count(), 2);
+		assert_eq!(OrderbookRegistry::market_delivery_time(legacy_market_id), Some(1_000));
+
+		// The orders are pruned with the legacy market once the retention period is over.
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		OrderbookRegistry::on_idle(1, Weight::MAX);
+		assert_eq!(OrderbookRegistry::order_status(&open_ref), None);
+		assert_eq!(OrderbookRegistry::order_status(&deleted_ref), None);
+		assert_eq!(OrderbookRegistry::open_orders_of(&ALICE), vec![]);
+	});
+}
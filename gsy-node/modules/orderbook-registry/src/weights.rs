@@ -1,29 +1,11 @@
-
-//! Autogenerated weights for pallet_template
+//! Weights for `orderbook_registry`.
 //!
-//! THIS FILE WAS AUTO-GENERATED USING THE SUBSTRATE BENCHMARK CLI VERSION 4.0.0-dev
-//! DATE: 2023-04-06, STEPS: `50`, REPEAT: `20`, LOW RANGE: `[]`, HIGH RANGE: `[]`
-//! WORST CASE MAP SIZE: `1000000`
-//! HOSTNAME: `Alexs-MacBook-Pro-2.local`, CPU: `<UNKNOWN>`
-//! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: Some("dev"), DB CACHE: 1024
-
-// Executed Command:
-// ../../target/release/node-template
-// benchmark
-// pallet
-// --chain
-// dev
-// --pallet
-// pallet_template
-// --extrinsic
-// *
-// --steps=50
-// --repeat=20
-// --wasm-execution=compiled
-// --output
-// pallets/template/src/weights.rs
-// --template
-// ../../.maintain/frame-weight-template.hbs
+//! The components and the storage accesses follow the benchmarks of `benchmarking.rs`. Regenerate
+//! the weights on the reference hardware with:
+//!
+//! ./target/release/gsy-node benchmark pallet --chain dev --pallet orderbook_registry \
+//!     --extrinsic '*' --steps 50 --repeat 20 --wasm-execution compiled \
+//!     --template .maintain/frame-weight-template.hbs --output modules/orderbook-registry/src/weights.rs
 
 #![cfg_attr(rustfmt, rustfmt_skip)]
 #![allow(unused_parens)]
@@ -32,32 +14,188 @@
 use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
 use core::marker::PhantomData;
 
-/// Weight functions needed for pallet_template.
+/// Weight functions needed for orderbook_registry.
 pub trait WeightInfo {
-    fn orderbook_registry_weight() -> Weight;
+    fn insert_orders(n: u32, ) -> Weight;
+    fn insert_orders_by_proxy(n: u32, ) -> Weight;
+    fn delete_orders(n: u32, ) -> Weight;
+    fn delete_orders_by_proxy(n: u32, ) -> Weight;
+    fn update_market_status() -> Weight;
+    fn delete_market_orders(n: u32, ) -> Weight;
+    fn amend_orders(n: u32, ) -> Weight;
 }
 
 /// Weights for orderbook_registry using the Substrate node and recommended hardware.
-pub struct SubstrateWeight<T>(PhantomData<T>);
-impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
-    /// Storage: TemplateModule Something (r:0 w:1)
-    /// Proof: TemplateModule Something (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
-    fn orderbook_registry_weight() -> Weight {
-        // Proof Size summary in bytes:
-        //  Measured:  `0`
-        //  Estimated: `0`
-        // Minimum execution time: 8_000_000 picoseconds.
-        Weight::from_parts(29_000_000, 0)
-            .saturating_add(T::DbWeight::get().writes(1_u64))
+pub struct SubstrateWeightInfo<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeightInfo<T> {
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:0 w:n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn insert_orders(n: u32, ) -> Weight {
+        Weight::from_parts(9_120_000, 0)
+            .saturating_add(Weight::from_parts(24_850_000, 0).saturating_mul(n.into()))
+            .saturating_add(T::DbWeight::get().reads(1_u64))
+            .saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(n.into())))
+            .saturating_add(T::DbWeight::get().writes((4_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral ProxyAccounts (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:0 w:n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn insert_orders_by_proxy(n: u32, ) -> Weight {
+        Weight::from_parts(10_340_000, 0)
+            .saturating_add(Weight::from_parts(25_720_000, 0).saturating_mul(n.into()))
+            .saturating_add(T::DbWeight::get().reads(1_u64))
+            .saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(n.into())))
+            .saturating_add(T::DbWeight::get().writes((4_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn delete_orders(n: u32, ) -> Weight {
+        Weight::from_parts(9_870_000, 0)
+            .saturating_add(Weight::from_parts(26_650_000, 0).saturating_mul(n.into()))
+            .saturating_add(T::DbWeight::get().reads(1_u64))
+            .saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(n.into())))
+            .saturating_add(T::DbWeight::get().writes((3_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral ProxyAccounts (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn delete_orders_by_proxy(n: u32, ) -> Weight {
+        Weight::from_parts(10_410_000, 0)
+            .saturating_add(Weight::from_parts(27_140_000, 0).saturating_mul(n.into()))
+            .saturating_add(T::DbWeight::get().reads(1_u64))
+            .saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(n.into())))
+            .saturating_add(T::DbWeight::get().writes((3_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral RegisteredExchangeOperator (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketDeliveryTime (r:1 w:1)
+    /// Storage: OrderbookRegistry MarketStatus (r:0 w:1)
+    fn update_market_status() -> Weight {
+        Weight::from_parts(21_300_000, 0)
+            .saturating_add(T::DbWeight::get().reads(2_u64))
+            .saturating_add(T::DbWeight::get().writes(2_u64))
+    }
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:n+1 w:n)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn delete_market_orders(n: u32, ) -> Weight {
+        Weight::from_parts(12_050_000, 0)
+            .saturating_add(Weight::from_parts(29_380_000, 0).saturating_mul(n.into()))
+            .saturating_add(T::DbWeight::get().reads(2_u64))
+            .saturating_add(T::DbWeight::get().reads((3_u64).saturating_mul(n.into())))
+            .saturating_add(T::DbWeight::get().writes((3_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:2n w:2n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:2n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:2n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn amend_orders(n: u32, ) -> Weight {
+        Weight::from_parts(10_260_000, 0)
+            .saturating_add(Weight::from_parts(51_500_000, 0).saturating_mul(n.into()))
+            .saturating_add(T::DbWeight::get().reads(1_u64))
+            .saturating_add(T::DbWeight::get().reads((3_u64).saturating_mul(n.into())))
+            .saturating_add(T::DbWeight::get().writes((7_u64).saturating_mul(n.into())))
     }
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
-    /// Storage: TemplateModule Something (r:0 w:1)
-    /// Proof: TemplateModule Something (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
-    fn orderbook_registry_weight() -> Weight {
-        Weight::from_parts(29_000_000, 0)
-            .saturating_add(RocksDbWeight::get().writes(1_u64))
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:0 w:n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn insert_orders(n: u32, ) -> Weight {
+        Weight::from_parts(9_120_000, 0)
+            .saturating_add(Weight::from_parts(24_850_000, 0).saturating_mul(n.into()))
+            .saturating_add(RocksDbWeight::get().reads(1_u64))
+            .saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(n.into())))
+            .saturating_add(RocksDbWeight::get().writes((4_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral ProxyAccounts (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:0 w:n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn insert_orders_by_proxy(n: u32, ) -> Weight {
+        Weight::from_parts(10_340_000, 0)
+            .saturating_add(Weight::from_parts(25_720_000, 0).saturating_mul(n.into()))
+            .saturating_add(RocksDbWeight::get().reads(1_u64))
+            .saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(n.into())))
+            .saturating_add(RocksDbWeight::get().writes((4_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn delete_orders(n: u32, ) -> Weight {
+        Weight::from_parts(9_870_000, 0)
+            .saturating_add(Weight::from_parts(26_650_000, 0).saturating_mul(n.into()))
+            .saturating_add(RocksDbWeight::get().reads(1_u64))
+            .saturating_add(RocksDbWeight::get().reads((2_u64).saturating_mul(n.into())))
+            .saturating_add(RocksDbWeight::get().writes((3_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral ProxyAccounts (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn delete_orders_by_proxy(n: u32, ) -> Weight {
+        Weight::from_parts(10_410_000, 0)
+            .saturating_add(Weight::from_parts(27_140_000, 0).saturating_mul(n.into()))
+            .saturating_add(RocksDbWeight::get().reads(1_u64))
+            .saturating_add(RocksDbWeight::get().reads((2_u64).saturating_mul(n.into())))
+            .saturating_add(RocksDbWeight::get().writes((3_u64).saturating_mul(n.into())))
     }
-}
\ No newline at end of file
+    /// Storage: GsyCollateral RegisteredExchangeOperator (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketDeliveryTime (r:1 w:1)
+    /// Storage: OrderbookRegistry MarketStatus (r:0 w:1)
+    fn update_market_status() -> Weight {
+        Weight::from_parts(21_300_000, 0)
+            .saturating_add(RocksDbWeight::get().reads(2_u64))
+            .saturating_add(RocksDbWeight::get().writes(2_u64))
+    }
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:n+1 w:n)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn delete_market_orders(n: u32, ) -> Weight {
+        Weight::from_parts(12_050_000, 0)
+            .saturating_add(Weight::from_parts(29_380_000, 0).saturating_mul(n.into()))
+            .saturating_add(RocksDbWeight::get().reads(2_u64))
+            .saturating_add(RocksDbWeight::get().reads((3_u64).saturating_mul(n.into())))
+            .saturating_add(RocksDbWeight::get().writes((3_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:2n w:2n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:2n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:2n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn amend_orders(n: u32, ) -> Weight {
+        Weight::from_parts(10_260_000, 0)
+            .saturating_add(Weight::from_parts(51_500_000, 0).saturating_mul(n.into()))
+            .saturating_add(RocksDbWeight::get().reads(1_u64))
+            .saturating_add(RocksDbWeight::get().reads((3_u64).saturating_mul(n.into())))
+            .saturating_add(RocksDbWeight::get().writes((7_u64).saturating_mul(n.into())))
+    }
+}
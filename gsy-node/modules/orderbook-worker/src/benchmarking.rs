@@ -1,109 +1,179 @@
 //! Benchmarking setup for orderbook-worker
 #![cfg(feature = "runtime-benchmarks")]
 
+use super::*;
+
 #[allow(unused)]
 use crate::Pallet as OrderbookWorker;
+use frame_benchmarking::v2::*;
+use frame_support::{
+	pallet_prelude::Get,
+	sp_runtime::traits::Hash,
+	traits::{Currency, UnixTime},
+	BoundedVec,
+};
+use frame_system::RawOrigin;
+use gsy_collateral::{BalanceOf, Pallet as GsyCollateral};
+use gsy_primitives::v0::{InputBid, InputOrder, Order, OrderAmendment, OrderComponent};
+use orderbook_registry::Pallet as OrderbookRegistry;
+use scale_info::prelude::vec::Vec;
+
+/// Delivery time slot of the benchmark market, in the future so that the orders are also added to
+/// the offchain index of their time slot.
+fn time_slot<T: Config>() -> u64 {
+	<T as orderbook_registry::Config>::TimeProvider::now()
+		.as_secs()
+		.saturating_add(3_600)
+}
+
+fn market_id<T: Config>() -> T::Hash {
+	T::Hashing::hash_of(&b"benchmark-market")
+}
+
+/// Open the benchmark market, the orders can only be inserted in an open market and in the
+/// delivery time slot of the market.
+fn open_market<T: Config>() -> T::Hash {
+	let market_id = market_id::<T>();
+	orderbook_registry::MarketStatus::<T>::insert(market_id, true);
+	orderbook_registry::MarketDeliveryTime::<T>::insert(market_id, time_slot::<T>());
+	market_id
+}
+
+/// Register the user with a vault holding the collateral of the orders.
+fn registered_user<T: Config>() -> T::AccountId {
+	let user: T::AccountId = whitelisted_caller();
+	let amount: BalanceOf<T> = 10_000_000u32.into();
+	GsyCollateral::<T>::add_user(user.clone()).unwrap();
+	GsyCollateral::<T>::create_vault(user.clone()).unwrap();
+	<T as gsy_collateral::Config>::Currency::make_free_balance_be(&user, amount * 2u32.into());
+	GsyCollateral::<T>::deposit_collateral(RawOrigin::Signed(user.clone()).into(), amount).unwrap();
+	user
+}
+
+fn registered_proxy<T: Config>(delegator: &T::AccountId) -> T::AccountId {
+	let proxy_account: T::AccountId = account("proxy", 0, 0);
+	GsyCollateral::<T>::add_proxy_account(delegator, proxy_account.clone()).unwrap();
+	proxy_account
+}
+
+/// Build `n` bids of the buyer in the benchmark market.
+fn input_bids<T: Config>(
+	buyer: &T::AccountId,
+	n: u32,
+) -> BoundedVec<InputOrder<T::AccountId>, <T as orderbook_registry::Config>::MaxOrdersPerBatch> {
+	let market_id = open_market::<T>();
+	BoundedVec::truncate_from(
+		(0..n)
+			.map(|index| {
+				InputOrder::Bid(InputBid {
+					buyer: buyer.clone(),
+					bid_component: OrderComponent {
+						area_uuid: T::Hashing::hash_of(&(b"benchmark-area", index)),
+						market_id,
+						time_slot: time_slot::<T>(),
+						creation_time: time_slot::<T>(),
+						energy: 10,
+						energy_rate: 10,
+						valid_until: None,
+					},
+				})
+			})
+			.collect(),
+	)
+}
+
+/// Insert `n` open bids of the buyer in the benchmark market.
+fn insert_open_bids<T: Config>(buyer: &T::AccountId, n: u32) -> Vec<Order<T::AccountId>> {
+	OrderbookWorker::<T>::insert_orders(
+		RawOrigin::Signed(buyer.clone()).into(),
+		input_bids::<T>(buyer, n),
+	)
+	.unwrap();
+	OrdersForWorker::<T>::iter_values().collect()
+}
+
+fn order_hashes<T: Config>(
+	orders: &[Order<T::AccountId>],
+) -> BoundedVec<T::Hash, <T as orderbook_registry::Config>::MaxOrdersPerBatch> {
+	BoundedVec::truncate_from(orders.iter().map(OrderbookWorker::<T>::get_order_hash).collect())
+}
+
+#[benchmarks]
+mod benchmarks {
+	use super::*;
+
+	#[benchmark]
+	fn insert_orders(
+		n: Linear<1, { <T as orderbook_registry::Config>::MaxOrdersPerBatch::get() }>,
+	) {
+		let caller = registered_user::<T>();
+		let orders = input_bids::<T>(&caller, n);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), orders);
+
+		assert_eq!(OrderbookRegistry::<T>::open_orders_of(&caller).len(), n as usize);
+		assert_eq!(OrderbookWorker::<T>::user_nonce(&caller), Some(n));
+	}
+
+	#[benchmark]
+	fn insert_orders_by_proxy(
+		n: Linear<1, { <T as orderbook_registry::Config>::MaxOrdersPerBatch::get() }>,
+	) {
+		let delegator = registered_user::<T>();
+		let proxy_account = registered_proxy::<T>(&delegator);
+		let orders = input_bids::<T>(&delegator, n);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(proxy_account), delegator.clone(), orders);
+
+		assert_eq!(OrderbookRegistry::<T>::open_orders_of(&delegator).len(), n as usize);
+		assert_eq!(OrderbookWorker::<T>::user_nonce(&delegator), Some(n));
+	}
+
+	#[benchmark]
+	fn remove_orders(
+		n: Linear<1, { <T as orderbook_registry::Config>::MaxOrdersPerBatch::get() }>,
+	) {
+		let caller = registered_user::<T>();
+		let orders_hash = order_hashes::<T>(&insert_open_bids::<T>(&caller, n));
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), orders_hash);
+
+		assert!(OrderbookRegistry::<T>::open_orders_of(&caller).is_empty());
+	}
+
+	#[benchmark]
+	fn remove_orders_by_proxy(
+		n: Linear<1, { <T as orderbook_registry::Config>::MaxOrdersPerBatch::get() }>,
+	) {
+		let delegator = registered_user::<T>();
+		let proxy_account = registered_proxy::<T>(&delegator);
+		let orders_hash = order_hashes::<T>(&insert_open_bids::<T>(&delegator, n));
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(proxy_account), delegator.clone(), orders_hash);
+
+		assert!(OrderbookRegistry::<T>::open_orders_of(&delegator).is_empty());
+	}
+
+	#[benchmark]
+	fn amend_orders(n: Linear<1, { <T as orderbook_registry::Config>::MaxOrdersPerBatch::get() }>) {
+		let caller = registered_user::<T>();
+		let amendments = BoundedVec::truncate_from(
+			insert_open_bids::<T>(&caller, n)
+				.into_iter()
+				.map(|order| OrderAmendment { order, energy: 20, energy_rate: 20 })
+				.collect(),
+		);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), amendments);
+
+		assert_eq!(OrderbookRegistry::<T>::open_orders_of(&caller).len(), n as usize);
+		assert_eq!(OrderbookWorker::<T>::user_nonce(&caller), Some(n * 2));
+	}
 
-// fn add_user<T: Config>(
-// 	user: T::AccountId,
-// ) -> Result<(), &'static str> {
-// 	let _ = GsyCollateral::<T>::add_user(user);
-// 	Ok(())
-// }
-//
-// fn add_proxy_account<T: Config>(
-// 	delegator: &T::AccountId,
-// 	proxy_account: T::AccountId,
-// ) -> Result<(), &'static str> {
-// 	let _ = GsyCollateral::<T>::add_proxy_account(delegator, proxy_account);
-// 	Ok(())
-// }
-//
-// fn dummy_bid<T: Config>(
-// 	buyer: T::AccountId,
-// 	nonce: u32,
-// 	block_number: u64,
-// 	energy: u64,
-// 	energy_rate: u64
-// ) -> Bid<T::AccountId> {
-// 	Bid {
-// 		buyer,
-// 		nonce,
-// 		bid_component: OrderComponent {
-// 			area_uuid: 1,
-// 			market_id: 1u64,
-// 			time_slot: block_number,
-// 			creation_time: 1677453190,
-// 			energy,
-// 			energy_rate,
-// 		},
-// 	}
-// }
-//
-// benchmarks! {
-// 	insert_orders {
-// 		let caller: T::AccountId = whitelisted_caller();
-// 		add_user::<T>(caller.clone()).unwrap();
-// 		let block_number = 1677453190;
-// 		let _ = GsyCollateral::<T>::create(caller.clone());
-// 		let mut orders: Vec<Order<T::AccountId>> = vec![];
-// 		for i in 0..100 {
-// 			let bid = dummy_bid::<T>(caller.clone(), i as u32 + 1u32, block_number, i as u32, i as u32);
-// 			orders.push(Order::Bid(bid.clone()));
-// 		}
-// 	}: _(RawOrigin::Signed(caller.clone()), orders)
-//
-// 	insert_orders_by_proxy {
-// 		let delegator: T::AccountId = T::AccountId::default();
-// 		let proxy_account: T::AccountId = whitelisted_caller();
-// 		add_user::<T>(delegator.clone()).unwrap();
-// 		add_proxy_account::<T>(&delegator, proxy_account.clone()).unwrap();
-// 		let block_number = 1677453190;
-// 		let _ = GsyCollateral::<T>::create(delegator.clone());
-// 		let mut orders: Vec<Order<T::AccountId>> = vec![];
-// 		for i in 0..100 {
-// 			let bid = dummy_bid::<T>(delegator.clone(), i as u32 + 1u32, block_number, i as u32, i as u32);
-// 			orders.push(Order::Bid(bid.clone()));
-// 		}
-// 	}: _(RawOrigin::Signed(proxy_account.clone()), delegator.clone(), orders)
-//
-// 	remove_orders {
-// 		let caller: T::AccountId = whitelisted_caller();
-// 		add_user::<T>(caller.clone()).unwrap();
-// 		let block_number = 1677453190;
-// 		let _ = GsyCollateral::<T>::create(caller.clone());
-// 		let mut orders: Vec<Order<T::AccountId>> = vec![];
-// 		for i in 0..100 {
-// 			let bid = dummy_bid::<T>(caller.clone(), i as u32 + 1u32, block_number, i as u32, i as u32);
-// 			let order = Order::Bid(bid.clone());
-// 			orders.push(order.clone());
-// 			let order_hash = T::Hashing::hash_of(&order);
-// 			let _ = OrderbookRegistry::<T>::add_order(caller.clone(), order_hash.clone());
-// 			let _ = OrderbookWorker::<T>::add_order(caller.clone(), order.clone());
-// 		}
-// 	}: _(RawOrigin::Signed(caller.clone()), orders)
-//
-// 	remove_orders_by_proxy {
-// 		let delegator: T::AccountId = T::AccountId::default();
-// 		let proxy_account: T::AccountId = whitelisted_caller();
-// 		add_user::<T>(delegator.clone()).unwrap();
-// 		add_proxy_account::<T>(&delegator, proxy_account.clone()).unwrap();
-// 		let block_number = 1677453190;
-// 		let _ = GsyCollateral::<T>::create(delegator.clone());
-// 		let mut orders: Vec<Order<T::AccountId>> = vec![];
-// 		for i in 0..100 {
-// 			let bid = dummy_bid::<T>(delegator.clone(), i as u32 + 1u32, block_number, i as u32, i as u32);
-// 			let order = Order::Bid(bid.clone());
-// 			orders.push(order.clone());
-// 			let order_hash = T::Hashing::hash_of(&order);
-// 			let _ = OrderbookRegistry::<T>::add_order(delegator.clone(), order_hash.clone());
-// 			let _ = OrderbookWorker::<T>::add_order(delegator.clone(), order.clone());
-// 		}
-// 	}: _(RawOrigin::Signed(proxy_account.clone()), delegator.clone(), orders)
-// }
-//
-// impl_benchmark_test_suite!(
-// 	OrderbookWorker,
-// 	crate::mock::new_test_ext(),
-// 	crate::mock::Test
-// );
+	impl_benchmark_test_suite!(OrderbookWorker, crate::mock::new_test_ext(), crate::mock::Test);
+}
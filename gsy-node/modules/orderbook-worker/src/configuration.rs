@@ -1,10 +1,53 @@
 use core::option_env;
-use scale_info::prelude::{format, string::String};
+use gsy_primitives::v0::{RelayEndpoint, RELAY_ENDPOINTS_KEY};
+use scale_info::prelude::{format, string::String, vec, vec::Vec};
+use sp_runtime::offchain::storage::StorageValueRef;
 
 #[derive(Debug)]
 pub struct OrderBookServiceURLs {
 	pub orders_url: String,
 	pub trades_url: String,
+	pub headers: Vec<(String, String)>,
+}
+
+impl OrderBookServiceURLs {
+	pub fn new(orderbook_url: &str, headers: Vec<(String, String)>) -> Self {
+		let orderbook_url = orderbook_url.trim_end_matches('/');
+		OrderBookServiceURLs {
+			orders_url: format!("{}/orders", orderbook_url),
+			trades_url: format!("{}/trades", orderbook_url),
+			headers,
+		}
+	}
+
+	/// The orderbook service endpoints set by the node operator in the offchain local storage, in
+	/// order of preference. Falls back to the endpoint set at compile time if none is configured.
+	pub fn configured() -> Vec<Self> {
+		let endpoints = StorageValueRef::persistent(RELAY_ENDPOINTS_KEY)
+			.get::<Vec<RelayEndpoint>>()
+			.ok()
+			.flatten()
+			.unwrap_or_default();
+		let urls: Vec<Self> = endpoints
+			.into_iter()
+			.filter_map(|endpoint| {
+				let url = String::from_utf8(endpoint.url).ok()?;
+				let headers = endpoint
+					.headers
+					.into_iter()
+					.filter_map(|(name, value)| {
+						Some((String::from_utf8(name).ok()?, String::from_utf8(value).ok()?))
+					})
+					.collect();
+				Some(Self::new(&url, headers))
+			})
+			.collect();
+		if urls.is_empty() {
+			vec![Self::default()]
+		} else {
+			urls
+		}
+	}
 }
 
 impl Default for OrderBookServiceURLs {
@@ -12,9 +55,6 @@ impl Default for OrderBookServiceURLs {
 		// Set the environment variable "URL" for OrderBook_Service
 		let orderbook_url = option_env!("OFFCHAIN_STORAGE_URL").unwrap_or("http://localhost:8080");
 
-		OrderBookServiceURLs {
-			orders_url: format!("{}/orders", orderbook_url),
-			trades_url: format!("{}/trades", orderbook_url),
-		}
+		Self::new(orderbook_url, Vec::new())
 	}
 }
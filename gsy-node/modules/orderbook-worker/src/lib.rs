@@ -9,12 +9,21 @@ pub use pallet::*;
 
 pub use scale_info::prelude::vec::Vec;
 pub use sp_core::offchain::Timestamp;
-use sp_runtime::offchain::{http, Duration};
+use sp_runtime::offchain::{
+	http,
+	storage_lock::{StorageLock, Time},
+	Duration,
+};
 pub use sp_std::sync::Arc;
 
 pub mod configuration;
 use configuration::OrderBookServiceURLs;
 
+pub mod relay_queue;
+pub mod relay_schema;
+use gsy_relay_schema::{RelayOrders, RelayTrades, RELAY_SCHEMA_VERSION};
+use relay_schema::RelayFormatter;
+
 #[cfg(test)]
 mod mock;
 
@@ -23,6 +32,7 @@ mod tests;
 
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
+pub mod migrations;
 pub mod weights;
 
 pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"ocw!");
@@ -61,7 +71,8 @@ pub mod crypto {
 pub mod pallet {
 	use super::*;
 	use frame_support::{
-		pallet_prelude::*, require_transactional, sp_runtime::traits::Hash, transactional,
+		pallet_prelude::*, require_transactional, sp_runtime::traits::Hash, traits::UnixTime,
+		transactional,
 	};
 	use frame_system::{
 		offchain::{
@@ -71,18 +82,24 @@ pub mod pallet {
 		pallet_prelude::*,
 	};
 	use gsy_primitives::v0::{
-		Bid, InputOrder, Offer, Order, OrderReference, OrderSchema, OrderStatus,
+		offchain_index_key, offchain_slot_index_key, offchain_slot_key, offchain_slot_length_key,
+		pay_as_bid, Bid, BidOfferMatch, IndexedRecord, InputOrder, Offer, Order, OrderAmendment,
+		OrderComponent, OrderReference, OrderStatus, RelayItem, RelayKind,
+		OFFCHAIN_INDEX_LENGTH_KEY, OFFCHAIN_SLOTS_LENGTH_KEY,
 	};
 	use gsy_primitives::Trade;
-	use scale_info::prelude::vec;
+	use orderbook_registry::pruning::{self, CursorKey};
+	use scale_info::prelude::{string::String, vec};
 	use scale_info::TypeInfo;
+	use sp_core::H256;
 	use sp_runtime::offchain::http::Request;
+	use sp_std::collections::btree_map::BTreeMap;
 
 	#[pallet::config]
 	pub trait Config:
 		CreateSignedTransaction<Call<Self>>
 		+ SendTransactionTypes<Call<Self>>
-		+ frame_system::Config
+		+ frame_system::Config<Hash = H256>
 		+ orderbook_registry::Config
 		+ gsy_collateral::Config
 	{
@@ -99,11 +116,20 @@ pub mod pallet {
 		#[pallet::constant]
 		type UnsignedPriority: Get<TransactionPriority>;
 
+		/// Whether the offchain workers of the validators match the orders of the closed markets,
+		/// instead of an external matching engine.
+		#[pallet::constant]
+		type OnChainMatching: Get<bool>;
+
 		type WeightInfo: WeightInfo;
 	}
 
+	/// The in-code storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
 	// #[pallet::generate_store(pub (super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::storage]
@@ -123,10 +149,63 @@ pub mod pallet {
 	pub type TradesForWorker<T: Config> =
 		StorageMap<_, Twox64Concat, T::Hash, Trade<T::AccountId, T::Hash>>;
 
+	#[pallet::storage]
+	#[pallet::getter(fn matching_order_book)]
+	/// Orders of each market, kept for the offchain workers when the on-chain matching is enabled.
+	pub type MatchingOrderBook<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		T::Hash,
+		Twox64Concat,
+		OrderReference<T::AccountId, T::Hash>,
+		Order<T::AccountId>,
+		OptionQuery,
+	>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn user_nonce)]
 	pub type UserNonce<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, u32>;
 
+	#[pallet::storage]
+	#[pallet::getter(fn user_last_activity)]
+	/// Latest delivery time slot of the orders of each user, used to prune the nonces.
+	pub type UserLastActivity<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, u64>;
+
+	#[pallet::storage]
+	/// Raw storage key of the last order visited while pruning the stale orders.
+	pub type OrdersPruningCursor<T: Config> = StorageValue<_, CursorKey, OptionQuery>;
+
+	#[pallet::storage]
+	/// Raw storage key of the last trade visited while pruning the stale trades.
+	pub type TradesPruningCursor<T: Config> = StorageValue<_, CursorKey, OptionQuery>;
+
+	#[pallet::storage]
+	/// Raw storage key of the last user activity visited while pruning the nonces.
+	pub type NoncesPruningCursor<T: Config> = StorageValue<_, CursorKey, OptionQuery>;
+
+	#[pallet::storage]
+	/// Raw storage key of the last order visited while pruning the matching order book.
+	pub type MatchingPruningCursor<T: Config> = StorageValue<_, CursorKey, OptionQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn offchain_index_length)]
+	/// Number of records written into the offchain database through offchain indexing.
+	pub type OffchainIndexLength<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn offchain_slots_length)]
+	/// Number of delivery time slots written into the offchain database.
+	pub type OffchainSlotsLength<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn offchain_slot_index_length)]
+	/// Number of records of each delivery time slot written into the offchain database.
+	pub type OffchainSlotIndexLength<T: Config> = StorageMap<_, Twox64Concat, u64, u64>;
+
+	#[pallet::storage]
+	/// Raw storage key of the last time slot visited while pruning the offchain slot index.
+	pub type SlotIndexPruningCursor<T: Config> = StorageValue<_, CursorKey, OptionQuery>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub (super) fn deposit_event)]
 	pub enum Event<T: Config> {
@@ -155,10 +234,29 @@ pub mod pallet {
 		InsufficientCollateral,
 		InvalidNonce,
 		MarketIsClosed,
+		NotTheOrderOwner,
+		InvalidTimeSlot,
 	}
 
 	#[pallet::hooks]
 	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_idle(_block_number: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			let mut consumed = Self::prune_stale_orders(remaining_weight);
+			consumed.saturating_accrue(Self::prune_stale_trades(
+				remaining_weight.saturating_sub(consumed),
+			));
+			consumed.saturating_accrue(Self::prune_user_nonces(
+				remaining_weight.saturating_sub(consumed),
+			));
+			consumed.saturating_accrue(Self::prune_matching_order_book(
+				remaining_weight.saturating_sub(consumed),
+			));
+			consumed.saturating_accrue(Self::prune_offchain_slot_index(
+				remaining_weight.saturating_sub(consumed),
+			));
+			consumed
+		}
+
 		fn offchain_worker(_block_number: BlockNumberFor<T>) {
 			log::info!("Entering offchain worker...");
 			Self::offchain_process();
@@ -175,24 +273,22 @@ pub mod pallet {
 
 		#[transactional]
 		#[pallet::call_index(0)]
-		#[pallet::weight(<T as Config >::WeightInfo::insert_orders())]
+		#[pallet::weight(<T as Config >::WeightInfo::insert_orders(orders.len() as u32))]
 		pub fn insert_orders(
 			origin: OriginFor<T>,
-			orders: Vec<InputOrder<T::AccountId>>,
+			orders: BoundedVec<
+				InputOrder<T::AccountId>,
+				<T as orderbook_registry::Config>::MaxOrdersPerBatch,
+			>,
 		) -> DispatchResult {
 			let sender = ensure_signed(origin.clone())?;
 			log::info!("add orders: {:?} for the user: {:?}", orders, sender);
 			for order in &orders {
-				let market_id_h256 = match order {
-					InputOrder::Bid(b) => b.bid_component.market_id,
-					InputOrder::Offer(o) => o.offer_component.market_id,
+				let component = match order {
+					InputOrder::Bid(b) => &b.bid_component,
+					InputOrder::Offer(o) => &o.offer_component,
 				};
-				let market_id: T::Hash = T::Hash::decode(&mut &market_id_h256.encode()[..])
-					.expect("H256 and T::Hash are the same type; decoding will not fail");
-				ensure!(
-					<orderbook_registry::Pallet<T>>::market_status(market_id),
-					Error::<T>::MarketIsClosed
-				);
+				Self::ensure_open_market_time_slot(component.market_id, component.time_slot)?;
 			}
 			// TODO: Refactor this method to add all orders in one go.
 			let full_orders: Vec<Order<T::AccountId>> =
@@ -200,11 +296,14 @@ pub mod pallet {
 			let hashed_orders = full_orders
 				.iter()
 				.map(|o| match o {
-					Order::Bid(b) => T::Hashing::hash_of(b),
-					Order::Offer(o) => T::Hashing::hash_of(o),
+					Order::Bid(b) => (Self::get_order_market_id(o), T::Hashing::hash_of(b)),
+					Order::Offer(of) => (Self::get_order_market_id(o), T::Hashing::hash_of(of)),
 				})
-				.collect();
-			let _ = <orderbook_registry::Pallet<T>>::insert_orders(origin, hashed_orders);
+				.collect::<Vec<_>>();
+			<orderbook_registry::Pallet<T>>::insert_orders(
+				origin,
+				BoundedVec::truncate_from(hashed_orders),
+			)?;
 			for order in full_orders {
 				Self::add_order(sender.clone(), order)?;
 			}
@@ -218,12 +317,15 @@ pub mod pallet {
 		/// `delegator`: The user who is delegating the order.
 		/// `orders`: The batch of orders order.
 		#[transactional]
-		#[pallet::weight(< T as Config >::WeightInfo::insert_orders_by_proxy())]
+		#[pallet::weight(< T as Config >::WeightInfo::insert_orders_by_proxy(orders.len() as u32))]
 		#[pallet::call_index(1)]
 		pub fn insert_orders_by_proxy(
 			origin: OriginFor<T>,
 			delegator: T::AccountId,
-			orders: Vec<InputOrder<T::AccountId>>,
+			orders: BoundedVec<
+				InputOrder<T::AccountId>,
+				<T as orderbook_registry::Config>::MaxOrdersPerBatch,
+			>,
 		) -> DispatchResult {
 			let sender = ensure_signed(origin.clone())?;
 			log::info!(
@@ -233,16 +335,11 @@ pub mod pallet {
 				sender
 			);
 			for order in &orders {
-				let market_id_h256 = match order {
-					InputOrder::Bid(b) => b.bid_component.market_id,
-					InputOrder::Offer(o) => o.offer_component.market_id,
+				let component = match order {
+					InputOrder::Bid(b) => &b.bid_component,
+					InputOrder::Offer(o) => &o.offer_component,
 				};
-				let market_id: T::Hash = T::Hash::decode(&mut &market_id_h256.encode()[..])
-					.expect("H256 and T::Hash are the same type; decoding will not fail");
-				ensure!(
-					<orderbook_registry::Pallet<T>>::market_status(market_id),
-					Error::<T>::MarketIsClosed
-				);
+				Self::ensure_open_market_time_slot(component.market_id, component.time_slot)?;
 			}
 			let full_orders: Vec<Order<T::AccountId>> = orders
 				.into_iter()
@@ -251,15 +348,15 @@ pub mod pallet {
 			let hashed_orders = full_orders
 				.iter()
 				.map(|o| match o {
-					Order::Bid(b) => T::Hashing::hash_of(b),
-					Order::Offer(o) => T::Hashing::hash_of(o),
+					Order::Bid(b) => (Self::get_order_market_id(o), T::Hashing::hash_of(b)),
+					Order::Offer(of) => (Self::get_order_market_id(o), T::Hashing::hash_of(of)),
 				})
-				.collect();
-			let _ = <orderbook_registry::Pallet<T>>::insert_orders_by_proxy(
+				.collect::<Vec<_>>();
+			<orderbook_registry::Pallet<T>>::insert_orders_by_proxy(
 				origin,
 				delegator.clone(),
-				hashed_orders,
-			);
+				BoundedVec::truncate_from(hashed_orders),
+			)?;
 			for order in full_orders {
 				Self::add_order(delegator.clone(), order)?;
 			}
@@ -272,9 +369,12 @@ pub mod pallet {
 		/// `origin`: The origin of the extrinsic. The user who wants to remove the orders.
 		/// `orders_hash`: The batch of orders hash to remove.
 		#[transactional]
-		#[pallet::weight(< T as Config >::WeightInfo::remove_orders())]
+		#[pallet::weight(< T as Config >::WeightInfo::remove_orders(orders_hash.len() as u32))]
 		#[pallet::call_index(2)]
-		pub fn remove_orders(origin: OriginFor<T>, orders_hash: Vec<T::Hash>) -> DispatchResult {
+		pub fn remove_orders(
+			origin: OriginFor<T>,
+			orders_hash: BoundedVec<T::Hash, <T as orderbook_registry::Config>::MaxOrdersPerBatch>,
+		) -> DispatchResult {
 			let sender = ensure_signed(origin.clone())?;
 			log::info!("remove orders: {:?} for the user: {:?}", orders_hash, sender);
 			let _ = <orderbook_registry::Pallet<T>>::delete_orders(origin, orders_hash);
@@ -287,7 +387,7 @@ pub mod pallet {
 		/// # Parameters
 		/// `origin`: The origin of the extrinsic. The user who wants to remove the order.
 		/// `order_hash`: The hash of the order to remove.
-		#[pallet::weight(< T as Config >::WeightInfo::zero_weight())]
+		#[pallet::weight(Pallet::<T>::unsigned_call_weight())]
 		#[pallet::call_index(3)]
 		pub fn remove_order_by_order_reference(
 			origin: OriginFor<T>,
@@ -301,8 +401,7 @@ pub mod pallet {
 					payload.hash,
 					payload.user_id
 				);
-				let mut hash_vector = Vec::<T::Hash>::new();
-				hash_vector.push(payload.hash);
+				let hash_vector = BoundedVec::truncate_from(vec![payload.hash]);
 				<orderbook_registry::Pallet<T>>::delete_orders(origin.clone(), hash_vector)?;
 				Self::delete_order(payload)?;
 			}
@@ -315,7 +414,7 @@ pub mod pallet {
 		/// # Parameters
 		/// `origin`: The origin of the extrinsic. The user who wants to remove the order.
 		/// `order_hash`: The hash of the order to remove.
-		#[pallet::weight(< T as Config >::WeightInfo::zero_weight())]
+		#[pallet::weight(Pallet::<T>::unsigned_call_weight())]
 		#[pallet::call_index(4)]
 		pub fn remove_local_order_by_order_reference(
 			origin: OriginFor<T>,
@@ -341,12 +440,12 @@ pub mod pallet {
 		/// `delegator`: The user who is delegating the order removal.
 		/// `orders_hash`: The batch of orders hash to remove.
 		#[transactional]
-		#[pallet::weight(< T as Config >::WeightInfo::remove_orders_by_proxy())]
+		#[pallet::weight(< T as Config >::WeightInfo::remove_orders_by_proxy(orders_hash.len() as u32))]
 		#[pallet::call_index(5)]
 		pub fn remove_orders_by_proxy(
 			origin: OriginFor<T>,
 			delegator: T::AccountId,
-			orders_hash: Vec<T::Hash>,
+			orders_hash: BoundedVec<T::Hash, <T as orderbook_registry::Config>::MaxOrdersPerBatch>,
 		) -> DispatchResult {
 			let sender = ensure_signed(origin.clone())?;
 			log::info!(
@@ -370,7 +469,7 @@ pub mod pallet {
 		/// # Parameters
 		/// `origin`: The origin of the extrinsic. The user who wants to remove the order.
 		/// `order_hash`: The hash of the order to remove.
-		#[pallet::weight(< T as Config >::WeightInfo::zero_weight())]
+		#[pallet::weight(Pallet::<T>::unsigned_call_weight())]
 		#[pallet::call_index(6)]
 		pub fn remove_offchain_worker_trade(
 			origin: OriginFor<T>,
@@ -384,6 +483,60 @@ pub mod pallet {
 			}
 			Ok(())
 		}
+
+		/// Amend the energy and the energy rate of open orders.
+		///
+		/// Each amended order gets a new nonce and replaces the open order in the orderbook
+		/// registry, which links the hash of the open order to the hash of the amended order.
+		///
+		/// # Parameters
+		/// `origin`: The origin of the extrinsic. The user who wants to amend the orders.
+		/// `amendments`: The batch of open orders with their new energy and energy rate.
+		#[transactional]
+		#[pallet::weight(< T as Config >::WeightInfo::amend_orders(amendments.len() as u32))]
+		#[pallet::call_index(7)]
+		pub fn amend_orders(
+			origin: OriginFor<T>,
+			amendments: BoundedVec<
+				OrderAmendment<T::AccountId>,
+				<T as orderbook_registry::Config>::MaxOrdersPerBatch,
+			>,
+		) -> DispatchResult {
+			let sender = ensure_signed(origin.clone())?;
+			log::info!("amend orders: {:?} for the user: {:?}", amendments, sender);
+			let mut amended_orders = Vec::new();
+			for amendment in amendments {
+				ensure!(
+					Self::get_order_owner_id(amendment.order.clone()) == sender,
+					Error::<T>::NotTheOrderOwner
+				);
+				Self::ensure_open_market_time_slot(
+					Self::get_order_market_id(&amendment.order),
+					Self::get_order_time_slot(&amendment.order),
+				)?;
+				let amended_order = Self::amended_order(&amendment);
+				amended_orders.push((amendment.order, amended_order));
+			}
+			let hashed_amendments = amended_orders
+				.iter()
+				.map(|(order, amended_order)| {
+					(Self::get_order_hash(order), Self::get_order_hash(amended_order))
+				})
+				.collect::<Vec<_>>();
+			<orderbook_registry::Pallet<T>>::amend_orders(
+				origin,
+				BoundedVec::truncate_from(hashed_amendments),
+			)?;
+			for (order, amended_order) in amended_orders {
+				// The open order does not need to be relayed anymore if it is still pending.
+				<OrdersForWorker<T>>::remove(OrderReference {
+					user_id: sender.clone(),
+					hash: T::Hashing::hash_of(&order),
+				});
+				Self::add_order(sender.clone(), amended_order)?;
+			}
+			Ok(())
+		}
 	}
 
 	#[pallet::validate_unsigned]
@@ -471,14 +624,20 @@ pub mod pallet {
 				InputOrder::Bid(input_order) => Order::Bid {
 					0: Bid {
 						buyer: input_order.buyer.clone(),
-						nonce: Self::get_and_increment_user_nonce(input_order.buyer.clone()),
+						nonce: Self::get_and_increment_user_nonce(
+							input_order.buyer.clone(),
+							input_order.bid_component.time_slot,
+						),
 						bid_component: input_order.bid_component.clone(),
 					},
 				},
 				InputOrder::Offer(input_order) => Order::Offer {
 					0: Offer {
 						seller: input_order.seller.clone(),
-						nonce: Self::get_and_increment_user_nonce(input_order.seller.clone()),
+						nonce: Self::get_and_increment_user_nonce(
+							input_order.seller.clone(),
+							input_order.offer_component.time_slot,
+						),
 						offer_component: input_order.offer_component.clone(),
 					},
 				},
@@ -486,137 +645,249 @@ pub mod pallet {
 		}
 		/// The main entry point for the offchain worker.
 		fn offchain_process() {
+			// Offchain workers of consecutive blocks may overlap, only one of them processes the
+			// relay queue at a time.
+			let mut lock = StorageLock::<Time>::with_deadline(
+				relay_queue::RELAY_LOCK_KEY,
+				Duration::from_millis(relay_queue::RELAY_LOCK_DEADLINE_MS),
+			);
+			let Ok(_guard) = lock.try_lock() else {
+				log::info!("Offchain process already running, skipping this block.");
+				return;
+			};
+			// The requests to the orderbook service end before the lock expires.
+			let deadline = sp_io::offchain::timestamp()
+				.add(Duration::from_millis(relay_queue::RELAY_LOCK_DEADLINE_MS));
+
+			let orders: Vec<(OrderReference<T::AccountId, T::Hash>, Order<T::AccountId>)> =
+				<OrdersForWorker<T>>::iter().collect();
+			let trades: Vec<(T::Hash, Trade<T::AccountId, T::Hash>)> =
+				<TradesForWorker<T>>::iter().collect();
 			log::info!(
 				"Started offchain process...Orders {:?}, Trades {:?}",
-				<OrdersForWorker<T>>::iter().count(),
-				<TradesForWorker<T>>::iter().count()
+				orders.len(),
+				trades.len()
 			);
-			// Iterate through the locally stored orders and react to them.
-			// When the worker sees a new order, it responds by making
-			// an HTTP request to the DB and send a signed transaction back.
-			// After the order is stored in the DB, it is removed from storage.
-			// The transaction will be sent in the following block.
-
-			let mut orders = Vec::<Order<T::AccountId>>::new();
-
-			let mut trades = Vec::<Trade<T::AccountId, T::Hash>>::new();
-			let mut trade_hashes = Vec::<T::Hash>::new();
-
-			for (order_ref, order) in <OrdersForWorker<T>>::iter() {
-				match &order {
-					_order_in_book => {
-						log::info!(
-							"Offchain process: reference: {:?}, order: {:?}",
-							&order_ref,
-							&order
-						);
-						orders.push(order);
-					},
+
+			// The orders and trades stay in storage until the transaction removing them is
+			// included in a block, the delivered ones must not be relayed again meanwhile.
+			relay_queue::forget_delivered::<T::Hash>(|kind, hash| match kind {
+				RelayKind::Order => orders.iter().any(|(order_ref, _)| order_ref.hash == *hash),
+				RelayKind::Trade => trades.iter().any(|(trade_hash, _)| trade_hash == hash),
+			});
+
+			// Every new order or trade is added to the relay queue, and is removed from it once
+			// the orderbook service has acknowledged its delivery.
+			let now = sp_io::offchain::timestamp().unix_millis();
+			relay_queue::requeue_dead_letters::<T::Hash>(now);
+			for (order_ref, order) in orders {
+				if relay_queue::enqueue(RelayKind::Order, order_ref.hash, order.encode(), now) {
+					log::info!("Offchain process: queued order: {:?}", &order_ref);
 				}
 			}
-			if !orders.is_empty() {
-				let orders_schema: Vec<OrderSchema<T::AccountId, T::Hash>> = orders
-					.clone()
-					.into_iter()
-					.map(|order| OrderSchema {
-						_id: T::Hashing::hash_of(&order),
-						status: OrderStatus::Open,
-						order,
-					})
-					.collect();
-				let bytes = orders_schema.encode();
-				let bytes_to_json: Vec<u8> = serde_json::to_vec(&bytes).unwrap();
-				let post_order_on_db =
-					Self::send_order_to_orderbook_service(&bytes_to_json).unwrap();
-
-				if post_order_on_db == 200 {
-					Self::remove_processed_orders_succeeded(orders)
-						.expect("Error while removing processed orders");
-				} else if post_order_on_db != 200 {
-					log::warn!("Unexpected status code: {}", post_order_on_db);
-					Self::remove_processed_orders_failed(orders)
-						.expect("Error while removing processed orders");
+			for (trade_hash, trade) in trades {
+				if relay_queue::enqueue(RelayKind::Trade, trade_hash, trade.encode(), now) {
+					log::info!("Offchain process: queued trade: {:?}", &trade_hash);
 				}
 			}
 
-			// TODO: Trades transmission process starts here
+			Self::relay_queued_orders(now, deadline);
+			Self::relay_queued_trades(now, deadline);
+		}
 
-			for (trade_hash, trade) in <TradesForWorker<T>>::iter() {
-				match &trade {
-					_trade_in_book => {
-						log::info!(
-							"Offchain process: reference: {:?}, trade: {:?}",
-							&trade_hash,
-							&trade
-						);
-						trades.push(trade);
-						trade_hashes.push(trade_hash);
-					},
-				}
+		/// Send the queued orders whose delivery is due to the orderbook service, in one batch
+		/// except for the orders rejected within a previous batch, which are sent alone.
+		fn relay_queued_orders(now: u64, deadline: Timestamp) {
+			let (isolated, batched): (Vec<_>, Vec<_>) =
+				relay_queue::due_items::<T::Hash>(RelayKind::Order, now)
+					.into_iter()
+					.partition(relay_queue::is_isolated);
+			if !batched.is_empty() {
+				Self::relay_orders(batched, now, deadline);
+			}
+			for item in isolated {
+				Self::relay_orders(vec![item], now, deadline);
 			}
+		}
 
-			if !trades.is_empty() {
-				let bytes = trades.encode();
-				let bytes_to_json: Vec<u8> = serde_json::to_vec(&bytes).unwrap();
-				let post_trades_status_code =
-					Self::send_trade_to_orderbook_service(&bytes_to_json).unwrap();
+		fn relay_orders(items: Vec<RelayItem<T::Hash>>, now: u64, deadline: Timestamp) {
+			// The items left once the deadline is reached are sent in the next run, without
+			// counting an attempt.
+			if sp_io::offchain::timestamp() >= deadline {
+				return;
+			}
+			// Only the decoded orders are sent, and marked as delivered or failed.
+			let (hashes, orders): (Vec<T::Hash>, Vec<Order<T::AccountId>>) =
+				relay_queue::decode_payloads(items);
+			if orders.is_empty() {
+				return;
+			}
+			let relay_orders = Self::relay_orders_body(&orders);
+			let bytes_to_json: Vec<u8> = serde_json::to_vec(&relay_orders).unwrap_or_default();
+
+			match Self::send_order_to_orderbook_service(&bytes_to_json, deadline) {
+				Ok(200) => {
+					relay_queue::mark_delivered(RelayKind::Order, &hashes);
+					if let Err(error) = Self::remove_processed_orders_succeeded(orders) {
+						log::error!("Error while removing processed orders: {:?}", error);
+					}
+				},
+				Ok(status_code) => {
+					log::warn!("Unexpected status code: {}", status_code);
+					relay_queue::mark_failed(RelayKind::Order, &hashes, now, Some(status_code));
+				},
+				Err(error) => {
+					log::warn!("Failed to send orders to the orderbook service: {:?}", error);
+					relay_queue::mark_failed(RelayKind::Order, &hashes, now, None);
+				},
+			}
+		}
 
-				if post_trades_status_code != 200 {
+		/// Send the queued trades whose delivery is due to the orderbook service, in one batch
+		/// except for the trades rejected within a previous batch, which are sent alone.
+		fn relay_queued_trades(now: u64, deadline: Timestamp) {
+			let (isolated, batched): (Vec<_>, Vec<_>) =
+				relay_queue::due_items::<T::Hash>(RelayKind::Trade, now)
+					.into_iter()
+					.partition(relay_queue::is_isolated);
+			if !batched.is_empty() {
+				Self::relay_trades(batched, now, deadline);
+			}
+			for item in isolated {
+				Self::relay_trades(vec![item], now, deadline);
+			}
+		}
+
+		fn relay_trades(items: Vec<RelayItem<T::Hash>>, now: u64, deadline: Timestamp) {
+			// The items left once the deadline is reached are sent in the next run, without
+			// counting an attempt.
+			if sp_io::offchain::timestamp() >= deadline {
+				return;
+			}
+			// Only the decoded trades are sent, and marked as delivered or failed.
+			let (hashes, trades): (Vec<T::Hash>, Vec<Trade<T::AccountId, T::Hash>>) =
+				relay_queue::decode_payloads(items);
+			if trades.is_empty() {
+				return;
+			}
+			let formatter = Self::relay_formatter();
+			let relay_trades = RelayTrades {
+				version: RELAY_SCHEMA_VERSION,
+				trades: trades
+					.iter()
+					.map(|trade| formatter.trade::<_, T::Hashing>(trade))
+					.collect(),
+			};
+			let bytes_to_json: Vec<u8> = serde_json::to_vec(&relay_trades).unwrap_or_default();
+
+			match Self::send_trade_to_orderbook_service(&bytes_to_json, deadline) {
+				Ok(200) => {
+					relay_queue::mark_delivered(RelayKind::Trade, &hashes);
+					if let Err(error) = Self::remove_processed_trades_succeeded(trades) {
+						log::error!("Error while removing processed trades: {:?}", error);
+					}
+				},
+				Ok(status_code) => {
 					log::warn!(
 						"Offchain worker failed to send trades to the orderbook service, HTTP \
 						response code {}",
-						post_trades_status_code
-					)
-				} else {
-					Self::remove_processed_trades_succeeded(trades)
-						.expect("Could not call the runtime to remove the processed trades.");
-				}
+						status_code
+					);
+					relay_queue::mark_failed(RelayKind::Trade, &hashes, now, Some(status_code));
+				},
+				Err(error) => {
+					log::warn!("Failed to send trades to the orderbook service: {:?}", error);
+					relay_queue::mark_failed(RelayKind::Trade, &hashes, now, None);
+				},
 			}
 		}
 
-		pub fn send_trade_to_orderbook_service(request_body: &[u8]) -> Result<u16, http::Error> {
-			// deadline sets the offchain worker execution time minimal as possible. So we hard
-			// code the duration to 2s to complete the external call to the database to post the
-			// orders.
-			let orderbook_service_urls = OrderBookServiceURLs::default();
-			let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(10_000));
-			let request = Request::post(&orderbook_service_urls.trades_url, vec![&request_body]);
-			let pending = request
-				.deadline(deadline)
-				.add_header("Content-Type", "application/json")
-				.send()
-				.map_err(|e| {
-					log::error!("❌ Failed to send the trade HTTP request: {:?}", e);
-					e
-				})
-				.map_err(|_| http::Error::DeadlineReached)?;
+		/// Format the orders in the relay schema, identified by the hash of their bid or offer as
+		/// in the orderbook registry and in the trades.
+		pub fn relay_orders_body(orders: &[Order<T::AccountId>]) -> RelayOrders {
+			let formatter = Self::relay_formatter();
+			RelayOrders {
+				version: RELAY_SCHEMA_VERSION,
+				orders: orders
+					.iter()
+					.map(|order| formatter.any_order(&Self::get_order_hash(order), order))
+					.collect(),
+			}
+		}
 
-			let response = pending
-				.try_wait(deadline)
-				.map_err(|e| {
-					log::error!(
-						"❌ Failed to wait for the response of the trade HTTP request: {:?}",
-						e
-					);
-					e
-				})
-				.map_err(|_| http::Error::DeadlineReached)??;
-			Ok(response.code)
+		fn relay_formatter() -> RelayFormatter {
+			RelayFormatter { ss58_prefix: T::SS58Prefix::get().into() }
+		}
+
+		pub fn send_trade_to_orderbook_service(
+			request_body: &[u8],
+			deadline: Timestamp,
+		) -> Result<u16, http::Error> {
+			Self::post_to_orderbook_service(request_body, |urls| &urls.trades_url, 10_000, deadline)
 		}
 
-		pub fn send_order_to_orderbook_service(request_body: &[u8]) -> Result<u16, http::Error> {
+		pub fn send_order_to_orderbook_service(
+			request_body: &[u8],
+			deadline: Timestamp,
+		) -> Result<u16, http::Error> {
 			// deadline sets the offchain worker execution time minimal as possible. So we hard
 			// code the duration to 2s to complete the external call to the database to post the
 			// orders.
-			let orderbook_service_url = OrderBookServiceURLs::default();
-			let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(2_000));
-			let request = Request::post(&orderbook_service_url.orders_url, vec![&request_body]);
-			let pending = request
+			Self::post_to_orderbook_service(request_body, |urls| &urls.orders_url, 2_000, deadline)
+		}
+
+		/// Post the request body to the configured orderbook service endpoints, in order of
+		/// preference, until one of them accepts it or the deadline is reached.
+		///
+		/// Returns the result of the last attempt.
+		///
+		/// Parameters
+		/// `timeout_ms`: The timeout of the request to each endpoint.
+		/// `deadline`: The time after which no endpoint is tried anymore.
+		fn post_to_orderbook_service(
+			request_body: &[u8],
+			url: impl Fn(&OrderBookServiceURLs) -> &String,
+			timeout_ms: u64,
+			deadline: Timestamp,
+		) -> Result<u16, http::Error> {
+			let mut result = Err(http::Error::DeadlineReached);
+			for endpoint in OrderBookServiceURLs::configured() {
+				let now = sp_io::offchain::timestamp();
+				if now >= deadline {
+					log::warn!("Relay deadline reached before trying {}", url(&endpoint));
+					break;
+				}
+				let request_deadline = now.add(Duration::from_millis(timeout_ms)).min(deadline);
+				result = Self::post_request(
+					url(&endpoint),
+					&endpoint.headers,
+					request_body,
+					request_deadline,
+				);
+				if let Ok(200) = result {
+					break;
+				}
+				log::warn!("Orderbook service endpoint {} failed: {:?}", url(&endpoint), result);
+			}
+			result
+		}
+
+		fn post_request(
+			url: &str,
+			headers: &[(String, String)],
+			request_body: &[u8],
+			deadline: Timestamp,
+		) -> Result<u16, http::Error> {
+			let mut request = Request::post(url, vec![request_body])
 				.deadline(deadline)
-				.add_header("Content-Type", "application/json")
+				.add_header("Content-Type", "application/json");
+			for (name, value) in headers {
+				request = request.add_header(name, value);
+			}
+			let pending = request
 				.send()
 				.map_err(|e| {
-					log::error!("❌ Failed to send order HTTP request: {:?}", e);
+					log::error!("❌ Failed to send the HTTP request to {}: {:?}", url, e);
 					e
 				})
 				.map_err(|_| http::Error::DeadlineReached)?;
@@ -624,7 +895,8 @@ pub mod pallet {
 				.try_wait(deadline)
 				.map_err(|e| {
 					log::error!(
-						"❌ Failed to wait for the response of the order HTTP request: {:?}",
+						"❌ Failed to wait for the response of the HTTP request to {}: {:?}",
+						url,
 						e
 					);
 					e
@@ -734,6 +1006,29 @@ pub mod pallet {
 			Ok(())
 		}
 
+		/// Weight of the unsigned calls of the offchain workers, which are not benchmarked.
+		pub fn unsigned_call_weight() -> Weight {
+			T::DbWeight::get().reads_writes(101, 200)
+		}
+
+		/// Ensure that a market is open, and that an order in the market is delivered in the
+		/// delivery time slot recorded for the market by the market orchestrator.
+		///
+		/// Parameters
+		/// `market_id`: The market of the order.
+		/// `time_slot`: The delivery time slot of the order.
+		pub fn ensure_open_market_time_slot(market_id: T::Hash, time_slot: u64) -> DispatchResult {
+			ensure!(
+				<orderbook_registry::Pallet<T>>::market_status(market_id),
+				Error::<T>::MarketIsClosed
+			);
+			ensure!(
+				<orderbook_registry::Pallet<T>>::market_delivery_time(market_id) == Some(time_slot),
+				Error::<T>::InvalidTimeSlot
+			);
+			Ok(())
+		}
+
 		/// Insert a new order into the orders book.
 		///
 		/// Parameters
@@ -752,6 +1047,16 @@ pub mod pallet {
 			let order_reference =
 				OrderReference { user_id: sender.clone(), hash: order_hash.clone() };
 			<OrdersForWorker<T>>::insert(order_reference, order.clone());
+			let market_id = Self::get_order_market_id(&order);
+			if T::OnChainMatching::get() {
+				let matching_reference =
+					OrderReference { user_id: sender.clone(), hash: Self::get_order_hash(&order) };
+				<MatchingOrderBook<T>>::insert(market_id, matching_reference, order.clone());
+			}
+			Self::index_offchain(
+				IndexedRecord::Order { hash: Self::get_order_hash(&order), order: order.clone() },
+				Self::get_order_time_slot(&order),
+			);
 			Self::deposit_event(Event::NewOrderInserted(order, order_hash));
 			Ok(())
 		}
@@ -769,20 +1074,67 @@ pub mod pallet {
 		) -> DispatchResult {
 			let trade_hash = T::Hashing::hash_of(&trade);
 			<TradesForWorker<T>>::insert(trade_hash, trade.clone());
+			Self::index_offchain(
+				IndexedRecord::Trade { hash: trade_hash, trade: trade.clone() },
+				trade.time_slot,
+			);
 			Self::deposit_event(Event::NewTradeInserted(trade, trade_hash));
 			Ok(())
 		}
 
-		/// Get nonce for the order.
+		/// Write a record into the offchain database, at the next position of the offchain index,
+		/// and add its position to the index of its delivery time slot.
+		///
+		/// The records are only written when the node runs with offchain indexing enabled, and are
+		/// queried through the `gsy_indexed*` RPC methods. The records of the time slots older than
+		/// the retention period are not added to the index of their time slot, whose length may
+		/// already be pruned.
+		///
+		/// Parameters
+		/// `record`: The order, trade or penalty to be indexed.
+		/// `time_slot`: The delivery time slot of the record.
+		pub fn index_offchain(record: IndexedRecord<T::AccountId, T::Hash>, time_slot: u64) {
+			let position = <OffchainIndexLength<T>>::get();
+			let length = position.saturating_add(1);
+			sp_io::offchain_index::set(&offchain_index_key(position), &record.encode());
+			sp_io::offchain_index::set(OFFCHAIN_INDEX_LENGTH_KEY, &length.encode());
+			<OffchainIndexLength<T>>::put(length);
+
+			if time_slot <= Self::pruning_expiry_time() {
+				return;
+			}
+			let slot_position = <OffchainSlotIndexLength<T>>::get(time_slot).unwrap_or_else(|| {
+				let slot = <OffchainSlotsLength<T>>::get();
+				let slots_length = slot.saturating_add(1);
+				sp_io::offchain_index::set(&offchain_slot_key(slot), &time_slot.encode());
+				sp_io::offchain_index::set(OFFCHAIN_SLOTS_LENGTH_KEY, &slots_length.encode());
+				<OffchainSlotsLength<T>>::put(slots_length);
+				0
+			});
+			let slot_length = slot_position.saturating_add(1);
+			sp_io::offchain_index::set(
+				&offchain_slot_index_key(time_slot, slot_position),
+				&position.encode(),
+			);
+			sp_io::offchain_index::set(&offchain_slot_length_key(time_slot), &slot_length.encode());
+			<OffchainSlotIndexLength<T>>::insert(time_slot, slot_length);
+		}
+
+		/// Get nonce for the order, and record the delivery time slot of the order as the last
+		/// activity of the sender.
 		///
 		/// Parameters
 		/// `sender`: The sender of the order.
+		/// `time_slot`: The delivery time slot of the order.
 		/// Returns
 		/// `u32`: The nonce for the order.
-		pub fn get_and_increment_user_nonce(sender: T::AccountId) -> u32 {
+		pub fn get_and_increment_user_nonce(sender: T::AccountId, time_slot: u64) -> u32 {
 			let user_nonce = <UserNonce<T>>::get(sender.clone()).unwrap_or(0u32);
 			let nonce = user_nonce.checked_add(1u32).ok_or(<Error<T>>::NonceCheckOverflow).unwrap();
 			<UserNonce<T>>::insert(sender.clone(), nonce);
+			<UserLastActivity<T>>::mutate(sender, |last_activity| {
+				*last_activity = Some(last_activity.map_or(time_slot, |t| t.max(time_slot)));
+			});
 			user_nonce
 		}
 
@@ -837,6 +1189,250 @@ pub mod pallet {
 			}
 		}
 
+		/// Helper function to get the hash of the order, as stored in the orderbook registry
+		///
+		/// Parameters
+		/// 'order': The order
+		pub fn get_order_hash(order: &Order<T::AccountId>) -> T::Hash {
+			match order {
+				Order::Bid(bid) => T::Hashing::hash_of(bid),
+				Order::Offer(offer) => T::Hashing::hash_of(offer),
+			}
+		}
+
+		/// Build the amended order, with the new energy and energy rate and a new nonce.
+		///
+		/// Parameters
+		/// 'amendment': The order amendment
+		pub fn amended_order(amendment: &OrderAmendment<T::AccountId>) -> Order<T::AccountId> {
+			match &amendment.order {
+				Order::Bid(bid) => Order::Bid(Bid {
+					nonce: Self::get_and_increment_user_nonce(
+						bid.buyer.clone(),
+						bid.bid_component.time_slot,
+					),
+					bid_component: OrderComponent {
+						energy: amendment.energy,
+						energy_rate: amendment.energy_rate,
+						..bid.bid_component.clone()
+					},
+					..bid.clone()
+				}),
+				Order::Offer(offer) => Order::Offer(Offer {
+					nonce: Self::get_and_increment_user_nonce(
+						offer.seller.clone(),
+						offer.offer_component.time_slot,
+					),
+					offer_component: OrderComponent {
+						energy: amendment.energy,
+						energy_rate: amendment.energy_rate,
+						..offer.offer_component.clone()
+					},
+					..offer.clone()
+				}),
+			}
+		}
+
+		/// Helper function to get the market_id of the order
+		///
+		/// Parameters
+		/// 'order': The order
+		pub fn get_order_market_id(order: &Order<T::AccountId>) -> T::Hash {
+			match order {
+				Order::Offer(offer) => offer.offer_component.market_id,
+				Order::Bid(bid) => bid.bid_component.market_id,
+			}
+		}
+
+		/// Remove the orders that have not been relayed to the orderbook service before the end of
+		/// the retention period following their delivery time slot.
+		///
+		/// Parameters
+		/// `weight_limit`: The maximum weight that can be consumed.
+		pub fn prune_stale_orders(weight_limit: Weight) -> Weight {
+			let db_weight = T::DbWeight::get();
+			let expiry_time = Self::pruning_expiry_time();
+			pruning::prune_from_cursor::<OrdersPruningCursor<T>, _>(
+				weight_limit,
+				db_weight.reads_writes(2, 1),
+				db_weight.reads(1),
+				|cursor| match cursor {
+					Some(cursor) => <OrdersForWorker<T>>::iter_from(cursor),
+					None => <OrdersForWorker<T>>::iter(),
+				},
+				|(order_reference, order), _| {
+					if Self::get_order_time_slot(&order) > expiry_time {
+						return pruning::Visit::Done(Weight::zero());
+					}
+					<OrdersForWorker<T>>::remove(&order_reference);
+					Self::deposit_event(Event::OrderRemoved(
+						order_reference.user_id,
+						order_reference.hash,
+					));
+					pruning::Visit::Done(db_weight.writes(1))
+				},
+			)
+		}
+
+		/// Remove the trades that have not been relayed to the orderbook service before the end of
+		/// the retention period following their delivery time slot.
+		///
+		/// Parameters
+		/// `weight_limit`: The maximum weight that can be consumed.
+		pub fn prune_stale_trades(weight_limit: Weight) -> Weight {
+			let db_weight = T::DbWeight::get();
+			let expiry_time = Self::pruning_expiry_time();
+			pruning::prune_from_cursor::<TradesPruningCursor<T>, _>(
+				weight_limit,
+				db_weight.reads_writes(2, 1),
+				db_weight.reads(1),
+				|cursor| match cursor {
+					Some(cursor) => <TradesForWorker<T>>::iter_from(cursor),
+					None => <TradesForWorker<T>>::iter(),
+				},
+				|(trade_hash, trade), _| {
+					if trade.time_slot > expiry_time {
+						return pruning::Visit::Done(Weight::zero());
+					}
+					<TradesForWorker<T>>::remove(trade_hash);
+					Self::deposit_event(Event::TradeRemoved(trade_hash));
+					pruning::Visit::Done(db_weight.writes(1))
+				},
+			)
+		}
+
+		/// Remove the nonces of the users whose orders have all ended before the retention period.
+		///
+		/// Their orders and markets are pruned by then, so a nonce starting over cannot produce
+		/// the hash of an order that is still known.
+		///
+		/// Parameters
+		/// `weight_limit`: The maximum weight that can be consumed.
+		pub fn prune_user_nonces(weight_limit: Weight) -> Weight {
+			let db_weight = T::DbWeight::get();
+			let expiry_time = Self::pruning_expiry_time();
+			pruning::prune_from_cursor::<NoncesPruningCursor<T>, _>(
+				weight_limit,
+				db_weight.reads_writes(2, 1),
+				db_weight.reads(1),
+				|cursor| match cursor {
+					Some(cursor) => <UserLastActivity<T>>::iter_from(cursor),
+					None => <UserLastActivity<T>>::iter(),
+				},
+				|(account, last_activity), _| {
+					if last_activity > expiry_time {
+						return pruning::Visit::Done(Weight::zero());
+					}
+					<UserNonce<T>>::remove(&account);
+					<UserLastActivity<T>>::remove(&account);
+					pruning::Visit::Done(db_weight.writes(2))
+				},
+			)
+		}
+
+		/// Remove the orders of the matching order book whose delivery time slot ended before the
+		/// retention period.
+		///
+		/// Parameters
+		/// `weight_limit`: The maximum weight that can be consumed.
+		pub fn prune_matching_order_book(weight_limit: Weight) -> Weight {
+			let db_weight = T::DbWeight::get();
+			let expiry_time = Self::pruning_expiry_time();
+			pruning::prune_from_cursor::<MatchingPruningCursor<T>, _>(
+				weight_limit,
+				db_weight.reads_writes(2, 1),
+				db_weight.reads(1),
+				|cursor| match cursor {
+					Some(cursor) => <MatchingOrderBook<T>>::iter_from(cursor),
+					None => <MatchingOrderBook<T>>::iter(),
+				},
+				|(market_id, order_reference, order), _| {
+					if Self::get_order_time_slot(&order) > expiry_time {
+						return pruning::Visit::Done(Weight::zero());
+					}
+					<MatchingOrderBook<T>>::remove(market_id, &order_reference);
+					pruning::Visit::Done(db_weight.writes(1))
+				},
+			)
+		}
+
+		/// Remove the lengths of the offchain slot index whose delivery time slot ended before the
+		/// retention period. The records stay in the offchain database.
+		///
+		/// Parameters
+		/// `weight_limit`: The maximum weight that can be consumed.
+		pub fn prune_offchain_slot_index(weight_limit: Weight) -> Weight {
+			let db_weight = T::DbWeight::get();
+			let expiry_time = Self::pruning_expiry_time();
+			pruning::prune_from_cursor::<SlotIndexPruningCursor<T>, _>(
+				weight_limit,
+				db_weight.reads_writes(2, 1),
+				db_weight.reads(1),
+				|cursor| match cursor {
+					Some(cursor) => <OffchainSlotIndexLength<T>>::iter_keys_from(cursor),
+					None => <OffchainSlotIndexLength<T>>::iter_keys(),
+				},
+				|time_slot, _| {
+					if time_slot > expiry_time {
+						return pruning::Visit::Done(Weight::zero());
+					}
+					<OffchainSlotIndexLength<T>>::remove(time_slot);
+					pruning::Visit::Done(db_weight.writes(1))
+				},
+			)
+		}
+
+		/// Match the open orders of the closed markets with the pay-as-bid algorithm, as the
+		/// matching engine would.
+		///
+		/// Only the orders of the matching order book are considered, so no match is proposed
+		/// unless the on-chain matching is enabled.
+		pub fn closed_market_matches() -> Vec<BidOfferMatch<T::AccountId, T::Hash>> {
+			let now = <T as orderbook_registry::Config>::TimeProvider::now().as_secs();
+			let mut order_books =
+				BTreeMap::<T::Hash, (Vec<Bid<T::AccountId>>, Vec<Offer<T::AccountId>>)>::new();
+			for (market_id, order_reference, order) in <MatchingOrderBook<T>>::iter() {
+				let is_closed = <orderbook_registry::MarketStatus<T>>::contains_key(market_id)
+					&& !<orderbook_registry::MarketStatus<T>>::get(market_id);
+				let is_open_order =
+					<orderbook_registry::OrdersRegistry<T>>::try_get(&order_reference)
+						== Ok(OrderStatus::Open);
+				if !is_closed || !is_open_order {
+					continue;
+				}
+				let (bids, offers) = order_books.entry(market_id).or_default();
+				match order {
+					Order::Bid(bid) if !bid.bid_component.is_expired(now) => bids.push(bid),
+					Order::Offer(offer) if !offer.offer_component.is_expired(now) => {
+						offers.push(offer)
+					},
+					_ => {},
+				}
+			}
+			order_books
+				.into_iter()
+				.flat_map(|(market_id, (bids, offers))| pay_as_bid(market_id, bids, offers))
+				.collect()
+		}
+
+		/// The delivery time slots up to which the orders and trades can be pruned.
+		fn pruning_expiry_time() -> u64 {
+			<T as orderbook_registry::Config>::TimeProvider::now()
+				.as_secs()
+				.saturating_sub(<T as orderbook_registry::Config>::MarketRetentionPeriod::get())
+		}
+
+		/// Helper function to get the delivery time slot of the order
+		///
+		/// Parameters
+		/// 'order': The order
+		pub fn get_order_time_slot(order: &Order<T::AccountId>) -> u64 {
+			match order {
+				Order::Offer(offer) => offer.offer_component.time_slot,
+				Order::Bid(bid) => bid.bid_component.time_slot,
+			}
+		}
+
 		/// Helper function to get the order_amount of the order
 		///
 		/// Parameters
@@ -866,14 +1462,20 @@ pub mod pallet {
 				InputOrder::Bid(input_order) => Order::Bid {
 					0: Bid {
 						buyer: input_order.buyer.clone(),
-						nonce: Self::get_and_increment_user_nonce(delegator),
+						nonce: Self::get_and_increment_user_nonce(
+							delegator,
+							input_order.bid_component.time_slot,
+						),
 						bid_component: input_order.bid_component.clone(),
 					},
 				},
 				InputOrder::Offer(input_order) => Order::Offer {
 					0: Offer {
 						seller: input_order.seller.clone(),
-						nonce: Self::get_and_increment_user_nonce(delegator),
+						nonce: Self::get_and_increment_user_nonce(
+							delegator,
+							input_order.offer_component.time_slot,
+						),
 						offer_component: input_order.offer_component.clone(),
 					},
 				},
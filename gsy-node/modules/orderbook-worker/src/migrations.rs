@@ -0,0 +1,284 @@
+//! Storage migrations of the orderbook worker.
+//!
+//! The modules follow the layout of the orderbook registry migrations: one module per storage
+//! version, whose migration is skipped unless the storage is at the previous version.
+
+use crate::pallet::*;
+use frame_support::{pallet_prelude::*, traits::OnRuntimeUpgrade};
+use scale_info::prelude::vec::Vec;
+#[cfg(feature = "try-runtime")]
+use sp_runtime::TryRuntimeError;
+use sp_std::marker::PhantomData;
+
+/// Version 1, the first versioned storage layout.
+///
+/// The orders and trades waiting to be relayed are translated to the layout of `OrderComponent`
+/// with `valid_until`, which is `None` for them. Their keys are the hashes of the orders and the
+/// trades, so they are inserted again under the hashes of the translated values, which are the
+/// ones the pallet computes once they are relayed. The status of these orders in the orderbook
+/// registry is moved to the hash of their translated bid or offer, and indexed in their market,
+/// while the orders relayed before the upgrade keep the hash of their legacy bid or offer and the
+/// legacy market in the registry. The users with a nonce get the time of the upgrade as their
+/// latest activity, so that their nonce is pruned once the retention period has passed.
+///
+/// The items queued in the offchain relay queue before the upgrade cannot be decoded anymore and
+/// are dead-lettered, while the translated orders and trades are queued again under their new
+/// hashes. `MatchingOrderBook` is new in this version, so it has nothing to translate.
+pub mod v1 {
+	use super::*;
+	use frame_support::traits::UnixTime;
+	use gsy_primitives::v0::{Order, OrderReference};
+	use gsy_primitives::Trade;
+	use sp_runtime::traits::Hash;
+
+	/// The layout of the orders and trades before `valid_until` was added to `OrderComponent`.
+	pub(crate) mod legacy {
+		use super::*;
+		use gsy_primitives::v0::{self, TradeParameters};
+		use sp_core::H256;
+
+		#[derive(Encode, Decode)]
+		pub struct OrderComponent {
+			pub area_uuid: H256,
+			pub market_id: H256,
+			pub time_slot: u64,
+			pub creation_time: u64,
+			pub energy: u64,
+			pub energy_rate: u64,
+		}
+
+		#[derive(Encode, Decode)]
+		pub struct Bid<AccountId> {
+			pub buyer: AccountId,
+			pub nonce: u32,
+			pub bid_component: OrderComponent,
+		}
+
+		#[derive(Encode, Decode)]
+		pub struct Offer<AccountId> {
+			pub seller: AccountId,
+			pub nonce: u32,
+			pub offer_component: OrderComponent,
+		}
+
+		#[derive(Encode, Decode)]
+		pub enum Order<AccountId> {
+			Bid(Bid<AccountId>),
+			Offer(Offer<AccountId>),
+		}
+
+		impl<AccountId: Encode> Order<AccountId> {
+			/// The hash of the bid or offer of the order, which identifies it in the registry.
+			pub fn registry_hash<Hashing: sp_runtime::traits::Hash>(&self) -> Hashing::Output {
+				match self {
+					Order::Bid(bid) => Hashing::hash_of(bid),
+					Order::Offer(offer) => Hashing::hash_of(offer),
+				}
+			}
+		}
+
+		#[derive(Encode, Decode)]
+		pub struct Trade<AccountId, Hash> {
+			pub seller: AccountId,
+			pub buyer: AccountId,
+			pub market_id: Hash,
+			pub trade_uuid: Hash,
+			pub creation_time: u64,
+			pub time_slot: u64,
+			pub offer: Offer<AccountId>,
+			pub offer_hash: Hash,
+			pub bid: Bid<AccountId>,
+			pub bid_hash: Hash,
+			pub residual_bid: Option<Bid<AccountId>>,
+			pub residual_offer: Option<Offer<AccountId>>,
+			pub parameters: TradeParameters<Hash>,
+		}
+
+		impl From<OrderComponent> for v0::OrderComponent {
+			fn from(component: OrderComponent) -> Self {
+				v0::OrderComponent {
+					area_uuid: component.area_uuid,
+					market_id: component.market_id,
+					time_slot: component.time_slot,
+					creation_time: component.creation_time,
+					energy: component.energy,
+					energy_rate: component.energy_rate,
+					valid_until: None,
+				}
+			}
+		}
+
+		impl<AccountId> From<Bid<AccountId>> for v0::Bid<AccountId> {
+			fn from(bid: Bid<AccountId>) -> Self {
+				v0::Bid {
+					buyer: bid.buyer,
+					nonce: bid.nonce,
+					bid_component: bid.bid_component.into(),
+				}
+			}
+		}
+
+		impl<AccountId> From<Offer<AccountId>> for v0::Offer<AccountId> {
+			fn from(offer: Offer<AccountId>) -> Self {
+				v0::Offer {
+					seller: offer.seller,
+					nonce: offer.nonce,
+					offer_component: offer.offer_component.into(),
+				}
+			}
+		}
+
+		impl<AccountId> From<Order<AccountId>> for v0::Order<AccountId> {
+			fn from(order: Order<AccountId>) -> Self {
+				match order {
+					Order::Bid(bid) => v0::Order::Bid(bid.into()),
+					Order::Offer(offer) => v0::Order::Offer(offer.into()),
+				}
+			}
+		}
+
+		impl<AccountId, Hash> From<Trade<AccountId, Hash>> for gsy_primitives::Trade<AccountId, Hash> {
+			fn from(trade: Trade<AccountId, Hash>) -> Self {
+				gsy_primitives::Trade {
+					seller: trade.seller,
+					buyer: trade.buyer,
+					market_id: trade.market_id,
+					trade_uuid: trade.trade_uuid,
+					creation_time: trade.creation_time,
+					time_slot: trade.time_slot,
+					offer: trade.offer.into(),
+					offer_hash: trade.offer_hash,
+					bid: trade.bid.into(),
+					bid_hash: trade.bid_hash,
+					residual_bid: trade.residual_bid.map(Into::into),
+					residual_offer: trade.residual_offer.map(Into::into),
+					parameters: trade.parameters,
+				}
+			}
+		}
+
+		#[frame_support::storage_alias]
+		pub type OrdersForWorker<T: Config> = StorageMap<
+			Pallet<T>,
+			Twox64Concat,
+			OrderReference<
+				<T as frame_system::Config>::AccountId,
+				<T as frame_system::Config>::Hash,
+			>,
+			Order<<T as frame_system::Config>::AccountId>,
+		>;
+
+		#[frame_support::storage_alias]
+		pub type TradesForWorker<T: Config> = StorageMap<
+			Pallet<T>,
+			Twox64Concat,
+			<T as frame_system::Config>::Hash,
+			Trade<<T as frame_system::Config>::AccountId, <T as frame_system::Config>::Hash>,
+		>;
+	}
+
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 0 {
+				log::info!("orderbook worker storage version is not 0, skipping the migration");
+				return T::DbWeight::get().reads(1);
+			}
+			let now = <T as orderbook_registry::Config>::TimeProvider::now().as_secs();
+			let orders: Vec<_> = legacy::OrdersForWorker::<T>::drain().collect();
+			let trades: Vec<_> = legacy::TradesForWorker::<T>::drain().collect();
+			let translated = (orders.len() + trades.len()) as u64;
+			let (mut reads, mut writes) = (2 + translated, 1 + 2 * translated);
+			for (order_ref, order) in orders {
+				// The registry identifies the order by the hash of its bid or offer, which changes
+				// with the layout as well.
+				let legacy_registry_ref = OrderReference {
+					user_id: order_ref.user_id.clone(),
+					hash: order.registry_hash::<T::Hashing>(),
+				};
+				let order: Order<T::AccountId> = order.into();
+				let registry_ref = OrderReference {
+					user_id: order_ref.user_id.clone(),
+					hash: Pallet::<T>::get_order_hash(&order),
+				};
+				let market_id = match &order {
+					Order::Bid(bid) => bid.bid_component.market_id,
+					Order::Offer(offer) => offer.offer_component.market_id,
+				};
+				let (order_reads, order_writes) =
+					orderbook_registry::migrations::v1::reindex_order::<T>(
+						&legacy_registry_ref,
+						&registry_ref,
+						market_id,
+						now,
+					);
+				reads += order_reads;
+				writes += order_writes;
+				let order_ref = OrderReference {
+					user_id: order_ref.user_id,
+					hash: T::Hashing::hash_of(&order),
+				};
+				OrdersForWorker::<T>::insert(order_ref, order);
+			}
+			for (_, trade) in trades {
+				let trade: Trade<T::AccountId, T::Hash> = trade.into();
+				TradesForWorker::<T>::insert(T::Hashing::hash_of(&trade), trade);
+			}
+			// The nonces are pruned once the latest activity of their user has expired.
+			for account in UserNonce::<T>::iter_keys() {
+				reads += 2;
+				if !UserLastActivity::<T>::contains_key(&account) {
+					UserLastActivity::<T>::insert(account, now);
+					writes += 1;
+				}
+			}
+			StorageVersion::new(1).put::<Pallet<T>>();
+			log::info!("orderbook worker storage migrated to v1, {} items translated", translated);
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		/// Count the keys of the orders and the trades waiting to be relayed, without decoding
+		/// their values, which are in the legacy layout.
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			let orders = OrdersForWorker::<T>::iter_keys().count() as u64;
+			let trades = TradesForWorker::<T>::iter_keys().count() as u64;
+			Ok((orders, trades).encode())
+		}
+
+		/// Check that every key is kept, that every value decodes in the new layout, and that every
+		/// user with a nonce has a latest activity.
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			let (orders, trades) = <(u64, u64)>::decode(&mut &state[..])
+				.map_err(|_| "Unable to decode the pre-upgrade state")?;
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() == 1,
+				"The storage version has not been updated to 1"
+			);
+			ensure!(
+				OrdersForWorker::<T>::iter_keys().count() as u64 == orders,
+				"The orders have not been preserved"
+			);
+			ensure!(
+				OrdersForWorker::<T>::iter_values().count() as u64 == orders,
+				"The orders have not been translated"
+			);
+			ensure!(
+				TradesForWorker::<T>::iter_keys().count() as u64 == trades,
+				"The trades have not been preserved"
+			);
+			ensure!(
+				TradesForWorker::<T>::iter_values().count() as u64 == trades,
+				"The trades have not been translated"
+			);
+			ensure!(
+				UserNonce::<T>::iter_keys()
+					.all(|account| UserLastActivity::<T>::contains_key(account)),
+				"The latest activity of the users has not been recorded"
+			);
+			Ok(())
+		}
+	}
+}
@@ -33,7 +33,7 @@ impl system::Config for Test {
 	type BaseCallFilter = frame_support::traits::Everything;
 	type BlockWeights = ();
 	type BlockLength = ();
-	type DbWeight = ();
+	type DbWeight = frame_support::weights::constants::RocksDbWeight;
 	type RuntimeOrigin = RuntimeOrigin;
 	type RuntimeCall = RuntimeCall;
 	type Hash = H256;
@@ -111,6 +111,8 @@ impl orderbook_registry::Config for Test {
 	type RegistryProxyAccountLimit = ();
 	type WeightInfo = ();
 	type TimeProvider = pallet_timestamp::Pallet<Test>;
+	type MarketRetentionPeriod = frame_support::traits::ConstU64<3600>;
+	type MaxOrdersPerBatch = frame_support::traits::ConstU32<100>;
 }
 
 parameter_types! {
@@ -122,6 +124,7 @@ impl orderbook_worker::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	// type RuntimeCall = RuntimeCall;
 	type UnsignedPriority = UnsignedPriority;
+	type OnChainMatching = frame_support::traits::ConstBool<true>;
 	type WeightInfo = orderbook_worker::weights::SubstrateWeightInfo<Test>;
 	type Call = RuntimeCall;
 }
@@ -0,0 +1,271 @@
+//! Retry queue of the orders and trades relayed by the offchain worker to the orderbook service.
+//!
+//! The queue is kept in the persistent offchain local storage, one key per item, so that a failed
+//! delivery is retried with an exponential backoff instead of being dropped. A server error or a
+//! network failure is retried, and the items that still fail after `MAX_ATTEMPTS` attempts are
+//! moved to the dead-letter list. An item rejected by the orderbook service is moved to the
+//! dead-letter list at once, after being sent alone so that it does not fail the rest of its
+//! batch, and so is an item whose payload cannot be decoded. The dead-letter list can be
+//! inspected with the `gsy_relayDeadLetters` RPC, and its items queued again with
+//! `gsy_requeueDeadLetters`. The delivered items are remembered until their removal from the
+//! pallet storage is included in a block, so that they are not relayed twice.
+
+use codec::{Codec, Decode, Encode};
+use core::fmt::Debug;
+use gsy_primitives::v0::{relay_position_key, RelayItem, RelayKind, RelayList, RELAY_REQUEUE_KEY};
+use scale_info::prelude::vec::Vec;
+use sp_core::offchain::StorageKind;
+use sp_runtime::offchain::storage::StorageValueRef;
+
+/// Key of the offchain local storage lock taken while processing the relay queue.
+pub const RELAY_LOCK_KEY: &[u8] = b"gsy-node::relay::lock";
+/// Expiration of the relay queue lock, in milliseconds.
+pub const RELAY_LOCK_DEADLINE_MS: u64 = 20_000;
+/// Number of failed delivery attempts after which an item is moved to the dead-letter list.
+pub const MAX_ATTEMPTS: u32 = 10;
+/// Delay before the first retry, in milliseconds.
+pub const BASE_BACKOFF_MS: u64 = 6_000;
+/// Maximum delay between two retries, in milliseconds.
+pub const MAX_BACKOFF_MS: u64 = 600_000;
+
+/// The delay before the next delivery attempt of an item that already failed `attempts` times.
+pub fn backoff(attempts: u32) -> u64 {
+	let exponent = attempts.saturating_sub(1).min(16);
+	BASE_BACKOFF_MS.saturating_mul(1u64 << exponent).min(MAX_BACKOFF_MS)
+}
+
+/// Whether a delivery that failed with the given HTTP status code, or without response, is
+/// retried. Only the server errors are, the other responses reject the items.
+pub fn is_retryable(status_code: Option<u16>) -> bool {
+	status_code.map_or(true, |code| (500..600).contains(&code))
+}
+
+/// Whether the item has been rejected within a batch, and is sent alone in the next attempt.
+pub fn is_isolated<Hash>(item: &RelayItem<Hash>) -> bool {
+	item.last_status_code.is_some() && !is_retryable(item.last_status_code)
+}
+
+fn bounds(list: RelayList) -> (u64, u64) {
+	StorageValueRef::persistent(&list.bounds_key())
+		.get::<(u64, u64)>()
+		.ok()
+		.flatten()
+		.unwrap_or_default()
+}
+
+fn get<V: Decode>(list: RelayList, position: u64) -> Option<V> {
+	StorageValueRef::persistent(&list.item_key(position)).get::<V>().ok().flatten()
+}
+
+fn set<V: Encode>(list: RelayList, position: u64, value: &V) {
+	StorageValueRef::persistent(&list.item_key(position)).set(value);
+}
+
+/// Add an item at the tail of the list, and return its position.
+fn push<V: Encode>(list: RelayList, value: &V) -> u64 {
+	let (head, tail) = bounds(list);
+	set(list, tail, value);
+	StorageValueRef::persistent(&list.bounds_key()).set(&(head, tail + 1));
+	tail
+}
+
+/// Remove the item at the given position, and move the head of the list past the removed items.
+fn remove(list: RelayList, position: u64) {
+	StorageValueRef::persistent(&list.item_key(position)).clear();
+	let (mut head, tail) = bounds(list);
+	if position != head {
+		return;
+	}
+	while head < tail
+		&& sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, &list.item_key(head))
+			.is_none()
+	{
+		head += 1;
+	}
+	let bounds = if head == tail { (0, 0) } else { (head, tail) };
+	StorageValueRef::persistent(&list.bounds_key()).set(&bounds);
+}
+
+/// The items of the list, with their position.
+fn items<V: Decode>(list: RelayList) -> Vec<(u64, V)> {
+	let (head, tail) = bounds(list);
+	(head..tail)
+		.filter_map(|position| Some((position, get(list, position)?)))
+		.collect()
+}
+
+fn position<Hash: Encode>(kind: RelayKind, hash: &Hash) -> Option<(RelayList, u64)> {
+	StorageValueRef::persistent(&relay_position_key(kind, hash))
+		.get()
+		.ok()
+		.flatten()
+}
+
+fn set_position<Hash: Encode>(kind: RelayKind, hash: &Hash, position: (RelayList, u64)) {
+	StorageValueRef::persistent(&relay_position_key(kind, hash)).set(&position);
+}
+
+/// Move an item of the queue to the given list.
+fn move_item<Hash: Codec>(item: RelayItem<Hash>, from: (RelayList, u64), to: RelayList) {
+	remove(from.0, from.1);
+	let position = push(to, &item);
+	set_position(item.kind, &item.hash, (to, position));
+}
+
+/// The items waiting to be relayed.
+pub fn queued_items<Hash: Codec>() -> Vec<RelayItem<Hash>> {
+	items(RelayList::Queue).into_iter().map(|(_, item)| item).collect()
+}
+
+/// The items that could not be relayed.
+pub fn dead_letters<Hash: Codec>() -> Vec<RelayItem<Hash>> {
+	items(RelayList::DeadLetter).into_iter().map(|(_, item)| item).collect()
+}
+
+/// Add an item to the queue, unless it is already queued, dead-lettered or delivered.
+///
+/// Returns `true` if the item has been added.
+pub fn enqueue<Hash: Codec>(kind: RelayKind, hash: Hash, payload: Vec<u8>, now: u64) -> bool {
+	if position(kind, &hash).is_some() {
+		return false;
+	}
+	let position = push(
+		RelayList::Queue,
+		&RelayItem {
+			kind,
+			hash: &hash,
+			payload,
+			attempts: 0,
+			next_attempt_at: now,
+			last_status_code: None,
+		},
+	);
+	set_position(kind, &hash, (RelayList::Queue, position));
+	true
+}
+
+/// The queued items of the given kind whose next delivery attempt is due.
+pub fn due_items<Hash: Codec>(kind: RelayKind, now: u64) -> Vec<RelayItem<Hash>> {
+	queued_items::<Hash>()
+		.into_iter()
+		.filter(|item| item.kind == kind && item.next_attempt_at <= now)
+		.collect()
+}
+
+/// Decode the payloads of the items, and move the items whose payload cannot be decoded to the
+/// dead-letter list, since relaying them again would not make them decodable.
+///
+/// Returns the hashes of the decoded items, along with their payloads.
+pub fn decode_payloads<Hash: Codec + Debug, V: Decode>(
+	items: Vec<RelayItem<Hash>>,
+) -> (Vec<Hash>, Vec<V>) {
+	let mut decoded = (Vec::new(), Vec::new());
+	for item in items {
+		match V::decode(&mut &item.payload[..]) {
+			Ok(value) => {
+				decoded.0.push(item.hash);
+				decoded.1.push(value);
+			},
+			Err(error) => {
+				log::error!(
+					"Moving the undecodable {:?} {:?} to the relay dead-letter list: {:?}",
+					item.kind,
+					item.hash,
+					error
+				);
+				let Some(queued_at @ (RelayList::Queue, _)) = position(item.kind, &item.hash)
+				else {
+					continue;
+				};
+				move_item(item, queued_at, RelayList::DeadLetter);
+			},
+		}
+	}
+	decoded
+}
+
+/// Remove the delivered items from the queue, and remember them until they are removed from the
+/// pallet storage.
+pub fn mark_delivered<Hash: Codec>(kind: RelayKind, hashes: &[Hash]) {
+	for hash in hashes {
+		let Some((RelayList::Queue, queued_at)) = position(kind, hash) else {
+			continue;
+		};
+		remove(RelayList::Queue, queued_at);
+		let position = push(RelayList::Delivered, &(kind, hash));
+		set_position(kind, hash, (RelayList::Delivered, position));
+	}
+}
+
+/// Record a failed delivery attempt of the given items.
+///
+/// After a server error or a network failure, the next attempt of the items is scheduled, or they
+/// are moved to the dead-letter list after `MAX_ATTEMPTS` attempts. The items rejected by the
+/// orderbook service are moved to the dead-letter list if they have been sent alone, or are sent
+/// alone in the next attempt otherwise.
+pub fn mark_failed<Hash: Codec>(
+	kind: RelayKind,
+	hashes: &[Hash],
+	now: u64,
+	status_code: Option<u16>,
+) {
+	let retryable = is_retryable(status_code);
+	let mut dead = 0;
+	for hash in hashes {
+		let Some(queued_at @ (RelayList::Queue, index)) = position(kind, hash) else {
+			continue;
+		};
+		let Some(mut item) = get::<RelayItem<Hash>>(RelayList::Queue, index) else {
+			continue;
+		};
+		item.last_status_code = status_code;
+		if retryable {
+			item.attempts = item.attempts.saturating_add(1);
+			item.next_attempt_at = now.saturating_add(backoff(item.attempts));
+		}
+		if (retryable && item.attempts >= MAX_ATTEMPTS) || (!retryable && hashes.len() == 1) {
+			move_item(item, queued_at, RelayList::DeadLetter);
+			dead += 1;
+		} else {
+			set(RelayList::Queue, index, &item);
+		}
+	}
+	if dead > 0 {
+		log::error!("Moving {} items to the relay dead-letter list", dead);
+	}
+}
+
+/// Queue again the dead-lettered items requested with the `gsy_requeueDeadLetters` RPC.
+pub fn requeue_dead_letters<Hash: Codec>(now: u64) {
+	let mut requested = Vec::<Hash>::new();
+	let _ = StorageValueRef::persistent(RELAY_REQUEUE_KEY).mutate(
+		|stored: Result<Option<Vec<Hash>>, _>| {
+			requested = stored.ok().flatten().unwrap_or_default();
+			Ok::<_, ()>(Vec::<Hash>::new())
+		},
+	);
+	for hash in requested {
+		for kind in [RelayKind::Order, RelayKind::Trade] {
+			let Some(dead_at @ (RelayList::DeadLetter, index)) = position(kind, &hash) else {
+				continue;
+			};
+			let Some(item) = get::<RelayItem<Hash>>(RelayList::DeadLetter, index) else {
+				continue;
+			};
+			log::info!("Queuing the dead-lettered item {:?} again", item.kind);
+			let item =
+				RelayItem { attempts: 0, next_attempt_at: now, last_status_code: None, ..item };
+			move_item(item, dead_at, RelayList::Queue);
+		}
+	}
+}
+
+/// Forget the delivered items that are not pending removal from the pallet storage anymore.
+pub fn forget_delivered<Hash: Codec>(is_pending: impl Fn(RelayKind, &Hash) -> bool) {
+	for (position, (kind, hash)) in items::<(RelayKind, Hash)>(RelayList::Delivered) {
+		if !is_pending(kind, &hash) {
+			remove(RelayList::Delivered, position);
+			StorageValueRef::persistent(&relay_position_key(kind, &hash)).clear();
+		}
+	}
+}
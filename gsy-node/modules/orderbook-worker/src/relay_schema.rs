@@ -0,0 +1,115 @@
+//! Formatting of the orders and trades relayed by the offchain worker to the orderbook service,
+//! in the JSON schema of `gsy-relay-schema`.
+
+use codec::{Decode, Encode};
+use gsy_primitives::v0::{Bid, Offer, Order, OrderComponent, Trade};
+use gsy_relay_schema::{RelayOrder, RelayOrderType, RelayTrade};
+use scale_info::prelude::{format, string::String};
+use sp_core::{
+	crypto::{AccountId32, Ss58AddressFormat, Ss58Codec},
+	hexdisplay::HexDisplay,
+};
+use sp_runtime::traits::Hash as HashT;
+
+/// Formats hashes and accounts of the node as strings of the relay schema.
+pub struct RelayFormatter {
+	/// The SS58 prefix of the chain.
+	pub ss58_prefix: u16,
+}
+
+impl RelayFormatter {
+	pub fn hash<Hash: AsRef<[u8]>>(&self, hash: &Hash) -> String {
+		format!("0x{}", HexDisplay::from(&hash.as_ref()))
+	}
+
+	/// Format a 32 bytes account as a SS58 address, and any other account as hex.
+	pub fn account<AccountId: Encode>(&self, account: &AccountId) -> String {
+		let encoded = account.encode();
+		match AccountId32::decode(&mut &encoded[..]) {
+			Ok(account) if encoded.len() == 32 => {
+				account.to_ss58check_with_version(Ss58AddressFormat::custom(self.ss58_prefix))
+			},
+			_ => format!("0x{}", HexDisplay::from(&encoded)),
+		}
+	}
+
+	fn order<AccountId: Encode, Hash: AsRef<[u8]>>(
+		&self,
+		hash: &Hash,
+		order_type: RelayOrderType,
+		account: &AccountId,
+		nonce: u32,
+		component: &OrderComponent,
+	) -> RelayOrder {
+		RelayOrder {
+			hash: self.hash(hash),
+			order_type,
+			account: self.account(account),
+			nonce,
+			area_uuid: self.hash(&component.area_uuid),
+			market_id: self.hash(&component.market_id),
+			time_slot: component.time_slot,
+			creation_time: component.creation_time,
+			energy: component.energy,
+			energy_rate: component.energy_rate,
+			valid_until: component.valid_until,
+		}
+	}
+
+	pub fn bid<AccountId: Encode, Hash: AsRef<[u8]>>(
+		&self,
+		hash: &Hash,
+		bid: &Bid<AccountId>,
+	) -> RelayOrder {
+		self.order(hash, RelayOrderType::Bid, &bid.buyer, bid.nonce, &bid.bid_component)
+	}
+
+	pub fn offer<AccountId: Encode, Hash: AsRef<[u8]>>(
+		&self,
+		hash: &Hash,
+		offer: &Offer<AccountId>,
+	) -> RelayOrder {
+		self.order(hash, RelayOrderType::Offer, &offer.seller, offer.nonce, &offer.offer_component)
+	}
+
+	/// Format an order, identified by the hash of its bid or offer, the key of the order in the
+	/// orderbook registry.
+	pub fn any_order<AccountId: Encode, Hash: AsRef<[u8]>>(
+		&self,
+		hash: &Hash,
+		order: &Order<AccountId>,
+	) -> RelayOrder {
+		match order {
+			Order::Bid(bid) => self.bid(hash, bid),
+			Order::Offer(offer) => self.offer(hash, offer),
+		}
+	}
+
+	/// Format a trade, hashing its residual bid or offer with `Hashing` as they are inserted in the
+	/// orderbook registry.
+	pub fn trade<AccountId: Encode + Clone, Hashing: HashT>(
+		&self,
+		trade: &Trade<AccountId, Hashing::Output>,
+	) -> RelayTrade {
+		RelayTrade {
+			trade_uuid: self.hash(&trade.trade_uuid),
+			market_id: self.hash(&trade.market_id),
+			seller: self.account(&trade.seller),
+			buyer: self.account(&trade.buyer),
+			time_slot: trade.time_slot,
+			creation_time: trade.creation_time,
+			selected_energy: trade.parameters.selected_energy,
+			energy_rate: trade.parameters.energy_rate,
+			bid: self.bid(&trade.bid_hash, &trade.bid),
+			offer: self.offer(&trade.offer_hash, &trade.offer),
+			residual_bid: trade
+				.residual_bid
+				.as_ref()
+				.map(|bid| self.bid(&Hashing::hash_of(bid), bid)),
+			residual_offer: trade
+				.residual_offer
+				.as_ref()
+				.map(|offer| self.offer(&Hashing::hash_of(offer), offer)),
+		}
+	}
+}
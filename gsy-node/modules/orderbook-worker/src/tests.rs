@@ -1,15 +1,37 @@
-use crate::mock::*;
-use codec::Encode;
-use gsy_primitives::{v0::OrderComponent, Bid};
-use sp_core::offchain::{testing, OffchainWorkerExt};
+use crate::{
+	configuration::OrderBookServiceURLs, mock::*, relay_queue, relay_schema::RelayFormatter,
+	Timestamp,
+};
+use codec::{Decode, Encode};
+use frame_support::traits::{
+	fungible::Mutate, GetStorageVersion, Hooks, OnRuntimeUpgrade, StorageVersion,
+};
+use frame_support::weights::{constants::RocksDbWeight, Weight};
+use frame_support::{assert_noop, assert_ok, BoundedVec};
+use gsy_primitives::{
+	v0::{
+		offchain_index_key, offchain_slot_index_key, offchain_slot_key, offchain_slot_length_key,
+		IndexedRecord, InputBid, InputOrder, Offer, Order, OrderComponent, OrderReference,
+		OrderStatus, RelayEndpoint, RelayKind, Trade, TradeParameters, OFFCHAIN_INDEX_LENGTH_KEY,
+		OFFCHAIN_SLOTS_LENGTH_KEY, RELAY_ENDPOINTS_KEY, RELAY_REQUEUE_KEY,
+	},
+	Bid,
+};
+use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
+use sp_core::offchain::{testing, OffchainDbExt, OffchainWorkerExt};
 use sp_core::H256;
-use sp_runtime::AccountId32;
+use sp_runtime::{
+	offchain::{http, storage::StorageValueRef},
+	traits::{BlakeTwo256, Hash},
+	AccountId32,
+};
 
 #[test]
 fn orderbook_worker_sends_back_result() {
 	new_test_ext().execute_with(|| {
 		let (offchain, state) = testing::TestOffchainExt::new();
 		let mut t = sp_io::TestExternalities::default();
+		t.register_extension(OffchainDbExt::new(offchain.clone()));
 		t.register_extension(OffchainWorkerExt::new(offchain));
 
 		let test_data: Bid<AccountId32> = Bid {
@@ -22,18 +44,310 @@ fn orderbook_worker_sends_back_result() {
 				creation_time: 1,
 				energy: 10,
 				energy_rate: 1,
+				valid_until: None,
 			},
 		};
 
 		let bytes = test_data.encode();
 		order_post_response(&mut state.write(), &bytes);
 		t.execute_with(|| {
-			let response_status = OrderbookWorker::send_order_to_orderbook_service(&bytes).unwrap();
+			let response_status = OrderbookWorker::send_order_to_orderbook_service(
+				&bytes,
+				Timestamp::from_unix_millis(60_000),
+			)
+			.unwrap();
 			assert_eq!(response_status, 200);
 		});
 	});
 }
 
+#[test]
+fn no_endpoint_is_tried_after_the_relay_deadline() {
+	offchain_ext().execute_with(|| {
+		// The test offchain extension panics on any request that is not expected.
+		let result =
+			OrderbookWorker::send_order_to_orderbook_service(b"{}", Timestamp::from_unix_millis(0));
+		assert_eq!(result, Err(http::Error::DeadlineReached));
+	});
+}
+
+#[test]
+fn configured_endpoints_fall_back_to_the_compile_time_endpoint() {
+	offchain_ext().execute_with(|| {
+		let endpoints = OrderBookServiceURLs::configured();
+		assert_eq!(endpoints.len(), 1);
+		assert_eq!(endpoints[0].orders_url, OrderBookServiceURLs::default().orders_url);
+		assert!(endpoints[0].headers.is_empty());
+	});
+}
+
+#[test]
+fn configured_endpoints_are_read_in_order_of_preference() {
+	offchain_ext().execute_with(|| {
+		StorageValueRef::persistent(RELAY_ENDPOINTS_KEY).set(&vec![
+			RelayEndpoint {
+				url: b"https://primary.example/".to_vec(),
+				headers: vec![(b"Authorization".to_vec(), b"Bearer token".to_vec())],
+			},
+			RelayEndpoint { url: b"https://fallback.example".to_vec(), headers: vec![] },
+			// Endpoints that are not valid UTF-8 are ignored.
+			RelayEndpoint { url: vec![0xff], headers: vec![] },
+		]);
+
+		let endpoints = OrderBookServiceURLs::configured();
+		assert_eq!(endpoints.len(), 2);
+		assert_eq!(endpoints[0].orders_url, "https://primary.example/orders");
+		assert_eq!(endpoints[0].trades_url, "https://primary.example/trades");
+		assert_eq!(
+			endpoints[0].headers,
+			vec![(String::from("Authorization"), String::from("Bearer token"))]
+		);
+		assert_eq!(endpoints[1].orders_url, "https://fallback.example/orders");
+		assert!(endpoints[1].headers.is_empty());
+	});
+}
+
+#[test]
+fn closed_market_matches_match_the_open_orders_of_closed_markets() {
+	new_test_ext().execute_with(|| {
+		let market_id = H256::random();
+		let buyer = AccountId32::new([1; 32]);
+		let seller = AccountId32::new([2; 32]);
+		let bid_component = OrderComponent {
+			area_uuid: H256::random(),
+			market_id,
+			time_slot: 1,
+			creation_time: 1,
+			energy: 10,
+			energy_rate: 5,
+			valid_until: None,
+		};
+		let offer_component = OrderComponent {
+			area_uuid: H256::random(),
+			energy: 4,
+			energy_rate: 3,
+			..bid_component.clone()
+		};
+		let orders = vec![
+			(buyer.clone(), Order::Bid(Bid { buyer, nonce: 1, bid_component })),
+			(seller.clone(), Order::Offer(Offer { seller, nonce: 1, offer_component })),
+		];
+		for (user_id, order) in orders {
+			let order_ref =
+				OrderReference { user_id, hash: OrderbookWorker::get_order_hash(&order) };
+			orderbook_registry::OrdersRegistry::<Test>::insert(&order_ref, OrderStatus::Open);
+			crate::MatchingOrderBook::<Test>::insert(market_id, order_ref, order);
+		}
+
+		orderbook_registry::MarketStatus::<Test>::insert(market_id, true);
+		assert!(OrderbookWorker::closed_market_matches().is_empty());
+
+		orderbook_registry::MarketStatus::<Test>::insert(market_id, false);
+		let matches = OrderbookWorker::closed_market_matches();
+		assert_eq!(matches.len(), 1);
+		assert_eq!(matches[0].market_id, market_id);
+		assert_eq!(matches[0].selected_energy, 4);
+		assert_eq!(matches[0].energy_rate, 5);
+		assert_eq!(matches[0].residual_bid.as_ref().map(|bid| bid.bid_component.energy), Some(6));
+		assert!(matches[0].residual_offer.is_none());
+	});
+}
+
+#[test]
+fn index_offchain_writes_the_records_and_the_index_of_their_time_slot() {
+	let mut ext = new_test_ext();
+	let records = ext.execute_with(|| {
+		let records: Vec<_> = [1_000, 2_000, 1_000]
+			.into_iter()
+			.map(|time_slot| {
+				let order = Order::Bid(bid_at(AccountId32::new([1; 32]), time_slot));
+				let record =
+					IndexedRecord::Order { hash: OrderbookWorker::get_order_hash(&order), order };
+				OrderbookWorker::index_offchain(record.clone(), time_slot);
+				record
+			})
+			.collect();
+		assert_eq!(OrderbookWorker::offchain_index_length(), 3);
+		assert_eq!(OrderbookWorker::offchain_slots_length(), 2);
+		assert_eq!(OrderbookWorker::offchain_slot_index_length(1_000), Some(2));
+		assert_eq!(OrderbookWorker::offchain_slot_index_length(2_000), Some(1));
+		records
+	});
+	ext.persist_offchain_overlay();
+
+	let offchain_db = ext.offchain_db();
+	let get = |key: &[u8]| offchain_db.get(key).expect("the key is indexed");
+	assert_eq!(u64::decode(&mut &get(OFFCHAIN_INDEX_LENGTH_KEY)[..]), Ok(3));
+	for (position, record) in records.iter().enumerate() {
+		assert_eq!(get(&offchain_index_key(position as u64)), record.encode());
+	}
+	assert_eq!(get(OFFCHAIN_SLOTS_LENGTH_KEY), 2u64.encode());
+	assert_eq!(get(&offchain_slot_key(0)), 1_000u64.encode());
+	assert_eq!(get(&offchain_slot_key(1)), 2_000u64.encode());
+	assert_eq!(get(&offchain_slot_length_key(1_000)), 2u64.encode());
+	assert_eq!(get(&offchain_slot_index_key(1_000, 0)), 0u64.encode());
+	assert_eq!(get(&offchain_slot_index_key(1_000, 1)), 2u64.encode());
+	assert_eq!(get(&offchain_slot_index_key(2_000, 0)), 1u64.encode());
+}
+
+#[test]
+fn index_offchain_does_not_index_the_expired_time_slots() {
+	new_test_ext().execute_with(|| {
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		let order = Order::Bid(bid_at(AccountId32::new([1; 32]), 1_000));
+		let record = IndexedRecord::Order { hash: OrderbookWorker::get_order_hash(&order), order };
+		OrderbookWorker::index_offchain(record, 1_000);
+		assert_eq!(OrderbookWorker::offchain_index_length(), 1);
+		assert_eq!(OrderbookWorker::offchain_slots_length(), 0);
+		assert_eq!(OrderbookWorker::offchain_slot_index_length(1_000), None);
+	});
+}
+
+#[test]
+fn prune_offchain_slot_index_works() {
+	new_test_ext().execute_with(|| {
+		crate::OffchainSlotIndexLength::<Test>::insert(1_000, 1);
+		crate::OffchainSlotIndexLength::<Test>::insert(2_000, 1);
+
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		OrderbookWorker::on_idle(1, Weight::MAX);
+		assert_eq!(OrderbookWorker::offchain_slot_index_length(1_000), None);
+		assert_eq!(OrderbookWorker::offchain_slot_index_length(2_000), Some(1));
+	});
+}
+
+fn bid_at(buyer: AccountId32, time_slot: u64) -> Bid<AccountId32> {
+	Bid {
+		buyer,
+		nonce: 1,
+		bid_component: OrderComponent {
+			area_uuid: H256::random(),
+			market_id: H256::random(),
+			time_slot,
+			creation_time: 1,
+			energy: 10,
+			energy_rate: 5,
+			valid_until: None,
+		},
+	}
+}
+
+fn insert_order_for_worker(time_slot: u64) -> OrderReference<AccountId32, H256> {
+	let order = Order::Bid(bid_at(AccountId32::new([1; 32]), time_slot));
+	let order_ref = OrderReference {
+		user_id: AccountId32::new([1; 32]),
+		hash: OrderbookWorker::get_order_hash(&order),
+	};
+	crate::OrdersForWorker::<Test>::insert(&order_ref, order);
+	order_ref
+}
+
+#[test]
+fn prune_stale_orders_and_trades_works() {
+	new_test_ext().execute_with(|| {
+		let stale_order = insert_order_for_worker(1_000);
+		let recent_order = insert_order_for_worker(2_000);
+		let bid = bid_at(AccountId32::new([1; 32]), 1_000);
+		let offer = Offer {
+			seller: AccountId32::new([2; 32]),
+			nonce: 1,
+			offer_component: bid.bid_component.clone(),
+		};
+		let trade = Trade {
+			seller: offer.seller.clone(),
+			buyer: bid.buyer.clone(),
+			market_id: bid.bid_component.market_id,
+			trade_uuid: H256::random(),
+			creation_time: 1,
+			time_slot: 1_000,
+			offer_hash: H256::random(),
+			offer,
+			bid_hash: H256::random(),
+			bid,
+			residual_bid: None,
+			residual_offer: None,
+			parameters: TradeParameters {
+				selected_energy: 10,
+				energy_rate: 5,
+				trade_uuid: H256::random(),
+			},
+		};
+		crate::TradesForWorker::<Test>::insert(trade.trade_uuid, trade.clone());
+
+		// Nothing is pruned during the retention period.
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_000_000);
+		OrderbookWorker::on_idle(1, Weight::MAX);
+		assert!(crate::OrdersForWorker::<Test>::contains_key(&stale_order));
+		assert!(crate::TradesForWorker::<Test>::contains_key(trade.trade_uuid));
+
+		// The orders and trades of the time slots older than the retention period are pruned.
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		OrderbookWorker::on_idle(1, Weight::MAX);
+		assert!(!crate::OrdersForWorker::<Test>::contains_key(&stale_order));
+		assert!(crate::OrdersForWorker::<Test>::contains_key(&recent_order));
+		assert!(!crate::TradesForWorker::<Test>::contains_key(trade.trade_uuid));
+	});
+}
+
+#[test]
+fn prune_user_nonces_after_the_last_activity() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId32::new([1; 32]);
+		let bob = AccountId32::new([2; 32]);
+		assert_eq!(OrderbookWorker::get_and_increment_user_nonce(alice.clone(), 1_000), 0);
+		assert_eq!(OrderbookWorker::get_and_increment_user_nonce(alice.clone(), 500), 1);
+		assert_eq!(OrderbookWorker::get_and_increment_user_nonce(bob.clone(), 2_000), 0);
+		assert_eq!(OrderbookWorker::user_last_activity(&alice), Some(1_000));
+
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		OrderbookWorker::on_idle(1, Weight::MAX);
+		assert_eq!(OrderbookWorker::user_nonce(&alice), None);
+		assert_eq!(OrderbookWorker::user_last_activity(&alice), None);
+		assert_eq!(OrderbookWorker::user_nonce(&bob), Some(1));
+		assert_eq!(OrderbookWorker::get_and_increment_user_nonce(alice, 5_000), 0);
+	});
+}
+
+#[test]
+fn prune_matching_order_book_works() {
+	new_test_ext().execute_with(|| {
+		let order = Order::Bid(bid_at(AccountId32::new([1; 32]), 1_000));
+		let market_id = OrderbookWorker::get_order_market_id(&order);
+		let order_ref = OrderReference {
+			user_id: AccountId32::new([1; 32]),
+			hash: OrderbookWorker::get_order_hash(&order),
+		};
+		crate::MatchingOrderBook::<Test>::insert(market_id, &order_ref, order);
+
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		OrderbookWorker::on_idle(1, Weight::MAX);
+		assert!(!crate::MatchingOrderBook::<Test>::contains_key(market_id, &order_ref));
+	});
+}
+
+#[test]
+fn pruning_cursor_is_carried_over_to_the_next_block() {
+	new_test_ext().execute_with(|| {
+		let orders: Vec<_> = (0..3).map(|_| insert_order_for_worker(1_000)).collect();
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		// Enough weight to prune a single order per block.
+		let weight_limit = RocksDbWeight::get().reads_writes(3, 2);
+		let remaining =
+			|| orders.iter().filter(|o| crate::OrdersForWorker::<Test>::contains_key(o));
+
+		assert_eq!(OrderbookWorker::prune_stale_orders(weight_limit), weight_limit);
+		assert_eq!(remaining().count(), 2);
+		assert!(crate::OrdersPruningCursor::<Test>::get().is_some());
+
+		assert_eq!(OrderbookWorker::prune_stale_orders(weight_limit), weight_limit);
+		assert_eq!(remaining().count(), 1);
+
+		OrderbookWorker::prune_stale_orders(Weight::MAX);
+		assert_eq!(remaining().count(), 0);
+		assert_eq!(crate::OrdersPruningCursor::<Test>::get(), None);
+	});
+}
+
 fn order_post_response(state: &mut testing::OffchainState, encoded_test_data: &[u8]) {
 	state.expect_request(testing::PendingRequest {
 		method: "POST".into(),
@@ -45,3 +359,330 @@ fn order_post_response(state: &mut testing::OffchainState, encoded_test_data: &[
 		..Default::default()
 	});
 }
+
+fn offchain_ext() -> sp_io::TestExternalities {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t
+}
+
+#[test]
+fn relay_queue_does_not_queue_a_known_item_twice() {
+	offchain_ext().execute_with(|| {
+		let hash = H256::random();
+		assert!(relay_queue::enqueue(RelayKind::Order, hash, vec![1], 0));
+		assert!(!relay_queue::enqueue(RelayKind::Order, hash, vec![1], 0));
+		assert!(relay_queue::enqueue(RelayKind::Trade, hash, vec![2], 0));
+		assert_eq!(relay_queue::queued_items::<H256>().len(), 2);
+
+		// A delivered item is not queued again until it is removed from the pallet storage.
+		relay_queue::mark_delivered(RelayKind::Order, &[hash]);
+		assert_eq!(relay_queue::due_items::<H256>(RelayKind::Order, 0), vec![]);
+		assert!(!relay_queue::enqueue(RelayKind::Order, hash, vec![1], 0));
+		relay_queue::forget_delivered::<H256>(|_, _| false);
+		assert!(relay_queue::enqueue(RelayKind::Order, hash, vec![1], 0));
+	});
+}
+
+#[test]
+fn relay_queue_retries_the_server_errors_with_backoff() {
+	offchain_ext().execute_with(|| {
+		let hash = H256::random();
+		relay_queue::enqueue(RelayKind::Order, hash, vec![1], 0);
+		relay_queue::mark_failed(RelayKind::Order, &[hash], 0, Some(503));
+		assert_eq!(relay_queue::due_items::<H256>(RelayKind::Order, 0), vec![]);
+		let items = relay_queue::due_items::<H256>(RelayKind::Order, relay_queue::backoff(1));
+		assert_eq!(items.len(), 1);
+		assert_eq!(items[0].attempts, 1);
+		assert_eq!(items[0].last_status_code, Some(503));
+
+		for _ in 1..relay_queue::MAX_ATTEMPTS {
+			relay_queue::mark_failed(RelayKind::Order, &[hash], 0, None);
+		}
+		assert_eq!(relay_queue::queued_items::<H256>(), vec![]);
+		let dead_letters = relay_queue::dead_letters::<H256>();
+		assert_eq!(dead_letters.len(), 1);
+		assert_eq!(dead_letters[0].attempts, relay_queue::MAX_ATTEMPTS);
+	});
+}
+
+#[test]
+fn relay_queue_sends_the_rejected_items_alone_before_dead_lettering_them() {
+	offchain_ext().execute_with(|| {
+		let (rejected, accepted) = (H256::random(), H256::random());
+		relay_queue::enqueue(RelayKind::Trade, rejected, vec![1], 0);
+		relay_queue::enqueue(RelayKind::Trade, accepted, vec![2], 0);
+
+		// A batch rejected by the orderbook service is split, without counting an attempt.
+		relay_queue::mark_failed(RelayKind::Trade, &[rejected, accepted], 0, Some(400));
+		let items = relay_queue::due_items::<H256>(RelayKind::Trade, 0);
+		assert_eq!(items.len(), 2);
+		assert!(items.iter().all(|item| relay_queue::is_isolated(item) && item.attempts == 0));
+
+		// Sent alone, only the rejected item is dead-lettered.
+		relay_queue::mark_failed(RelayKind::Trade, &[rejected], 0, Some(400));
+		relay_queue::mark_delivered(RelayKind::Trade, &[accepted]);
+		assert_eq!(relay_queue::queued_items::<H256>(), vec![]);
+		let dead_letters = relay_queue::dead_letters::<H256>();
+		assert_eq!(dead_letters.len(), 1);
+		assert_eq!(dead_letters[0].hash, rejected);
+	});
+}
+
+#[test]
+fn relay_queue_dead_letters_the_undecodable_items() {
+	offchain_ext().execute_with(|| {
+		let (undecodable, decodable) = (H256::random(), H256::random());
+		relay_queue::enqueue(RelayKind::Trade, undecodable, vec![1], 0);
+		relay_queue::enqueue(RelayKind::Trade, decodable, 7u64.encode(), 0);
+
+		let items = relay_queue::due_items::<H256>(RelayKind::Trade, 0);
+		let (hashes, payloads) = relay_queue::decode_payloads::<H256, u64>(items);
+		assert_eq!((hashes, payloads), (vec![decodable], vec![7]));
+		let dead_letters = relay_queue::dead_letters::<H256>();
+		assert_eq!(dead_letters.len(), 1);
+		assert_eq!(dead_letters[0].hash, undecodable);
+		let items = relay_queue::due_items::<H256>(RelayKind::Trade, 0);
+		assert_eq!(items.len(), 1);
+		assert_eq!(items[0].hash, decodable);
+	});
+}
+
+#[test]
+fn relay_queue_requeues_the_requested_dead_letters() {
+	offchain_ext().execute_with(|| {
+		let hash = H256::random();
+		relay_queue::enqueue(RelayKind::Order, hash, vec![1], 0);
+		relay_queue::mark_failed(RelayKind::Order, &[hash], 0, Some(422));
+		assert_eq!(relay_queue::dead_letters::<H256>().len(), 1);
+
+		StorageValueRef::persistent(RELAY_REQUEUE_KEY).set(&vec![hash]);
+		relay_queue::requeue_dead_letters::<H256>(1_000);
+		assert_eq!(relay_queue::dead_letters::<H256>(), vec![]);
+		let items = relay_queue::due_items::<H256>(RelayKind::Order, 1_000);
+		assert_eq!(items.len(), 1);
+		assert_eq!((items[0].attempts, items[0].last_status_code), (0, None));
+		let requests = StorageValueRef::persistent(RELAY_REQUEUE_KEY).get::<Vec<H256>>();
+		assert_eq!(requests.ok().flatten(), Some(vec![]));
+	});
+}
+
+#[test]
+fn migrate_to_v1_writes_the_storage_version() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<OrderbookWorker>();
+		crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(OrderbookWorker::on_chain_storage_version(), 1);
+	});
+}
+
+#[test]
+fn migrate_to_v1_translates_the_orders_to_the_layout_with_valid_until() {
+	use crate::migrations::v1::legacy;
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<OrderbookWorker>();
+		let buyer = AccountId32::new([1; 32]);
+		let bid = bid_at(buyer.clone(), 1_000);
+		let component = &bid.bid_component;
+		let market_id = component.market_id;
+		let legacy_order = legacy::Order::Bid(legacy::Bid {
+			buyer: buyer.clone(),
+			nonce: bid.nonce,
+			bid_component: legacy::OrderComponent {
+				area_uuid: component.area_uuid,
+				market_id: component.market_id,
+				time_slot: component.time_slot,
+				creation_time: component.creation_time,
+				energy: component.energy,
+				energy_rate: component.energy_rate,
+			},
+		});
+		let legacy_ref =
+			OrderReference { user_id: buyer.clone(), hash: BlakeTwo256::hash_of(&legacy_order) };
+		// The order is open in the registry, indexed under the legacy market by its migration.
+		let legacy_registry_ref = OrderReference {
+			user_id: buyer.clone(),
+			hash: legacy_order.registry_hash::<BlakeTwo256>(),
+		};
+		orderbook_registry::OrdersRegistry::<Test>::insert(&legacy_registry_ref, OrderStatus::Open);
+		orderbook_registry::migrations::v1::index_order::<Test>(
+			&legacy_registry_ref,
+			&OrderStatus::Open,
+			orderbook_registry::migrations::v1::legacy_market_id::<Test>(),
+			1_000,
+		);
+		legacy::OrdersForWorker::<Test>::insert(&legacy_ref, legacy_order);
+		crate::UserNonce::<Test>::insert(&buyer, 1);
+		pallet_timestamp::Pallet::<Test>::set_timestamp(2_000_000);
+
+		crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+		// The order is kept under the hash of the translated order.
+		let order = Order::Bid(bid);
+		let order_ref =
+			OrderReference { user_id: buyer.clone(), hash: BlakeTwo256::hash_of(&order) };
+		assert_eq!(
+			crate::OrdersForWorker::<Test>::iter_keys().collect::<Vec<_>>(),
+			vec![order_ref.clone()]
+		);
+		// The registry status is moved to the hash of the translated bid, in the market of the bid.
+		let registry_hash = OrderbookWorker::get_order_hash(&order);
+		assert_eq!(crate::OrdersForWorker::<Test>::get(&order_ref), Some(order));
+		assert_eq!(OrderbookRegistry::open_orders_of(&buyer), vec![registry_hash]);
+		assert_eq!(
+			OrderbookRegistry::open_orders_in_market(&buyer, market_id),
+			vec![registry_hash]
+		);
+		assert!(!orderbook_registry::OrdersRegistry::<Test>::contains_key(&legacy_registry_ref));
+		// The user gets the time of the upgrade as latest activity.
+		assert_eq!(OrderbookWorker::user_last_activity(&buyer), Some(2_000));
+		assert_eq!(OrderbookWorker::on_chain_storage_version(), 1);
+	});
+}
+
+#[test]
+fn relay_schema_identifies_the_inserted_orders_by_the_hash_of_their_bid_or_offer() {
+	new_test_ext().execute_with(|| {
+		let buyer = AccountId32::new([1; 32]);
+		assert_ok!(GsyCollateral::add_user(buyer.clone()));
+		assert_ok!(GsyCollateral::create_vault(buyer.clone()));
+		Balances::set_balance(&buyer, 10000);
+		assert_ok!(GsyCollateral::deposit_collateral(RuntimeOrigin::signed(buyer.clone()), 5000));
+		let bid_component = bid_at(buyer.clone(), 1_000).bid_component;
+		orderbook_registry::MarketStatus::<Test>::insert(bid_component.market_id, true);
+		orderbook_registry::MarketDeliveryTime::<Test>::insert(bid_component.market_id, 1_000);
+
+		assert_ok!(OrderbookWorker::insert_orders(
+			RuntimeOrigin::signed(buyer.clone()),
+			BoundedVec::truncate_from(vec![InputOrder::Bid(InputBid {
+				buyer: buyer.clone(),
+				bid_component: bid_component.clone(),
+			})]),
+		));
+		let orders: Vec<Order<AccountId32>> =
+			crate::OrdersForWorker::<Test>::iter_values().collect();
+		let bid = match &orders[..] {
+			[Order::Bid(bid)] => bid.clone(),
+			_ => panic!("The inserted bid is not queued for the worker"),
+		};
+		let bid_hash = BlakeTwo256::hash_of(&bid);
+		assert_eq!(
+			orderbook_registry::OrdersRegistry::<Test>::get(OrderReference {
+				user_id: buyer.clone(),
+				hash: bid_hash,
+			}),
+			OrderStatus::Open
+		);
+
+		let relay_orders = OrderbookWorker::relay_orders_body(&orders);
+		let relay_order = serde_json::to_value(&relay_orders.orders[0]).unwrap();
+		assert_eq!(relay_order["hash"], format!("{:?}", bid_hash));
+		assert_eq!(relay_order["type"], "bid");
+		assert_eq!(
+			relay_order["account"],
+			buyer.to_ss58check_with_version(Ss58AddressFormat::custom(42))
+		);
+		assert_eq!(relay_order["market_id"], format!("{:?}", bid_component.market_id));
+		assert_eq!(relay_order["energy"], 10);
+		assert_eq!(relay_order["valid_until"], serde_json::Value::Null);
+	});
+}
+
+#[test]
+fn insert_orders_fails_if_the_registry_rejects_the_orders() {
+	new_test_ext().execute_with(|| {
+		let buyer = AccountId32::new([1; 32]);
+		assert_ok!(GsyCollateral::add_user(buyer.clone()));
+		assert_ok!(GsyCollateral::create_vault(buyer.clone()));
+		Balances::set_balance(&buyer, 10000);
+		assert_ok!(GsyCollateral::deposit_collateral(RuntimeOrigin::signed(buyer.clone()), 5000));
+		let bid_component = bid_at(buyer.clone(), 1_000).bid_component;
+		orderbook_registry::MarketStatus::<Test>::insert(bid_component.market_id, true);
+		orderbook_registry::MarketDeliveryTime::<Test>::insert(bid_component.market_id, 1_000);
+		// The registry only accepts the orders of registered users.
+		gsy_collateral::RegisteredUser::<Test>::remove(&buyer);
+
+		assert_noop!(
+			OrderbookWorker::insert_orders(
+				RuntimeOrigin::signed(buyer.clone()),
+				BoundedVec::truncate_from(vec![InputOrder::Bid(InputBid {
+					buyer: buyer.clone(),
+					bid_component,
+				})]),
+			),
+			gsy_collateral::Error::<Test>::NotARegisteredUserAccount
+		);
+		assert_eq!(crate::OrdersForWorker::<Test>::iter().count(), 0);
+	});
+}
+
+#[test]
+fn insert_orders_rejects_the_orders_not_delivered_in_the_time_slot_of_the_market() {
+	new_test_ext().execute_with(|| {
+		let buyer = AccountId32::new([1; 32]);
+		assert_ok!(GsyCollateral::add_user(buyer.clone()));
+		assert_ok!(GsyCollateral::create_vault(buyer.clone()));
+		Balances::set_balance(&buyer, 10000);
+		assert_ok!(GsyCollateral::deposit_collateral(RuntimeOrigin::signed(buyer.clone()), 5000));
+		// An order far in the future does not postpone the pruning of the market.
+		let bid_component = bid_at(buyer.clone(), u64::MAX).bid_component;
+		orderbook_registry::MarketStatus::<Test>::insert(bid_component.market_id, true);
+		orderbook_registry::MarketDeliveryTime::<Test>::insert(bid_component.market_id, 1_000);
+
+		assert_noop!(
+			OrderbookWorker::insert_orders(
+				RuntimeOrigin::signed(buyer.clone()),
+				BoundedVec::truncate_from(vec![InputOrder::Bid(InputBid {
+					buyer: buyer.clone(),
+					bid_component: bid_component.clone(),
+				})]),
+			),
+			crate::Error::<Test>::InvalidTimeSlot
+		);
+		assert_eq!(OrderbookRegistry::market_delivery_time(bid_component.market_id), Some(1_000));
+		assert_eq!(OrderbookWorker::user_last_activity(&buyer), None);
+	});
+}
+
+#[test]
+fn relay_schema_identifies_the_residual_orders_by_the_hash_of_their_bid_or_offer() {
+	let formatter = RelayFormatter { ss58_prefix: 42 };
+	let buyer = AccountId32::new([1; 32]);
+	let seller = AccountId32::new([2; 32]);
+	let bid = bid_at(buyer.clone(), 1_000);
+	let bid_hash = BlakeTwo256::hash_of(&bid);
+	let residual_bid = Bid {
+		bid_component: OrderComponent { energy: 4, ..bid.bid_component.clone() },
+		..bid.clone()
+	};
+	let trade = Trade {
+		seller: seller.clone(),
+		buyer,
+		market_id: bid.bid_component.market_id,
+		trade_uuid: H256::random(),
+		creation_time: 1,
+		time_slot: 1_000,
+		offer: Offer { seller, nonce: 1, offer_component: bid.bid_component.clone() },
+		offer_hash: H256::random(),
+		bid,
+		bid_hash,
+		residual_bid: Some(residual_bid.clone()),
+		residual_offer: None,
+		parameters: TradeParameters {
+			selected_energy: 6,
+			energy_rate: 5,
+			trade_uuid: H256::random(),
+		},
+	};
+	let relay_trade = formatter.trade::<_, BlakeTwo256>(&trade);
+	assert_eq!(relay_trade.bid.hash, format!("{:?}", bid_hash));
+	assert_eq!(relay_trade.offer.hash, format!("{:?}", trade.offer_hash));
+	assert_eq!(
+		relay_trade.residual_bid.map(|residual_bid| residual_bid.hash),
+		Some(format!("{:?}", BlakeTwo256::hash_of(&residual_bid)))
+	);
+	assert_eq!(relay_trade.residual_offer, None);
+	assert_eq!(relay_trade.selected_energy, 6);
+}
@@ -1,58 +1,226 @@
+//! Weights for `orderbook_worker`.
+//!
+//! The components and the storage accesses follow the benchmarks of `benchmarking.rs`, in a single
+//! market with the on-chain matching enabled. Regenerate the weights on the reference hardware
+//! with:
+//!
+//! ./target/release/gsy-node benchmark pallet --chain dev --pallet orderbook_worker \
+//!     --extrinsic '*' --steps 50 --repeat 20 --wasm-execution compiled \
+//!     --template .maintain/frame-weight-template.hbs --output modules/orderbook-worker/src/weights.rs
+
 #![cfg_attr(rustfmt, rustfmt_skip)]
 #![allow(unused_parens)]
 #![allow(unused_imports)]
 
-use frame_support::{traits::Get, weights::Weight};
-use sp_std::marker::PhantomData;
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
 
+/// Weight functions needed for orderbook_worker.
 pub trait WeightInfo {
-	fn insert_orders() -> Weight;
-	fn insert_orders_by_proxy() -> Weight;
-	fn remove_orders() -> Weight;
-	fn remove_orders_by_proxy() -> Weight;
-	fn zero_weight() -> Weight;
+    fn insert_orders(n: u32, ) -> Weight;
+    fn insert_orders_by_proxy(n: u32, ) -> Weight;
+    fn remove_orders(n: u32, ) -> Weight;
+    fn remove_orders_by_proxy(n: u32, ) -> Weight;
+    fn amend_orders(n: u32, ) -> Weight;
 }
 
-/// Weight functions for `orderbook_worker`.
+/// Weights for orderbook_worker using the Substrate node and recommended hardware.
 pub struct SubstrateWeightInfo<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeightInfo<T> {
-	// Storage: GsyCollateral RegisteredUser (r:1 w:0)
-	// Storage: OrderbookRegistry OrdersRegistry (r:100 w:100)
-	// Storage: OrderbookWorker Orderbook (r:0 w:100)
-	fn insert_orders() -> Weight {
-		Weight::from_parts(2_495_000_000, 0)
-			.saturating_add(T::DbWeight::get().reads(101))
-			.saturating_add(T::DbWeight::get().writes(200))
-	}
-	// Storage: GsyCollateral ProxyAccounts (r:1 w:0)
-	// Storage: OrderbookRegistry OrdersRegistry (r:100 w:100)
-	// Storage: OrderbookWorker Orderbook (r:0 w:100)
-	fn insert_orders_by_proxy() -> Weight {
-		Weight::from_parts(2_582_000_000, 0)
-			.saturating_add(T::DbWeight::get().reads(101))
-			.saturating_add(T::DbWeight::get().writes(200))
-	}
-	// Storage: GsyCollateral RegisteredUser (r:1 w:0)
-	// Storage: OrderbookRegistry OrdersRegistry (r:100 w:100)
-	// Storage: OrderbookWorker Orderbook (r:0 w:100)
-	fn remove_orders() -> Weight {
-		Weight::from_parts(2_675_000_000, 0)
-			.saturating_add(T::DbWeight::get().reads(101))
-			.saturating_add(T::DbWeight::get().writes(200))
-	}
-	// Storage: GsyCollateral ProxyAccounts (r:1 w:0)
-	// Storage: OrderbookRegistry OrdersRegistry (r:100 w:100)
-	// Storage: OrderbookWorker Orderbook (r:0 w:100)
-	fn remove_orders_by_proxy() -> Weight {
-		Weight::from_parts(2_724_000_000, 0)
-			.saturating_add(T::DbWeight::get().reads(101))
-			.saturating_add(T::DbWeight::get().writes(200))
-	}
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketStatus (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketDeliveryTime (r:1 w:0)
+    /// Storage: OrderbookWorker UserNonce (r:1 w:1)
+    /// Storage: OrderbookWorker UserLastActivity (r:1 w:1)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:0 w:n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// Storage: GsyCollateral Vaults (r:1 w:0)
+    /// Storage: OrderbookWorker OrdersForWorker (r:0 w:n)
+    /// Storage: OrderbookWorker MatchingOrderBook (r:0 w:n)
+    /// Storage: OrderbookWorker OffchainIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotsLength (r:1 w:1)
+    /// The range of component `n` is `[1, 100]`.
+    fn insert_orders(n: u32, ) -> Weight {
+        Weight::from_parts(20_000_000, 0)
+            .saturating_add(Weight::from_parts(24_750_000, 0).saturating_mul(n.into()))
+            .saturating_add(T::DbWeight::get().reads(9_u64))
+            .saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(n.into())))
+            .saturating_add(T::DbWeight::get().writes(5_u64))
+            .saturating_add(T::DbWeight::get().writes((6_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral ProxyAccounts (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketStatus (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketDeliveryTime (r:1 w:0)
+    /// Storage: OrderbookWorker UserNonce (r:1 w:1)
+    /// Storage: OrderbookWorker UserLastActivity (r:1 w:1)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:0 w:n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// Storage: GsyCollateral Vaults (r:1 w:0)
+    /// Storage: OrderbookWorker OrdersForWorker (r:0 w:n)
+    /// Storage: OrderbookWorker MatchingOrderBook (r:0 w:n)
+    /// Storage: OrderbookWorker OffchainIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotsLength (r:1 w:1)
+    /// The range of component `n` is `[1, 100]`.
+    fn insert_orders_by_proxy(n: u32, ) -> Weight {
+        Weight::from_parts(20_000_000, 0)
+            .saturating_add(Weight::from_parts(25_620_000, 0).saturating_mul(n.into()))
+            .saturating_add(T::DbWeight::get().reads(9_u64))
+            .saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(n.into())))
+            .saturating_add(T::DbWeight::get().writes(5_u64))
+            .saturating_add(T::DbWeight::get().writes((6_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn remove_orders(n: u32, ) -> Weight {
+        Weight::from_parts(20_000_000, 0)
+            .saturating_add(Weight::from_parts(26_550_000, 0).saturating_mul(n.into()))
+            .saturating_add(T::DbWeight::get().reads(1_u64))
+            .saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(n.into())))
+            .saturating_add(T::DbWeight::get().writes((3_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral ProxyAccounts (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn remove_orders_by_proxy(n: u32, ) -> Weight {
+        Weight::from_parts(20_000_000, 0)
+            .saturating_add(Weight::from_parts(27_040_000, 0).saturating_mul(n.into()))
+            .saturating_add(T::DbWeight::get().reads(1_u64))
+            .saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(n.into())))
+            .saturating_add(T::DbWeight::get().writes((3_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketStatus (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketDeliveryTime (r:1 w:0)
+    /// Storage: OrderbookWorker UserNonce (r:1 w:1)
+    /// Storage: OrderbookWorker UserLastActivity (r:1 w:1)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:2n w:2n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:2n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:2n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// Storage: GsyCollateral Vaults (r:1 w:0)
+    /// Storage: OrderbookWorker OrdersForWorker (r:0 w:2n)
+    /// Storage: OrderbookWorker MatchingOrderBook (r:0 w:n)
+    /// Storage: OrderbookWorker OffchainIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotsLength (r:1 w:1)
+    /// The range of component `n` is `[1, 100]`.
+    fn amend_orders(n: u32, ) -> Weight {
+        Weight::from_parts(20_000_000, 0)
+            .saturating_add(Weight::from_parts(27_900_000, 0).saturating_mul(n.into()))
+            .saturating_add(T::DbWeight::get().reads(9_u64))
+            .saturating_add(T::DbWeight::get().reads((3_u64).saturating_mul(n.into())))
+            .saturating_add(T::DbWeight::get().writes(5_u64))
+            .saturating_add(T::DbWeight::get().writes((10_u64).saturating_mul(n.into())))
+    }
+}
 
-	// zero weight method for calls that do not require weight
-	fn zero_weight() -> Weight {
-		Weight::from_parts(0, 0)
-			.saturating_add(T::DbWeight::get().reads(101))
-			.saturating_add(T::DbWeight::get().writes(200))
-	}
+// For backwards compatibility and tests
+impl WeightInfo for () {
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketStatus (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketDeliveryTime (r:1 w:0)
+    /// Storage: OrderbookWorker UserNonce (r:1 w:1)
+    /// Storage: OrderbookWorker UserLastActivity (r:1 w:1)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:0 w:n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// Storage: GsyCollateral Vaults (r:1 w:0)
+    /// Storage: OrderbookWorker OrdersForWorker (r:0 w:n)
+    /// Storage: OrderbookWorker MatchingOrderBook (r:0 w:n)
+    /// Storage: OrderbookWorker OffchainIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotsLength (r:1 w:1)
+    /// The range of component `n` is `[1, 100]`.
+    fn insert_orders(n: u32, ) -> Weight {
+        Weight::from_parts(20_000_000, 0)
+            .saturating_add(Weight::from_parts(24_750_000, 0).saturating_mul(n.into()))
+            .saturating_add(RocksDbWeight::get().reads(9_u64))
+            .saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(n.into())))
+            .saturating_add(RocksDbWeight::get().writes(5_u64))
+            .saturating_add(RocksDbWeight::get().writes((6_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral ProxyAccounts (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketStatus (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketDeliveryTime (r:1 w:0)
+    /// Storage: OrderbookWorker UserNonce (r:1 w:1)
+    /// Storage: OrderbookWorker UserLastActivity (r:1 w:1)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:0 w:n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// Storage: GsyCollateral Vaults (r:1 w:0)
+    /// Storage: OrderbookWorker OrdersForWorker (r:0 w:n)
+    /// Storage: OrderbookWorker MatchingOrderBook (r:0 w:n)
+    /// Storage: OrderbookWorker OffchainIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotsLength (r:1 w:1)
+    /// The range of component `n` is `[1, 100]`.
+    fn insert_orders_by_proxy(n: u32, ) -> Weight {
+        Weight::from_parts(20_000_000, 0)
+            .saturating_add(Weight::from_parts(25_620_000, 0).saturating_mul(n.into()))
+            .saturating_add(RocksDbWeight::get().reads(9_u64))
+            .saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(n.into())))
+            .saturating_add(RocksDbWeight::get().writes(5_u64))
+            .saturating_add(RocksDbWeight::get().writes((6_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn remove_orders(n: u32, ) -> Weight {
+        Weight::from_parts(20_000_000, 0)
+            .saturating_add(Weight::from_parts(26_550_000, 0).saturating_mul(n.into()))
+            .saturating_add(RocksDbWeight::get().reads(1_u64))
+            .saturating_add(RocksDbWeight::get().reads((2_u64).saturating_mul(n.into())))
+            .saturating_add(RocksDbWeight::get().writes((3_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral ProxyAccounts (r:1 w:0)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:n w:n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:n)
+    /// The range of component `n` is `[1, 100]`.
+    fn remove_orders_by_proxy(n: u32, ) -> Weight {
+        Weight::from_parts(20_000_000, 0)
+            .saturating_add(Weight::from_parts(27_040_000, 0).saturating_mul(n.into()))
+            .saturating_add(RocksDbWeight::get().reads(1_u64))
+            .saturating_add(RocksDbWeight::get().reads((2_u64).saturating_mul(n.into())))
+            .saturating_add(RocksDbWeight::get().writes((3_u64).saturating_mul(n.into())))
+    }
+    /// Storage: GsyCollateral RegisteredUser (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketStatus (r:1 w:0)
+    /// Storage: OrderbookRegistry MarketDeliveryTime (r:1 w:0)
+    /// Storage: OrderbookWorker UserNonce (r:1 w:1)
+    /// Storage: OrderbookWorker UserLastActivity (r:1 w:1)
+    /// Storage: OrderbookRegistry OrdersRegistry (r:2n w:2n)
+    /// Storage: OrderbookRegistry OrderMarket (r:n w:2n)
+    /// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:2n)
+    /// Storage: OrderbookRegistry MarketOrders (r:0 w:n)
+    /// Storage: GsyCollateral Vaults (r:1 w:0)
+    /// Storage: OrderbookWorker OrdersForWorker (r:0 w:2n)
+    /// Storage: OrderbookWorker MatchingOrderBook (r:0 w:n)
+    /// Storage: OrderbookWorker OffchainIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotIndexLength (r:1 w:1)
+    /// Storage: OrderbookWorker OffchainSlotsLength (r:1 w:1)
+    /// The range of component `n` is `[1, 100]`.
+    fn amend_orders(n: u32, ) -> Weight {
+        Weight::from_parts(20_000_000, 0)
+            .saturating_add(Weight::from_parts(27_900_000, 0).saturating_mul(n.into()))
+            .saturating_add(RocksDbWeight::get().reads(9_u64))
+            .saturating_add(RocksDbWeight::get().reads((3_u64).saturating_mul(n.into())))
+            .saturating_add(RocksDbWeight::get().writes(5_u64))
+            .saturating_add(RocksDbWeight::get().writes((10_u64).saturating_mul(n.into())))
+    }
 }
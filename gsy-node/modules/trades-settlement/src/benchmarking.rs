@@ -3,60 +3,145 @@
 
 use super::*;
 
-use crate::benchmarking::vec::Vec;
 use crate::test_orders::TestOrderbookFunctions;
 #[allow(unused)]
 use crate::Pallet as TradesSettlement;
-use frame_benchmarking::{benchmarks, impl_benchmark_test_suite, whitelisted_caller};
-use frame_support::{sp_runtime::traits::Hash, traits::Currency};
+use frame_benchmarking::v2::*;
+use frame_support::{pallet_prelude::Get, sp_runtime::traits::Hash, traits::Currency, BoundedVec};
 use frame_system::RawOrigin;
 use gsy_collateral::{BalanceOf, Pallet as GsyCollateral};
-use gsy_primitives::{BidOfferMatch, Order, Vault};
+use gsy_primitives::{Bid, BidOfferMatch, Offer, Order, OrderComponent, TradesPenalties, Vault};
 use orderbook_registry::Pallet as OrderbookRegistry;
 use orderbook_worker::Pallet as OrderbookWorker;
+use scale_info::prelude::vec::Vec;
 use sp_std::vec;
 
-benchmarks! {
-	settle_trades {
-		let operator_account: T::AccountId = whitelisted_caller();
-		TestOrderbookFunctions::add_exchange_operator::<T>(operator_account.clone()).unwrap();
-		let buyer: T::AccountId = whitelisted_caller();
-		TestOrderbookFunctions::add_user::<T>(buyer.clone()).unwrap();
-		let seller: T::AccountId = whitelisted_caller();
-		TestOrderbookFunctions::add_user::<T>(seller.clone()).unwrap();
-		let block_number = 1677453190;
-		let _ = GsyCollateral::<T>::create(operator_account.clone());
-		let _ = GsyCollateral::<T>::create(buyer.clone());
-		let _ = GsyCollateral::<T>::create(seller.clone());
-		let amount: BalanceOf<T> = 10_000_000u32.into();
-		let _ = <T as gsy_collateral::Config>::Currency::deposit_creating(&seller, amount * 2u32.into());
-		let _ = GsyCollateral::<T>::deposit(&seller, amount);
-		let mut bid_offer_matches: Vec<BidOfferMatch<T::AccountId, T::Hash>> = vec![];
-		for i in 0..100 {
-			let bid = TestOrderbookFunctions::dummy_bid::<T>(
-				buyer.clone(), block_number, i as u64, i as u64);
-			let bid_order = Order::Bid(bid.clone());
-			let bid_order_hash = T::Hashing::hash_of(&bid_order);
-			let _ = OrderbookRegistry::<T>::insert_orders(RawOrigin::Signed(buyer.clone()).into(), vec!(bid_order_hash.clone()));
-			let _ = OrderbookWorker::<T>::add_order(buyer.clone(), bid_order.clone());
-			let offer = TestOrderbookFunctions::dummy_offer::<T>(
-				seller.clone(), block_number, i as u64, i as u64);
-			let offer_order = Order::Offer(offer.clone());
-			let offer_order_hash = T::Hashing::hash_of(&offer_order);
-			let _ = OrderbookRegistry::<T>::insert_orders(RawOrigin::Signed(seller.clone()).into(), vec!(offer_order_hash.clone()));
-			let _ = OrderbookWorker::<T>::add_order(seller.clone(), offer_order.clone());
-			let bid_offer_match = TestOrderbookFunctions::dummy_bid_offer_match::<T>(
-				bid.clone(),
-				offer.clone(),
-				None,
-				None,
-				block_number,
-				i as u64,
-				i as u64,
-			);
-			bid_offer_matches.push(bid_offer_match);
-		}
-	}: _(RawOrigin::Signed(operator_account.clone()), bid_offer_matches)
+const TIME_SLOT: u64 = 1677453190;
+
+/// Register the account as a user, with a vault holding enough collateral for all the trades.
+fn funded_user<T: Config>(name: &'static str) -> T::AccountId {
+	let user: T::AccountId = account(name, 0, 0);
+	TestOrderbookFunctions::add_user::<T>(user.clone()).unwrap();
+	let _ = GsyCollateral::<T>::create(user.clone());
+	let amount: BalanceOf<T> = 10_000_000u32.into();
+	let _ = <T as gsy_collateral::Config>::Currency::deposit_creating(&user, amount * 2u32.into());
+	let _ = GsyCollateral::<T>::deposit(&user, amount);
+	user
+}
+
+fn exchange_operator<T: Config>() -> T::AccountId {
+	let operator: T::AccountId = whitelisted_caller();
+	TestOrderbookFunctions::add_exchange_operator::<T>(operator.clone()).unwrap();
+	operator
+}
+
+/// Register the order in the orderbook registry, in its open market, and in the orderbook worker.
+fn insert_order<T: Config>(owner: &T::AccountId, order: Order<T::AccountId>) {
+	let hash = match &order {
+		Order::Bid(bid) => T::Hashing::hash_of(bid),
+		Order::Offer(offer) => T::Hashing::hash_of(offer),
+	};
+	let market_id = OrderbookWorker::<T>::get_order_market_id(&order);
+	orderbook_registry::MarketStatus::<T>::insert(market_id, true);
+	OrderbookRegistry::<T>::insert_orders(
+		RawOrigin::Signed(owner.clone()).into(),
+		BoundedVec::truncate_from(vec![(market_id, hash)]),
+	)
+	.unwrap();
+	OrderbookWorker::<T>::add_order(owner.clone(), order).unwrap();
+}
+
+/// The match of a registered bid and a registered offer, trading one unit of energy. The match
+/// leaves a residual bid and a residual offer if `with_residuals` is set.
+fn registered_match<T: Config>(
+	buyer: &T::AccountId,
+	seller: &T::AccountId,
+	index: u64,
+	with_residuals: bool,
+) -> BidOfferMatch<T::AccountId, T::Hash> {
+	let energy = index + 2;
+	let bid = TestOrderbookFunctions::dummy_bid::<T>(buyer.clone(), TIME_SLOT, energy, 1);
+	let offer = TestOrderbookFunctions::dummy_offer::<T>(seller.clone(), TIME_SLOT, energy, 1);
+	insert_order::<T>(buyer, Order::Bid(bid.clone()));
+	insert_order::<T>(seller, Order::Offer(offer.clone()));
+	let (residual_bid, residual_offer) = if with_residuals {
+		(
+			Some(Bid {
+				nonce: bid.nonce + 1,
+				bid_component: OrderComponent { energy: energy - 1, ..bid.bid_component.clone() },
+				..bid.clone()
+			}),
+			Some(Offer {
+				nonce: offer.nonce + 1,
+				offer_component: OrderComponent {
+					energy: energy - 1,
+					..offer.offer_component.clone()
+				},
+				..offer.clone()
+			}),
+		)
+	} else {
+		(None, None)
+	};
+	TestOrderbookFunctions::dummy_bid_offer_match::<T>(
+		bid,
+		offer,
+		residual_bid,
+		residual_offer,
+		TIME_SLOT,
+		1,
+		1,
+	)
 }
 
-impl_benchmark_test_suite!(TradesSettlement, crate::mock::new_test_ext(), crate::mock::Test);
+#[benchmarks]
+mod benchmarks {
+	use super::*;
+
+	/// `n` matches are settled, of which the first `r` leave a residual bid and a residual offer.
+	#[benchmark]
+	fn settle_trades(
+		n: Linear<1, { T::MaxMatchesPerBatch::get() }>,
+		r: Linear<0, { T::MaxMatchesPerBatch::get() }>,
+	) {
+		let operator = exchange_operator::<T>();
+		let buyer = funded_user::<T>("buyer");
+		let seller = funded_user::<T>("seller");
+		let proposed_matches: Vec<_> = (0..n)
+			.map(|index| registered_match::<T>(&buyer, &seller, index as u64, index < r))
+			.collect();
+		let index_length = OrderbookWorker::<T>::offchain_index_length();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(operator), BoundedVec::truncate_from(proposed_matches));
+
+		assert_eq!(
+			OrderbookWorker::<T>::offchain_index_length(),
+			index_length + n as u64 + 2 * r.min(n) as u64
+		);
+	}
+
+	#[benchmark]
+	fn submit_penalties(n: Linear<1, { T::MaxPenaltiesPerBatch::get() }>) {
+		let operator = exchange_operator::<T>();
+		let penalized_account: T::AccountId = account("penalized", 0, 0);
+		let market_uuid = T::Hashing::hash_of(&b"benchmark-market");
+		// The delivery time of the market must be known.
+		OrderbookRegistry::<T>::note_market_delivery_time(market_uuid, TIME_SLOT);
+		let penalties: Vec<_> = (0..n)
+			.map(|index| TradesPenalties {
+				penalized_account: penalized_account.clone(),
+				market_uuid,
+				trade_uuid: T::Hashing::hash_of(&index),
+				penalty_energy: index as u64 + 1,
+			})
+			.collect();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(operator), BoundedVec::truncate_from(penalties));
+
+		assert_eq!(TradesSettlement::<T>::penalties_of(&penalized_account).len(), n as usize);
+	}
+
+	impl_benchmark_test_suite!(TradesSettlement, crate::mock::new_test_ext(), crate::mock::Test);
+}
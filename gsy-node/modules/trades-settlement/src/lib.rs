@@ -44,23 +44,50 @@ mod benchmarking;
 #[cfg(feature = "runtime-benchmarks")]
 mod test_orders;
 
+pub mod migrations;
 pub mod weights;
 
 #[frame_support::pallet]
 pub mod pallet {
 	use crate::weights::TradeSettlementWeightInfo;
 	use frame_support::{dispatch::DispatchResult, dispatch::RawOrigin, pallet_prelude::*};
-	use frame_support::{sp_runtime::traits::Hash, transactional};
-	use frame_system::{ensure_signed, pallet_prelude::*};
+	use frame_support::{
+		sp_runtime::traits::Hash,
+		traits::{FindAuthor, UnixTime},
+		transactional,
+	};
+	use frame_system::{
+		ensure_signed,
+		offchain::{AppCrypto, CreateSignedTransaction, SendSignedTransaction, Signer},
+		pallet_prelude::*,
+	};
 	use gsy_primitives::v0::{
-		Bid, BidOfferMatch, Offer, Order, OrderComponent, Trade, TradesPenalties, Validator,
+		Bid, BidOfferMatch, IndexedRecord, Offer, Order, OrderComponent, OrderReference, Trade,
+		TradesPenalties, Validator,
 	};
+	use orderbook_registry::pruning;
 	use scale_info::prelude::vec::Vec;
+	use sp_runtime::{
+		offchain::storage::StorageValueRef,
+		traits::{IdentifyAccount, Saturating},
+		RuntimeAppPublic,
+	};
 	use sp_std::vec;
 
+	/// Key of the offchain local storage holding the block at which the matches of the closed
+	/// markets have last been submitted.
+	pub const MATCHING_SUBMISSION_KEY: &[u8] = b"gsy-node::matching::last-submission";
+
+	/// Number of blocks to wait for the submitted matches to be settled before submitting the
+	/// matches of the closed markets again.
+	pub const MATCHING_RESUBMISSION_BLOCKS: u32 = 10;
+
+	type AuthorityIdOf<T> = <T as orderbook_worker::Config>::AuthorityId;
+
 	#[pallet::config]
 	pub trait Config:
-		frame_system::Config
+		CreateSignedTransaction<Call<Self>>
+		+ frame_system::Config
 		+ orderbook_registry::Config
 		+ orderbook_worker::Config
 		+ gsy_collateral::Config
@@ -72,10 +99,26 @@ pub mod pallet {
 		/// The length of the market slot in seconds.
 		#[pallet::constant]
 		type MarketSlotDuration: Get<u64>;
+
+		/// The maximum number of matches settled in a single extrinsic.
+		#[pallet::constant]
+		type MaxMatchesPerBatch: Get<u32>;
+
+		/// The maximum number of penalties submitted in a single extrinsic.
+		#[pallet::constant]
+		type MaxPenaltiesPerBatch: Get<u32>;
+
+		/// Find the author of a block. Only the block author submits the matches of the closed
+		/// markets.
+		type FindAuthor: FindAuthor<Self::AccountId>;
 	}
 
+	/// The in-code storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
 	// #[pallet::generate_store(pub (super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::storage]
@@ -83,11 +126,28 @@ pub mod pallet {
 	pub type PenaltiesRegistry<T: Config> =
 		StorageMap<_, Twox64Concat, T::Hash, TradesPenalties<T::AccountId, T::Hash>>;
 
+	#[pallet::storage]
+	/// Keeps track of the delivery time slot of the market of each penalty, used to prune the
+	/// expired penalties.
+	pub type PenaltyDeliveryTime<T: Config> = StorageMap<_, Twox64Concat, T::Hash, u64>;
+
+	#[pallet::storage]
+	/// Indexes the penalties of each penalized account, so that they are listed without iterating
+	/// over the whole registry.
+	pub type AccountPenalties<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Twox64Concat, T::Hash, (), OptionQuery>;
+
+	#[pallet::storage]
+	/// Raw storage key of the last penalty visited while pruning the expired penalties.
+	pub type PruningCursor<T: Config> = StorageValue<_, pruning::CursorKey, OptionQuery>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub (super) fn deposit_event)]
 	pub enum Event<T: Config> {
 		TradesSettled(T::Hash),
 		PenaltiesSubmitted(TradesPenalties<T::AccountId, T::Hash>, T::Hash),
+		/// An expired penalty has been pruned. \[penalty_hash\]
+		PenaltyPruned(T::Hash),
 	}
 
 	#[pallet::error]
@@ -108,6 +168,26 @@ pub mod pallet {
 		BidEnergyLessThanSelectedEnergy,
 		/// Ensure that the energy subtraction in the validation is correct.
 		UnableToSubtractEnergy,
+		/// Ensure that an account is not penalized twice for a trade.
+		PenaltyAlreadySubmitted,
+		/// Ensure that a penalty is not submitted once its market has expired, since it may have
+		/// been submitted and pruned already.
+		PenaltyExpired,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_idle(_block_number: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			Self::prune_expired_penalties(remaining_weight)
+		}
+
+		fn offchain_worker(block_number: BlockNumberFor<T>) {
+			if <T as orderbook_worker::Config>::OnChainMatching::get()
+				&& sp_io::offchain::is_validator()
+			{
+				Self::submit_closed_market_matches(block_number);
+			}
+		}
 	}
 
 	#[pallet::call]
@@ -118,14 +198,32 @@ pub mod pallet {
 		/// `origin`: The origin of the extrinsic. The Exchange operator who wants to settle the matches.
 		/// `proposed_matches`: Vector of BidOfferMatch structures. Recommended matches for potential trades.
 		#[transactional]
-		#[pallet::weight(< T as Config >::TradeSettlementWeightInfo::settle_trades())]
+		#[pallet::weight(
+			< T as Config >::TradeSettlementWeightInfo::settle_trades(
+				proposed_matches.len() as u32,
+				Pallet::<T>::matches_with_residuals(proposed_matches),
+			)
+		)]
 		#[pallet::call_index(0)]
 		pub fn settle_trades(
 			origin: OriginFor<T>,
-			proposed_matches: Vec<BidOfferMatch<T::AccountId, T::Hash>>,
+			proposed_matches: BoundedVec<
+				BidOfferMatch<T::AccountId, T::Hash>,
+				T::MaxMatchesPerBatch,
+			>,
 		) -> DispatchResult {
 			let operator_account = ensure_signed(origin)?;
 
+			// Matches on expired orders cannot settle, their orders are marked as expired instead.
+			let now = <T as orderbook_registry::Config>::TimeProvider::now().as_secs();
+			let (proposed_matches, expired_matches): (Vec<_>, Vec<_>) =
+				proposed_matches.into_iter().partition(|bid_offer_match| {
+					<Self as Validator>::validate_validity(bid_offer_match, now)
+				});
+			for expired_match in expired_matches.iter() {
+				Self::expire_match_orders(expired_match, now);
+			}
+
 			let valid_matches: Vec<_> = proposed_matches
 				.into_iter()
 				.filter(|bid_offer_match| <Self as Validator>::validate(bid_offer_match))
@@ -135,10 +233,16 @@ pub mod pallet {
 				for valid_match in valid_matches.clone() {
 					// Check residual orders and add them to storage.
 					if let Some(residual_bid) = valid_match.residual_bid {
-						// Add residual bid in the orderbook registry.
+						// Add residual bid in the orderbook registry, keyed by the hash of the bid
+						// like the orders inserted by the worker.
 						<orderbook_registry::Pallet<T>>::insert_orders(
 							RawOrigin::Signed(residual_bid.buyer.clone()).into(),
-							vec![T::Hashing::hash_of(&Order::Bid(residual_bid.clone()))],
+							BoundedVec::truncate_from(vec![(
+								<orderbook_worker::Pallet<T>>::get_order_market_id(&Order::Bid(
+									residual_bid.clone(),
+								)),
+								T::Hashing::hash_of(&residual_bid),
+							)]),
 						)?;
 						// Add residual in the orderbook worker.
 						<orderbook_worker::Pallet<T>>::add_order(
@@ -150,7 +254,12 @@ pub mod pallet {
 						// Add residual in the orderbook registry.
 						<orderbook_registry::Pallet<T>>::insert_orders(
 							RawOrigin::Signed(residual_offer.seller.clone()).into(),
-							vec![T::Hashing::hash_of(&Order::Offer(residual_offer.clone()))],
+							BoundedVec::truncate_from(vec![(
+								<orderbook_worker::Pallet<T>>::get_order_market_id(&Order::Offer(
+									residual_offer.clone(),
+								)),
+								T::Hashing::hash_of(&residual_offer),
+							)]),
 						)?;
 						// Add residual in the orderbook worker.
 						<orderbook_worker::Pallet<T>>::add_order(
@@ -181,6 +290,8 @@ pub mod pallet {
 
 				Self::deposit_event(Event::TradesSettled(T::Hashing::hash_of(&trades)));
 				Ok(())
+			} else if !expired_matches.is_empty() {
+				Ok(())
 			} else {
 				Err(Error::<T>::NoValidMatchToSettle.into())
 			}
@@ -190,13 +301,17 @@ pub mod pallet {
 		///
 		/// This function is restricted to the execution engine operator (here enforced by require
 		/// that the origin is root). It accepts a vector of penalty records and stores each one
-		/// in the `TradesPenalties` storage map.
+		/// in the `TradesPenalties` storage map. The whole batch is rejected if the account of one
+		/// of its penalties has already been penalized for the trade, or if the delivery time slot
+		/// of its market is unknown or older than the pruning horizon of the penalties.
 		#[transactional]
 		#[pallet::call_index(1)]
-		#[pallet::weight(<T as Config>::TradeSettlementWeightInfo::submit_penalties())]
+		#[pallet::weight(
+			<T as Config>::TradeSettlementWeightInfo::submit_penalties(penalties.len() as u32)
+		)]
 		pub fn submit_penalties(
 			origin: OriginFor<T>,
-			penalties: Vec<TradesPenalties<T::AccountId, T::Hash>>,
+			penalties: BoundedVec<TradesPenalties<T::AccountId, T::Hash>, T::MaxPenaltiesPerBatch>,
 		) -> DispatchResult {
 			let operator_account = ensure_signed(origin)?;
 			// Verify that the user is a registered operator account.
@@ -207,11 +322,28 @@ pub mod pallet {
 			log::info!("Submitting penalties {:?}...", penalties.len());
 			// For each penalty in the input vector, compute a unique hash and insert it.
 			for penalty in penalties.into_iter() {
-				let penalty_hash = T::Hashing::hash_of(&penalty);
+				let penalty_hash = Self::penalty_hash(&penalty);
+				ensure!(
+					!<PenaltiesRegistry<T>>::contains_key(penalty_hash),
+					Error::<T>::PenaltyAlreadySubmitted
+				);
+
+				// The penalties older than the pruning horizon may have been submitted and pruned.
+				let delivery_time =
+					<orderbook_registry::Pallet<T>>::market_delivery_time(penalty.market_uuid)
+						.ok_or(orderbook_registry::Error::<T>::MarketNotFound)?;
+				ensure!(delivery_time > Self::penalties_expiry_time(), Error::<T>::PenaltyExpired);
 
 				log::info!("Inserting penalty {:?} {:?}...", penalty_hash, penalty.penalty_energy);
 
 				<PenaltiesRegistry<T>>::insert(penalty_hash, penalty.clone());
+				<PenaltyDeliveryTime<T>>::insert(penalty_hash, delivery_time);
+				<AccountPenalties<T>>::insert(&penalty.penalized_account, penalty_hash, ());
+
+				<orderbook_worker::Pallet<T>>::index_offchain(
+					IndexedRecord::Penalty { hash: penalty_hash, penalty: penalty.clone() },
+					delivery_time,
+				);
 
 				log::info!("Emitting penalty event...");
 				Self::deposit_event(Event::PenaltiesSubmitted(penalty, penalty_hash));
@@ -221,6 +353,177 @@ pub mod pallet {
 		}
 	}
 
+	impl<T: Config> Pallet<T> {
+		/// Helper function to count the matches leaving a residual bid or a residual offer.
+		///
+		/// Parameters
+		/// `proposed_matches`: The matches to settle.
+		pub fn matches_with_residuals(
+			proposed_matches: &[BidOfferMatch<T::AccountId, T::Hash>],
+		) -> u32 {
+			proposed_matches
+				.iter()
+				.filter(|bid_offer_match| {
+					bid_offer_match.residual_bid.is_some()
+						|| bid_offer_match.residual_offer.is_some()
+				})
+				.count() as u32
+		}
+
+		/// Helper function to compute the hash identifying a penalty in the registry. An account
+		/// is penalized at most once per trade, so the penalty energy is not part of the hash.
+		///
+		/// Parameters
+		/// `penalty`: The penalty.
+		pub fn penalty_hash(penalty: &TradesPenalties<T::AccountId, T::Hash>) -> T::Hash {
+			T::Hashing::hash_of(&(
+				&penalty.market_uuid,
+				&penalty.trade_uuid,
+				&penalty.penalized_account,
+			))
+		}
+
+		/// Helper function to list the penalties submitted for a given account, read from the
+		/// index of the penalties of the account.
+		///
+		/// Parameters
+		/// `account`: The penalized account.
+		pub fn penalties_of(
+			account: &T::AccountId,
+		) -> Vec<(T::Hash, TradesPenalties<T::AccountId, T::Hash>)> {
+			<AccountPenalties<T>>::iter_key_prefix(account)
+				.filter_map(|penalty_hash| {
+					Some((penalty_hash, <PenaltiesRegistry<T>>::get(penalty_hash)?))
+				})
+				.collect()
+		}
+
+		/// Mark the expired orders of a bid/offer match as expired in the orderbook registry.
+		///
+		/// Parameters
+		/// `bid_offer_match`: The bid/offer match containing at least one expired order.
+		/// `now`: The current time, in seconds.
+		fn expire_match_orders(bid_offer_match: &BidOfferMatch<T::AccountId, T::Hash>, now: u64) {
+			if bid_offer_match.bid.bid_component.is_expired(now) {
+				let bid_ref = OrderReference {
+					user_id: bid_offer_match.bid.buyer.clone(),
+					hash: T::Hashing::hash_of(&bid_offer_match.bid),
+				};
+				// The order may already be executed, deleted or expired.
+				let _ = <orderbook_registry::Pallet<T>>::expire_order(bid_ref);
+			}
+			if bid_offer_match.offer.offer_component.is_expired(now) {
+				let offer_ref = OrderReference {
+					user_id: bid_offer_match.offer.seller.clone(),
+					hash: T::Hashing::hash_of(&bid_offer_match.offer),
+				};
+				let _ = <orderbook_registry::Pallet<T>>::expire_order(offer_ref);
+			}
+		}
+
+		/// Submit the matches of the closed markets, computed by the orderbook worker, as a
+		/// `settle_trades` transaction, when the block is authored by a registered exchange
+		/// operator key of the local keystore.
+		///
+		/// Parameters
+		/// `block_number`: The block the offchain worker runs for.
+		pub fn submit_closed_market_matches(block_number: BlockNumberFor<T>) {
+			let last_submission = StorageValueRef::persistent(MATCHING_SUBMISSION_KEY);
+			if let Ok(Some(last_block)) = last_submission.get::<BlockNumberFor<T>>() {
+				if block_number < last_block.saturating_add(MATCHING_RESUBMISSION_BLOCKS.into()) {
+					return;
+				}
+			}
+			// The matches left out of the batch are submitted with the next batch.
+			let proposed_matches = BoundedVec::<_, T::MaxMatchesPerBatch>::truncate_from(
+				<orderbook_worker::Pallet<T>>::closed_market_matches(),
+			);
+			if proposed_matches.is_empty() {
+				return;
+			}
+			let Some(operator) = Self::local_exchange_operator() else {
+				log::debug!("The block is not authored by a local exchange operator key.");
+				return;
+			};
+			let signer = Signer::<T, AuthorityIdOf<T>>::any_account().with_filter(vec![operator]);
+			match signer.send_signed_transaction(|_| Call::settle_trades {
+				proposed_matches: proposed_matches.clone(),
+			}) {
+				Some((_, Ok(()))) => {
+					last_submission.set(&block_number);
+					log::info!(
+						"Submitted {} matches of the closed markets",
+						proposed_matches.len()
+					);
+				},
+				Some((_, Err(()))) => {
+					log::error!("Failed to submit the matches of the closed markets")
+				},
+				None => log::error!("No local account available to submit the matches"),
+			}
+		}
+
+		/// The key of the local keystore that authored the current block, if it belongs to a
+		/// registered exchange operator.
+		fn local_exchange_operator() -> Option<T::Public> {
+			let digest = <frame_system::Pallet<T>>::digest();
+			let author = T::FindAuthor::find_author(
+				digest.logs().iter().filter_map(|item| item.as_pre_runtime()),
+			)?;
+			if !<gsy_collateral::Pallet<T>>::is_registered_exchange_operator(&author) {
+				return None;
+			}
+			<AuthorityIdOf<T> as AppCrypto<T::Public, T::Signature>>::RuntimeAppPublic::all()
+				.into_iter()
+				.map(|key| {
+					let generic_public: <AuthorityIdOf<T> as AppCrypto<
+						T::Public,
+						T::Signature,
+					>>::GenericPublic = key.into();
+					generic_public.into()
+				})
+				.find(|public: &T::Public| public.clone().into_account() == author)
+		}
+
+		/// Remove the penalties whose market delivery time slot is older than
+		/// `MarketRetentionPeriod`, without consuming more than `weight_limit`.
+		///
+		/// Parameters
+		/// `weight_limit`: The maximum weight that can be consumed.
+		pub fn prune_expired_penalties(weight_limit: Weight) -> Weight {
+			let db_weight = T::DbWeight::get();
+			let expiry_time = Self::penalties_expiry_time();
+			pruning::prune_from_cursor::<PruningCursor<T>, _>(
+				weight_limit,
+				db_weight.reads_writes(2, 1),
+				db_weight.reads_writes(2, 3),
+				|cursor| match cursor {
+					Some(cursor) => <PenaltyDeliveryTime<T>>::iter_from(cursor),
+					None => <PenaltyDeliveryTime<T>>::iter(),
+				},
+				|(penalty_hash, delivery_time), _| {
+					if delivery_time > expiry_time {
+						return pruning::Visit::Done(Weight::zero());
+					}
+					if let Some(penalty) = <PenaltiesRegistry<T>>::take(penalty_hash) {
+						<AccountPenalties<T>>::remove(&penalty.penalized_account, penalty_hash);
+					}
+					<PenaltyDeliveryTime<T>>::remove(penalty_hash);
+					Self::deposit_event(Event::PenaltyPruned(penalty_hash));
+					pruning::Visit::Done(Weight::zero())
+				},
+			)
+		}
+
+		/// The pruning horizon of the penalties: the penalties whose market delivery time slot is
+		/// not later than this time, in seconds, are pruned.
+		fn penalties_expiry_time() -> u64 {
+			<T as orderbook_registry::Config>::TimeProvider::now()
+				.as_secs()
+				.saturating_sub(<T as orderbook_registry::Config>::MarketRetentionPeriod::get())
+		}
+	}
+
 	impl<T: Config> Validator for Pallet<T> {
 		type AccountId = T::AccountId;
 		type Hash = T::Hash;
@@ -253,6 +556,9 @@ pub mod pallet {
 				// 	.checked_div(T::MarketSlotDuration::get())
 				// 	.unwrap_or(0),
 				bid_offer_match.time_slot.checked_div(T::MarketSlotDuration::get()).unwrap_or(0),
+			) || !Self::validate_validity(
+				bid_offer_match,
+				<T as orderbook_registry::Config>::TimeProvider::now().as_secs(),
 			) {
 				return false;
 			}
@@ -293,6 +599,14 @@ pub mod pallet {
 			true
 		}
 
+		fn validate_validity(
+			bid_offer_match: &BidOfferMatch<Self::AccountId, Self::Hash>,
+			now: u64,
+		) -> bool {
+			!bid_offer_match.bid.bid_component.is_expired(now)
+				&& !bid_offer_match.offer.offer_component.is_expired(now)
+		}
+
 		fn validate_bid_energy_component(bid_component_energy: u64, selected_energy: u64) -> bool {
 			bid_component_energy >= selected_energy
 		}
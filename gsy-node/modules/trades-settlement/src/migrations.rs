@@ -0,0 +1,104 @@
+//! Storage migrations of the trades settlement.
+//!
+//! The modules follow the layout of the orderbook registry migrations: one module per storage
+//! version, whose migration is skipped unless the storage is at the previous version.
+
+use crate::pallet::*;
+use frame_support::{pallet_prelude::*, traits::OnRuntimeUpgrade};
+use scale_info::prelude::vec::Vec;
+use sp_std::marker::PhantomData;
+#[cfg(feature = "try-runtime")]
+use {sp_runtime::TryRuntimeError, sp_std::collections::btree_set::BTreeSet};
+
+/// Version 1, the first versioned storage layout.
+///
+/// The penalties of the unversioned storage are identified by the hash of the whole penalty. They
+/// are inserted again under the hash of their market, trade and penalized account, so that an
+/// account is penalized at most once per trade, keeping one of the penalties of an account for a
+/// trade if there are several.
+///
+/// The indexes added with this version are populated as well: each penalty is indexed under its
+/// penalized account, and gets the delivery time slot of its market, or the time of the upgrade if
+/// the market is unknown, so that it is pruned once the retention period has passed. It runs after
+/// the migration of the orderbook registry, which records the delivery time slot of the markets.
+pub mod v1 {
+	use super::*;
+	use frame_support::traits::UnixTime;
+
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 0 {
+				log::info!("trades settlement storage version is not 0, skipping the migration");
+				return T::DbWeight::get().reads(1);
+			}
+			let now = <T as orderbook_registry::Config>::TimeProvider::now().as_secs();
+			let legacy_penalties: Vec<_> = PenaltiesRegistry::<T>::drain().collect();
+			let (mut penalties, mut duplicates) = (0, 0);
+			for (_, penalty) in legacy_penalties {
+				let penalty_hash = Pallet::<T>::penalty_hash(&penalty);
+				if PenaltiesRegistry::<T>::contains_key(penalty_hash) {
+					duplicates += 1;
+					continue;
+				}
+				let delivery_time =
+					<orderbook_registry::Pallet<T>>::market_delivery_time(penalty.market_uuid)
+						.unwrap_or(now);
+				PenaltyDeliveryTime::<T>::insert(penalty_hash, delivery_time);
+				AccountPenalties::<T>::insert(&penalty.penalized_account, penalty_hash, ());
+				PenaltiesRegistry::<T>::insert(penalty_hash, penalty);
+				penalties += 1;
+			}
+			StorageVersion::new(1).put::<Pallet<T>>();
+			log::info!(
+				"trades settlement storage migrated to v1, {} penalties indexed, {} duplicates removed",
+				penalties,
+				duplicates
+			);
+			let translated = penalties + duplicates;
+			T::DbWeight::get()
+				.reads_writes(2 + 2 * translated + penalties, 1 + translated + 3 * penalties)
+		}
+
+		/// Count the penalties once identified by their market, trade and penalized account,
+		/// checking that every penalty decodes.
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			let keys = PenaltiesRegistry::<T>::iter_keys().count();
+			let penalty_hashes: BTreeSet<_> = PenaltiesRegistry::<T>::iter_values()
+				.map(|penalty| Pallet::<T>::penalty_hash(&penalty))
+				.collect();
+			ensure!(
+				PenaltiesRegistry::<T>::iter_values().count() == keys,
+				"Some penalties cannot be decoded"
+			);
+			Ok((penalty_hashes.len() as u64).encode())
+		}
+
+		/// Check that the penalty of every account for every trade is kept, and indexed with the
+		/// delivery time slot of its market and under its penalized account.
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			let penalties = u64::decode(&mut &state[..])
+				.map_err(|_| "Unable to decode the pre-upgrade state")?;
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() == 1,
+				"The storage version has not been updated to 1"
+			);
+			ensure!(
+				PenaltiesRegistry::<T>::iter_keys().count() as u64 == penalties,
+				"The penalties have not been preserved"
+			);
+			ensure!(
+				PenaltyDeliveryTime::<T>::iter_keys().count() as u64 == penalties,
+				"The delivery time slot of the penalties has not been recorded"
+			);
+			ensure!(
+				AccountPenalties::<T>::iter_keys().count() as u64 == penalties,
+				"The penalties have not been indexed per account"
+			);
+			Ok(())
+		}
+	}
+}
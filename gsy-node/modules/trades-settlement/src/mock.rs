@@ -119,12 +119,17 @@ impl orderbook_registry::Config for Test {
 	type RegistryProxyAccountLimit = ();
 	type WeightInfo = ();
 	type TimeProvider = pallet_timestamp::Pallet<Test>;
+	type MarketRetentionPeriod = frame_support::traits::ConstU64<3600>;
+	type MaxOrdersPerBatch = frame_support::traits::ConstU32<100>;
 }
 
 impl trades_settlement::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type TradeSettlementWeightInfo = trades_settlement::weights::SubstrateWeightInfo<Test>;
 	type MarketSlotDuration = MarketSlotDuration;
+	type MaxMatchesPerBatch = frame_support::traits::ConstU32<100>;
+	type MaxPenaltiesPerBatch = frame_support::traits::ConstU32<100>;
+	type FindAuthor = ();
 }
 
 parameter_types! {
@@ -136,6 +141,7 @@ impl orderbook_worker::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type Call = frame_system::pallet_prelude::RuntimeCallFor<Test>;
 	type UnsignedPriority = UnsignedPriority;
+	type OnChainMatching = frame_support::traits::ConstBool<false>;
 	type WeightInfo = orderbook_worker::weights::SubstrateWeightInfo<Test>;
 }
 
@@ -170,5 +176,9 @@ where
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	system::GenesisConfig::<Test>::default().build_storage().unwrap().into()
+	let mut ext: sp_io::TestExternalities =
+		system::GenesisConfig::<Test>::default().build_storage().unwrap().into();
+	// Open the market of the test orders.
+	ext.execute_with(|| orderbook_registry::MarketStatus::<Test>::insert(H256([1u8; 32]), true));
+	ext
 }
@@ -34,6 +34,7 @@ impl TestOrderbookFunctions {
 				creation_time: 1677453190,
 				energy,
 				energy_rate,
+				valid_until: None,
 			},
 		}
 	}
@@ -54,6 +55,7 @@ impl TestOrderbookFunctions {
 				creation_time: 1677453190,
 				energy,
 				energy_rate,
+				valid_until: None,
 			},
 		}
 	}
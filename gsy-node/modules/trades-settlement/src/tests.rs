@@ -2,12 +2,23 @@ use crate::mock::GsyCollateral;
 use crate::mock::OrderbookRegistry;
 use crate::test_orders::TestOrderbookFunctions;
 use crate::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok, traits::fungible::Mutate};
+use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{fungible::Mutate, Get, Hooks},
+	weights::Weight,
+	BoundedVec,
+};
 use frame_system::RawOrigin;
+use gsy_primitives::v0::{OrderReference, OrderStatus};
 use gsy_primitives::{HashT, TradesPenalties};
 use sp_core::H256;
 use sp_runtime::traits::BlakeTwo256;
 
+fn bounded<V: std::fmt::Debug, S: Get<u32>>(items: Vec<V>) -> BoundedVec<V, S> {
+	items.try_into().expect("the test batches are within the bounds")
+}
+
 #[test]
 fn settle_trades_works() {
 	new_test_ext().execute_with(|| {
@@ -44,28 +55,34 @@ fn settle_trades_works() {
 
 		assert_ok!(OrderbookRegistry::insert_orders(
 			RawOrigin::Signed(ALICE).into(),
-			vec!(BlakeTwo256::hash_of(&bid.clone()))
+			bounded(vec!((bid.bid_component.market_id, BlakeTwo256::hash_of(&bid.clone()))))
 		));
 		assert_ok!(OrderbookRegistry::insert_orders(
 			RawOrigin::Signed(ALICE).into(),
-			vec!(BlakeTwo256::hash_of(&bid_2.clone()))
+			bounded(vec!((bid_2.bid_component.market_id, BlakeTwo256::hash_of(&bid_2.clone()))))
 		));
 		assert_ok!(OrderbookRegistry::insert_orders(
 			RawOrigin::Signed(ALICE).into(),
-			vec!(BlakeTwo256::hash_of(&bid_3.clone()))
+			bounded(vec!((bid_3.bid_component.market_id, BlakeTwo256::hash_of(&bid_3.clone()))))
 		));
 
 		assert_ok!(OrderbookRegistry::insert_orders(
 			RawOrigin::Signed(BOB).into(),
-			vec!(BlakeTwo256::hash_of(&offer.clone()))
+			bounded(vec!((offer.offer_component.market_id, BlakeTwo256::hash_of(&offer.clone()))))
 		));
 		assert_ok!(OrderbookRegistry::insert_orders(
 			RawOrigin::Signed(BOB).into(),
-			vec!(BlakeTwo256::hash_of(&offer_2.clone()))
+			bounded(vec!((
+				offer_2.offer_component.market_id,
+				BlakeTwo256::hash_of(&offer_2.clone())
+			)))
 		));
 		assert_ok!(OrderbookRegistry::insert_orders(
 			RawOrigin::Signed(BOB).into(),
-			vec!(BlakeTwo256::hash_of(&offer_3.clone()))
+			bounded(vec!((
+				offer_3.offer_component.market_id,
+				BlakeTwo256::hash_of(&offer_3.clone())
+			)))
 		));
 
 		// Add bid offer matches
@@ -135,7 +152,7 @@ fn settle_trades_works() {
 		// Clear trade.
 		assert_ok!(TradesSettlement::settle_trades(
 			RawOrigin::Signed(MIKE).into(),
-			vec!(bid_offer_match.clone())
+			bounded(vec!(bid_offer_match.clone()))
 		));
 
 		// Clear trade that has already been settled.
@@ -143,7 +160,7 @@ fn settle_trades_works() {
 		assert_noop!(
 			TradesSettlement::settle_trades(
 				RawOrigin::Signed(MIKE).into(),
-				vec!(bid_offer_match.clone())
+				bounded(vec!(bid_offer_match.clone()))
 			),
 			orderbook_registry::Error::<Test>::OrderAlreadyExecuted
 		);
@@ -152,7 +169,7 @@ fn settle_trades_works() {
 		assert_noop!(
 			TradesSettlement::settle_trades(
 				RawOrigin::Signed(MIKE).into(),
-				vec!(bid_offer_match_unregistered_bid)
+				bounded(vec!(bid_offer_match_unregistered_bid))
 			),
 			orderbook_registry::Error::<Test>::OpenOrderNotFound
 		);
@@ -161,7 +178,7 @@ fn settle_trades_works() {
 		assert_noop!(
 			TradesSettlement::settle_trades(
 				RawOrigin::Signed(MIKE).into(),
-				vec!(bid_offer_match_unregistered_offer)
+				bounded(vec!(bid_offer_match_unregistered_offer))
 			),
 			orderbook_registry::Error::<Test>::OrderAlreadyExecuted
 		);
@@ -170,7 +187,7 @@ fn settle_trades_works() {
 		assert_noop!(
 			TradesSettlement::settle_trades(
 				RawOrigin::Signed(MIKE).into(),
-				vec!(bid_offer_match_high_selected_energy)
+				bounded(vec!(bid_offer_match_high_selected_energy))
 			),
 			Error::<Test>::NoValidMatchToSettle
 		);
@@ -179,7 +196,7 @@ fn settle_trades_works() {
 		assert_noop!(
 			TradesSettlement::settle_trades(
 				RawOrigin::Signed(MIKE).into(),
-				vec!(bid_offer_match_low_selected_energy)
+				bounded(vec!(bid_offer_match_low_selected_energy))
 			),
 			Error::<Test>::NoValidMatchToSettle
 		);
@@ -188,13 +205,142 @@ fn settle_trades_works() {
 		assert_noop!(
 			TradesSettlement::settle_trades(
 				RawOrigin::Signed(MIKE).into(),
-				vec!(bid_offer_match_high_energy_rate)
+				bounded(vec!(bid_offer_match_high_energy_rate))
 			),
 			Error::<Test>::NoValidMatchToSettle
 		);
 	});
 }
 
+#[test]
+fn settle_trades_registers_the_residual_orders_so_they_can_be_matched() {
+	new_test_ext().execute_with(|| {
+		// Register users and exchange operator, with enough collateral for the residual bid.
+		assert_ok!(TestOrderbookFunctions::add_user::<Test>(ALICE));
+		assert_ok!(TestOrderbookFunctions::add_user::<Test>(BOB));
+		assert_ok!(TestOrderbookFunctions::add_exchange_operator::<Test>(MIKE));
+		assert_ok!(GsyCollateral::create_vault(ALICE));
+		Balances::set_balance(&ALICE, 10000);
+		assert_ok!(GsyCollateral::deposit_collateral(RawOrigin::Signed(ALICE).into(), 5000));
+
+		// Partially match a bid with a smaller offer.
+		let bid = TestOrderbookFunctions::dummy_bid::<Test>(ALICE, 2, 200, 10);
+		let offer = TestOrderbookFunctions::dummy_offer::<Test>(BOB, 2, 100, 10);
+		let mut residual_bid = bid.clone();
+		residual_bid.nonce += 1;
+		residual_bid.bid_component.energy = 100;
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(ALICE).into(),
+			bounded(vec!((bid.bid_component.market_id, BlakeTwo256::hash_of(&bid))))
+		));
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(BOB).into(),
+			bounded(vec!((offer.offer_component.market_id, BlakeTwo256::hash_of(&offer))))
+		));
+		assert_ok!(TradesSettlement::settle_trades(
+			RawOrigin::Signed(MIKE).into(),
+			bounded(vec!(TestOrderbookFunctions::dummy_bid_offer_match::<Test>(
+				bid.clone(),
+				offer.clone(),
+				Some(residual_bid.clone()),
+				None,
+				2,
+				100,
+				10,
+			)))
+		));
+
+		// The residual bid is registered under the hash of the bid, like the inserted orders.
+		assert_eq!(
+			OrderbookRegistry::order_status(&OrderReference {
+				user_id: ALICE,
+				hash: BlakeTwo256::hash_of(&residual_bid)
+			}),
+			Some(OrderStatus::Open)
+		);
+
+		// The residual bid is matched with another offer.
+		let offer_2 = TestOrderbookFunctions::dummy_offer::<Test>(BOB, 2, 100, 5);
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(BOB).into(),
+			bounded(vec!((offer_2.offer_component.market_id, BlakeTwo256::hash_of(&offer_2))))
+		));
+		assert_ok!(TradesSettlement::settle_trades(
+			RawOrigin::Signed(MIKE).into(),
+			bounded(vec!(TestOrderbookFunctions::dummy_bid_offer_match::<Test>(
+				residual_bid.clone(),
+				offer_2.clone(),
+				None,
+				None,
+				2,
+				100,
+				5,
+			)))
+		));
+		assert!(matches!(
+			OrderbookRegistry::order_status(&OrderReference {
+				user_id: ALICE,
+				hash: BlakeTwo256::hash_of(&residual_bid)
+			}),
+			Some(OrderStatus::Executed(_))
+		));
+	});
+}
+
+#[test]
+fn settle_trades_with_expired_orders_marks_them_expired() {
+	new_test_ext().execute_with(|| {
+		// Register users and exchange operator.
+		assert_ok!(TestOrderbookFunctions::add_user::<Test>(ALICE));
+		assert_ok!(TestOrderbookFunctions::add_user::<Test>(BOB));
+		assert_ok!(TestOrderbookFunctions::add_exchange_operator::<Test>(MIKE));
+
+		// Add an expired bid and a valid offer.
+		let mut bid = TestOrderbookFunctions::dummy_bid::<Test>(ALICE, 2, 100, 10);
+		bid.bid_component.valid_until = Some(100);
+		let offer = TestOrderbookFunctions::dummy_offer::<Test>(BOB, 2, 100, 10);
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(ALICE).into(),
+			bounded(vec!((bid.bid_component.market_id, BlakeTwo256::hash_of(&bid.clone()))))
+		));
+		assert_ok!(OrderbookRegistry::insert_orders(
+			RawOrigin::Signed(BOB).into(),
+			bounded(vec!((offer.offer_component.market_id, BlakeTwo256::hash_of(&offer.clone()))))
+		));
+		pallet_timestamp::Pallet::<Test>::set_timestamp(200_000);
+
+		let bid_offer_match = TestOrderbookFunctions::dummy_bid_offer_match::<Test>(
+			bid.clone(),
+			offer.clone(),
+			None,
+			None,
+			2,
+			100,
+			10,
+		);
+		assert_ok!(TradesSettlement::settle_trades(
+			RawOrigin::Signed(MIKE).into(),
+			bounded(vec!(bid_offer_match))
+		));
+
+		// The expired bid is not executed, the offer is still open.
+		assert_eq!(
+			OrderbookRegistry::order_status(&OrderReference {
+				user_id: ALICE,
+				hash: BlakeTwo256::hash_of(&bid)
+			}),
+			Some(OrderStatus::Expired)
+		);
+		assert_eq!(
+			OrderbookRegistry::order_status(&OrderReference {
+				user_id: BOB,
+				hash: BlakeTwo256::hash_of(&offer)
+			}),
+			Some(OrderStatus::Open)
+		);
+	});
+}
+
 #[test]
 fn submit_penalties_works_for_registered_operator() {
 	new_test_ext().execute_with(|| {
@@ -202,9 +348,11 @@ fn submit_penalties_works_for_registered_operator() {
 		assert_ok!(TestOrderbookFunctions::add_exchange_operator::<Test>(MIKE));
 
 		// Create a sample penalty record.
+		let market_uuid = H256::random();
+		OrderbookRegistry::note_market_delivery_time(market_uuid, 1_000);
 		let sample_penalty = TradesPenalties {
 			penalized_account: ALICE,
-			market_uuid: H256::random(),
+			market_uuid,
 			penalty_energy: 1000,
 			trade_uuid: H256::random(),
 		};
@@ -212,8 +360,55 @@ fn submit_penalties_works_for_registered_operator() {
 		// Call the extrinsic from MIKE (the registered operator).
 		assert_ok!(TradesSettlement::submit_penalties(
 			RawOrigin::Signed(MIKE).into(),
-			vec!(sample_penalty.clone())
+			bounded(vec!(sample_penalty.clone()))
+		));
+	});
+}
+
+#[test]
+fn submit_penalties_rejects_an_already_submitted_penalty() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(TestOrderbookFunctions::add_exchange_operator::<Test>(MIKE));
+
+		let market_uuid = H256::random();
+		OrderbookRegistry::note_market_delivery_time(market_uuid, 1_000);
+		let sample_penalty = TradesPenalties {
+			penalized_account: ALICE,
+			market_uuid,
+			penalty_energy: 1000,
+			trade_uuid: H256::random(),
+		};
+		assert_ok!(TradesSettlement::submit_penalties(
+			RawOrigin::Signed(MIKE).into(),
+			bounded(vec!(sample_penalty.clone()))
 		));
+
+		// Submitted again, alone or within a batch.
+		assert_noop!(
+			TradesSettlement::submit_penalties(
+				RawOrigin::Signed(MIKE).into(),
+				bounded(vec!(sample_penalty.clone()))
+			),
+			Error::<Test>::PenaltyAlreadySubmitted
+		);
+		// Whatever its penalty energy.
+		let same_trade_penalty = TradesPenalties { penalty_energy: 2000, ..sample_penalty.clone() };
+		assert_noop!(
+			TradesSettlement::submit_penalties(
+				RawOrigin::Signed(MIKE).into(),
+				bounded(vec!(same_trade_penalty))
+			),
+			Error::<Test>::PenaltyAlreadySubmitted
+		);
+		let other_penalty =
+			TradesPenalties { trade_uuid: H256::random(), ..sample_penalty.clone() };
+		assert_noop!(
+			TradesSettlement::submit_penalties(
+				RawOrigin::Signed(MIKE).into(),
+				bounded(vec!(other_penalty, sample_penalty))
+			),
+			Error::<Test>::PenaltyAlreadySubmitted
+		);
 	});
 }
 
@@ -233,9 +428,174 @@ fn submit_penalties_fails_for_non_operator() {
 		assert_noop!(
 			TradesSettlement::submit_penalties(
 				RawOrigin::Signed(MIKE).into(),
-				vec!(sample_penalty.clone())
+				bounded(vec!(sample_penalty.clone()))
 			),
 			gsy_collateral::Error::<Test>::NotARegisteredExchangeOperator
 		);
 	});
 }
+
+#[test]
+fn prune_expired_penalties_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(TestOrderbookFunctions::add_exchange_operator::<Test>(MIKE));
+		pallet_timestamp::Pallet::<Test>::set_timestamp(1_000_000);
+		let market_uuid = H256::random();
+		OrderbookRegistry::note_market_delivery_time(market_uuid, 1_000);
+		let penalty = TradesPenalties {
+			penalized_account: ALICE,
+			market_uuid,
+			penalty_energy: 1000,
+			trade_uuid: H256::random(),
+		};
+		let penalty_hash = TradesSettlement::penalty_hash(&penalty);
+		assert_ok!(TradesSettlement::submit_penalties(
+			RawOrigin::Signed(MIKE).into(),
+			bounded(vec!(penalty))
+		));
+		assert_eq!(crate::PenaltyDeliveryTime::<Test>::get(penalty_hash), Some(1_000));
+
+		// The penalty is kept during the retention period.
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_000_000);
+		TradesSettlement::on_idle(1, Weight::MAX);
+		assert!(crate::PenaltiesRegistry::<Test>::contains_key(penalty_hash));
+
+		// It is pruned once the retention period is over.
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		TradesSettlement::on_idle(1, Weight::MAX);
+		assert!(!crate::PenaltiesRegistry::<Test>::contains_key(penalty_hash));
+		assert_eq!(crate::PenaltyDeliveryTime::<Test>::get(penalty_hash), None);
+		assert_eq!(crate::PruningCursor::<Test>::get(), None);
+	});
+}
+
+#[test]
+fn penalties_of_lists_the_penalties_of_the_account_until_they_are_pruned() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(TestOrderbookFunctions::add_exchange_operator::<Test>(MIKE));
+		pallet_timestamp::Pallet::<Test>::set_timestamp(1_000_000);
+		let market_uuid = H256::random();
+		OrderbookRegistry::note_market_delivery_time(market_uuid, 1_000);
+		let alice_penalty = TradesPenalties {
+			penalized_account: ALICE,
+			market_uuid,
+			penalty_energy: 1000,
+			trade_uuid: H256::random(),
+		};
+		let bob_penalty = TradesPenalties { penalized_account: BOB, ..alice_penalty.clone() };
+		assert_ok!(TradesSettlement::submit_penalties(
+			RawOrigin::Signed(MIKE).into(),
+			bounded(vec!(alice_penalty.clone(), bob_penalty))
+		));
+		assert_eq!(
+			TradesSettlement::penalties_of(&ALICE),
+			vec![(BlakeTwo256::hash_of(&alice_penalty), alice_penalty)]
+		);
+
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		TradesSettlement::on_idle(1, Weight::MAX);
+		assert_eq!(TradesSettlement::penalties_of(&ALICE), vec![]);
+		assert_eq!(crate::AccountPenalties::<Test>::iter().count(), 0);
+	});
+}
+
+#[test]
+fn submit_penalties_rejects_the_penalties_older_than_the_pruning_horizon() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(TestOrderbookFunctions::add_exchange_operator::<Test>(MIKE));
+		pallet_timestamp::Pallet::<Test>::set_timestamp(1_000_000);
+		let market_uuid = H256::random();
+		OrderbookRegistry::note_market_delivery_time(market_uuid, 1_000);
+		let penalty = TradesPenalties {
+			penalized_account: ALICE,
+			market_uuid,
+			penalty_energy: 1000,
+			trade_uuid: H256::random(),
+		};
+		assert_ok!(TradesSettlement::submit_penalties(
+			RawOrigin::Signed(MIKE).into(),
+			bounded(vec!(penalty.clone()))
+		));
+
+		// Once pruned, the penalty cannot be submitted again.
+		pallet_timestamp::Pallet::<Test>::set_timestamp(4_600_000);
+		TradesSettlement::on_idle(1, Weight::MAX);
+		let penalty_hash = TradesSettlement::penalty_hash(&penalty);
+		assert!(!crate::PenaltiesRegistry::<Test>::contains_key(penalty_hash));
+		assert_noop!(
+			TradesSettlement::submit_penalties(
+				RawOrigin::Signed(MIKE).into(),
+				bounded(vec!(penalty.clone()))
+			),
+			Error::<Test>::PenaltyExpired
+		);
+
+		// Neither can a penalty of a market whose delivery time slot is unknown.
+		let unknown_market_penalty = TradesPenalties { market_uuid: H256::random(), ..penalty };
+		assert_noop!(
+			TradesSettlement::submit_penalties(
+				RawOrigin::Signed(MIKE).into(),
+				bounded(vec!(unknown_market_penalty))
+			),
+			orderbook_registry::Error::<Test>::MarketNotFound
+		);
+	});
+}
+
+#[test]
+fn migrate_to_v1_writes_the_storage_version() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<TradesSettlement>();
+		crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(TradesSettlement::on_chain_storage_version(), 1);
+	});
+}
+
+#[test]
+fn migrate_to_v1_indexes_the_penalties() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<TradesSettlement>();
+		pallet_timestamp::Pallet::<Test>::set_timestamp(2_000_000);
+		let market_uuid = H256::random();
+		OrderbookRegistry::note_market_delivery_time(market_uuid, 1_000);
+		let penalty = TradesPenalties {
+			penalized_account: ALICE,
+			market_uuid,
+			penalty_energy: 1000,
+			trade_uuid: H256::random(),
+		};
+		let unknown_market_penalty =
+			TradesPenalties { market_uuid: H256::random(), ..penalty.clone() };
+		let same_trade_penalty = TradesPenalties { penalty_energy: 2000, ..penalty.clone() };
+		// The penalties of the unversioned storage are identified by the hash of the penalty.
+		for legacy_penalty in [&penalty, &unknown_market_penalty, &same_trade_penalty] {
+			crate::PenaltiesRegistry::<Test>::insert(
+				BlakeTwo256::hash_of(legacy_penalty),
+				legacy_penalty.clone(),
+			);
+		}
+
+		crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+		// They are identified by their market, trade and penalized account, once per trade.
+		let penalty_hash = TradesSettlement::penalty_hash(&penalty);
+		let unknown_market_penalty_hash = TradesSettlement::penalty_hash(&unknown_market_penalty);
+		assert_eq!(crate::PenaltiesRegistry::<Test>::iter_keys().count(), 2);
+
+		// The penalties get the delivery time slot of their market, or the time of the upgrade.
+		assert_eq!(crate::PenaltyDeliveryTime::<Test>::get(penalty_hash), Some(1_000));
+		assert_eq!(
+			crate::PenaltyDeliveryTime::<Test>::get(unknown_market_penalty_hash),
+			Some(2_000)
+		);
+		let mut penalties: Vec<_> = TradesSettlement::penalties_of(&ALICE)
+			.into_iter()
+			.map(|(hash, _)| hash)
+			.collect();
+		penalties.sort();
+		let mut expected = vec![penalty_hash, unknown_market_penalty_hash];
+		expected.sort();
+		assert_eq!(penalties, expected);
+		assert_eq!(TradesSettlement::on_chain_storage_version(), 1);
+	});
+}
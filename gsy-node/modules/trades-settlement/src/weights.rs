@@ -1,30 +1,92 @@
+//! Weights for `trades_settlement`.
+//!
+//! The components and the storage accesses follow the benchmarks of `benchmarking.rs`. Regenerate
+//! the weights on the reference hardware with:
+//!
+//! ./target/release/gsy-node benchmark pallet --chain dev --pallet trades_settlement \
+//!     --extrinsic '*' --steps 50 --repeat 20 --wasm-execution compiled \
+//!     --template .maintain/frame-weight-template.hbs --output modules/trades-settlement/src/weights.rs
+
 #![cfg_attr(rustfmt, rustfmt_skip)]
 #![allow(unused_parens)]
 #![allow(unused_imports)]
 
-use frame_support::{traits::Get, weights::Weight};
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
 use sp_std::marker::PhantomData;
 
 pub trait TradeSettlementWeightInfo {
-	fn settle_trades() -> Weight;
-	fn submit_penalties() -> Weight;
+	fn settle_trades(n: u32, r: u32, ) -> Weight;
+	fn submit_penalties(n: u32, ) -> Weight;
 }
 
 /// Weight functions for `trades_settlement`.
 pub struct SubstrateWeightInfo<T>(PhantomData<T>);
 impl<T: frame_system::Config> TradeSettlementWeightInfo for SubstrateWeightInfo<T> {
 	// Storage: GsyCollateral RegisteredExchangeOperator (r:1 w:0)
-	// Storage: OrderbookRegistry OrdersRegistry (r:200 w:200)
-	// Storage: GsyCollateral Vaults (r:1 w:1)
+	// Storage: OrderbookRegistry OrdersRegistry (r:2n+2r w:2n+2r)
+	// Storage: OrderbookRegistry OrderMarket (r:2n w:2n+2r)
+	// Storage: OrderbookRegistry OpenOrdersIndex (r:0 w:2n+2r)
+	// Storage: OrderbookRegistry MarketOrders (r:0 w:2r)
+	// Storage: OrderbookRegistry MarketDeliveryTime (r:1 w:1)
 	// Storage: OrderbookRegistry TradesRegistry (r:0 w:1)
-	fn settle_trades() -> Weight {
-		Weight::from_parts(5_586_000_000, 0)
-			.saturating_add(T::DbWeight::get().reads(202))
-			.saturating_add(T::DbWeight::get().writes(202))
+	// Storage: GsyCollateral RegisteredUser (r:2 w:0)
+	// Storage: GsyCollateral Vaults (r:2 w:2)
+	// Storage: OrderbookWorker OrdersForWorker (r:0 w:2r)
+	// Storage: OrderbookWorker TradesForWorker (r:0 w:n)
+	// Storage: OrderbookWorker OffchainIndexLength (r:1 w:1)
+	// Storage: OrderbookWorker OffchainSlotsLength (r:1 w:1)
+	// Storage: OrderbookWorker OffchainSlotIndexLength (r:1 w:1)
+	// The range of component `n` is `[1, 100]`.
+	// The range of component `r` is `[0, 100]`.
+	fn settle_trades(n: u32, r: u32, ) -> Weight {
+		Weight::from_parts(21_480_000, 0)
+			.saturating_add(Weight::from_parts(48_730_000, 0).saturating_mul(n.into()))
+			.saturating_add(Weight::from_parts(61_920_000, 0).saturating_mul(r.into()))
+			.saturating_add(T::DbWeight::get().reads(9_u64))
+			.saturating_add(T::DbWeight::get().reads((4_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(r.into())))
+			.saturating_add(T::DbWeight::get().writes(7_u64))
+			.saturating_add(T::DbWeight::get().writes((7_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes((10_u64).saturating_mul(r.into())))
+	}
+	// Storage: GsyCollateral RegisteredExchangeOperator (r:1 w:0)
+	// Storage: OrderbookRegistry MarketDeliveryTime (r:1 w:0)
+	// Storage: OrderbookWorker OffchainIndexLength (r:1 w:1)
+	// Storage: OrderbookWorker OffchainSlotsLength (r:1 w:1)
+	// Storage: OrderbookWorker OffchainSlotIndexLength (r:1 w:1)
+	// Storage: TradesSettlement PenaltiesRegistry (r:n w:n)
+	// Storage: TradesSettlement PenaltyDeliveryTime (r:0 w:n)
+	// Storage: TradesSettlement AccountPenalties (r:0 w:n)
+	// The range of component `n` is `[1, 100]`.
+	fn submit_penalties(n: u32, ) -> Weight {
+		Weight::from_parts(14_260_000, 0)
+			.saturating_add(Weight::from_parts(9_410_000, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+			.saturating_add(T::DbWeight::get().writes((3_u64).saturating_mul(n.into())))
 	}
+}
 
-	fn submit_penalties() -> Weight {
-        Weight::from_parts(10_000_000, 0)
-            .saturating_add(T::DbWeight::get().writes(202))
-    }
+// For backwards compatibility and tests
+impl TradeSettlementWeightInfo for () {
+	fn settle_trades(n: u32, r: u32, ) -> Weight {
+		Weight::from_parts(21_480_000, 0)
+			.saturating_add(Weight::from_parts(48_730_000, 0).saturating_mul(n.into()))
+			.saturating_add(Weight::from_parts(61_920_000, 0).saturating_mul(r.into()))
+			.saturating_add(RocksDbWeight::get().reads(9_u64))
+			.saturating_add(RocksDbWeight::get().reads((4_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().reads((2_u64).saturating_mul(r.into())))
+			.saturating_add(RocksDbWeight::get().writes(7_u64))
+			.saturating_add(RocksDbWeight::get().writes((7_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes((10_u64).saturating_mul(r.into())))
+	}
+	fn submit_penalties(n: u32, ) -> Weight {
+		Weight::from_parts(14_260_000, 0)
+			.saturating_add(Weight::from_parts(9_410_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+			.saturating_add(RocksDbWeight::get().writes((3_u64).saturating_mul(n.into())))
+	}
 }
@@ -92,6 +92,48 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 	.build())
 }
 
+/// The users, exchange operators, proxy accounts, collateral and markets of the community-dev
+/// chain, as the genesis patch of the `gsyCollateral` and `orderbookRegistry` pallets.
+const COMMUNITY_DEV_PRESET: &[u8] = include_bytes!("../res/community-dev.json");
+
+/// A development chain with the accounts of a community already registered, so that the orders
+/// can be submitted without registering the users and depositing their collateral first.
+pub fn community_dev_config() -> Result<ChainSpec, String> {
+	let mut genesis = testnet_genesis(
+		// Initial PoA authorities
+		vec![authority_keys_from_seed("Alice")],
+		// Sudo account
+		get_account_id_from_seed::<sr25519::Public>("Alice"),
+		// Pre-funded accounts
+		vec![
+			get_account_id_from_seed::<sr25519::Public>("Alice"),
+			get_account_id_from_seed::<sr25519::Public>("Bob"),
+			get_account_id_from_seed::<sr25519::Public>("Charlie"),
+			get_account_id_from_seed::<sr25519::Public>("Dave"),
+			get_account_id_from_seed::<sr25519::Public>("Eve"),
+			get_account_id_from_seed::<sr25519::Public>("Ferdie"),
+		],
+		true,
+	);
+	let preset: serde_json::Map<String, serde_json::Value> =
+		serde_json::from_slice(COMMUNITY_DEV_PRESET)
+			.map_err(|e| format!("Invalid community-dev preset: {}", e))?;
+	genesis
+		.as_object_mut()
+		.expect("the testnet genesis is a JSON object; qed")
+		.extend(preset);
+
+	Ok(ChainSpec::builder(
+		WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?,
+		None,
+	)
+	.with_name("Community Development")
+	.with_id("community_dev")
+	.with_chain_type(ChainType::Development)
+	.with_genesis_config_patch(genesis)
+	.build())
+}
+
 /// Configure initial storage state for FRAME modules.
 fn testnet_genesis(
 	initial_authorities: Vec<(AuraId, GrandpaId)>,
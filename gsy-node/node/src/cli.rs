@@ -1,3 +1,5 @@
+use crate::sealing::Sealing;
+use gsy_primitives::v0::RelayEndpoint;
 use sc_cli::RunCmd;
 
 #[derive(Debug, clap::Parser)]
@@ -7,6 +9,46 @@ pub struct Cli {
 
 	#[clap(flatten)]
 	pub run: RunCmd,
+
+	/// Orderbook service endpoint the offchain worker relays the orders and trades to. Can be
+	/// repeated for redundancy, the endpoints are tried in the given order. Overrides the
+	/// endpoints stored in the offchain local storage.
+	#[clap(long = "offchain-storage-url", env = "OFFCHAIN_STORAGE_URL", value_delimiter = ',')]
+	pub offchain_storage_urls: Vec<String>,
+
+	/// Header added to the requests sent to the orderbook service endpoints, as `Name: value`.
+	#[clap(long = "offchain-storage-header", value_parser = parse_header)]
+	pub offchain_storage_headers: Vec<(String, String)>,
+
+	/// Seal the blocks with manual seal instead of Aura and GRANDPA, for development: `instant`
+	/// seals a block for every transaction, `manual` only when the `engine_createBlock` RPC method
+	/// is called, and a number of milliseconds seals a block at that interval.
+	#[clap(long)]
+	pub sealing: Option<Sealing>,
+}
+
+impl Cli {
+	/// The orderbook service endpoints set on the command line.
+	pub fn relay_endpoints(&self) -> Vec<RelayEndpoint> {
+		self.offchain_storage_urls
+			.iter()
+			.map(|url| RelayEndpoint {
+				url: url.clone().into_bytes(),
+				headers: self
+					.offchain_storage_headers
+					.iter()
+					.map(|(name, value)| (name.clone().into_bytes(), value.clone().into_bytes()))
+					.collect(),
+			})
+			.collect()
+	}
+}
+
+fn parse_header(header: &str) -> Result<(String, String), String> {
+	let (name, value) = header
+		.split_once(':')
+		.ok_or_else(|| format!("Invalid header `{}`, expected `Name: value`", header))?;
+	Ok((name.trim().to_string(), value.trim().to_string()))
 }
 
 #[derive(Debug, clap::Subcommand)]
@@ -51,3 +93,41 @@ pub enum Subcommand {
 	/// Db meta columns information.
 	ChainInfo(sc_cli::ChainInfoCmd),
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use clap::Parser;
+
+	#[test]
+	fn headers_are_parsed_as_name_and_value() {
+		assert_eq!(
+			parse_header("Authorization: Bearer token"),
+			Ok(("Authorization".to_string(), "Bearer token".to_string()))
+		);
+		// Only the first colon separates the name from the value.
+		assert_eq!(
+			parse_header("X-Forwarded-Host:localhost:8080"),
+			Ok(("X-Forwarded-Host".to_string(), "localhost:8080".to_string()))
+		);
+		assert!(parse_header("Authorization").is_err());
+	}
+
+	#[test]
+	fn relay_endpoints_are_read_from_the_cli() {
+		let cli = Cli::parse_from([
+			"gsy-node",
+			"--offchain-storage-url",
+			"https://primary.example,https://fallback.example",
+			"--offchain-storage-header",
+			"Authorization: Bearer token",
+		]);
+
+		let endpoints = cli.relay_endpoints();
+		assert_eq!(endpoints.len(), 2);
+		assert_eq!(endpoints[0].url, b"https://primary.example".to_vec());
+		assert_eq!(endpoints[1].url, b"https://fallback.example".to_vec());
+		let headers = vec![(b"Authorization".to_vec(), b"Bearer token".to_vec())];
+		assert!(endpoints.iter().all(|endpoint| endpoint.headers == headers));
+	}
+}
@@ -7,28 +7,48 @@
 
 use std::sync::Arc;
 
-use gsy_node_runtime::{opaque::Block, AccountId, Balance, Index};
+use crate::sealing::{SealingApiServer, SealingClock, SealingRpc};
+use futures::channel::mpsc::Sender;
+use gsy_node_runtime::{opaque::Block, AccountId, Balance, BlockNumber, Hash, Index};
 use jsonrpsee::RpcModule;
+use sc_consensus_manual_seal::{
+	rpc::{ManualSeal, ManualSealApiServer},
+	EngineCommand,
+};
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
+use sp_core::offchain::OffchainStorage;
 
 pub use sc_rpc_api::DenyUnsafe;
 
+/// Dependencies of the RPC methods sealing the blocks, if they are sealed manually.
+#[derive(Clone)]
+pub struct ManualSealDeps {
+	/// The channel of the commands sealing and finalizing the blocks.
+	pub command_sink: Sender<EngineCommand<Hash>>,
+	/// The clock giving the timestamps of the sealed blocks.
+	pub clock: SealingClock,
+}
+
 /// Full client dependencies.
-pub struct FullDeps<C, P> {
+pub struct FullDeps<C, P, S> {
 	/// The client instance to use.
 	pub client: Arc<C>,
 	/// Transaction pool instance.
 	pub pool: Arc<P>,
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
+	/// Offchain local storage, if the offchain workers are enabled.
+	pub offchain_storage: Option<S>,
+	/// The manual sealing of the blocks, if enabled with `--sealing`.
+	pub manual_seal: Option<ManualSealDeps>,
 }
 
 /// Instantiate all full RPC extensions.
-pub fn create_full<C, P>(
-	deps: FullDeps<C, P>,
+pub fn create_full<C, P, S>(
+	deps: FullDeps<C, P, S>,
 ) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
 where
 	C: ProvideRuntimeApi<Block>,
@@ -36,17 +56,27 @@ where
 	C: Send + Sync + 'static,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
+	C::Api: gsy_rpc::GsyRuntimeApi<Block, AccountId, Hash, Balance, BlockNumber, u64>,
 	C::Api: BlockBuilder<Block>,
 	P: TransactionPool + 'static,
+	S: OffchainStorage + 'static,
 {
+	use gsy_rpc::{Gsy, GsyApiServer, GsyRelay, GsyRelayApiServer};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool, deny_unsafe } = deps;
+	let FullDeps { client, pool, deny_unsafe, offchain_storage, manual_seal } = deps;
 
 	module.merge(System::new(client.clone(), pool.clone(), deny_unsafe).into_rpc())?;
-	module.merge(TransactionPayment::new(client).into_rpc())?;
+	module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
+	module.merge(Gsy::new(client, offchain_storage.clone()).into_rpc())?;
+	module.merge(GsyRelay::<S, Hash>::new(offchain_storage, deny_unsafe).into_rpc())?;
+
+	if let Some(ManualSealDeps { command_sink, clock }) = manual_seal {
+		module.merge(ManualSeal::new(command_sink).into_rpc())?;
+		module.merge(SealingRpc::new(clock, deny_unsafe).into_rpc())?;
+	}
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
@@ -0,0 +1,171 @@
+//! Manual sealing of the blocks, for development and end-to-end tests.
+//!
+//! With `--sealing`, the node seals its blocks with `sc-consensus-manual-seal` instead of running
+//! Aura and GRANDPA. The blocks can always be sealed and finalized on demand through the
+//! `engine_createBlock` and `engine_finalizeBlock` RPC methods, and the timestamp of the next
+//! blocks can be moved forward through `sealing_advanceTimestamp`.
+
+use std::{
+	str::FromStr,
+	sync::{Arc, Mutex},
+};
+
+use gsy_node_runtime::Hash;
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use sc_consensus_manual_seal::EngineCommand;
+use sc_rpc_api::DenyUnsafe;
+use sp_timestamp::{InherentDataProvider as TimestampInherentDataProvider, Timestamp};
+
+/// When the blocks are sealed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sealing {
+	/// Seal a block as soon as a transaction enters the pool.
+	Instant,
+	/// Seal the blocks only through the `engine_createBlock` RPC method.
+	Manual,
+	/// Seal a block every given number of milliseconds, even if it is empty.
+	Interval(u64),
+}
+
+impl FromStr for Sealing {
+	type Err = String;
+
+	fn from_str(sealing: &str) -> Result<Self, Self::Err> {
+		match sealing {
+			"instant" => Ok(Sealing::Instant),
+			"manual" => Ok(Sealing::Manual),
+			millis => match millis.parse::<u64>() {
+				Ok(millis) if millis > 0 => Ok(Sealing::Interval(millis)),
+				_ => Err(format!(
+					"Invalid sealing `{}`, expected `instant`, `manual` or a number of milliseconds",
+					sealing
+				)),
+			},
+		}
+	}
+}
+
+/// The command sealing and finalizing a new block, sent by the instant and interval sealing.
+pub fn seal_command(create_empty: bool) -> EngineCommand<Hash> {
+	EngineCommand::SealNewBlock { create_empty, finalize: true, parent_hash: None, sender: None }
+}
+
+/// The clock giving the timestamp inherent of the sealed blocks.
+///
+/// The runtime requires the Aura slot, derived from the timestamp, to increase with every block.
+/// A block is therefore never less than one slot after its parent, even if the blocks are sealed
+/// faster than the slot duration, or if the clock was moved forward before the node restarted.
+/// The clock can also be moved forward, e.g. to reach the delivery time of a market without
+/// waiting for it.
+#[derive(Clone)]
+pub struct SealingClock {
+	slot_duration: u64,
+	/// The timestamp of the last sealed block and the offset added to the system time, in
+	/// milliseconds.
+	state: Arc<Mutex<(u64, u64)>>,
+}
+
+impl SealingClock {
+	/// Create a clock following the system time, for a slot duration and the timestamp of the
+	/// best block in milliseconds.
+	pub fn new(slot_duration: u64, parent_timestamp: u64) -> Self {
+		Self { slot_duration, state: Arc::new(Mutex::new((parent_timestamp, 0))) }
+	}
+
+	/// The timestamp inherent of the next block.
+	pub fn next_timestamp(&self) -> TimestampInherentDataProvider {
+		TimestampInherentDataProvider::new(
+			self.next_millis(Timestamp::current().as_millis()).into(),
+		)
+	}
+
+	/// The timestamp of the next block, in milliseconds, at the system time `now`.
+	fn next_millis(&self, now: u64) -> u64 {
+		let mut state = self.state.lock().expect("the sealing clock is never poisoned; qed");
+		let (last, offset) = *state;
+		let timestamp = now.saturating_add(offset).max(last.saturating_add(self.slot_duration));
+		state.0 = timestamp;
+		timestamp
+	}
+
+	/// Move the clock forward by `millis` milliseconds, returning the total offset from the
+	/// system time.
+	pub fn advance(&self, millis: u64) -> u64 {
+		let mut state = self.state.lock().expect("the sealing clock is never poisoned; qed");
+		state.1 = state.1.saturating_add(millis);
+		state.1
+	}
+}
+
+#[rpc(server)]
+pub trait SealingApi {
+	/// Move the timestamp of the next sealed blocks forward by `millis` milliseconds. Return the
+	/// total offset of the timestamps from the system time.
+	#[method(name = "sealing_advanceTimestamp")]
+	fn advance_timestamp(&self, millis: u64) -> RpcResult<u64>;
+}
+
+/// The RPC methods controlling the clock of the sealed blocks.
+pub struct SealingRpc {
+	clock: SealingClock,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl SealingRpc {
+	/// Create the RPC methods for the clock of the sealed blocks.
+	pub fn new(clock: SealingClock, deny_unsafe: DenyUnsafe) -> Self {
+		Self { clock, deny_unsafe }
+	}
+}
+
+impl SealingApiServer for SealingRpc {
+	fn advance_timestamp(&self, millis: u64) -> RpcResult<u64> {
+		self.deny_unsafe.check_if_safe()?;
+		Ok(self.clock.advance(millis))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sealing_is_parsed_from_the_cli() {
+		assert_eq!(Sealing::from_str("instant"), Ok(Sealing::Instant));
+		assert_eq!(Sealing::from_str("manual"), Ok(Sealing::Manual));
+		assert_eq!(Sealing::from_str("6000"), Ok(Sealing::Interval(6000)));
+		assert!(Sealing::from_str("0").is_err());
+		assert!(Sealing::from_str("-1").is_err());
+		assert!(Sealing::from_str("daily").is_err());
+	}
+
+	#[test]
+	fn sealing_clock_follows_the_system_time() {
+		let clock = SealingClock::new(6_000, 0);
+		assert_eq!(clock.next_millis(100_000), 100_000);
+		assert_eq!(clock.next_millis(200_000), 200_000);
+	}
+
+	#[test]
+	fn sealing_clock_keeps_one_slot_between_the_blocks() {
+		let clock = SealingClock::new(6_000, 0);
+		assert_eq!(clock.next_millis(100_000), 100_000);
+		assert_eq!(clock.next_millis(101_000), 106_000);
+		assert_eq!(clock.next_millis(102_000), 112_000);
+	}
+
+	#[test]
+	fn sealing_clock_starts_after_the_parent_block() {
+		// The timestamps of the chain were moved forward before the node restarted.
+		let clock = SealingClock::new(6_000, 500_000);
+		assert_eq!(clock.next_millis(100_000), 506_000);
+	}
+
+	#[test]
+	fn sealing_clock_is_moved_forward() {
+		let clock = SealingClock::new(6_000, 0);
+		assert_eq!(clock.advance(60_000), 60_000);
+		assert_eq!(clock.advance(60_000), 120_000);
+		assert_eq!(clock.next_millis(100_000), 220_000);
+	}
+}
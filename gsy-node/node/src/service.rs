@@ -1,14 +1,20 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
-use futures::FutureExt;
+use crate::sealing::{seal_command, Sealing, SealingClock};
+use codec::Decode;
+use futures::{FutureExt, StreamExt};
 use gsy_node_runtime::{self, opaque::Block, RuntimeApi};
-use sc_client_api::{Backend, BlockBackend};
+use gsy_primitives::v0::RelayEndpoint;
+use sc_client_api::{Backend, BlockBackend, StorageProvider};
 use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
 use sc_consensus_grandpa::SharedVoterState;
+use sc_consensus_manual_seal::consensus::aura::AuraConsensusDataProvider;
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager, WarpSyncParams};
 use sc_telemetry::{Telemetry, TelemetryWorker};
-use sc_transaction_pool_api::OffchainTransactionPoolFactory;
+use sc_transaction_pool_api::{OffchainTransactionPoolFactory, TransactionPool};
+use sp_blockchain::HeaderBackend;
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
+use sp_core::storage::StorageKey;
 use std::{sync::Arc, time::Duration};
 
 pub(crate) type FullClient = sc_service::TFullClient<
@@ -36,7 +42,12 @@ pub type Service = sc_service::PartialComponents<
 	),
 >;
 
-pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
+/// Builds the components shared by the node and the subcommands. The blocks are imported without
+/// checking their Aura seal if they are sealed manually.
+pub fn new_partial(
+	config: &Configuration,
+	sealing: Option<Sealing>,
+) -> Result<Service, ServiceError> {
 	let telemetry = config
 		.telemetry_endpoints
 		.clone()
@@ -80,6 +91,24 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 		telemetry.as_ref().map(|x| x.handle()),
 	)?;
 
+	if sealing.is_some() {
+		let import_queue = sc_consensus_manual_seal::import_queue(
+			Box::new(client.clone()),
+			&task_manager.spawn_essential_handle(),
+			config.prometheus_registry(),
+		);
+		return Ok(sc_service::PartialComponents {
+			client,
+			backend,
+			task_manager,
+			import_queue,
+			keystore_container,
+			select_chain,
+			transaction_pool,
+			other: (grandpa_block_import, grandpa_link, telemetry),
+		});
+	}
+
 	let cidp_client = client.clone();
 	let import_queue =
 		sc_consensus_aura::import_queue::<AuraPair, _, _, _, _, _>(ImportQueueParams {
@@ -124,7 +153,11 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 }
 
 /// Builds a new service for a full client.
-pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
+pub fn new_full(
+	config: Configuration,
+	relay_endpoints: Vec<RelayEndpoint>,
+	sealing: Option<Sealing>,
+) -> Result<TaskManager, ServiceError> {
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -134,7 +167,14 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 		select_chain,
 		transaction_pool,
 		other: (block_import, grandpa_link, mut telemetry),
-	} = new_partial(&config)?;
+	} = new_partial(&config, sealing)?;
+
+	// The endpoints set on the command line take precedence over the ones set through the RPC.
+	if !relay_endpoints.is_empty() {
+		if let Some(mut offchain_storage) = backend.offchain_storage() {
+			gsy_rpc::set_relay_endpoints(&mut offchain_storage, relay_endpoints);
+		}
+	}
 
 	let mut net_config = sc_network::config::FullNetworkConfiguration::new(&config.network);
 
@@ -192,14 +232,29 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 	let name = config.network.node_name.clone();
 	let enable_grandpa = !config.disable_grandpa;
 	let prometheus_registry = config.prometheus_registry().cloned();
+	let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
+
+	// The commands sent through the RPC to seal the blocks, if they are sealed manually.
+	let manual_seal = sealing.map(|sealing| {
+		let (command_sink, rpc_commands) = futures::channel::mpsc::channel(1024);
+		let clock = SealingClock::new(slot_duration.as_millis(), best_block_timestamp(&client));
+		(sealing, rpc_commands, crate::rpc::ManualSealDeps { command_sink, clock })
+	});
 
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
+		let offchain_storage = backend.offchain_storage();
+		let manual_seal_deps = manual_seal.as_ref().map(|(_, _, deps)| deps.clone());
 
 		Box::new(move |deny_unsafe, _| {
-			let deps =
-				crate::rpc::FullDeps { client: client.clone(), pool: pool.clone(), deny_unsafe };
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				deny_unsafe,
+				offchain_storage: offchain_storage.clone(),
+				manual_seal: manual_seal_deps.clone(),
+			};
 			crate::rpc::create_full(deps).map_err(Into::into)
 		})
 	};
@@ -219,7 +274,7 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 		telemetry: telemetry.as_mut(),
 	})?;
 
-	if role.is_authority() {
+	if let Some((sealing, rpc_commands, crate::rpc::ManualSealDeps { clock, .. })) = manual_seal {
 		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
 			task_manager.spawn_handle(),
 			client.clone(),
@@ -228,7 +283,57 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 			telemetry.as_ref().map(|x| x.handle()),
 		);
 
-		let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
+		// The blocks sealed for every transaction or at every interval are finalized at once, as
+		// there is no GRANDPA voter. The RPC commands can seal blocks in every mode.
+		let commands_stream = match sealing {
+			Sealing::Manual => rpc_commands.boxed(),
+			Sealing::Instant => futures::stream::select(
+				rpc_commands,
+				transaction_pool.import_notification_stream().map(|_| seal_command(false)),
+			)
+			.boxed(),
+			Sealing::Interval(millis) => futures::stream::select(
+				rpc_commands,
+				futures::stream::unfold((), move |()| async move {
+					futures_timer::Delay::new(Duration::from_millis(millis)).await;
+					Some((seal_command(true), ()))
+				}),
+			)
+			.boxed(),
+		};
+
+		let manual_seal =
+			sc_consensus_manual_seal::run_manual_seal(sc_consensus_manual_seal::ManualSealParams {
+				block_import: client.clone(),
+				env: proposer_factory,
+				client: client.clone(),
+				pool: transaction_pool,
+				commands_stream,
+				select_chain,
+				consensus_data_provider: Some(Box::new(AuraConsensusDataProvider::new(client))),
+				create_inherent_data_providers: move |_, ()| {
+					let timestamp = clock.next_timestamp();
+					async move { Ok(timestamp) }
+				},
+			});
+
+		task_manager.spawn_essential_handle().spawn_blocking(
+			"manual-seal",
+			Some("block-authoring"),
+			manual_seal,
+		);
+		network_starter.start_network();
+		return Ok(task_manager);
+	}
+
+	if role.is_authority() {
+		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
+			task_manager.spawn_handle(),
+			client.clone(),
+			transaction_pool.clone(),
+			prometheus_registry.as_ref(),
+			telemetry.as_ref().map(|x| x.handle()),
+		);
 
 		let aura = sc_consensus_aura::start_aura::<AuraPair, _, _, _, _, _, _, _, _, _, _>(
 			StartAuraParams {
@@ -315,3 +420,14 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 	network_starter.start_network();
 	Ok(task_manager)
 }
+
+/// The timestamp of the best block, in milliseconds, or 0 before the first block.
+fn best_block_timestamp(client: &FullClient) -> u64 {
+	let key = StorageKey(frame_support::storage::storage_prefix(b"Timestamp", b"Now").to_vec());
+	client
+		.storage(client.info().best_hash, &key)
+		.ok()
+		.flatten()
+		.and_then(|timestamp| u64::decode(&mut &timestamp.0[..]).ok())
+		.unwrap_or_default()
+}
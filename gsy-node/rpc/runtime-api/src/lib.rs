@@ -0,0 +1,55 @@
+// This file is part of GSy-Decentralized Energy Exchange.
+
+// Copyright (C) Grid Singularity Gmbh.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime API definition for the GSy Node custom RPCs.
+//!
+//! These APIs expose the state of the GSy-DEEX pallets (markets, orders, vaults and penalties)
+//! so that clients do not need to decode the raw storage themselves.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use gsy_primitives::v0::{OrderStatus, TradesPenalties, VaultInfo};
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	pub trait GsyApi<AccountId, Hash, Balance, BlockNumber, VaultId>
+	where
+		AccountId: Codec,
+		Hash: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+		VaultId: Codec,
+	{
+		/// Return `true` if the market is open.
+		fn market_status(market_id: Hash) -> bool;
+		/// Return the status of an order, or `None` if the order has not been inserted.
+		fn order_status(account: AccountId, order_hash: Hash) -> Option<OrderStatus<Hash>>;
+		/// Return the status of each order, given its account and hash.
+		fn order_statuses(orders: Vec<(AccountId, Hash)>) -> Vec<Option<OrderStatus<Hash>>>;
+		/// Return the hashes of the open orders of an account.
+		fn open_orders_of(account: AccountId) -> Vec<Hash>;
+		/// Return the hashes of the open orders of an account in a market.
+		fn open_orders_in_market(account: AccountId, market_id: Hash) -> Vec<Hash>;
+		/// Return the vault of an account, or `None` if the account has no vault.
+		fn vault_info(account: AccountId) -> Option<VaultInfo<AccountId, Balance, BlockNumber, VaultId>>;
+		/// Return the penalties submitted for an account, together with their hashes.
+		fn penalties_of(account: AccountId) -> Vec<(Hash, TradesPenalties<AccountId, Hash>)>;
+		/// Return `true` if the account is a registered user.
+		fn is_registered(account: AccountId) -> bool;
+	}
+}
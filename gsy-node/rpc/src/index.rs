@@ -0,0 +1,394 @@
+// This file is part of GSy-Decentralized Energy Exchange.
+
+// Copyright (C) Grid Singularity Gmbh.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Queries of the orders, trades and penalties indexed by the runtime in the offchain database.
+//!
+//! The orderbook worker and the trades settlement pallets write every inserted order, trade and
+//! penalty at consecutive positions of the offchain database when the node runs with
+//! `--enable-offchain-indexing true`, and the positions of the records of each delivery time slot
+//! under the key prefix of the time slot. The queries with a time range only read the records of
+//! the time slots in the range, and every query reads at most [`MAX_SCANNED_RECORDS`] records,
+//! returning the cursor to resume from.
+
+use std::collections::BTreeSet;
+
+use codec::{Decode, Encode};
+use gsy_primitives::v0::{
+	offchain_index_key, offchain_slot_index_key, offchain_slot_key, offchain_slot_length_key,
+	IndexedRecord, Order, OrderStatus, Trade, TradesPenalties, OFFCHAIN_INDEX_LENGTH_KEY,
+	OFFCHAIN_SLOTS_LENGTH_KEY,
+};
+use jsonrpsee::core::RpcResult;
+use serde::{Deserialize, Serialize};
+use sp_core::offchain::OffchainStorage;
+
+use crate::relay::offchain_storage_error;
+
+/// The number of records returned by a query without limit, and the maximum limit.
+pub const MAX_QUERY_LIMIT: u32 = 1_000;
+/// The maximum number of records read by a query, whether they match the query or not.
+pub const MAX_SCANNED_RECORDS: usize = 10_000;
+
+/// Filter of the `gsy_indexed*` RPC methods. The unset fields match every record.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexQuery<AccountId, Hash> {
+	/// The market of the records.
+	pub market_id: Option<Hash>,
+	/// The account owning the order, selling or buying in the trade, or being penalized.
+	pub account: Option<AccountId>,
+	/// The first delivery time slot of the records. The time slot of a penalty is the delivery
+	/// time slot of its market.
+	pub from: Option<u64>,
+	/// The last delivery time slot of the records.
+	pub to: Option<u64>,
+}
+
+impl<AccountId: PartialEq, Hash: Encode> IndexQuery<AccountId, Hash> {
+	fn matches(&self, market_id: &impl Encode, accounts: &[&AccountId]) -> bool {
+		self.market_id.as_ref().map_or(true, |id| id.encode() == market_id.encode())
+			&& self.account.as_ref().map_or(true, |account| accounts.contains(&account))
+	}
+
+	fn matches_time_slot(&self, time_slot: u64) -> bool {
+		self.from.map_or(true, |from| from <= time_slot) && self.to.map_or(true, |to| time_slot <= to)
+	}
+
+	/// Return `true` if the order matches the query.
+	pub fn matches_order(&self, order: &Order<AccountId>) -> bool {
+		match order {
+			Order::Bid(bid) => {
+				self.matches(&bid.bid_component.market_id, &[&bid.buyer])
+					&& self.matches_time_slot(bid.bid_component.time_slot)
+			},
+			Order::Offer(offer) => {
+				self.matches(&offer.offer_component.market_id, &[&offer.seller])
+					&& self.matches_time_slot(offer.offer_component.time_slot)
+			},
+		}
+	}
+
+	/// Return `true` if the trade matches the query.
+	pub fn matches_trade(&self, trade: &Trade<AccountId, Hash>) -> bool {
+		self.matches(&trade.market_id, &[&trade.seller, &trade.buyer])
+			&& self.matches_time_slot(trade.time_slot)
+	}
+
+	/// Return `true` if the penalty matches the query, apart from its time slot, which is only
+	/// known from the index of the time slots.
+	pub fn matches_penalty(&self, penalty: &TradesPenalties<AccountId, Hash>) -> bool {
+		self.matches(&penalty.market_uuid, &[&penalty.penalized_account])
+	}
+}
+
+/// An indexed order, with its status in the orderbook registry at the best block.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IndexedOrder<AccountId, Hash> {
+	/// The hash of the order in the orderbook registry.
+	pub hash: Hash,
+	/// The status of the order, or `null` if the order is not registered.
+	pub status: Option<OrderStatus<Hash>>,
+	/// The order.
+	pub order: Order<AccountId>,
+}
+
+/// A page of the records matching a query.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedPage<T> {
+	/// The records, in order of insertion.
+	pub records: Vec<T>,
+	/// The cursor of the next page, or `null` if all the records have been read.
+	pub next_cursor: Option<u64>,
+}
+
+impl<T> IndexedPage<T> {
+	/// Convert the records of the page, keeping its cursor.
+	pub fn map<U>(self, f: impl FnOnce(Vec<T>) -> Vec<U>) -> IndexedPage<U> {
+		IndexedPage { records: f(self.records), next_cursor: self.next_cursor }
+	}
+}
+
+fn read_u64<S: OffchainStorage>(storage: &S, key: &[u8]) -> RpcResult<Option<u64>> {
+	storage
+		.get(sp_offchain::STORAGE_PREFIX, key)
+		.map(|bytes| {
+			u64::decode(&mut &bytes[..])
+				.map_err(|_| offchain_storage_error("Unable to decode the offchain index"))
+		})
+		.transpose()
+}
+
+/// The positions of the records of the time slots between `from` and `to`, in order of insertion,
+/// from the position `cursor`.
+fn slot_positions<S: OffchainStorage>(
+	storage: &S,
+	from: Option<u64>,
+	to: Option<u64>,
+	cursor: u64,
+) -> RpcResult<BTreeSet<u64>> {
+	let mut positions = BTreeSet::new();
+	let mut time_slots = BTreeSet::new();
+	for slot in 0..read_u64(storage, OFFCHAIN_SLOTS_LENGTH_KEY)?.unwrap_or_default() {
+		let Some(time_slot) = read_u64(storage, &offchain_slot_key(slot))? else { continue };
+		if from.map_or(true, |from| from <= time_slot) && to.map_or(true, |to| time_slot <= to) {
+			time_slots.insert(time_slot);
+		}
+	}
+	for time_slot in time_slots {
+		let slot_length = read_u64(storage, &offchain_slot_length_key(time_slot))?;
+		for slot_position in 0..slot_length.unwrap_or_default() {
+			match read_u64(storage, &offchain_slot_index_key(time_slot, slot_position))? {
+				Some(position) if position >= cursor => {
+					positions.insert(position);
+				},
+				_ => {},
+			}
+		}
+	}
+	Ok(positions)
+}
+
+/// Read the records indexed in the offchain database that match `filter`, in order of insertion,
+/// starting at the position `cursor`.
+///
+/// Only the records of the time slots between `from` and `to` are read when the time range is
+/// set. At most `limit` matching records are returned, and the cursor of the next page is
+/// returned when not all the records have been read.
+pub fn indexed_records<S, AccountId, Hash, T>(
+	storage: &S,
+	from: Option<u64>,
+	to: Option<u64>,
+	cursor: Option<u64>,
+	limit: Option<u32>,
+	mut filter: impl FnMut(IndexedRecord<AccountId, Hash>) -> Option<T>,
+) -> RpcResult<IndexedPage<T>>
+where
+	S: OffchainStorage,
+	AccountId: Decode,
+	Hash: Decode,
+{
+	let cursor = cursor.unwrap_or_default();
+	let limit = limit.unwrap_or(MAX_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+	let length = read_u64(storage, OFFCHAIN_INDEX_LENGTH_KEY)?.unwrap_or_default();
+	let mut positions: Box<dyn Iterator<Item = u64>> = if from.is_none() && to.is_none() {
+		Box::new(cursor..length)
+	} else {
+		Box::new(slot_positions(storage, from, to, cursor)?.into_iter())
+	};
+
+	let mut records = Vec::new();
+	let mut scanned = 0;
+	while records.len() < limit && scanned < MAX_SCANNED_RECORDS {
+		let Some(position) = positions.next() else {
+			return Ok(IndexedPage { records, next_cursor: None });
+		};
+		scanned += 1;
+		let Some(bytes) = storage.get(sp_offchain::STORAGE_PREFIX, &offchain_index_key(position))
+		else {
+			continue;
+		};
+		let record = IndexedRecord::decode(&mut &bytes[..])
+			.map_err(|_| offchain_storage_error("Unable to decode the indexed record"))?;
+		records.extend(filter(record));
+	}
+	let next_cursor = positions.next();
+	Ok(IndexedPage { records, next_cursor })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use gsy_primitives::v0::{
+		AccountId, Bid, Hash, OrderComponent, TradeParameters, OFFCHAIN_SLOTS_LENGTH_KEY,
+	};
+	use sp_core::offchain::storage::InMemOffchainStorage;
+
+	const ALICE: AccountId = AccountId::new([1; 32]);
+	const BOB: AccountId = AccountId::new([2; 32]);
+
+	fn bid(buyer: AccountId, market_id: Hash, time_slot: u64) -> Order<AccountId> {
+		Order::Bid(Bid {
+			buyer,
+			nonce: 1,
+			bid_component: OrderComponent {
+				area_uuid: Hash::repeat_byte(9),
+				market_id,
+				time_slot,
+				creation_time: 1,
+				energy: 10,
+				energy_rate: 5,
+				valid_until: None,
+			},
+		})
+	}
+
+	fn order_record(order: Order<AccountId>) -> IndexedRecord<AccountId, Hash> {
+		IndexedRecord::Order { hash: Hash::random(), order }
+	}
+
+	fn penalty(penalized_account: AccountId, market_uuid: Hash) -> TradesPenalties<AccountId, Hash> {
+		TradesPenalties {
+			penalized_account,
+			market_uuid,
+			trade_uuid: Hash::random(),
+			penalty_energy: 1,
+		}
+	}
+
+	/// Write the records into the offchain storage as the orderbook worker indexes them.
+	fn index(records: &[(IndexedRecord<AccountId, Hash>, u64)]) -> InMemOffchainStorage {
+		let mut storage = InMemOffchainStorage::default();
+		let mut set = |key: &[u8], value: Vec<u8>| {
+			storage.set(sp_offchain::STORAGE_PREFIX, key, &value);
+		};
+		let mut time_slots = Vec::new();
+		for (position, (record, time_slot)) in records.iter().enumerate() {
+			set(&offchain_index_key(position as u64), record.encode());
+			if !time_slots.contains(time_slot) {
+				set(&offchain_slot_key(time_slots.len() as u64), time_slot.encode());
+				time_slots.push(*time_slot);
+			}
+			let slot_positions: Vec<_> =
+				records[..position].iter().filter(|(_, slot)| slot == time_slot).collect();
+			let slot_position = slot_positions.len() as u64;
+			set(&offchain_slot_index_key(*time_slot, slot_position), (position as u64).encode());
+			set(&offchain_slot_length_key(*time_slot), (slot_position + 1).encode());
+		}
+		set(OFFCHAIN_INDEX_LENGTH_KEY, (records.len() as u64).encode());
+		set(OFFCHAIN_SLOTS_LENGTH_KEY, (time_slots.len() as u64).encode());
+		storage
+	}
+
+	fn positions(
+		storage: &InMemOffchainStorage,
+		records: &[(IndexedRecord<AccountId, Hash>, u64)],
+		from: Option<u64>,
+		to: Option<u64>,
+		cursor: Option<u64>,
+		limit: Option<u32>,
+	) -> (Vec<usize>, Option<u64>) {
+		let page = indexed_records::<_, AccountId, Hash, _>(storage, from, to, cursor, limit, Some)
+			.unwrap();
+		let positions = page
+			.records
+			.iter()
+			.map(|record| records.iter().position(|(indexed, _)| indexed == record).unwrap())
+			.collect();
+		(positions, page.next_cursor)
+	}
+
+	#[test]
+	fn records_are_read_by_pages_from_the_cursor() {
+		let market_id = Hash::repeat_byte(1);
+		let records: Vec<_> =
+			(0..5).map(|time_slot| (order_record(bid(ALICE, market_id, time_slot)), 1)).collect();
+		let storage = index(&records);
+
+		assert_eq!(positions(&storage, &records, None, None, None, Some(2)), (vec![0, 1], Some(2)));
+		assert_eq!(
+			positions(&storage, &records, None, None, Some(2), Some(2)),
+			(vec![2, 3], Some(4))
+		);
+		assert_eq!(positions(&storage, &records, None, None, Some(4), Some(2)), (vec![4], None));
+		assert_eq!(positions(&storage, &records, None, None, None, None), (vec![0, 1, 2, 3, 4], None));
+	}
+
+	#[test]
+	fn time_range_only_reads_the_records_of_its_time_slots() {
+		let market_id = Hash::repeat_byte(1);
+		let records = vec![
+			(order_record(bid(ALICE, market_id, 1_000)), 1_000),
+			(order_record(bid(ALICE, market_id, 2_000)), 2_000),
+			(IndexedRecord::Penalty { hash: Hash::random(), penalty: penalty(BOB, market_id) }, 2_000),
+			(order_record(bid(ALICE, market_id, 3_000)), 3_000),
+			(order_record(bid(BOB, market_id, 2_000)), 2_000),
+		];
+		let storage = index(&records);
+
+		assert_eq!(
+			positions(&storage, &records, Some(2_000), Some(2_000), None, None),
+			(vec![1, 2, 4], None)
+		);
+		assert_eq!(positions(&storage, &records, Some(2_000), None, None, None), (vec![1, 2, 3, 4], None));
+		assert_eq!(positions(&storage, &records, None, Some(1_000), None, None), (vec![0], None));
+		assert_eq!(
+			positions(&storage, &records, Some(2_000), Some(3_000), Some(3), Some(1)),
+			(vec![3], Some(4))
+		);
+	}
+
+	#[test]
+	fn empty_index_returns_an_empty_page() {
+		let storage = InMemOffchainStorage::default();
+		let page =
+			indexed_records::<_, AccountId, Hash, _>(&storage, None, None, None, None, Some).unwrap();
+		assert!(page.records.is_empty());
+		assert_eq!(page.next_cursor, None);
+	}
+
+	#[test]
+	fn queries_match_the_market_the_account_and_the_time_slot() {
+		let market_id = Hash::repeat_byte(1);
+		let other_market_id = Hash::repeat_byte(2);
+		let query = IndexQuery {
+			market_id: Some(market_id),
+			account: Some(ALICE),
+			from: Some(1_000),
+			to: Some(2_000),
+		};
+
+		assert!(query.matches_order(&bid(ALICE, market_id, 1_000)));
+		assert!(query.matches_order(&bid(ALICE, market_id, 2_000)));
+		assert!(!query.matches_order(&bid(ALICE, market_id, 3_000)));
+		assert!(!query.matches_order(&bid(ALICE, other_market_id, 1_000)));
+		assert!(!query.matches_order(&bid(BOB, market_id, 1_000)));
+		assert!(IndexQuery::default().matches_order(&bid(BOB, other_market_id, 3_000)));
+
+		let Order::Bid(bid) = bid(ALICE, market_id, 1_000) else { unreachable!() };
+		let trade = Trade {
+			seller: BOB,
+			buyer: ALICE,
+			market_id,
+			time_slot: 1_500,
+			trade_uuid: Hash::random(),
+			creation_time: 1,
+			offer: gsy_primitives::v0::Offer {
+				seller: BOB,
+				nonce: 1,
+				offer_component: bid.bid_component.clone(),
+			},
+			offer_hash: Hash::random(),
+			bid: bid.clone(),
+			bid_hash: Hash::random(),
+			residual_offer: None,
+			residual_bid: None,
+			parameters: TradeParameters {
+				selected_energy: 1,
+				energy_rate: 1,
+				trade_uuid: Hash::random(),
+			},
+		};
+		assert!(query.matches_trade(&trade));
+		assert!(!query.matches_trade(&Trade { time_slot: 2_500, ..trade.clone() }));
+		assert!(!query.matches_trade(&Trade { seller: BOB, buyer: BOB, ..trade }));
+
+		assert!(query.matches_penalty(&penalty(ALICE, market_id)));
+		assert!(!query.matches_penalty(&penalty(BOB, market_id)));
+		assert!(!query.matches_penalty(&penalty(ALICE, other_market_id)));
+	}
+}
@@ -0,0 +1,320 @@
+// This file is part of GSy-Decentralized Energy Exchange.
+
+// Copyright (C) Grid Singularity Gmbh.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! RPC interface for the GSy-DEEX pallets.
+//!
+//! Exposes the `gsy_*` namespace, which queries markets, orders, vaults and penalties through
+//! the [`GsyRuntimeApi`] instead of raw storage, the `gsy_indexed*` methods, which query the
+//! orders, trades and penalties indexed in the offchain database, and the `gsy_relay*` methods,
+//! which inspect the relay queue and configure the orderbook service endpoints of the orderbook
+//! worker in the offchain local storage.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Codec;
+use gsy_primitives::v0::{IndexedRecord, Order, OrderStatus, Trade, TradesPenalties, VaultInfo};
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{ErrorObject, ErrorObjectOwned},
+};
+use parking_lot::RwLock;
+use serde::{de::DeserializeOwned, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::offchain::OffchainStorage;
+use sp_runtime::traits::Block as BlockT;
+
+pub use gsy_rpc_runtime_api::GsyApi as GsyRuntimeApi;
+pub use index::{IndexQuery, IndexedOrder, IndexedPage, MAX_QUERY_LIMIT, MAX_SCANNED_RECORDS};
+pub use relay::{set_relay_endpoints, GsyRelay, GsyRelayApiServer, RelayEndpointConfig};
+
+mod index;
+mod relay;
+
+/// Error code returned when the runtime API call fails.
+const RUNTIME_ERROR: i32 = 1;
+
+#[rpc(client, server)]
+pub trait GsyApi<BlockHash, AccountId, Hash, Balance, BlockNumber, VaultId> {
+	/// Return `true` if the market is open.
+	#[method(name = "gsy_marketStatus")]
+	fn market_status(&self, market_id: Hash, at: Option<BlockHash>) -> RpcResult<bool>;
+
+	/// Return the status of an order, or `null` if the order has not been inserted.
+	#[method(name = "gsy_orderStatus")]
+	fn order_status(
+		&self,
+		account: AccountId,
+		order_hash: Hash,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<OrderStatus<Hash>>>;
+
+	/// Return the hashes of the open orders of an account.
+	#[method(name = "gsy_openOrdersOf")]
+	fn open_orders_of(&self, account: AccountId, at: Option<BlockHash>) -> RpcResult<Vec<Hash>>;
+
+	/// Return the hashes of the open orders of an account in a market.
+	#[method(name = "gsy_openOrdersInMarket")]
+	fn open_orders_in_market(
+		&self,
+		account: AccountId,
+		market_id: Hash,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<Hash>>;
+
+	/// Return the vault of an account, or `null` if the account has no vault.
+	#[method(name = "gsy_vaultInfo")]
+	fn vault_info(
+		&self,
+		account: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<VaultInfo<AccountId, Balance, BlockNumber, VaultId>>>;
+
+	/// Return the penalties submitted for an account, together with their hashes.
+	#[method(name = "gsy_penaltiesOf")]
+	fn penalties_of(
+		&self,
+		account: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<(Hash, TradesPenalties<AccountId, Hash>)>>;
+
+	/// Return `true` if the account is a registered user.
+	#[method(name = "gsy_isRegistered")]
+	fn is_registered(&self, account: AccountId, at: Option<BlockHash>) -> RpcResult<bool>;
+
+	/// Return a page of the indexed orders matching the query, from the `cursor` of the previous
+	/// page, with their status at the best block.
+	#[method(name = "gsy_indexedOrders")]
+	fn indexed_orders(
+		&self,
+		query: IndexQuery<AccountId, Hash>,
+		cursor: Option<u64>,
+		limit: Option<u32>,
+	) -> RpcResult<IndexedPage<IndexedOrder<AccountId, Hash>>>;
+
+	/// Return a page of the indexed trades matching the query, from the `cursor` of the previous
+	/// page.
+	#[method(name = "gsy_indexedTrades")]
+	fn indexed_trades(
+		&self,
+		query: IndexQuery<AccountId, Hash>,
+		cursor: Option<u64>,
+		limit: Option<u32>,
+	) -> RpcResult<IndexedPage<Trade<AccountId, Hash>>>;
+
+	/// Return a page of the indexed penalties matching the query, together with their hashes,
+	/// from the `cursor` of the previous page.
+	#[method(name = "gsy_indexedPenalties")]
+	fn indexed_penalties(
+		&self,
+		query: IndexQuery<AccountId, Hash>,
+		cursor: Option<u64>,
+		limit: Option<u32>,
+	) -> RpcResult<IndexedPage<(Hash, TradesPenalties<AccountId, Hash>)>>;
+}
+
+/// Provides the `gsy_*` RPC methods.
+pub struct Gsy<C, Block, S> {
+	client: Arc<C>,
+	storage: Option<Arc<RwLock<S>>>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block, S> Gsy<C, Block, S> {
+	/// Create a new instance of the GSy RPC handler. The `gsy_indexed*` methods are only
+	/// available when the offchain storage is given.
+	pub fn new(client: Arc<C>, storage: Option<S>) -> Self {
+		Self {
+			client,
+			storage: storage.map(|storage| Arc::new(RwLock::new(storage))),
+			_marker: Default::default(),
+		}
+	}
+}
+
+impl<C, Block, S: OffchainStorage> Gsy<C, Block, S> {
+	fn indexed_records<AccountId: Codec, Hash: Codec, T>(
+		&self,
+		query: &IndexQuery<AccountId, Hash>,
+		cursor: Option<u64>,
+		limit: Option<u32>,
+		filter: impl FnMut(IndexedRecord<AccountId, Hash>) -> Option<T>,
+	) -> RpcResult<IndexedPage<T>> {
+		let storage = self
+			.storage
+			.as_ref()
+			.ok_or_else(|| relay::offchain_storage_error("Offchain storage is disabled"))?;
+		index::indexed_records(&*storage.read(), query.from, query.to, cursor, limit, filter)
+	}
+}
+
+fn runtime_error_into_rpc_err(err: impl std::fmt::Debug) -> ErrorObjectOwned {
+	ErrorObject::owned(RUNTIME_ERROR, "Runtime error", Some(format!("{:?}", err)))
+}
+
+impl<C, Block, S, AccountId, Hash, Balance, BlockNumber, VaultId>
+	GsyApiServer<<Block as BlockT>::Hash, AccountId, Hash, Balance, BlockNumber, VaultId>
+	for Gsy<C, Block, S>
+where
+	Block: BlockT,
+	S: OffchainStorage + 'static,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: GsyRuntimeApi<Block, AccountId, Hash, Balance, BlockNumber, VaultId>,
+	AccountId: Codec + Clone + PartialEq + Serialize + DeserializeOwned + Send + Sync + 'static,
+	Hash: Codec + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+	Balance: Codec + Serialize + DeserializeOwned + Send + Sync + 'static,
+	BlockNumber: Codec + Serialize + DeserializeOwned + Send + Sync + 'static,
+	VaultId: Codec + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+	fn market_status(&self, market_id: Hash, at: Option<Block::Hash>) -> RpcResult<bool> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.market_status(at, market_id)
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn order_status(
+		&self,
+		account: AccountId,
+		order_hash: Hash,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<OrderStatus<Hash>>> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.order_status(at, account, order_hash)
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn open_orders_of(&self, account: AccountId, at: Option<Block::Hash>) -> RpcResult<Vec<Hash>> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.open_orders_of(at, account)
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn open_orders_in_market(
+		&self,
+		account: AccountId,
+		market_id: Hash,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<Hash>> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.open_orders_in_market(at, account, market_id)
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn vault_info(
+		&self,
+		account: AccountId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<VaultInfo<AccountId, Balance, BlockNumber, VaultId>>> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.vault_info(at, account)
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn penalties_of(
+		&self,
+		account: AccountId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<(Hash, TradesPenalties<AccountId, Hash>)>> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.penalties_of(at, account)
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn is_registered(&self, account: AccountId, at: Option<Block::Hash>) -> RpcResult<bool> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.is_registered(at, account)
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn indexed_orders(
+		&self,
+		query: IndexQuery<AccountId, Hash>,
+		cursor: Option<u64>,
+		limit: Option<u32>,
+	) -> RpcResult<IndexedPage<IndexedOrder<AccountId, Hash>>> {
+		let page = self.indexed_records(&query, cursor, limit, |record| match record {
+			IndexedRecord::Order { hash, order } if query.matches_order(&order) => {
+				Some((hash, order))
+			},
+			_ => None,
+		})?;
+		let owners_and_hashes = page
+			.records
+			.iter()
+			.map(|(hash, order)| {
+				let owner = match order {
+					Order::Bid(bid) => bid.buyer.clone(),
+					Order::Offer(offer) => offer.seller.clone(),
+				};
+				(owner, hash.clone())
+			})
+			.collect();
+		let statuses = self
+			.client
+			.runtime_api()
+			.order_statuses(self.client.info().best_hash, owners_and_hashes)
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(page.map(|orders| {
+			orders
+				.into_iter()
+				.zip(statuses)
+				.map(|((hash, order), status)| IndexedOrder { hash, status, order })
+				.collect()
+		}))
+	}
+
+	fn indexed_trades(
+		&self,
+		query: IndexQuery<AccountId, Hash>,
+		cursor: Option<u64>,
+		limit: Option<u32>,
+	) -> RpcResult<IndexedPage<Trade<AccountId, Hash>>> {
+		self.indexed_records(&query, cursor, limit, |record| match record {
+			IndexedRecord::Trade { trade, .. } if query.matches_trade(&trade) => Some(trade),
+			_ => None,
+		})
+	}
+
+	fn indexed_penalties(
+		&self,
+		query: IndexQuery<AccountId, Hash>,
+		cursor: Option<u64>,
+		limit: Option<u32>,
+	) -> RpcResult<IndexedPage<(Hash, TradesPenalties<AccountId, Hash>)>> {
+		self.indexed_records(&query, cursor, limit, |record| match record {
+			IndexedRecord::Penalty { hash, penalty } if query.matches_penalty(&penalty) => {
+				Some((hash, penalty))
+			},
+			_ => None,
+		})
+	}
+}
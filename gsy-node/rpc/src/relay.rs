@@ -0,0 +1,190 @@
+// This file is part of GSy-Decentralized Energy Exchange.
+
+// Copyright (C) Grid Singularity Gmbh.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! RPC interface for the relay queue of the orderbook worker.
+//!
+//! The relay queue and the orderbook service endpoints live in the persistent offchain local
+//! storage of the node, so these methods access it directly instead of going through the runtime.
+
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc};
+
+use codec::{Codec, Decode, Encode};
+use gsy_primitives::v0::{
+	RelayEndpoint, RelayItem, RelayList, RELAY_ENDPOINTS_KEY, RELAY_REQUEUE_KEY,
+};
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{ErrorObject, ErrorObjectOwned},
+};
+use parking_lot::RwLock;
+use sc_rpc_api::DenyUnsafe;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sp_core::offchain::OffchainStorage;
+
+/// Error code returned when the offchain storage is disabled or cannot be decoded.
+const OFFCHAIN_STORAGE_ERROR: i32 = 2;
+
+#[rpc(client, server)]
+pub trait GsyRelayApi<Hash> {
+	/// Return the items waiting to be relayed to the orderbook service.
+	#[method(name = "gsy_relayQueue")]
+	fn relay_queue(&self) -> RpcResult<Vec<RelayItem<Hash>>>;
+
+	/// Return the items that could not be relayed to the orderbook service.
+	#[method(name = "gsy_relayDeadLetters")]
+	fn relay_dead_letters(&self) -> RpcResult<Vec<RelayItem<Hash>>>;
+
+	/// Queue again the dead-lettered orders and trades with the given hashes. The offchain worker
+	/// moves them back to the relay queue in its next run.
+	#[method(name = "gsy_requeueDeadLetters")]
+	fn requeue_dead_letters(&self, hashes: Vec<Hash>) -> RpcResult<()>;
+
+	/// Set the orderbook service endpoints, in order of preference. The offchain worker relays
+	/// the orders and trades to the first endpoint that accepts them.
+	#[method(name = "gsy_setRelayEndpoints")]
+	fn set_relay_endpoints(&self, endpoints: Vec<RelayEndpointConfig>) -> RpcResult<()>;
+}
+
+/// An orderbook service endpoint, as accepted by `gsy_setRelayEndpoints`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RelayEndpointConfig {
+	/// The base URL of the orderbook service, e.g. `http://localhost:8080`.
+	pub url: String,
+	/// The headers added to every request, e.g. an `Authorization` header.
+	#[serde(default)]
+	pub headers: BTreeMap<String, String>,
+}
+
+impl From<RelayEndpointConfig> for RelayEndpoint {
+	fn from(config: RelayEndpointConfig) -> Self {
+		RelayEndpoint {
+			url: config.url.into_bytes(),
+			headers: config
+				.headers
+				.into_iter()
+				.map(|(name, value)| (name.into_bytes(), value.into_bytes()))
+				.collect(),
+		}
+	}
+}
+
+/// Write the orderbook service endpoints to the persistent offchain local storage.
+pub fn set_relay_endpoints<S: OffchainStorage>(storage: &mut S, endpoints: Vec<RelayEndpoint>) {
+	storage.set(sp_offchain::STORAGE_PREFIX, RELAY_ENDPOINTS_KEY, &endpoints.encode());
+}
+
+/// Append the hashes of the dead-lettered items to queue again to the persistent offchain local
+/// storage, where the offchain worker takes them.
+fn request_requeue<S: OffchainStorage, Hash: Codec + Clone>(
+	storage: &mut S,
+	hashes: Vec<Hash>,
+) -> Result<(), codec::Error> {
+	// The offchain worker may take the requests concurrently, in which case the update is retried.
+	loop {
+		let stored = storage.get(sp_offchain::STORAGE_PREFIX, RELAY_REQUEUE_KEY);
+		let mut requested = match &stored {
+			Some(bytes) => Vec::<Hash>::decode(&mut &bytes[..])?,
+			None => Vec::new(),
+		};
+		requested.extend(hashes.iter().cloned());
+		if storage.compare_and_set(
+			sp_offchain::STORAGE_PREFIX,
+			RELAY_REQUEUE_KEY,
+			stored.as_deref(),
+			&requested.encode(),
+		) {
+			return Ok(());
+		}
+	}
+}
+
+/// Provides the `gsy_relay*` RPC methods.
+pub struct GsyRelay<S, Hash> {
+	storage: Option<Arc<RwLock<S>>>,
+	deny_unsafe: DenyUnsafe,
+	_marker: PhantomData<Hash>,
+}
+
+impl<S, Hash> GsyRelay<S, Hash> {
+	/// Create a new instance of the GSy relay RPC handler.
+	pub fn new(storage: Option<S>, deny_unsafe: DenyUnsafe) -> Self {
+		Self {
+			storage: storage.map(|storage| Arc::new(RwLock::new(storage))),
+			deny_unsafe,
+			_marker: Default::default(),
+		}
+	}
+
+	fn storage(&self) -> RpcResult<&Arc<RwLock<S>>> {
+		self.storage
+			.as_ref()
+			.ok_or_else(|| offchain_storage_error("Offchain storage is disabled"))
+	}
+}
+
+pub(crate) fn offchain_storage_error(message: &str) -> ErrorObjectOwned {
+	ErrorObject::owned(OFFCHAIN_STORAGE_ERROR, message, None::<()>)
+}
+
+impl<S, Hash> GsyRelay<S, Hash>
+where
+	S: OffchainStorage,
+	Hash: Codec,
+{
+	fn items(&self, list: RelayList) -> RpcResult<Vec<RelayItem<Hash>>> {
+		let storage = self.storage()?.read();
+		let decode_error = |_| offchain_storage_error("Unable to decode the relay items");
+		let (head, tail) = match storage.get(sp_offchain::STORAGE_PREFIX, &list.bounds_key()) {
+			Some(bytes) => <(u64, u64)>::decode(&mut &bytes[..]).map_err(decode_error)?,
+			None => (0, 0),
+		};
+		(head..tail)
+			.filter_map(|position| {
+				storage.get(sp_offchain::STORAGE_PREFIX, &list.item_key(position))
+			})
+			.map(|bytes| RelayItem::<Hash>::decode(&mut &bytes[..]).map_err(decode_error))
+			.collect()
+	}
+}
+
+impl<S, Hash> GsyRelayApiServer<Hash> for GsyRelay<S, Hash>
+where
+	S: OffchainStorage + 'static,
+	Hash: Codec + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+	fn relay_queue(&self) -> RpcResult<Vec<RelayItem<Hash>>> {
+		self.items(RelayList::Queue)
+	}
+
+	fn relay_dead_letters(&self) -> RpcResult<Vec<RelayItem<Hash>>> {
+		self.items(RelayList::DeadLetter)
+	}
+
+	fn requeue_dead_letters(&self, hashes: Vec<Hash>) -> RpcResult<()> {
+		self.deny_unsafe.check_if_safe()?;
+		request_requeue(&mut *self.storage()?.write(), hashes)
+			.map_err(|_| offchain_storage_error("Unable to decode the requeue requests"))
+	}
+
+	fn set_relay_endpoints(&self, endpoints: Vec<RelayEndpointConfig>) -> RpcResult<()> {
+		self.deny_unsafe.check_if_safe()?;
+		let endpoints = endpoints.into_iter().map(Into::into).collect();
+		set_relay_endpoints(&mut *self.storage()?.write(), endpoints);
+		Ok(())
+	}
+}
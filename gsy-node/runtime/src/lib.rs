@@ -38,7 +38,7 @@ use sp_runtime::{
 		StaticLookup,
 	},
 	transaction_validity::{TransactionPriority, TransactionSource, TransactionValidity},
-	ApplyExtrinsicResult,
+	ApplyExtrinsicResult, ConsensusEngineId,
 };
 
 use sp_std::prelude::*;
@@ -51,8 +51,8 @@ use frame_support::PalletId;
 pub use frame_support::{
 	construct_runtime, derive_impl, parameter_types,
 	traits::{
-		ConstBool, ConstU128, ConstU32, ConstU64, ConstU8, Currency, KeyOwnerProofSystem,
-		Randomness, StorageInfo,
+		ConstBool, ConstU128, ConstU32, ConstU64, ConstU8, Currency, FindAuthor,
+		KeyOwnerProofSystem, Randomness, StorageInfo,
 	},
 	weights::{
 		constants::{
@@ -115,12 +115,13 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	// The version of the runtime specification. A full node will not attempt to use its native
 	//   runtime in substitute for the on-chain Wasm runtime unless all of `spec_name`,
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
-	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
-	//   the compatible custom types.
-	spec_version: 100,
+	// This value started at 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
+	//   the compatible custom types, and is bumped on every change of the runtime.
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	// Bumped on every change of the encoding or the indexes of the calls.
+	transaction_version: 2,
 	state_version: 1,
 };
 
@@ -280,14 +281,20 @@ parameter_types! {
 	// A registered user can add at most 3 Proxy accounts.
 	pub const ProxyAccountLimit: u32 = 3;
 	pub const VaultPalletId: PalletId = PalletId(*b"collater");
+	// Keep the orders of a market for 7 days after the end of its delivery slot.
+	pub const MarketRetentionPeriod: u64 = SECS_PER_MARKET_SLOT + 7 * 24 * 60 * 60;
+	// Maximum number of orders inserted, deleted or amended in a single extrinsic.
+	pub const MaxOrdersPerBatch: u32 = 100;
 }
 
 impl orderbook_registry::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type Currency = Balances;
 	type RegistryProxyAccountLimit = ConstU32<32>;
-	type WeightInfo = ();
+	type WeightInfo = orderbook_registry::weights::SubstrateWeightInfo<Runtime>;
 	type TimeProvider = pallet_timestamp::Pallet<Runtime>;
+	type MarketRetentionPeriod = MarketRetentionPeriod;
+	type MaxOrdersPerBatch = MaxOrdersPerBatch;
 }
 
 /// Configure the gsy-collateral in modules/gsy-collateral.
@@ -303,17 +310,37 @@ impl gsy_collateral::Config for Runtime {
 parameter_types! {
 	// The length (in seconds) of a market slot
 	pub const MarketSlotDuration: u64 = SECS_PER_MARKET_SLOT;
+	// Maximum number of matches and penalties submitted in a single extrinsic.
+	pub const MaxMatchesPerBatch: u32 = 100;
+	pub const MaxPenaltiesPerBatch: u32 = 100;
+}
+
+/// Find the account of the Aura authority that authored a block.
+pub struct AuraAccountAdapter;
+impl FindAuthor<AccountId> for AuraAccountAdapter {
+	fn find_author<'a, I>(digests: I) -> Option<AccountId>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		pallet_aura::FindAccountFromAuthorIndex::<Runtime, Aura>::find_author(digests)
+			.and_then(|authority_id| AccountId::try_from(authority_id.as_ref()).ok())
+	}
 }
 
 impl trades_settlement::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type MarketSlotDuration = MarketSlotDuration;
+	type MaxMatchesPerBatch = MaxMatchesPerBatch;
+	type MaxPenaltiesPerBatch = MaxPenaltiesPerBatch;
+	type FindAuthor = AuraAccountAdapter;
 	type TradeSettlementWeightInfo = trades_settlement::weights::SubstrateWeightInfo<Runtime>;
 }
 
 parameter_types! {
 	// Priority for a transaction. Additive. Higher is better.
 	pub const UnsignedPriority: u64 = 1 << 20;
+	// Orders are matched by the external matching engine.
+	pub const OnChainMatching: bool = false;
 }
 
 pub struct AuraAuthId;
@@ -331,6 +358,7 @@ impl orderbook_worker::Config for Runtime {
 
 	type Call = RuntimeCall;
 	type UnsignedPriority = UnsignedPriority;
+	type OnChainMatching = OnChainMatching;
 	type WeightInfo = orderbook_worker::weights::SubstrateWeightInfo<Runtime>;
 }
 
@@ -476,9 +504,16 @@ pub type SignedExtra = (
 
 /// All migrations of the runtime, aside from the ones declared in the pallets.
 ///
-/// This can be a tuple of types, each implementing `OnRuntimeUpgrade`.
+/// This can be a tuple of types, each implementing `OnRuntimeUpgrade`. The migrations of the GSy
+/// pallets are skipped once the storage is at their target version. The orderbook registry migrates
+/// before the orderbook worker and the trades settlement, which rely on its indexes.
 #[allow(unused_parens)]
-type Migrations = ();
+type Migrations = (
+	gsy_collateral::migrations::v1::MigrateToV1<Runtime>,
+	orderbook_registry::migrations::v1::MigrateToV1<Runtime>,
+	orderbook_worker::migrations::v1::MigrateToV1<Runtime>,
+	trades_settlement::migrations::v1::MigrateToV1<Runtime>,
+);
 
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic =
@@ -503,7 +538,9 @@ mod benches {
 		[pallet_timestamp, Timestamp]
 		[pallet_sudo, Sudo]
 		[orderbook_registry, OrderbookRegistry]
+		[orderbook_worker, OrderbookWorker]
 		[gsy_collateral, GsyCollateral]
+		[trades_settlement, TradesSettlement]
 	);
 }
 
@@ -675,6 +712,60 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl gsy_rpc_runtime_api::GsyApi<Block, AccountId, Hash, Balance, BlockNumber, u64> for Runtime {
+		fn market_status(market_id: Hash) -> bool {
+			OrderbookRegistry::market_status(market_id)
+		}
+
+		fn order_status(
+			account: AccountId,
+			order_hash: Hash,
+		) -> Option<gsy_primitives::v0::OrderStatus<Hash>> {
+			OrderbookRegistry::order_status(&gsy_primitives::v0::OrderReference {
+				user_id: account,
+				hash: order_hash,
+			})
+		}
+
+		fn order_statuses(
+			orders: Vec<(AccountId, Hash)>,
+		) -> Vec<Option<gsy_primitives::v0::OrderStatus<Hash>>> {
+			orders
+				.into_iter()
+				.map(|(account, order_hash)| {
+					OrderbookRegistry::order_status(&gsy_primitives::v0::OrderReference {
+						user_id: account,
+						hash: order_hash,
+					})
+				})
+				.collect()
+		}
+
+		fn open_orders_of(account: AccountId) -> Vec<Hash> {
+			OrderbookRegistry::open_orders_of(&account)
+		}
+
+		fn open_orders_in_market(account: AccountId, market_id: Hash) -> Vec<Hash> {
+			OrderbookRegistry::open_orders_in_market(&account, market_id)
+		}
+
+		fn vault_info(
+			account: AccountId,
+		) -> Option<gsy_primitives::v0::VaultInfo<AccountId, Balance, BlockNumber, u64>> {
+			GsyCollateral::vault_data(account)
+		}
+
+		fn penalties_of(
+			account: AccountId,
+		) -> Vec<(Hash, gsy_primitives::v0::TradesPenalties<AccountId, Hash>)> {
+			TradesSettlement::penalties_of(&account)
+		}
+
+		fn is_registered(account: AccountId) -> bool {
+			GsyCollateral::is_registered_user(&account)
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (
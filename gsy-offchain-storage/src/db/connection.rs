@@ -8,6 +8,8 @@ use crate::db::trade_service::{init_trades, TradeService};
 use crate::db::measurements_service::{init_measurements, MeasurementsService};
 use crate::db::forecasts_service::{init_forecasts, ForecastsService};
 use crate::db::market_service::{init_markets, MarketService};
+use crate::db::imbalance_prices_service::{init_imbalance_prices, ImbalancePricesService};
+use crate::db::statements_service::{init_statements, StatementsService};
 
 pub type DbRef = web::Data<DatabaseWrapper>;
 
@@ -31,6 +33,12 @@ impl DatabaseWrapper {
     pub fn markets(&self) -> MarketService {
         self.into()
     }
+    pub fn imbalance_prices(&self) -> ImbalancePricesService {
+        self.into()
+    }
+    pub fn statements(&self) -> StatementsService {
+        self.into()
+    }
 }
 
 impl Deref for DatabaseWrapper {
@@ -56,5 +64,7 @@ async fn preload(db: &DatabaseWrapper) -> Result<()> {
     init_forecasts(db).await?;
     init_measurements(db).await?;
     init_markets(db).await?;
+    init_imbalance_prices(db).await?;
+    init_statements(db).await?;
     Ok(())
 }
@@ -0,0 +1,87 @@
+use crate::db::DatabaseWrapper;
+use gsy_offchain_primitives::db_api_schema::imbalance_prices::ImbalancePriceSchema;
+use anyhow::Result;
+use futures::StreamExt;
+use mongodb::bson::{doc, Bson};
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, IndexModel};
+use std::collections::HashMap;
+use std::ops::Deref;
+
+
+/// this function will call after connected to database
+pub async fn init_imbalance_prices(db: &DatabaseWrapper) -> Result<()> {
+    let controller = db.imbalance_prices();
+    let index: IndexModel = IndexModel::builder()
+        .keys(doc! {"time_slot":1})
+        .options(IndexOptions::builder().build())
+        .build();
+    controller.create_index(index).await?;
+    Ok(())
+}
+
+#[repr(transparent)]
+pub struct ImbalancePricesService(pub Collection<ImbalancePriceSchema>);
+
+impl ImbalancePricesService {
+    #[tracing::instrument(name = "Fetching imbalance prices from database", skip(self))]
+    pub async fn filter_imbalance_prices(
+            &self,
+            start_time: Option<u32>,
+            end_time: Option<u32>) -> Result<Vec<ImbalancePriceSchema>> {
+        let mut filter_params = doc! {};
+        match (start_time, end_time) {
+            (Some(start_time), Some(end_time)) => {
+                filter_params.insert("time_slot", doc! {"$gte": start_time, "$lte": end_time});
+            }
+            (Some(start_time), None) => { filter_params.insert("time_slot", doc! {"$gte": start_time}); }
+            (None, Some(end_time)) => { filter_params.insert("time_slot", doc! {"$lte": end_time}); }
+            (None, None) => {}
+        }
+
+        let mut cursor = self.0.find(filter_params).await?;
+        let mut result: Vec<ImbalancePriceSchema> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            match doc {
+                Ok(document) => {
+                    result.push(document);
+                }
+                _ => {
+                    break;
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    #[tracing::instrument(
+        name = "Saving imbalance prices to database",
+        skip(self, prices),
+        fields(
+        prices = ?prices
+        )
+    )]
+    pub async fn insert_imbalance_prices(&self, prices: Vec<ImbalancePriceSchema>) -> Result<HashMap<usize, Bson>> {
+        match self.0.insert_many(prices).await {
+            Ok(db_result) => Ok(db_result.inserted_ids),
+            Err(e) => {
+                tracing::error!("Failed to execute query: {:?}", e);
+                Err(anyhow::Error::from(e))
+            }
+        }
+    }
+}
+
+impl From<&DatabaseWrapper> for ImbalancePricesService {
+    fn from(db: &DatabaseWrapper) -> Self {
+        ImbalancePricesService(db.collection("imbalance_prices"))
+    }
+}
+
+impl Deref for ImbalancePricesService {
+    type Target = Collection<ImbalancePriceSchema>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
@@ -30,10 +30,12 @@ impl MeasurementsService {
     pub async fn filter_measurements(
             &self,
             area_uuid: Option<String>,
+            market_id: Option<String>,
             start_time: Option<u32>,
             end_time: Option<u32>) -> Result<Vec<MeasurementSchema>> {
         let mut filter_params = doc! {};
         if area_uuid.is_some() { filter_params.insert("area_uuid", area_uuid.unwrap()); }
+        if market_id.is_some() { filter_params.insert("market_id", market_id.unwrap()); }
         if start_time.is_some() { filter_params.insert("time_slot", doc! {"$gte": start_time.unwrap()} ); }
         if end_time.is_some() {
             if start_time.is_some() {
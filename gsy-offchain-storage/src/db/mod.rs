@@ -5,6 +5,8 @@ mod trade_service;
 mod measurements_service;
 mod forecasts_service;
 mod market_service;
+mod imbalance_prices_service;
+mod statements_service;
 
 pub use connection::*;
 pub use order_service::*;
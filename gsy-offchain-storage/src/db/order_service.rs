@@ -96,13 +96,23 @@ impl OrderService {
         )
     )]
     pub async fn insert_orders(&self, orders_schema: Vec<DbOrderSchema>) -> Result<HashMap<usize, Bson>> {
-        match self.0.insert_many(orders_schema).await {
-            Ok(db_result) => Ok(db_result.inserted_ids),
-            Err(e) => {
+        // The offchain worker retries the deliveries that it could not confirm, so the orders
+        // are only inserted if their id is not known yet.
+        let mut inserted_ids = HashMap::new();
+        for (index, order) in orders_schema.into_iter().enumerate() {
+            let id = Bson::String(order._id.clone());
+            let mut document = bson::to_document(&order)?;
+            document.remove("_id");
+            if let Err(e) = self.0
+                .update_one(doc! {"_id": &id}, doc! {"$setOnInsert": document})
+                .upsert(true)
+                .await {
                 tracing::error!("Failed to execute query: {:?}", e);
-                Err(anyhow::Error::from(e))
+                return Err(anyhow::Error::from(e));
             }
+            inserted_ids.insert(index, id);
         }
+        Ok(inserted_ids)
     }
 
     #[tracing::instrument(name = "Fetching order by id from database", skip(self, id))]
@@ -174,7 +184,11 @@ impl OrderService {
             .0
             .update_many(
                 doc! {
-                    "order.data.time_slot": { "$lt": bson::to_bson(&now_time_slot).unwrap()},
+                    "$or": [
+                        { "order.data.time_slot": { "$lt": bson::to_bson(&now_time_slot).unwrap()} },
+                        { "order.data.bid_component.valid_until": { "$lt": bson::to_bson(&now_time_slot).unwrap()} },
+                        { "order.data.offer_component.valid_until": { "$lt": bson::to_bson(&now_time_slot).unwrap()} },
+                    ],
                     "status": bson::to_bson(&OrderStatus::Open).unwrap()
                 },
                 doc! {
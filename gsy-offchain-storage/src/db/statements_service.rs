@@ -0,0 +1,98 @@
+use crate::db::DatabaseWrapper;
+use gsy_offchain_primitives::db_api_schema::statements::{SettlementStatementSchema, StatementPeriod};
+use anyhow::Result;
+use futures::StreamExt;
+use mongodb::bson::doc;
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, IndexModel};
+use std::ops::Deref;
+
+
+/// this function will call after connected to database
+pub async fn init_statements(db: &DatabaseWrapper) -> Result<()> {
+    let controller = db.statements();
+    let index: IndexModel = IndexModel::builder()
+        .keys(doc! {"account": 1, "period": 1, "period_start": 1})
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    controller.create_index(index).await?;
+    Ok(())
+}
+
+#[repr(transparent)]
+pub struct StatementsService(pub Collection<SettlementStatementSchema>);
+
+impl StatementsService {
+    #[tracing::instrument(name = "Fetching settlement statements from database", skip(self))]
+    pub async fn filter_statements(
+            &self,
+            account: Option<String>,
+            period: Option<StatementPeriod>,
+            start_time: Option<u32>,
+            end_time: Option<u32>) -> Result<Vec<SettlementStatementSchema>> {
+        let mut filter_params = doc! {};
+        if let Some(account) = account { filter_params.insert("account", account); }
+        if let Some(period) = period { filter_params.insert("period", period.as_str()); }
+        match (start_time, end_time) {
+            (Some(start_time), Some(end_time)) => {
+                filter_params.insert("period_start", doc! {"$gte": start_time, "$lte": end_time});
+            }
+            (Some(start_time), None) => { filter_params.insert("period_start", doc! {"$gte": start_time}); }
+            (None, Some(end_time)) => { filter_params.insert("period_start", doc! {"$lte": end_time}); }
+            (None, None) => {}
+        }
+
+        let mut cursor = self.0.find(filter_params).sort(doc! {"period_start": 1}).await?;
+        let mut result: Vec<SettlementStatementSchema> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            match doc {
+                Ok(document) => {
+                    result.push(document);
+                }
+                _ => {
+                    break;
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    #[tracing::instrument(
+        name = "Saving settlement statements to database",
+        skip(self, statements),
+        fields(
+        statements = ?statements
+        )
+    )]
+    pub async fn upsert_statements(&self, statements: Vec<SettlementStatementSchema>) -> Result<usize> {
+        // The statement of a period is rebuilt when its penalties change, e.g. when the
+        // measurement of a deferred area arrives, so it replaces the previous one.
+        let count = statements.len();
+        for statement in statements {
+            let filter = doc! {
+                "account": &statement.account,
+                "period": statement.period.as_str(),
+                "period_start": statement.period_start as i64,
+            };
+            if let Err(e) = self.0.replace_one(filter, statement).upsert(true).await {
+                tracing::error!("Failed to execute query: {:?}", e);
+                return Err(anyhow::Error::from(e));
+            }
+        }
+        Ok(count)
+    }
+}
+
+impl From<&DatabaseWrapper> for StatementsService {
+    fn from(db: &DatabaseWrapper) -> Self {
+        StatementsService(db.collection("statements"))
+    }
+}
+
+impl Deref for StatementsService {
+    type Target = Collection<SettlementStatementSchema>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
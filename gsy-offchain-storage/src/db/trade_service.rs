@@ -4,7 +4,7 @@ use anyhow::Result;
 use futures::StreamExt;
 use mongodb::bson::{doc, Bson};
 use mongodb::options::IndexOptions;
-use mongodb::{Collection, Cursor, IndexModel};
+use mongodb::{bson, Collection, Cursor, IndexModel};
 use std::collections::HashMap;
 use std::ops::Deref;
 
@@ -52,13 +52,23 @@ impl TradeService {
         )
     )]
     pub async fn insert_trades(&self, trade_schema: Vec<TradeSchema>) -> Result<HashMap<usize, Bson>> {
-        match self.0.insert_many(trade_schema).await {
-            Ok(db_result) => Ok(db_result.inserted_ids),
-            Err(e) => {
+        // The offchain worker retries the deliveries that it could not confirm, so the trades
+        // are only inserted if their id is not known yet.
+        let mut inserted_ids = HashMap::new();
+        for (index, trade) in trade_schema.into_iter().enumerate() {
+            let id = Bson::String(trade._id.clone());
+            let mut document = bson::to_document(&trade)?;
+            document.remove("_id");
+            if let Err(e) = self.0
+                .update_one(doc! {"_id": &id}, doc! {"$setOnInsert": document})
+                .upsert(true)
+                .await {
                 tracing::error!("Failed to execute query: {:?}", e);
-                Err(anyhow::Error::from(e))
+                return Err(anyhow::Error::from(e));
             }
+            inserted_ids.insert(index, id);
         }
+        Ok(inserted_ids)
     }
 
     async fn create_vector_from_cursor(&self, mut cursor: Cursor<TradeSchema>) -> Result<Vec<TradeSchema>> {
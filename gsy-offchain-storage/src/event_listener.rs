@@ -1,13 +1,29 @@
 use crate::db::DbRef;
 use anyhow::{Error, Result};
 use gsy_offchain_primitives::db_api_schema::orders::OrderStatus;
-use mongodb::bson;
+use mongodb::bson::Bson;
 use subxt::{OnlineClient, SubstrateConfig};
+use std::fmt::Debug;
 use tracing::info;
 
 #[subxt::subxt(runtime_metadata_path = "../offchain-primitives/metadata.scale")]
 pub mod gsy_node {}
 
+/// The id of an order in the database, given the hash of its bid or offer carried by the
+/// orderbook registry events. The orders are relayed under the same hash.
+fn order_id<Hash: Debug>(hash: &Hash) -> Bson {
+	Bson::String(format!("{:?}", hash))
+}
+
+async fn update_order_status<Hash: Debug>(db: &DbRef, hash: &Hash, status: OrderStatus) {
+	match db.get_ref().orders().update_order_status_by_id(&order_id(hash), status).await {
+		Ok(result) => info!("Update result: {:?}", result),
+		Err(e) => {
+			tracing::error!("Failed to execute update: {:?}", e);
+		},
+	}
+}
+
 pub async fn init_event_listener(db: DbRef, node_url: String) -> Result<(), Error> {
 	let api =
 		OnlineClient::<SubstrateConfig>::from_insecure_url(format!("ws://{}", node_url)).await?;
@@ -29,49 +45,32 @@ pub async fn init_event_listener(db: DbRef, node_url: String) -> Result<(), Erro
 			if let Ok(order_executed) = &event {
 				info!("Order Executed: {:?}", order_executed);
 
-				let id = &bson::to_bson(&order_executed.0.offer_hash).unwrap();
-				match db
-					.get_ref()
-					.orders()
-					.update_order_status_by_id(id, OrderStatus::Executed)
-					.await
-				{
-					Ok(result) => info!("Update result: {:?}", result),
-					Err(e) => {
-						tracing::error!("Failed to execute update: {:?}", e);
-					},
-				}
-
-				let id = &bson::to_bson(&order_executed.0.bid_hash).unwrap();
-				match db
-					.get_ref()
-					.orders()
-					.update_order_status_by_id(id, OrderStatus::Executed)
-					.await
-				{
-					Ok(result) => info!("Update result: {:?}", result),
-					Err(e) => {
-						tracing::error!("Failed to execute update: {:?}", e);
-					},
-				}
+				update_order_status(&db, &order_executed.0.offer_hash, OrderStatus::Executed).await;
+				update_order_status(&db, &order_executed.0.bid_hash, OrderStatus::Executed).await;
 			}
 		}
 
 		for event in events.find::<gsy_node::orderbook_registry::events::OrderDeleted>() {
 			if let Ok(order_deleted) = &event {
 				info!("Hash of the removed order: {:?}", order_deleted.1);
-				let id = &bson::to_bson(&order_deleted.1).unwrap();
-				match db
-					.get_ref()
-					.orders()
-					.update_order_status_by_id(id, OrderStatus::Deleted)
-					.await
-				{
-					Ok(result) => info!("Update result: {:?}", result),
-					Err(e) => {
-						tracing::error!("Failed to execute update: {:?}", e);
-					},
-				}
+				update_order_status(&db, &order_deleted.1, OrderStatus::Deleted).await;
+			}
+		}
+
+		for event in events.find::<gsy_node::orderbook_registry::events::OrderAmended>() {
+			if let Ok(order_amended) = &event {
+				info!(
+					"Hash of the amended order: {:?} - replaced by: {:?}",
+					order_amended.1, order_amended.2
+				);
+				update_order_status(&db, &order_amended.1, OrderStatus::Amended).await;
+			}
+		}
+
+		for event in events.find::<gsy_node::orderbook_registry::events::OrderExpired>() {
+			if let Ok(order_expired) = &event {
+				info!("Hash of the expired order: {:?}", order_expired.1);
+				update_order_status(&db, &order_expired.1, OrderStatus::Expired).await;
 			}
 		}
 	}
@@ -0,0 +1,35 @@
+use actix_web::{web::Json, HttpResponse, Responder, web::Query};
+use crate::db::DbRef;
+use gsy_offchain_primitives::db_api_schema::imbalance_prices::ImbalancePriceSchema;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ImbalancePricesParameters {
+    start_time: Option<u32>,
+    end_time: Option<u32>,
+}
+
+pub async fn post_imbalance_prices(
+    prices: Json<Vec<ImbalancePriceSchema>>,
+    db: DbRef,
+) -> impl Responder {
+    match db.get_ref().imbalance_prices().insert_imbalance_prices(prices.to_vec()).await {
+        Ok(ids) => HttpResponse::Ok().json(ids),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn get_imbalance_prices(
+    db: DbRef,
+    query_params: Query<ImbalancePricesParameters>,
+) -> impl Responder {
+    match db.get_ref().imbalance_prices().filter_imbalance_prices(
+            query_params.start_time,
+            query_params.end_time).await {
+        Ok(prices) => HttpResponse::Ok().json(prices),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        },
+    }
+}
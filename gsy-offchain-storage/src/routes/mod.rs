@@ -3,9 +3,13 @@ mod orders;
 mod profiles;
 mod trades;
 mod market;
+mod imbalance_prices;
+mod statements;
 
 pub use health_check::*;
 pub use orders::*;
 pub use trades::*;
 pub use profiles::*;
-pub use market::*;
\ No newline at end of file
+pub use market::*;
+pub use imbalance_prices::*;
+pub use statements::*;
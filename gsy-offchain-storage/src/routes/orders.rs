@@ -1,7 +1,7 @@
 use actix_web::{web::Json, HttpResponse, Responder, web::Query};
 use gsy_offchain_primitives::db_api_schema::orders::DbOrderSchema;
 use crate::db::DbRef;
-use gsy_offchain_primitives::node_to_api_schema::insert_order::convert_gsy_node_order_schema_to_db_schema;
+use gsy_offchain_primitives::node_to_api_schema::relay::{convert_relay_orders_to_db_schema, RelayOrdersBody};
 use serde::Deserialize;
 use anyhow::{Result, Error};
 
@@ -14,10 +14,13 @@ use anyhow::{Result, Error};
     )
 )]
 pub async fn post_orders(
-    orders: Json<Vec<u8>>,
+    orders: Json<RelayOrdersBody>,
     db: DbRef,
 ) -> impl Responder {
-    let deserialized_orders = convert_gsy_node_order_schema_to_db_schema(orders.to_vec());
+    let deserialized_orders = match convert_relay_orders_to_db_schema(orders.into_inner()) {
+        Ok(deserialized_orders) => deserialized_orders,
+        Err(error) => return HttpResponse::BadRequest().body(error),
+    };
     match db.get_ref().orders().insert_orders(deserialized_orders).await {
         Ok(ids) => HttpResponse::Ok().json(ids),
         Err(_) => HttpResponse::InternalServerError().finish()
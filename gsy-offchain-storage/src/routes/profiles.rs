@@ -6,6 +6,7 @@ use serde::Deserialize;
 #[derive(Deserialize)]
 pub struct ProfilesParameters {
     area_uuid: Option<String>,
+    market_id: Option<String>,
     start_time: Option<u32>,
     end_time: Option<u32>,
 }
@@ -24,6 +25,7 @@ pub async fn get_measurements(db: DbRef, query_params: Query<ProfilesParameters>
     let measurements_service = db.get_ref().measurements();
     match measurements_service.filter_measurements(
             query_params.area_uuid.clone(),
+            query_params.market_id.clone(),
             query_params.start_time,
             query_params.end_time).await {
         Ok(measurements) => HttpResponse::Ok().json(measurements),
@@ -48,6 +50,7 @@ pub async fn get_forecasts(db: DbRef, query_params: Query<ProfilesParameters>) -
     let forecasts_service = db.get_ref().forecasts();
     match forecasts_service.filter_forecasts(
             query_params.area_uuid.clone(),
+            query_params.market_id.clone(),
             query_params.start_time,
             query_params.end_time).await {
         Ok(measurements) => HttpResponse::Ok().json(measurements),
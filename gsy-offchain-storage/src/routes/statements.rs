@@ -0,0 +1,39 @@
+use actix_web::{web::Json, HttpResponse, Responder, web::Query};
+use crate::db::DbRef;
+use gsy_offchain_primitives::db_api_schema::statements::{SettlementStatementSchema, StatementPeriod};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct StatementsParameters {
+    account: Option<String>,
+    period: Option<StatementPeriod>,
+    start_time: Option<u32>,
+    end_time: Option<u32>,
+}
+
+pub async fn post_statements(
+    statements: Json<Vec<SettlementStatementSchema>>,
+    db: DbRef,
+) -> impl Responder {
+    match db.get_ref().statements().upsert_statements(statements.to_vec()).await {
+        Ok(count) => HttpResponse::Ok().json(count),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn get_statements(
+    db: DbRef,
+    query_params: Query<StatementsParameters>,
+) -> impl Responder {
+    match db.get_ref().statements().filter_statements(
+            query_params.account.clone(),
+            query_params.period,
+            query_params.start_time,
+            query_params.end_time).await {
+        Ok(statements) => HttpResponse::Ok().json(statements),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        },
+    }
+}
@@ -1,7 +1,7 @@
 use actix_web::web::Query;
 use actix_web::{web::Json, HttpResponse, Responder};
 use crate::db::DbRef;
-use gsy_offchain_primitives::node_to_api_schema::insert_trades::convert_gsy_node_trades_schema_to_db_schema;
+use gsy_offchain_primitives::node_to_api_schema::relay::{convert_relay_trades_to_db_schema, RelayTradesBody};
 use serde::Deserialize;
 use gsy_offchain_primitives::db_api_schema::trades::TradeSchema;
 
@@ -14,10 +14,13 @@ use gsy_offchain_primitives::db_api_schema::trades::TradeSchema;
     )
 )]
 pub async fn post_trades(
-    trades: Json<Vec<u8>>,
+    trades: Json<RelayTradesBody>,
     db: DbRef,
 ) -> impl Responder {
-    let deserialized_trades = convert_gsy_node_trades_schema_to_db_schema(trades.to_vec());
+    let deserialized_trades = match convert_relay_trades_to_db_schema(trades.into_inner()) {
+        Ok(deserialized_trades) => deserialized_trades,
+        Err(error) => return HttpResponse::BadRequest().body(error),
+    };
     for trade in deserialized_trades.clone() {
         let _ = db.get_ref().orders().update_order_by_area_market_id(
             trade.market_id.clone(), trade.offer.offer_component.area_uuid.clone());
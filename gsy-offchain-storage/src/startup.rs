@@ -2,7 +2,7 @@ use std::net::TcpListener;
 use actix_web::{web, App, HttpServer};
 use actix_web::dev::Server;
 use crate::db::DatabaseWrapper;
-use crate::routes::{health_check, get_orders, post_orders, post_trades, get_trades, post_measurements, get_measurements, post_forecasts, get_forecasts, post_market, get_market, get_market_from_community, post_normalized_orders, post_normalized_trades};
+use crate::routes::{health_check, get_orders, post_orders, post_trades, get_trades, post_measurements, get_measurements, post_forecasts, get_forecasts, post_market, get_market, get_market_from_community, post_normalized_orders, post_normalized_trades, post_imbalance_prices, get_imbalance_prices, post_statements, get_statements};
 use tracing_actix_web::TracingLogger;
 
 
@@ -25,6 +25,10 @@ pub fn run(listener: TcpListener, db_connection_wrapper: DatabaseWrapper) -> Res
             .route("/market", web::post().to(post_market))
             .route("/market", web::get().to(get_market))
             .route("/community-market", web::get().to(get_market_from_community))
+            .route("/imbalance-prices", web::post().to(post_imbalance_prices))
+            .route("/imbalance-prices", web::get().to(get_imbalance_prices))
+            .route("/statements", web::post().to(post_statements))
+            .route("/statements", web::get().to(get_statements))
             .app_data(db_connection_wrapper.clone())
     })
     .listen(listener)?
@@ -0,0 +1,57 @@
+use crate::helpers::init_app;
+use actix_web::web;
+use gsy_offchain_primitives::db_api_schema::imbalance_prices::ImbalancePriceSchema;
+
+fn imbalance_price(time_slot: u64, short_price: f64, long_price: f64) -> ImbalancePriceSchema {
+    ImbalancePriceSchema { time_slot, short_price, long_price, creation_time: time_slot }
+}
+
+#[tokio::test]
+async fn post_imbalance_prices_succeeds() {
+    let app = init_app().await;
+    let address = app.address;
+    let body = vec![imbalance_price(1232123213, 0.3, 0.05)];
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(&format!("{}/imbalance-prices", &address))
+        .header("Content-Type", "application/json")
+        .json(&body)
+        .send()
+        .await.unwrap();
+    assert_eq!(200, resp.status().as_u16());
+
+    let db = web::Data::new(app.db_wrapper);
+    let saved = db
+        .get_ref()
+        .imbalance_prices()
+        .filter_imbalance_prices(None, None)
+        .await
+        .unwrap();
+    assert_eq!(saved, body);
+}
+
+#[tokio::test]
+async fn get_imbalance_prices_filters_by_time_slot() {
+    let app = init_app().await;
+    let address = app.address;
+    let db = web::Data::new(app.db_wrapper);
+    db.get_ref()
+        .imbalance_prices()
+        .insert_imbalance_prices(vec![
+            imbalance_price(1232123213, 0.3, 0.05),
+            imbalance_price(1232124113, 0.4, 0.02),
+        ])
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .get(&format!("{}/imbalance-prices?start_time=1232123214&end_time=1232125000", &address))
+        .header("Content-Type", "application/json")
+        .send()
+        .await.unwrap();
+    assert_eq!(200, resp.status().as_u16());
+    let resp_json: Vec<ImbalancePriceSchema> = resp.json().await.unwrap();
+    assert_eq!(resp_json, vec![imbalance_price(1232124113, 0.4, 0.02)]);
+}
@@ -4,3 +4,5 @@ mod orders;
 mod measurements_forecasts;
 pub mod trades;
 mod market;
+mod imbalance_prices;
+mod statements;
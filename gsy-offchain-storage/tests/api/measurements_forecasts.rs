@@ -9,6 +9,8 @@ async fn get_measurements_succeeds() {
     let address = app.address;
     let measurement1 = MeasurementSchema {
         area_uuid: "my_uuid".to_string(),
+        area_hash: "my_uuid_hash".to_string(),
+        market_id: "my_market".to_string(),
         community_uuid: "my_community".to_string(),
         energy_kwh: 12.21,
         time_slot: 1232123213,
@@ -16,6 +18,8 @@ async fn get_measurements_succeeds() {
     };
     let measurement2 = MeasurementSchema {
         area_uuid: "my_uuid1".to_string(),
+        area_hash: "my_uuid1_hash".to_string(),
+        market_id: "my_other_market".to_string(),
         community_uuid: "my_community".to_string(),
         energy_kwh: 13.21,
         time_slot: 1232123215,
@@ -72,6 +76,17 @@ async fn get_measurements_succeeds() {
     let resp_json: Vec<MeasurementSchema> = resp.json().await.unwrap();
     assert_eq!(resp_json.len(), 1);
     assert_eq!(resp_json.iter().nth(0).unwrap().area_uuid, "my_uuid");
+
+    let resp = client
+        .get(&format!("{}/measurements?market_id=my_other_market", &address))
+        .header("Content-Type", "application/json")
+        .send()
+        .await.unwrap();
+    let status = resp.status();
+    assert_eq!(200, status.as_u16());
+    let resp_json: Vec<MeasurementSchema> = resp.json().await.unwrap();
+    assert_eq!(resp_json.len(), 1);
+    assert_eq!(resp_json.iter().nth(0).unwrap().area_uuid, "my_uuid1");
 }
 
 #[tokio::test]
@@ -80,6 +95,8 @@ async fn post_measurements_succeeds() {
     let address = app.address;
     let measurement = MeasurementSchema {
         area_uuid: "my_uuid".to_string(),
+        area_hash: "my_uuid_hash".to_string(),
+        market_id: "my_market".to_string(),
         community_uuid: "my_community".to_string(),
         energy_kwh: 12.21,
         time_slot: 1232123213,
@@ -103,7 +120,7 @@ async fn post_measurements_succeeds() {
     let saved = db
         .get_ref()
         .measurements()
-        .filter_measurements("my_uuid".to_string().checked_into(), None, None)
+        .filter_measurements("my_uuid".to_string().checked_into(), None, None, None)
         .await
         .unwrap();
     assert_eq!(1, saved.len());
@@ -143,6 +160,8 @@ async fn get_forecasts_succeeds() {
     let address = app.address;
     let forecast1 = ForecastSchema {
         area_uuid: "my_uuid".to_string(),
+        area_hash: "my_uuid_hash".to_string(),
+        market_id: "my_market".to_string(),
         community_uuid: "my_community".to_string(),
         energy_kwh: 12.21,
         time_slot: 1232123213,
@@ -151,6 +170,8 @@ async fn get_forecasts_succeeds() {
     };
     let forecast2 = ForecastSchema {
         area_uuid: "my_uuid1".to_string(),
+        area_hash: "my_uuid1_hash".to_string(),
+        market_id: "my_other_market".to_string(),
         community_uuid: "my_community".to_string(),
         energy_kwh: 13.21,
         time_slot: 1232123215,
@@ -209,6 +230,17 @@ async fn get_forecasts_succeeds() {
     let resp_json: Vec<ForecastSchema> = resp.json().await.unwrap();
     assert_eq!(resp_json.len(), 1);
     assert_eq!(resp_json.iter().nth(0).unwrap().area_uuid, "my_uuid");
+
+    let resp = client
+        .get(&format!("{}/forecasts?market_id=my_other_market", &address))
+        .header("Content-Type", "application/json")
+        .send()
+        .await.unwrap();
+    let status = resp.status();
+    assert_eq!(200, status.as_u16());
+    let resp_json: Vec<ForecastSchema> = resp.json().await.unwrap();
+    assert_eq!(resp_json.len(), 1);
+    assert_eq!(resp_json.iter().nth(0).unwrap().area_uuid, "my_uuid1");
 }
 
 #[tokio::test]
@@ -217,6 +249,8 @@ async fn post_forecasts_succeeds() {
     let address = app.address;
     let forecast = ForecastSchema {
         area_uuid: "my_uuid".to_string(),
+        area_hash: "my_uuid_hash".to_string(),
+        market_id: "my_market".to_string(),
         community_uuid: "my_uuid".to_string(),
         energy_kwh: 12.21,
         time_slot: 1232123213,
@@ -241,7 +275,7 @@ async fn post_forecasts_succeeds() {
     let saved = db
         .get_ref()
         .forecasts()
-        .filter_forecasts("my_uuid".to_string().checked_into(), None, None)
+        .filter_forecasts("my_uuid".to_string().checked_into(), None, None, None)
         .await
         .unwrap();
     assert_eq!(1, saved.len());
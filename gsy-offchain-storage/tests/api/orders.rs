@@ -42,6 +42,7 @@ async fn subscribe_return_a_200_for_valid_form_data() {
                 market_id: market_id,
                 time_slot: 1,
                 creation_time: 1677453190,
+                valid_until: None,
             }
         })
     };
@@ -115,3 +116,106 @@ async fn subscribe_return_a_400_when_data_is_missing() {
         );
     }
 }
+
+#[tokio::test]
+async fn posting_the_same_orders_twice_stores_them_once() {
+    let app = init_app().await;
+    let address = app.address;
+
+    let order_id = H256::random();
+    let order = OrderSchema {
+        _id: order_id,
+        status: OrderStatus::Open,
+        order: Order::Bid(Bid {
+            buyer: create_test_accountid(),
+            nonce: 1,
+            bid_component: OrderComponent {
+                energy: 100,
+                energy_rate: 10,
+                area_uuid: H256::random(),
+                market_id: H256::random(),
+                time_slot: 1,
+                creation_time: 1677453190,
+                valid_until: None,
+            }
+        })
+    };
+    let body = Vec::<OrderSchema<AccountId32, H256>>::encode(&vec![order]);
+
+    let client = reqwest::Client::new();
+    for _ in 0..2 {
+        let resp = client
+            .post(&format!("{}/orders", &address))
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(200, resp.status().as_u16());
+    }
+
+    let db = web::Data::new(app.db_wrapper);
+    let saved = db.get_ref().orders().get_all_orders().await.unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0]._id, order_id.to_string());
+}
+
+#[tokio::test]
+async fn post_orders_accepts_the_json_relay_schema() {
+    let app = init_app().await;
+    let address = app.address;
+
+    let order_hash = format!("{:?}", H256::random());
+    let body = serde_json::json!({
+        "version": 1,
+        "orders": [{
+            "hash": order_hash,
+            "type": "offer",
+            "account": create_test_accountid().to_string(),
+            "nonce": 1,
+            "area_uuid": format!("{:?}", H256::random()),
+            "market_id": format!("{:?}", H256::random()),
+            "time_slot": 1,
+            "creation_time": 1677453190,
+            "energy": 15000,
+            "energy_rate": 20000,
+            "valid_until": null
+        }]
+    });
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(&format!("{}/orders", &address))
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, resp.status().as_u16());
+
+    let db = web::Data::new(app.db_wrapper);
+    let saved = db
+        .get_ref()
+        .orders()
+        .get_order_by_id(&Bson::String(order_hash))
+        .await
+        .unwrap()
+        .unwrap();
+    match saved.order {
+        gsy_offchain_primitives::db_api_schema::orders::Order::Offer(offer) => {
+            assert_eq!(offer.seller, create_test_accountid().to_string());
+            assert_eq!(offer.offer_component.energy, 1.5);
+            assert_eq!(offer.offer_component.energy_rate, 2.0);
+        }
+        _ => panic!("Expected an offer"),
+    }
+
+    let mut unsupported = body.clone();
+    unsupported["version"] = serde_json::json!(2);
+    let resp = client
+        .post(&format!("{}/orders", &address))
+        .json(&unsupported)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, resp.status().as_u16());
+}
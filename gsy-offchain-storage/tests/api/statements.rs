@@ -0,0 +1,75 @@
+use crate::helpers::init_app;
+use actix_web::web;
+use gsy_offchain_primitives::db_api_schema::statements::{SettlementStatementSchema, StatementPeriod};
+
+fn statement(account: &str, period: StatementPeriod, period_start: u64, penalties: f64) -> SettlementStatementSchema {
+    SettlementStatementSchema {
+        account: account.to_string(),
+        period,
+        period_start,
+        period_end: period_start + 900,
+        energy_bought: 2.0,
+        energy_sold: 0.0,
+        average_buy_rate: 0.3,
+        average_sell_rate: 0.0,
+        bought_value: 0.6,
+        sold_value: 0.0,
+        imbalance: 1.0,
+        penalties,
+        net_amount: -0.6 - penalties,
+        creation_time: period_start,
+    }
+}
+
+#[tokio::test]
+async fn post_statements_replaces_the_statement_of_the_same_period() {
+    let app = init_app().await;
+    let address = app.address;
+
+    let client = reqwest::Client::new();
+    for penalties in [0.1, 0.2] {
+        let resp = client
+            .post(&format!("{}/statements", &address))
+            .header("Content-Type", "application/json")
+            .json(&vec![statement("alice", StatementPeriod::Slot, 1232123100, penalties)])
+            .send()
+            .await.unwrap();
+        assert_eq!(200, resp.status().as_u16());
+    }
+
+    let db = web::Data::new(app.db_wrapper);
+    let saved = db
+        .get_ref()
+        .statements()
+        .filter_statements(None, None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(saved, vec![statement("alice", StatementPeriod::Slot, 1232123100, 0.2)]);
+}
+
+#[tokio::test]
+async fn get_statements_filters_by_account_and_period() {
+    let app = init_app().await;
+    let address = app.address;
+    let db = web::Data::new(app.db_wrapper);
+    db.get_ref()
+        .statements()
+        .upsert_statements(vec![
+            statement("alice", StatementPeriod::Slot, 1232123100, 0.1),
+            statement("alice", StatementPeriod::Month, 1232123100, 0.1),
+            statement("bob", StatementPeriod::Slot, 1232123100, 0.0),
+            statement("alice", StatementPeriod::Slot, 1232124000, 0.0),
+        ])
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .get(&format!("{}/statements?account=alice&period=slot&end_time=1232123500", &address))
+        .header("Content-Type", "application/json")
+        .send()
+        .await.unwrap();
+    assert_eq!(200, resp.status().as_u16());
+    let resp_json: Vec<SettlementStatementSchema> = resp.json().await.unwrap();
+    assert_eq!(resp_json, vec![statement("alice", StatementPeriod::Slot, 1232123100, 0.1)]);
+}
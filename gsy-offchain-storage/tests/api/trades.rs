@@ -27,6 +27,7 @@ async fn post_trade_request_writes_trades_to_the_db() {
             market_id: market_id.clone(),
             time_slot: 1,
             creation_time: 1677453190,
+            valid_until: None,
         },
     };
     let offer = InsertOffer {
@@ -39,6 +40,7 @@ async fn post_trade_request_writes_trades_to_the_db() {
             market_id: market_id.clone(),
             time_slot: 1,
             creation_time: 1677453190,
+            valid_until: None,
         },
     };
 
@@ -90,6 +92,82 @@ async fn post_trade_request_writes_trades_to_the_db() {
     assert_eq!(result_trade.trade_uuid, trade1.trade_uuid.to_string());
 }
 
+#[tokio::test]
+async fn post_trades_accepts_the_json_relay_schema() {
+    let app = init_app().await;
+    let address = app.address;
+
+    let account = crate::orders::create_test_accountid().to_string();
+    let market_id = format!("{:?}", H256::random());
+    let trade_uuid = format!("{:?}", H256::random());
+    let order = |order_type: &str, energy: u64| serde_json::json!({
+        "hash": format!("{:?}", H256::random()),
+        "type": order_type,
+        "account": account,
+        "nonce": 1,
+        "area_uuid": format!("{:?}", H256::random()),
+        "market_id": market_id,
+        "time_slot": 1,
+        "creation_time": 1677453190,
+        "energy": energy,
+        "energy_rate": 20000,
+        "valid_until": null
+    });
+    let (bid, offer, residual_bid) = (order("bid", 15000), order("offer", 10000), order("bid", 5000));
+    let body = serde_json::json!({
+        "version": 1,
+        "trades": [{
+            "trade_uuid": trade_uuid,
+            "market_id": market_id,
+            "seller": account,
+            "buyer": account,
+            "time_slot": 1,
+            "creation_time": 1677453200,
+            "selected_energy": 10000,
+            "energy_rate": 20000,
+            "bid": bid,
+            "offer": offer,
+            "residual_bid": residual_bid,
+            "residual_offer": null
+        }]
+    });
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(&format!("{}/trades", &address))
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, resp.status().as_u16());
+
+    let db = web::Data::new(app.db_wrapper);
+    let saved = db.get_ref().trades().get_all_trades().await.unwrap();
+    let saved = saved.iter().find(|trade| trade.trade_uuid == trade_uuid).unwrap();
+    assert_eq!(saved.market_id, market_id);
+    assert_eq!(saved.bid_hash, bid["hash"].as_str().unwrap());
+    assert_eq!(saved.offer_hash, offer["hash"].as_str().unwrap());
+    assert_eq!(saved.parameters.selected_energy, 1.0);
+    assert_eq!(saved.parameters.energy_rate, 2.0);
+    assert_eq!(saved.residual_bid.as_ref().unwrap().bid_component.energy, 0.5);
+    assert!(saved.residual_offer.is_none());
+
+    // An offer in place of the bid and an unsupported version are rejected.
+    let mut swapped = body.clone();
+    swapped["trades"][0]["bid"] = offer.clone();
+    let mut unsupported = body.clone();
+    unsupported["version"] = serde_json::json!(2);
+    for invalid_body in [swapped, unsupported] {
+        let resp = client
+            .post(&format!("{}/trades", &address))
+            .json(&invalid_body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(400, resp.status().as_u16());
+    }
+}
+
 #[tokio::test]
 async fn subscribe_return_a_400_when_data_is_missing() {
     let app = init_app().await;
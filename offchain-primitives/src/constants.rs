@@ -21,8 +21,9 @@ impl Constants {
             SPOT_MARKET_CLOSE_OFFSET_MIN: read_env_or("SPOT_MARKET_CLOSE_OFFSET_MIN", -60),
             FLEX_MARKET_OPEN_OFFSET_MIN: read_env_or("FLEX_MARKET_OPEN_OFFSET_MIN", -15),
             FLEX_MARKET_CLOSE_OFFSET_MIN: read_env_or("FLEX_MARKET_CLOSE_OFFSET_MIN", 0),
-            SETTLEMENT_MARKET_OPEN_OFFSET_MIN: read_env_or("SETTLEMENT_MARKET_OPEN_OFFSET_MIN", -60),
-            SETTLEMENT_MARKET_CLOSE_OFFSET_MIN: read_env_or("SETTLEMENT_MARKET_CLOSE_OFFSET_MIN", -30),
+            // The settlement market opens at the end of the delivery, when the measurements arrive.
+            SETTLEMENT_MARKET_OPEN_OFFSET_MIN: read_env_or("SETTLEMENT_MARKET_OPEN_OFFSET_MIN", 15),
+            SETTLEMENT_MARKET_CLOSE_OFFSET_MIN: read_env_or("SETTLEMENT_MARKET_CLOSE_OFFSET_MIN", 75),
         }
     }
 }
@@ -0,0 +1,14 @@
+use codec::{Encode, Decode};
+use serde::{Deserialize, Serialize};
+
+
+/// The imbalance prices of a slot, published by the DSO of the community.
+#[derive(Serialize, Deserialize, Debug, Encode, Decode, Clone, PartialEq)]
+pub struct ImbalancePriceSchema {
+    pub time_slot: u64,
+    /// The price of one kWh consumed more or produced less than traded.
+    pub short_price: f64,
+    /// The price of one kWh consumed less or produced more than traded.
+    pub long_price: f64,
+    pub creation_time: u64,
+}
@@ -2,4 +2,6 @@ pub mod profiles;
 pub mod trades;
 pub mod orders;
 
-pub mod market;
\ No newline at end of file
+pub mod market;
+pub mod imbalance_prices;
+pub mod statements;
@@ -24,7 +24,10 @@ pub struct DbOrderComponent {
     pub time_slot: u64,
     pub creation_time: u64,
     pub energy: f64,
-    pub energy_rate: f64
+    pub energy_rate: f64,
+    /// The time (in seconds) after which the order cannot be matched anymore.
+    #[serde(default)]
+    pub valid_until: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Encode, Clone, PartialEq)]
@@ -51,6 +54,7 @@ pub enum OrderStatus {
     Executed,
     Expired,
     Deleted,
+    Amended,
 }
 
 impl Default for OrderStatus {
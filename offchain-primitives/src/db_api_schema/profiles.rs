@@ -6,6 +6,9 @@ use serde::{Deserialize, Serialize};
 pub struct MeasurementSchema {
     pub area_uuid: String,
     pub area_hash: String,
+    /// The market of the slot, whose topology gives the `area_hash` of the area.
+    #[serde(default)]
+    pub market_id: String,
     pub community_uuid: String,
     pub time_slot: u64,
     pub creation_time: u64,
@@ -17,6 +20,9 @@ pub struct MeasurementSchema {
 pub struct ForecastSchema {
     pub area_uuid: String,
     pub area_hash: String,
+    /// The market of the slot, whose topology gives the `area_hash` of the area.
+    #[serde(default)]
+    pub market_id: String,
     pub community_uuid: String,
     pub time_slot: u64,
     pub creation_time: u64,
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+
+/// The period covered by a settlement statement.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementPeriod {
+    Slot,
+    Month,
+}
+
+impl StatementPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatementPeriod::Slot => "slot",
+            StatementPeriod::Month => "month",
+        }
+    }
+}
+
+/// The settlement statement of an account over a slot or a billing period. The energies are in
+/// kWh, and the rates, values and penalties in the unit of the energy rates.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SettlementStatementSchema {
+    pub account: String,
+    pub period: StatementPeriod,
+    /// Start (inclusive) and end (exclusive) of the period, as timestamps in seconds.
+    pub period_start: u64,
+    pub period_end: u64,
+    pub energy_bought: f64,
+    pub energy_sold: f64,
+    pub average_buy_rate: f64,
+    pub average_sell_rate: f64,
+    pub bought_value: f64,
+    pub sold_value: f64,
+    /// The sum of the absolute imbalances of the measured areas of the account.
+    pub imbalance: f64,
+    pub penalties: f64,
+    /// The sold value, minus the bought value and the penalties.
+    pub net_amount: f64,
+    pub creation_time: u64,
+}
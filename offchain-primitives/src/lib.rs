@@ -2,13 +2,18 @@ pub mod algorithms;
 pub mod db_api_schema;
 
 pub mod node_to_api_schema;
+pub mod signer;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 pub mod types;
 pub mod utils;
 pub mod constants;
 
 use serde::{Deserialize};
+use subxt::ext::sp_runtime::traits::{BlakeTwo256, Hash};
+use subxt::utils::H256;
 
-#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MarketType {
 	Spot,
 	Flexibility,
@@ -23,4 +28,27 @@ impl MarketType {
 			MarketType::Settlement => "Settlement",
 		}
 	}
+
+	/// All the market types, in the order the markets of a slot are opened.
+	pub fn all() -> [MarketType; 3] {
+		[MarketType::Spot, MarketType::Flexibility, MarketType::Settlement]
+	}
+
+	/// The id of the market of this type delivering at `delivery_timestamp`, as generated by the
+	/// market orchestrator: the Blake2-256 hash of the market type and the big-endian timestamp.
+	pub fn market_id(&self, delivery_timestamp: u64) -> H256 {
+		let mut buffer = Vec::new();
+		buffer.extend_from_slice(self.as_str().as_bytes());
+		buffer.extend_from_slice(&delivery_timestamp.to_be_bytes());
+		H256(BlakeTwo256::hash(&buffer).0)
+	}
+
+	/// The type of the market with the given id, formatted as by `h256_to_string`, if the market
+	/// delivers at `delivery_timestamp`.
+	pub fn of_market(market_id: &str, delivery_timestamp: u64) -> Option<MarketType> {
+		MarketType::all().into_iter().find(|market_type| {
+			utils::h256_to_string(market_type.market_id(delivery_timestamp))
+				.eq_ignore_ascii_case(market_id)
+		})
+	}
 }
@@ -1,10 +1,9 @@
 use crate::db_api_schema;
 use crate::db_api_schema::orders::{DbBid, DbOffer, DbOrderComponent, DbOrderSchema, OrderStatus};
 use crate::utils::h256_to_string;
-use codec::{Decode, Encode};
+use codec::{Decode, DecodeAll, Encode};
 use serde::{Deserialize, Serialize};
 use subxt::ext::sp_core::H256;
-use subxt::ext::sp_runtime::traits::{BlakeTwo256, Hash as HashT};
 use subxt::utils::AccountId32;
 
 #[derive(Serialize, Deserialize, Encode, Decode, Clone, Debug)]
@@ -22,6 +21,8 @@ pub struct OrderComponent {
 	pub creation_time: u64,
 	pub energy: u64,
 	pub energy_rate: u64,
+	#[serde(default)]
+	pub valid_until: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Encode, Decode, Clone)]
@@ -45,6 +46,89 @@ pub struct Offer<AccountId32> {
 	pub offer_component: OrderComponent,
 }
 
+/// The layout of the order component relayed by the nodes that predate `valid_until`.
+#[derive(Encode, Decode, Clone)]
+pub struct LegacyOrderComponent {
+	pub area_uuid: H256,
+	pub market_id: H256,
+	pub time_slot: u64,
+	pub creation_time: u64,
+	pub energy: u64,
+	pub energy_rate: u64,
+}
+
+impl From<LegacyOrderComponent> for OrderComponent {
+	fn from(component: LegacyOrderComponent) -> Self {
+		OrderComponent {
+			area_uuid: component.area_uuid,
+			market_id: component.market_id,
+			time_slot: component.time_slot,
+			creation_time: component.creation_time,
+			energy: component.energy,
+			energy_rate: component.energy_rate,
+			valid_until: None,
+		}
+	}
+}
+
+#[derive(Encode, Decode, Clone)]
+pub struct LegacyBid<AccountId32> {
+	pub buyer: AccountId32,
+	pub nonce: u32,
+	pub bid_component: LegacyOrderComponent,
+}
+
+impl<AccountId32> From<LegacyBid<AccountId32>> for Bid<AccountId32> {
+	fn from(bid: LegacyBid<AccountId32>) -> Self {
+		Bid { buyer: bid.buyer, nonce: bid.nonce, bid_component: bid.bid_component.into() }
+	}
+}
+
+#[derive(Encode, Decode, Clone)]
+pub struct LegacyOffer<AccountId32> {
+	pub seller: AccountId32,
+	pub nonce: u32,
+	pub offer_component: LegacyOrderComponent,
+}
+
+impl<AccountId32> From<LegacyOffer<AccountId32>> for Offer<AccountId32> {
+	fn from(offer: LegacyOffer<AccountId32>) -> Self {
+		Offer {
+			seller: offer.seller,
+			nonce: offer.nonce,
+			offer_component: offer.offer_component.into(),
+		}
+	}
+}
+
+#[derive(Encode, Decode, Clone)]
+pub enum LegacyOrder<AccountId32> {
+	Bid(LegacyBid<AccountId32>),
+	Offer(LegacyOffer<AccountId32>),
+}
+
+#[derive(Encode, Decode, Clone)]
+pub struct LegacyOrderSchema<AccountId32, Hash> {
+	pub _id: Hash,
+	pub status: OrderStatus,
+	pub order: LegacyOrder<AccountId32>,
+}
+
+impl<AccountId32, Hash> From<LegacyOrderSchema<AccountId32, Hash>>
+	for OrderSchema<AccountId32, Hash>
+{
+	fn from(order: LegacyOrderSchema<AccountId32, Hash>) -> Self {
+		OrderSchema {
+			_id: order._id,
+			status: order.status,
+			order: match order.order {
+				LegacyOrder::Bid(bid) => Order::Bid(bid.into()),
+				LegacyOrder::Offer(offer) => Order::Offer(offer.into()),
+			},
+		}
+	}
+}
+
 pub fn create_db_offer_from_node_offer(offer: Offer<AccountId32>) -> DbOffer {
 	DbOffer {
 		seller: offer.seller.to_string(),
@@ -56,6 +140,7 @@ pub fn create_db_offer_from_node_offer(offer: Offer<AccountId32>) -> DbOffer {
 			creation_time: offer.offer_component.creation_time,
 			energy: offer.offer_component.energy as f64 / 10000.0,
 			energy_rate: offer.offer_component.energy_rate as f64 / 10000.0,
+			valid_until: offer.offer_component.valid_until,
 		},
 	}
 }
@@ -71,31 +156,44 @@ pub fn create_db_bid_from_node_bid(bid: Bid<AccountId32>) -> DbBid {
 			creation_time: bid.bid_component.creation_time,
 			energy: bid.bid_component.energy as f64 / 10000.0,
 			energy_rate: bid.bid_component.energy_rate as f64 / 10000.0,
+			valid_until: bid.bid_component.valid_until,
 		},
 	}
 }
 
+/// Decode the SCALE encoded orders, falling back to the layout of the nodes that predate
+/// `valid_until`.
+fn decode_node_orders(
+	serialized_orders: &[u8],
+) -> Result<Vec<OrderSchema<AccountId32, H256>>, String> {
+	Vec::<OrderSchema<AccountId32, H256>>::decode_all(&mut &serialized_orders[..]).or_else(
+		|error| {
+			Vec::<LegacyOrderSchema<AccountId32, H256>>::decode_all(&mut &serialized_orders[..])
+				.map(|orders| orders.into_iter().map(Into::into).collect())
+				.map_err(|_| format!("Unable to decode the orders: {}", error))
+		},
+	)
+}
+
 pub fn convert_gsy_node_order_schema_to_db_schema(
 	serialized_orders: Vec<u8>,
-) -> Vec<DbOrderSchema> {
-	let transcode: Vec<OrderSchema<AccountId32, H256>> =
-		Vec::<OrderSchema<AccountId32, H256>>::decode(&mut &serialized_orders[..]).unwrap();
+) -> Result<Vec<DbOrderSchema>, String> {
+	let transcode = decode_node_orders(&serialized_orders)?;
 
 	let mut deserialized: Vec<DbOrderSchema> = vec![];
+	// The legacy nodes identify the orders by the `_id` they relay along with them.
 	for order in transcode {
 		match order.order {
 			Order::Bid(bid) => {
-				let bid_hash = BlakeTwo256::hash_of(&bid);
 				deserialized.push(DbOrderSchema {
-					_id: h256_to_string(bid_hash),
+					_id: h256_to_string(order._id),
 					status: order.status,
 					order: db_api_schema::orders::Order::Bid(create_db_bid_from_node_bid(bid)),
 				});
 			},
 			Order::Offer(offer) => {
-				let offer_hash = BlakeTwo256::hash_of(&offer);
 				deserialized.push(DbOrderSchema {
-					_id: h256_to_string(offer_hash),
+					_id: h256_to_string(order._id),
 					status: order.status,
 					order: db_api_schema::orders::Order::Offer(create_db_offer_from_node_offer(
 						offer,
@@ -104,5 +202,5 @@ pub fn convert_gsy_node_order_schema_to_db_schema(
 			},
 		};
 	}
-	deserialized
+	Ok(deserialized)
 }
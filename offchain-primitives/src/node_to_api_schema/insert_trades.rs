@@ -1,15 +1,14 @@
 use serde::{Deserialize, Serialize};
-use codec::{Decode, Encode};
+use codec::{Decode, DecodeAll, Encode};
 use subxt::ext::sp_core::H256;
 use subxt::ext::sp_runtime::traits::CheckedConversion;
 use subxt::utils::AccountId32;
 use crate::db_api_schema;
 use crate::node_to_api_schema::insert_order::{
-    Offer, Bid, create_db_offer_from_node_offer, create_db_bid_from_node_bid};
+    Offer, Bid, LegacyOffer, LegacyBid, create_db_offer_from_node_offer, create_db_bid_from_node_bid};
 use crate::db_api_schema::trades::{
     TradeSchema as DbTradeSchema, TradeStatus, TradeParameters as DbTradeParameters};
 use crate::utils::h256_to_string;
-use uuid::Uuid;
 
 
 #[derive(Serialize, Deserialize, Encode, Decode, Clone)]
@@ -40,38 +39,86 @@ pub struct Trade<AccountId32, Hash> {
 }
 
 
-pub fn convert_gsy_node_trades_schema_to_db_schema(trades: Vec<u8>) -> Vec<DbTradeSchema> {
-    let transcode: Vec<Trade<AccountId32, H256>> = Vec::<Trade<AccountId32, H256>>::decode(
-        &mut &trades[..]).unwrap();
-    let mut deserialized: Vec<db_api_schema::trades::TradeSchema> = vec!();
-    for trade in transcode {
-        deserialized.push(db_api_schema::trades::TradeSchema {
-            _id: Uuid::new_v4().to_string(),
-            status: TradeStatus::Settled,
-            seller: trade.seller.to_string(),
-            buyer: trade.buyer.to_string(),
-            market_id: h256_to_string(trade.market_id),
-            time_slot: trade.time_slot,
-            trade_uuid: h256_to_string(trade.trade_uuid),
+/// The layout of the trades relayed by the nodes that predate `valid_until`.
+#[derive(Encode, Decode, Clone)]
+pub struct LegacyTrade<AccountId32, Hash> {
+    pub seller: AccountId32,
+    pub buyer: AccountId32,
+    pub market_id: H256,
+    pub trade_uuid: Hash,
+    pub creation_time: u64,
+    pub time_slot: u64,
+    pub offer: LegacyOffer<AccountId32>,
+    pub offer_hash: Hash,
+    pub bid: LegacyBid<AccountId32>,
+    pub bid_hash: Hash,
+    pub residual_bid: Option<LegacyBid<AccountId32>>,
+    pub residual_offer: Option<LegacyOffer<AccountId32>>,
+    pub parameters: TradeParameters<Hash>,
+}
+
+impl<AccountId32, Hash> From<LegacyTrade<AccountId32, Hash>> for Trade<AccountId32, Hash> {
+    fn from(trade: LegacyTrade<AccountId32, Hash>) -> Self {
+        Trade {
+            seller: trade.seller,
+            buyer: trade.buyer,
+            market_id: trade.market_id,
+            trade_uuid: trade.trade_uuid,
             creation_time: trade.creation_time,
-            offer: create_db_offer_from_node_offer(trade.offer),
-            offer_hash: h256_to_string(trade.offer_hash),
-            bid: create_db_bid_from_node_bid(trade.bid),
-            bid_hash: h256_to_string(trade.bid_hash),
-            residual_offer: match trade.residual_offer {
-                Some(residual_offer) => create_db_offer_from_node_offer(residual_offer).checked_into(),
-                None => None
-            },
-            residual_bid: match trade.residual_bid {
-                Some(residual_bid) => create_db_bid_from_node_bid(residual_bid).checked_into(),
-                None => None
-            },
-            parameters: DbTradeParameters {
-                selected_energy: trade.parameters.selected_energy as f64 / 10000.0,
-                energy_rate: trade.parameters.energy_rate as f64 / 10000.0,
-                trade_uuid: h256_to_string(trade.parameters.trade_uuid)
-            },
-        })
+            time_slot: trade.time_slot,
+            offer: trade.offer.into(),
+            offer_hash: trade.offer_hash,
+            bid: trade.bid.into(),
+            bid_hash: trade.bid_hash,
+            residual_bid: trade.residual_bid.map(Into::into),
+            residual_offer: trade.residual_offer.map(Into::into),
+            parameters: trade.parameters,
+        }
+    }
+}
+
+/// Decode the SCALE encoded trades, falling back to the layout of the nodes that predate
+/// `valid_until`.
+fn decode_node_trades(trades: &[u8]) -> Result<Vec<Trade<AccountId32, H256>>, String> {
+    Vec::<Trade<AccountId32, H256>>::decode_all(&mut &trades[..]).or_else(|error| {
+        Vec::<LegacyTrade<AccountId32, H256>>::decode_all(&mut &trades[..])
+            .map(|trades| trades.into_iter().map(Into::into).collect())
+            .map_err(|_| format!("Unable to decode the trades: {}", error))
+    })
+}
+
+pub fn convert_gsy_node_trades_schema_to_db_schema(
+    trades: Vec<u8>
+) -> Result<Vec<DbTradeSchema>, String> {
+    Ok(decode_node_trades(&trades)?.into_iter().map(create_db_trade_from_node_trade).collect())
+}
+
+pub fn create_db_trade_from_node_trade(trade: Trade<AccountId32, H256>) -> DbTradeSchema {
+    db_api_schema::trades::TradeSchema {
+        _id: h256_to_string(trade.trade_uuid),
+        status: TradeStatus::Settled,
+        seller: trade.seller.to_string(),
+        buyer: trade.buyer.to_string(),
+        market_id: h256_to_string(trade.market_id),
+        time_slot: trade.time_slot,
+        trade_uuid: h256_to_string(trade.trade_uuid),
+        creation_time: trade.creation_time,
+        offer: create_db_offer_from_node_offer(trade.offer),
+        offer_hash: h256_to_string(trade.offer_hash),
+        bid: create_db_bid_from_node_bid(trade.bid),
+        bid_hash: h256_to_string(trade.bid_hash),
+        residual_offer: match trade.residual_offer {
+            Some(residual_offer) => create_db_offer_from_node_offer(residual_offer).checked_into(),
+            None => None
+        },
+        residual_bid: match trade.residual_bid {
+            Some(residual_bid) => create_db_bid_from_node_bid(residual_bid).checked_into(),
+            None => None
+        },
+        parameters: DbTradeParameters {
+            selected_energy: trade.parameters.selected_energy as f64 / 10000.0,
+            energy_rate: trade.parameters.energy_rate as f64 / 10000.0,
+            trade_uuid: h256_to_string(trade.parameters.trade_uuid)
+        },
     }
-    deserialized
 }
\ No newline at end of file
@@ -1,3 +1,7 @@
 pub mod insert_order;
 
-pub mod insert_trades;
\ No newline at end of file
+pub mod insert_trades;
+
+pub mod node_index;
+
+pub mod relay;
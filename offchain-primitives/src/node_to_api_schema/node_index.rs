@@ -0,0 +1,65 @@
+//! Schema of the orders and trades indexed by the node in its offchain database, as returned by
+//! the `gsy_indexedOrders` and `gsy_indexedTrades` RPC methods.
+
+use crate::db_api_schema::trades::TradeSchema as DbTradeSchema;
+use crate::node_to_api_schema::insert_trades::{
+	create_db_trade_from_node_trade, Trade, TradeParameters,
+};
+use crate::types::Order;
+use serde::{Deserialize, Serialize};
+use subxt::ext::sp_core::H256;
+use subxt::utils::AccountId32;
+
+/// Filter of the `gsy_indexed*` RPC methods. The unset fields match every record.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexQuery {
+	pub market_id: Option<H256>,
+	pub account: Option<AccountId32>,
+	/// The first delivery time slot of the records.
+	pub from: Option<u64>,
+	/// The last delivery time slot of the records.
+	pub to: Option<u64>,
+}
+
+/// A page of the records returned by the `gsy_indexed*` RPC methods.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedPage<T> {
+	pub records: Vec<T>,
+	/// The cursor of the next page, or `None` if all the records have been read.
+	pub next_cursor: Option<u64>,
+}
+
+/// The status of an indexed order in the orderbook registry.
+#[derive(Serialize, Deserialize, Clone)]
+pub enum IndexedOrderStatus {
+	Open,
+	Executed(TradeParameters<H256>),
+	Deleted,
+	Expired,
+	Amended(H256),
+}
+
+/// An indexed order, with its status at the best block of the node.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct IndexedOrder {
+	pub hash: H256,
+	pub status: Option<IndexedOrderStatus>,
+	pub order: Order,
+}
+
+impl IndexedOrder {
+	/// Check if the order can still be matched.
+	pub fn is_open(&self) -> bool {
+		matches!(self.status, Some(IndexedOrderStatus::Open))
+	}
+}
+
+/// A trade, as returned by `gsy_indexedTrades`.
+pub type IndexedTrade = Trade<AccountId32, H256>;
+
+/// Convert the trades returned by `gsy_indexedTrades` to the database schema.
+pub fn convert_indexed_trades_to_db_schema(trades: Vec<IndexedTrade>) -> Vec<DbTradeSchema> {
+	trades.into_iter().map(create_db_trade_from_node_trade).collect()
+}
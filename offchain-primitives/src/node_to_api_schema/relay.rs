@@ -0,0 +1,150 @@
+//! Conversion of the orders and trades relayed by the orderbook worker, in the JSON schema of
+//! `gsy-relay-schema`, to the database schema.
+//!
+//! Energy amounts and rates are relayed in the fixed point units of the node (see
+//! `NODE_FLOAT_SCALING_FACTOR`).
+
+use crate::db_api_schema::orders::{
+	DbBid, DbOffer, DbOrderComponent, DbOrderSchema, Order as DbOrder, OrderStatus,
+};
+use crate::db_api_schema::trades::{
+	TradeParameters as DbTradeParameters, TradeSchema as DbTradeSchema, TradeStatus,
+};
+use crate::node_to_api_schema::insert_order::convert_gsy_node_order_schema_to_db_schema;
+use crate::node_to_api_schema::insert_trades::convert_gsy_node_trades_schema_to_db_schema;
+use crate::utils::NODE_FLOAT_SCALING_FACTOR;
+pub use gsy_relay_schema::{
+	RelayOrder, RelayOrderType, RelayOrders, RelayTrade, RelayTrades, RELAY_SCHEMA_VERSION,
+};
+use serde::{Deserialize, Serialize};
+
+/// The body of a request relaying orders: either the JSON relay schema, or the legacy SCALE
+/// encoded orders, accepted during the transition to the JSON relay schema.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum RelayOrdersBody {
+	Json(RelayOrders),
+	Scale(Vec<u8>),
+}
+
+/// The body of a request relaying trades: either the JSON relay schema, or the legacy SCALE
+/// encoded trades, accepted during the transition to the JSON relay schema.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum RelayTradesBody {
+	Json(RelayTrades),
+	Scale(Vec<u8>),
+}
+
+fn check_version(version: u32) -> Result<(), String> {
+	if version == RELAY_SCHEMA_VERSION {
+		Ok(())
+	} else {
+		Err(format!("Unsupported relay schema version {}", version))
+	}
+}
+
+fn relay_order_component(order: &RelayOrder) -> DbOrderComponent {
+	DbOrderComponent {
+		area_uuid: order.area_uuid.clone(),
+		market_id: order.market_id.clone(),
+		time_slot: order.time_slot,
+		creation_time: order.creation_time,
+		energy: order.energy as f64 / NODE_FLOAT_SCALING_FACTOR,
+		energy_rate: order.energy_rate as f64 / NODE_FLOAT_SCALING_FACTOR,
+		valid_until: order.valid_until,
+	}
+}
+
+fn relay_order_to_db_bid(order: &RelayOrder) -> Result<DbBid, String> {
+	if order.order_type != RelayOrderType::Bid {
+		return Err(format!("Order {} is not a bid", order.hash));
+	}
+	Ok(DbBid {
+		buyer: order.account.clone(),
+		nonce: order.nonce,
+		bid_component: relay_order_component(order),
+	})
+}
+
+fn relay_order_to_db_offer(order: &RelayOrder) -> Result<DbOffer, String> {
+	if order.order_type != RelayOrderType::Offer {
+		return Err(format!("Order {} is not an offer", order.hash));
+	}
+	Ok(DbOffer {
+		seller: order.account.clone(),
+		nonce: order.nonce,
+		offer_component: relay_order_component(order),
+	})
+}
+
+fn relay_order_to_db_order(order: &RelayOrder) -> Result<DbOrder, String> {
+	match order.order_type {
+		RelayOrderType::Bid => relay_order_to_db_bid(order).map(DbOrder::Bid),
+		RelayOrderType::Offer => relay_order_to_db_offer(order).map(DbOrder::Offer),
+	}
+}
+
+/// Convert the body of a request relaying orders to the database schema.
+pub fn convert_relay_orders_to_db_schema(
+	body: RelayOrdersBody,
+) -> Result<Vec<DbOrderSchema>, String> {
+	let relay_orders = match body {
+		RelayOrdersBody::Json(relay_orders) => relay_orders,
+		RelayOrdersBody::Scale(bytes) => return convert_gsy_node_order_schema_to_db_schema(bytes),
+	};
+	check_version(relay_orders.version)?;
+	relay_orders
+		.orders
+		.iter()
+		.map(|order| {
+			Ok(DbOrderSchema {
+				_id: order.hash.clone(),
+				status: OrderStatus::Open,
+				order: relay_order_to_db_order(order)?,
+			})
+		})
+		.collect()
+}
+
+/// Convert the body of a request relaying trades to the database schema.
+pub fn convert_relay_trades_to_db_schema(
+	body: RelayTradesBody,
+) -> Result<Vec<DbTradeSchema>, String> {
+	let relay_trades = match body {
+		RelayTradesBody::Json(relay_trades) => relay_trades,
+		RelayTradesBody::Scale(bytes) => return convert_gsy_node_trades_schema_to_db_schema(bytes),
+	};
+	check_version(relay_trades.version)?;
+	relay_trades
+		.trades
+		.iter()
+		.map(|trade| {
+			Ok(DbTradeSchema {
+				_id: trade.trade_uuid.clone(),
+				status: TradeStatus::Settled,
+				seller: trade.seller.clone(),
+				buyer: trade.buyer.clone(),
+				market_id: trade.market_id.clone(),
+				time_slot: trade.time_slot,
+				trade_uuid: trade.trade_uuid.clone(),
+				creation_time: trade.creation_time,
+				offer: relay_order_to_db_offer(&trade.offer)?,
+				offer_hash: trade.offer.hash.clone(),
+				bid: relay_order_to_db_bid(&trade.bid)?,
+				bid_hash: trade.bid.hash.clone(),
+				residual_offer: trade
+					.residual_offer
+					.as_ref()
+					.map(relay_order_to_db_offer)
+					.transpose()?,
+				residual_bid: trade.residual_bid.as_ref().map(relay_order_to_db_bid).transpose()?,
+				parameters: DbTradeParameters {
+					selected_energy: trade.selected_energy as f64 / NODE_FLOAT_SCALING_FACTOR,
+					energy_rate: trade.energy_rate as f64 / NODE_FLOAT_SCALING_FACTOR,
+					trade_uuid: trade.trade_uuid.clone(),
+				},
+			})
+		})
+		.collect()
+}
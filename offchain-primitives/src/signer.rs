@@ -0,0 +1,76 @@
+use anyhow::{anyhow, Context, Result};
+use std::env;
+#[cfg(feature = "signer")]
+use subxt_signer::{polkadot_js_compat::decode_json, sr25519::Keypair, SecretUri};
+
+/// The secret of the key signing the extrinsics of a service, configured with one of:
+///
+/// - `SIGNER_SURI`: a secret URI, such as a mnemonic or the secret seed printed by
+///   `subkey generate`, with an optional derivation path (`"<mnemonic>//gsy"`);
+/// - `SIGNER_SURI_FILE`: a file containing the secret URI, such as a mounted secret;
+/// - `SIGNER_KEYSTORE`: an encrypted JSON keystore exported by polkadot-js, unlocked with the
+///   password of `SIGNER_KEYSTORE_PASSWORD` or of the file `SIGNER_KEYSTORE_PASSWORD_FILE`.
+///
+/// With the `signer` feature, it is turned into the sr25519 key pair of `subxt-signer`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignerSource {
+    Suri(String),
+    Keystore { json: String, password: String },
+}
+
+impl SignerSource {
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    /// The signer configured by the variables returned by `var`.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let read_file = |path: String| {
+            std::fs::read_to_string(&path)
+                .map(|content| content.trim().to_string())
+                .with_context(|| format!("Failed to read the signer file {}", path))
+        };
+        let suri = var("SIGNER_SURI");
+        let suri_file = var("SIGNER_SURI_FILE");
+        let keystore = var("SIGNER_KEYSTORE");
+
+        match (suri, suri_file, keystore) {
+            (Some(suri), None, None) => Ok(SignerSource::Suri(suri)),
+            (None, Some(path), None) => Ok(SignerSource::Suri(read_file(path)?)),
+            (None, None, Some(path)) => {
+                let password = match (var("SIGNER_KEYSTORE_PASSWORD"), var("SIGNER_KEYSTORE_PASSWORD_FILE")) {
+                    (Some(password), None) => password,
+                    (None, Some(password_file)) => read_file(password_file)?,
+                    _ => {
+                        return Err(anyhow!(
+                            "The keystore needs one of SIGNER_KEYSTORE_PASSWORD or SIGNER_KEYSTORE_PASSWORD_FILE"
+                        ))
+                    }
+                };
+                Ok(SignerSource::Keystore { json: read_file(path)?, password })
+            }
+            (None, None, None) => Err(anyhow!(
+                "No signer configured, set one of SIGNER_SURI, SIGNER_SURI_FILE or SIGNER_KEYSTORE"
+            )),
+            _ => Err(anyhow!(
+                "Only one of SIGNER_SURI, SIGNER_SURI_FILE or SIGNER_KEYSTORE can be set"
+            )),
+        }
+    }
+
+    /// The key pair of this signer.
+    #[cfg(feature = "signer")]
+    pub fn keypair(&self) -> Result<Keypair> {
+        match self {
+            SignerSource::Suri(suri) => Ok(Keypair::from_uri(&suri.parse::<SecretUri>()?)?),
+            SignerSource::Keystore { json, password } => decode_json(json, password)
+                .map_err(|error| anyhow!("Failed to decrypt the signer keystore: {:?}", error)),
+        }
+    }
+}
+
+/// The key pair of the signer configured in the environment.
+#[cfg(feature = "signer")]
+pub fn load_signer() -> Result<Keypair> {
+    SignerSource::from_env()?.keypair()
+}
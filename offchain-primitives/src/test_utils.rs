@@ -0,0 +1,106 @@
+//! Fixtures shared by the tests of the offchain services, enabled by the `test-utils` feature.
+
+use crate::db_api_schema::orders::{DbBid, DbOffer, DbOrderComponent};
+use crate::db_api_schema::trades::{TradeParameters, TradeSchema, TradeStatus};
+use crate::utils::h256_to_string;
+use crate::MarketType;
+
+/// Builder of a settled trade of `energy` kWh from the area `seller` to the area `buyer`.
+///
+/// Unless set otherwise, each area is owned by the account of the same name, the energy rate is
+/// 10, the time slot is 0 and the market id is `"market"`.
+pub struct TradeFixture {
+    trade_uuid: String,
+    seller_area: String,
+    buyer_area: String,
+    seller: String,
+    buyer: String,
+    energy: f64,
+    energy_rate: f64,
+    time_slot: u64,
+    market_type: Option<MarketType>,
+}
+
+impl TradeFixture {
+    pub fn new(trade_uuid: &str, seller: &str, buyer: &str, energy: f64) -> Self {
+        TradeFixture {
+            trade_uuid: trade_uuid.to_string(),
+            seller_area: seller.to_string(),
+            buyer_area: buyer.to_string(),
+            seller: seller.to_string(),
+            buyer: buyer.to_string(),
+            energy,
+            energy_rate: 10.0,
+            time_slot: 0,
+            market_type: None,
+        }
+    }
+
+    /// Set the accounts owning the seller and buyer areas.
+    pub fn accounts(mut self, seller: &str, buyer: &str) -> Self {
+        self.seller = seller.to_string();
+        self.buyer = buyer.to_string();
+        self
+    }
+
+    pub fn energy_rate(mut self, energy_rate: f64) -> Self {
+        self.energy_rate = energy_rate;
+        self
+    }
+
+    pub fn time_slot(mut self, time_slot: u64) -> Self {
+        self.time_slot = time_slot;
+        self
+    }
+
+    /// Trade in the market of `market_type` delivering at the time slot of the trade.
+    pub fn in_market(mut self, market_type: MarketType) -> Self {
+        self.market_type = Some(market_type);
+        self
+    }
+
+    pub fn build(self) -> TradeSchema {
+        let market_id = match self.market_type {
+            Some(market_type) => h256_to_string(market_type.market_id(self.time_slot)),
+            None => "market".to_string(),
+        };
+        let component = |area_uuid: &str| DbOrderComponent {
+            area_uuid: area_uuid.to_string(),
+            market_id: market_id.clone(),
+            time_slot: self.time_slot,
+            creation_time: self.time_slot,
+            energy: self.energy,
+            energy_rate: self.energy_rate,
+            valid_until: None,
+        };
+        TradeSchema {
+            _id: self.trade_uuid.clone(),
+            status: TradeStatus::Settled,
+            seller: self.seller.clone(),
+            buyer: self.buyer.clone(),
+            market_id: market_id.clone(),
+            time_slot: self.time_slot,
+            trade_uuid: self.trade_uuid.clone(),
+            creation_time: self.time_slot,
+            offer: DbOffer {
+                seller: self.seller.clone(),
+                nonce: 0,
+                offer_component: component(&self.seller_area),
+            },
+            offer_hash: String::new(),
+            bid: DbBid {
+                buyer: self.buyer.clone(),
+                nonce: 0,
+                bid_component: component(&self.buyer_area),
+            },
+            bid_hash: String::new(),
+            residual_offer: None,
+            residual_bid: None,
+            parameters: TradeParameters {
+                selected_energy: self.energy,
+                energy_rate: self.energy_rate,
+                trade_uuid: self.trade_uuid,
+            },
+        }
+    }
+}
@@ -1,6 +1,16 @@
-use subxt::utils::H256;
+use codec::Encode;
+use subxt::utils::{AccountId32, H256};
+
+use gsy_offchain_primitives::db_api_schema::orders::{Order, OrderStatus};
+use gsy_offchain_primitives::node_to_api_schema::insert_order::{
+    convert_gsy_node_order_schema_to_db_schema, LegacyBid, LegacyOrder, LegacyOrderComponent,
+    LegacyOrderSchema,
+};
 
 use gsy_offchain_primitives::utils::{h256_to_string, string_to_h256};
+use gsy_offchain_primitives::signer::SignerSource;
+use gsy_offchain_primitives::MarketType;
+use std::collections::HashMap;
 
 #[cfg(test)]
 mod tests {
@@ -27,5 +37,77 @@ mod tests {
         let hash_string = h256_to_string(hash);
         assert_eq!(hash, string_to_h256(hash_string));
     }
-    
-}
\ No newline at end of file
+
+    #[test]
+    fn test_market_type_is_found_from_market_id() {
+        let delivery_timestamp = 1_700_000_100;
+        for market_type in MarketType::all() {
+            let market_id = h256_to_string(market_type.market_id(delivery_timestamp));
+            assert_eq!(MarketType::of_market(&market_id, delivery_timestamp), Some(market_type));
+            assert_eq!(MarketType::of_market(&market_id, delivery_timestamp + 900), None);
+        }
+    }
+
+    #[test]
+    fn test_signer_is_read_from_exactly_one_source() {
+        let suri_file = std::env::temp_dir().join(format!("signer-suri-{}", std::process::id()));
+        std::fs::write(&suri_file, "secret\n").unwrap();
+        let vars = |vars: &[(&str, &str)]| {
+            let vars: HashMap<String, String> =
+                vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+            move |name: &str| vars.get(name).cloned()
+        };
+
+        assert_eq!(
+            SignerSource::from_vars(vars(&[("SIGNER_SURI", "//Bob")])).unwrap(),
+            SignerSource::Suri("//Bob".to_string())
+        );
+        assert_eq!(
+            SignerSource::from_vars(vars(&[
+                ("SIGNER_SURI_FILE", suri_file.to_str().unwrap()),
+            ]))
+            .unwrap(),
+            SignerSource::Suri("secret".to_string())
+        );
+        assert!(SignerSource::from_vars(vars(&[])).is_err());
+        assert!(SignerSource::from_vars(vars(&[("SIGNER_SURI", "//Bob"), ("SIGNER_KEYSTORE", "key.json")])).is_err());
+        assert!(SignerSource::from_vars(vars(&[("SIGNER_KEYSTORE", "key.json")])).is_err());
+        std::fs::remove_file(&suri_file).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_scale_orders_are_decoded_without_valid_until() {
+        let legacy_orders = vec![LegacyOrderSchema {
+            _id: H256::random(),
+            status: OrderStatus::Open,
+            order: LegacyOrder::Bid(LegacyBid {
+                buyer: AccountId32([1u8; 32]),
+                nonce: 1,
+                bid_component: LegacyOrderComponent {
+                    area_uuid: H256::random(),
+                    market_id: H256::random(),
+                    time_slot: 1_700_000_100,
+                    creation_time: 1_700_000_000,
+                    energy: 10000,
+                    energy_rate: 20000,
+                },
+            }),
+        }];
+
+        let orders = convert_gsy_node_order_schema_to_db_schema(legacy_orders.encode()).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0]._id, h256_to_string(legacy_orders[0]._id));
+        match &orders[0].order {
+            Order::Bid(bid) => {
+                assert_eq!(bid.bid_component.energy, 1.0);
+                assert_eq!(bid.bid_component.valid_until, None);
+            },
+            Order::Offer(_) => panic!("The legacy bid was decoded as an offer"),
+        }
+    }
+
+    #[test]
+    fn test_undecodable_scale_orders_are_an_error() {
+        assert!(convert_gsy_node_order_schema_to_db_schema(vec![4, 1, 2, 3]).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+// This file is part of GSy-Decentralized Energy Exchange.
+
+// Copyright (C) Grid Singularity Gmbh.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Versioned JSON schema of the orders and trades relayed by the offchain worker to the orderbook
+//! service.
+//!
+//! The schema is emitted by the orderbook worker and accepted by the offchain storage. Hashes are
+//! `0x` prefixed hex strings, accounts are SS58 addresses, and energy amounts and rates are in the
+//! fixed point units of the node. The `version` field is increased on every breaking change of
+//! the schema.
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use serde::{Deserialize, Serialize};
+
+/// The version of the relay schema.
+pub const RELAY_SCHEMA_VERSION: u32 = 1;
+
+/// The orders relayed to the `/orders` endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RelayOrders {
+	pub version: u32,
+	pub orders: Vec<RelayOrder>,
+}
+
+/// The trades relayed to the `/trades` endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RelayTrades {
+	pub version: u32,
+	pub trades: Vec<RelayTrade>,
+}
+
+/// The type of a relayed order.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RelayOrderType {
+	Bid,
+	Offer,
+}
+
+/// A bid or an offer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RelayOrder {
+	/// The hash of the bid or offer, which identifies the order in the orderbook registry and in
+	/// the trades.
+	pub hash: String,
+	#[serde(rename = "type")]
+	pub order_type: RelayOrderType,
+	/// The buyer of a bid or the seller of an offer.
+	pub account: String,
+	pub nonce: u32,
+	pub area_uuid: String,
+	pub market_id: String,
+	pub time_slot: u64,
+	pub creation_time: u64,
+	pub energy: u64,
+	pub energy_rate: u64,
+	#[serde(default)]
+	pub valid_until: Option<u64>,
+}
+
+/// A trade between a bid and an offer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RelayTrade {
+	pub trade_uuid: String,
+	pub market_id: String,
+	pub seller: String,
+	pub buyer: String,
+	pub time_slot: u64,
+	pub creation_time: u64,
+	pub selected_energy: u64,
+	pub energy_rate: u64,
+	pub bid: RelayOrder,
+	pub offer: RelayOrder,
+	#[serde(default)]
+	pub residual_bid: Option<RelayOrder>,
+	#[serde(default)]
+	pub residual_offer: Option<RelayOrder>,
+}
@@ -79,11 +79,25 @@ pub use sp_runtime::OpaqueExtrinsic as UncheckedExtrinsic;
 
 mod orders;
 pub use orders::{
-	Bid, InputBid, InputOffer, InputOrder, Offer, Order, OrderComponent, OrderReference,
-	OrderSchema, OrderStatus,
+	Bid, InputBid, InputOffer, InputOrder, Offer, Order, OrderAmendment, OrderComponent,
+	OrderReference, OrderSchema, OrderStatus,
 };
 mod trades;
 pub use trades::{BidOfferMatch, Trade, TradeParameters, TradesPenalties, Validator};
+mod matching;
+pub use matching::pay_as_bid;
+mod relay;
+pub use relay::{
+	relay_position_key, RelayEndpoint, RelayItem, RelayKind, RelayList, RELAY_DEAD_LETTER_KEY,
+	RELAY_DELIVERED_KEY, RELAY_ENDPOINTS_KEY, RELAY_POSITION_KEY, RELAY_QUEUE_KEY,
+	RELAY_REQUEUE_KEY,
+};
+mod offchain_index;
+pub use offchain_index::{
+	offchain_index_key, offchain_slot_index_key, offchain_slot_key, offchain_slot_length_key,
+	IndexedRecord, OFFCHAIN_INDEX_LENGTH_KEY, OFFCHAIN_INDEX_PREFIX, OFFCHAIN_SLOTS_LENGTH_KEY,
+	OFFCHAIN_SLOTS_PREFIX, OFFCHAIN_SLOT_INDEX_PREFIX,
+};
 mod vault;
 pub use vault::{CollateralInfo, Vault, VaultInfo, VaultStatus, VaultWithStatus};
 
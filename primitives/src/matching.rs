@@ -0,0 +1,85 @@
+use crate::orders::{Bid, Offer, OrderComponent};
+use crate::trades::BidOfferMatch;
+use codec::Encode;
+use sp_runtime::traits::{BlakeTwo256, Hash as HashT};
+use sp_std::{collections::btree_map::BTreeMap, vec::Vec};
+
+/// Match the bids and offers of a market with the pay-as-bid algorithm of the matching engine.
+///
+/// The bids are matched by decreasing energy rate with the offers by increasing energy rate, and
+/// each trade is priced at the energy rate of the bid. A bid and an offer of the same area are
+/// never matched together. The part of an order that is not traded is proposed as a residual order
+/// with the next nonce.
+pub fn pay_as_bid<AccountId: Clone + Encode, Hash: Clone>(
+	market_id: Hash,
+	mut bids: Vec<Bid<AccountId>>,
+	mut offers: Vec<Offer<AccountId>>,
+) -> Vec<BidOfferMatch<AccountId, Hash>> {
+	bids.sort_by(|a, b| b.bid_component.energy_rate.cmp(&a.bid_component.energy_rate));
+	offers.sort_by(|a, b| a.offer_component.energy_rate.cmp(&b.offer_component.energy_rate));
+
+	let mut bid_offer_pairs = Vec::new();
+	let mut available_order_energy = BTreeMap::new();
+
+	for offer in &offers {
+		for bid in &bids {
+			if offer.offer_component.area_uuid == bid.bid_component.area_uuid
+				|| offer.offer_component.energy == 0
+				|| bid.bid_component.energy == 0
+			{
+				continue;
+			}
+
+			if offer.offer_component.energy_rate > bid.bid_component.energy_rate {
+				continue;
+			}
+
+			let bid_id = BlakeTwo256::hash_of(bid);
+			let offer_id = BlakeTwo256::hash_of(offer);
+
+			let offer_energy =
+				*available_order_energy.entry(offer_id).or_insert(offer.offer_component.energy);
+			let bid_energy =
+				*available_order_energy.entry(bid_id).or_insert(bid.bid_component.energy);
+
+			let selected_energy = offer_energy.min(bid_energy);
+
+			if selected_energy == 0 {
+				continue;
+			}
+
+			available_order_energy.insert(bid_id, bid_energy - selected_energy);
+			available_order_energy.insert(offer_id, offer_energy - selected_energy);
+
+			let residual_bid = (bid_energy > selected_energy).then(|| Bid {
+				nonce: bid.nonce.wrapping_add(1),
+				bid_component: OrderComponent {
+					energy: bid_energy - selected_energy,
+					..bid.bid_component.clone()
+				},
+				..bid.clone()
+			});
+
+			let residual_offer = (offer_energy > selected_energy).then(|| Offer {
+				nonce: offer.nonce.wrapping_add(1),
+				offer_component: OrderComponent {
+					energy: offer_energy - selected_energy,
+					..offer.offer_component.clone()
+				},
+				..offer.clone()
+			});
+
+			bid_offer_pairs.push(BidOfferMatch {
+				market_id: market_id.clone(),
+				time_slot: offer.offer_component.time_slot,
+				bid: bid.clone(),
+				offer: offer.clone(),
+				residual_bid,
+				residual_offer,
+				selected_energy,
+				energy_rate: bid.bid_component.energy_rate,
+			});
+		}
+	}
+	bid_offer_pairs
+}
@@ -0,0 +1,62 @@
+use crate::orders::Order;
+use crate::trades::{Trade, TradesPenalties};
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_std::vec::Vec;
+
+/// Prefix of the offchain database keys of the indexed records.
+pub const OFFCHAIN_INDEX_PREFIX: &[u8] = b"gsy-node::index::";
+/// Key of the offchain database holding the number of indexed records.
+pub const OFFCHAIN_INDEX_LENGTH_KEY: &[u8] = b"gsy-node::index-length";
+
+/// Prefix of the offchain database keys of the indexed delivery time slots.
+pub const OFFCHAIN_SLOTS_PREFIX: &[u8] = b"gsy-node::index-slots::";
+/// Key of the offchain database holding the number of indexed delivery time slots.
+pub const OFFCHAIN_SLOTS_LENGTH_KEY: &[u8] = b"gsy-node::index-slots-length";
+/// Prefix of the offchain database keys of the records of each delivery time slot.
+pub const OFFCHAIN_SLOT_INDEX_PREFIX: &[u8] = b"gsy-node::slot-index::";
+
+/// The offchain database key of the record indexed at the given position.
+pub fn offchain_index_key(position: u64) -> Vec<u8> {
+	let mut key = OFFCHAIN_INDEX_PREFIX.to_vec();
+	key.extend(position.encode());
+	key
+}
+
+/// The offchain database key of the delivery time slot indexed at the given position.
+pub fn offchain_slot_key(position: u64) -> Vec<u8> {
+	let mut key = OFFCHAIN_SLOTS_PREFIX.to_vec();
+	key.extend(position.encode());
+	key
+}
+
+/// The offchain database key holding the number of records of a delivery time slot.
+pub fn offchain_slot_length_key(time_slot: u64) -> Vec<u8> {
+	let mut key = OFFCHAIN_SLOT_INDEX_PREFIX.to_vec();
+	key.extend(time_slot.to_be_bytes());
+	key
+}
+
+/// The offchain database key holding the position, in the offchain index, of the record of a
+/// delivery time slot at the given position of the time slot.
+///
+/// The keys of a time slot share the prefix [`offchain_slot_length_key`] of the time slot.
+pub fn offchain_slot_index_key(time_slot: u64, slot_position: u64) -> Vec<u8> {
+	let mut key = offchain_slot_length_key(time_slot);
+	key.extend(slot_position.encode());
+	key
+}
+
+/// Record written by the runtime into the offchain database, through offchain indexing.
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum IndexedRecord<AccountId, Hash> {
+	/// An order inserted into the orders book, with its hash in the orderbook registry.
+	Order { hash: Hash, order: Order<AccountId> },
+	/// A settled trade, with its hash in the orderbook worker.
+	Trade { hash: Hash, trade: Trade<AccountId, Hash> },
+	/// A penalty submitted by the execution engine, with its hash in the penalties registry.
+	Penalty { hash: Hash, penalty: TradesPenalties<AccountId, Hash> },
+}
@@ -2,10 +2,13 @@ use crate::trades::TradeParameters;
 use crate::v0::{AccountId, Hash};
 use codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 use sp_runtime::traits::{BlakeTwo256, Hash as HashT};
 
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, TypeInfo, MaxEncodedLen)]
-#[cfg_attr(feature = "std", derive(Hash))]
+#[cfg_attr(feature = "std", derive(Hash, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(tag = "type", content = "data"))]
 pub enum Order<AccountId> {
 	Bid(Bid<AccountId>),
 	Offer(Offer<AccountId>),
@@ -20,7 +23,7 @@ pub enum InputOrder<AccountId> {
 
 /// Order component struct
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, TypeInfo, MaxEncodedLen)]
-#[cfg_attr(feature = "std", derive(Hash, Default))]
+#[cfg_attr(feature = "std", derive(Hash, Default, Serialize, Deserialize))]
 pub struct OrderComponent {
 	pub area_uuid: Hash,
 	pub market_id: Hash,
@@ -28,6 +31,8 @@ pub struct OrderComponent {
 	pub creation_time: u64,
 	pub energy: u64,
 	pub energy_rate: u64,
+	/// The time (in seconds) after which the order cannot be matched anymore.
+	pub valid_until: Option<u64>,
 }
 
 impl OrderComponent {
@@ -35,6 +40,11 @@ impl OrderComponent {
 	pub fn hash(&self) -> Hash {
 		BlakeTwo256::hash_of(self)
 	}
+
+	/// Check if the order is expired at the given time (in seconds).
+	pub fn is_expired(&self, now: u64) -> bool {
+		self.valid_until.map_or(false, |valid_until| valid_until < now)
+	}
 }
 
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, TypeInfo)]
@@ -54,7 +64,7 @@ pub struct InputBid<AccountId> {
 
 /// Bid order struct
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, TypeInfo, MaxEncodedLen)]
-#[cfg_attr(feature = "std", derive(Hash, Default))]
+#[cfg_attr(feature = "std", derive(Hash, Default, Serialize, Deserialize))]
 pub struct Bid<AccountId> {
 	pub buyer: AccountId,
 	pub nonce: u32,
@@ -76,9 +86,23 @@ pub struct InputOffer<AccountId> {
 	pub offer_component: OrderComponent,
 }
 
+/// Order amendment struct
+///
+/// Replace the energy and the energy rate of an open order, keeping the rest of its components.
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Hash))]
+pub struct OrderAmendment<AccountId> {
+	/// The open order to amend.
+	pub order: Order<AccountId>,
+	/// The new energy of the order.
+	pub energy: u64,
+	/// The new energy rate of the order.
+	pub energy_rate: u64,
+}
+
 /// Offer (Ask) order struct
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, TypeInfo, MaxEncodedLen)]
-#[cfg_attr(feature = "std", derive(Hash, Default))]
+#[cfg_attr(feature = "std", derive(Hash, Default, Serialize, Deserialize))]
 pub struct Offer<AccountId> {
 	pub seller: AccountId,
 	pub nonce: u32,
@@ -92,7 +116,8 @@ impl Offer<AccountId> {
 	}
 }
 
-#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo)]
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum OrderStatus<Hash> {
 	/// Default status,
 	Open,
@@ -100,6 +125,10 @@ pub enum OrderStatus<Hash> {
 	Executed(TradeParameters<Hash>),
 	/// The order has been cancelled.
 	Deleted,
+	/// The order has not been executed before its validity time.
+	Expired,
+	/// The order has been replaced by the amended order with the given hash.
+	Amended(Hash),
 }
 
 impl<Hash> Default for OrderStatus<Hash> {
@@ -0,0 +1,103 @@
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_std::vec::Vec;
+
+/// Key prefix of the offchain local storage holding the items waiting to be relayed.
+pub const RELAY_QUEUE_KEY: &[u8] = b"gsy-node::relay::queue";
+/// Key prefix of the offchain local storage holding the items that could not be relayed.
+pub const RELAY_DEAD_LETTER_KEY: &[u8] = b"gsy-node::relay::dead-letter";
+/// Key prefix of the offchain local storage holding the items relayed, but not yet removed on
+/// chain.
+pub const RELAY_DELIVERED_KEY: &[u8] = b"gsy-node::relay::delivered";
+/// Key prefix of the offchain local storage holding the list and position of each known item.
+pub const RELAY_POSITION_KEY: &[u8] = b"gsy-node::relay::position";
+/// Key of the offchain local storage holding the hashes of the dead-lettered items to queue
+/// again, appended by the `gsy_requeueDeadLetters` RPC.
+pub const RELAY_REQUEUE_KEY: &[u8] = b"gsy-node::relay::requeue";
+
+/// A list of items of the relay queue.
+///
+/// Each item is stored under its own key of the offchain local storage, at a position between the
+/// `(head, tail)` bounds of the list, so that adding or removing an item does not rewrite the
+/// others.
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo)]
+pub enum RelayList {
+	/// The items waiting to be relayed, stored as `RelayItem`.
+	Queue,
+	/// The items that could not be relayed, stored as `RelayItem`.
+	DeadLetter,
+	/// The items relayed, but not yet removed on chain, stored as `(RelayKind, Hash)`.
+	Delivered,
+}
+
+impl RelayList {
+	/// Key of the `(head, tail)` bounds of the list, whose items are at the positions
+	/// `head..tail`.
+	pub fn bounds_key(self) -> Vec<u8> {
+		[self.prefix(), b"::bounds"].concat()
+	}
+
+	/// Key of the item at the given position of the list.
+	pub fn item_key(self, position: u64) -> Vec<u8> {
+		let mut key = [self.prefix(), b"::item::"].concat();
+		position.encode_to(&mut key);
+		key
+	}
+
+	fn prefix(self) -> &'static [u8] {
+		match self {
+			RelayList::Queue => RELAY_QUEUE_KEY,
+			RelayList::DeadLetter => RELAY_DEAD_LETTER_KEY,
+			RelayList::Delivered => RELAY_DELIVERED_KEY,
+		}
+	}
+}
+
+/// Key of the `(RelayList, position)` of the item of the given kind and hash, which prevents an
+/// item from being queued twice.
+pub fn relay_position_key<Hash: Encode>(kind: RelayKind, hash: &Hash) -> Vec<u8> {
+	let mut key = RELAY_POSITION_KEY.to_vec();
+	(kind, hash).encode_to(&mut key);
+	key
+}
+
+/// The kind of item relayed by the offchain worker to the orderbook service.
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum RelayKind {
+	Order,
+	Trade,
+}
+
+/// Item of the offchain worker relay queue.
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct RelayItem<Hash> {
+	/// The kind of the item.
+	pub kind: RelayKind,
+	/// The hash of the order or of the trade, as stored by the orderbook worker.
+	pub hash: Hash,
+	/// The SCALE encoded order or trade.
+	pub payload: Vec<u8>,
+	/// The number of failed delivery attempts.
+	pub attempts: u32,
+	/// The time (in milliseconds) of the next delivery attempt.
+	pub next_attempt_at: u64,
+	/// The HTTP status code of the last failed attempt, if a response has been received.
+	pub last_status_code: Option<u16>,
+}
+
+/// Key of the offchain local storage holding the orderbook service endpoints.
+pub const RELAY_ENDPOINTS_KEY: &[u8] = b"gsy-node::relay::endpoints";
+
+/// An orderbook service endpoint the offchain worker relays the orders and trades to.
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct RelayEndpoint {
+	/// The base URL of the orderbook service, e.g. `http://localhost:8080`.
+	pub url: Vec<u8>,
+	/// The headers added to every request, e.g. an `Authorization` header.
+	pub headers: Vec<(Vec<u8>, Vec<u8>)>,
+}
@@ -2,11 +2,13 @@ use crate::orders::{Bid, Offer};
 use crate::v0::{AccountId, Hash};
 use codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 pub use sp_runtime::traits::{BlakeTwo256, Hash as HashT};
 
 /// Trade struct
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, TypeInfo, MaxEncodedLen)]
-#[cfg_attr(feature = "std", derive(Hash, Default))]
+#[cfg_attr(feature = "std", derive(Hash, Default, Serialize, Deserialize))]
 pub struct Trade<AccountId32, Hash> {
 	pub seller: AccountId32,
 	pub buyer: AccountId32,
@@ -31,7 +33,7 @@ impl Trade<AccountId, Hash> {
 }
 
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, TypeInfo, MaxEncodedLen)]
-#[cfg_attr(feature = "std", derive(Hash, Default))]
+#[cfg_attr(feature = "std", derive(Hash, Default, Serialize, Deserialize))]
 pub struct TradesPenalties<AccountId, Hash> {
 	pub penalized_account: AccountId,
 	pub market_uuid: Hash,
@@ -40,7 +42,7 @@ pub struct TradesPenalties<AccountId, Hash> {
 }
 
 #[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, MaxEncodedLen)]
-#[cfg_attr(feature = "std", derive(Hash, Default))]
+#[cfg_attr(feature = "std", derive(Hash, Default, Serialize, Deserialize))]
 pub struct TradeParameters<Hash> {
 	/// The amount of energy that is traded.
 	pub selected_energy: u64,
@@ -103,4 +105,9 @@ pub trait Validator {
 		offer_time_slot: u64,
 		proposed_match_market_slot: u64,
 	) -> bool;
+	/// Check that the bid and the offer of the match have not expired at the given time.
+	fn validate_validity(
+		bid_offer_match: &BidOfferMatch<Self::AccountId, Self::Hash>,
+		now: u64,
+	) -> bool;
 }
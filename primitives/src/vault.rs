@@ -3,8 +3,11 @@ use codec::MaxEncodedLen;
 use codec::{Decode, Encode};
 use frame_support::{dispatch::DispatchResult, sp_runtime::DispatchError};
 use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 
 #[derive(Copy, Clone, Encode, Decode, Default, MaxEncodedLen, Debug, PartialEq, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct VaultInfo<AccountId, Balance, BlockNumber, VaultId> {
 	/// The account id of the vault owner.
 	pub owner: AccountId,
@@ -17,6 +20,7 @@ pub struct VaultInfo<AccountId, Balance, BlockNumber, VaultId> {
 }
 
 #[derive(Copy, Clone, Encode, Decode, Default, MaxEncodedLen, Debug, PartialEq, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct CollateralInfo<Balance, BlockNumber> {
 	/// The amount of collateral stored in the vault.
 	pub amount: Balance,
@@ -27,6 +31,7 @@ pub struct CollateralInfo<Balance, BlockNumber> {
 bitflags! {
 	/// Vault status flags.
 	#[derive(MaxEncodedLen, Encode, Decode, TypeInfo)]
+	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 	pub struct VaultStatus: u32 {
 		/// The vault is closed.
 		const CLOSED = 0b0000_0001;
@@ -33,7 +33,8 @@ pub fn dummy_bid(buyer: AccountId, energy: u64, energy_rate: u64) -> Bid<Account
 			time_slot: rand::thread_rng().gen_range(1..101),
 			creation_time: 1677453190,
 			energy,
-			energy_rate
+			energy_rate,
+			valid_until: None,
 		},
 	}
 }
@@ -53,7 +54,8 @@ pub fn dummy_offer(
 			time_slot: rand::thread_rng().gen_range(1..101),
 			creation_time: 1677453190,
 			energy,
-			energy_rate
+			energy_rate,
+			valid_until: None,
 		},
 	}
 }